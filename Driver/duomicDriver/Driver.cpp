@@ -7,13 +7,18 @@
 #include <CoreAudio/AudioServerPlugIn.h>
 
 #include <atomic>
+#include <cctype>
+#include <cstdio>
 #include <cmath>
+#include <cstdlib>
 #include <cstring>
 #include <fstream>
 #include <limits>
+#include <map>
 #include <memory>
 #include <mutex>
 #include <sstream>
+#include <string>
 #include <thread>
 #include <vector>
 #include <fcntl.h>
@@ -28,14 +33,26 @@ namespace {
 constexpr UInt32 SampleRate = 48000;
 constexpr UInt32 ChannelCount = 1;
 
-// IPC paths
-constexpr const char* SOCKET_PATH = "/tmp/duomic.sock";
-constexpr const char* SHM_PATH = "/tmp/duomic_audio";
-constexpr const char* CONFIG_PATH = "/tmp/duomic_config";
+// IPC paths live in DUOMIC_RUNTIME_DIR (absolute; default /tmp), same as the CLI.
+// The system coreaudiod never has it set, so installed drivers always use /tmp
+// and one driver serves every user; the variable is for hand-started hosts.
+std::string RuntimePath(const char* name) {
+    static const std::string dir = [] {
+        const char* env = std::getenv("DUOMIC_RUNTIME_DIR");
+        return (env && env[0] == '/') ? std::string(env) : std::string("/tmp");
+    }();
+    return dir + "/" + name;
+}
+
+const std::string SOCKET_PATH = RuntimePath("duomic.sock");
+const std::string SHM_PATH = RuntimePath("duomic_audio");
+const std::string CONFIG_PATH = RuntimePath("duomic_config");
 
 constexpr size_t MAX_CHANNELS = 8;
+// Capture devices the CLI can feed at once (the "source" ADD metadata)
+constexpr int MAX_SOURCES = 8;
 constexpr size_t RING_BUFFER_FRAMES = 8192;
-constexpr size_t HEADER_SIZE = 16;
+constexpr size_t HEADER_SIZE = 20;
 
 // Forward declarations
 class DuomicIOHandler;
@@ -44,6 +61,7 @@ class DuomicIOHandler;
 struct DeviceInfo {
     std::string name;
     int channel;
+    std::map<std::string, std::string> metadata;  // From ADD, echoed by LIST
     std::shared_ptr<aspl::Device> device;
     std::shared_ptr<DuomicIOHandler> handler;
 };
@@ -56,23 +74,36 @@ static std::mutex g_devicesMutex;
 static std::atomic<bool> g_running{true};
 static std::thread g_ipcThread;
 
+// Shared memory of source 0 is duomic_audio, source N is duomic_audio_N
+std::string ShmPath(int source) {
+    return source == 0 ? SHM_PATH : SHM_PATH + "_" + std::to_string(source);
+}
+
 // Shared memory accessor
 class SharedAudioBuffer {
 public:
-    SharedAudioBuffer() = default;
+    explicit SharedAudioBuffer(std::string path) : path_(std::move(path)) {}
     ~SharedAudioBuffer() { disconnect(); }
 
     void connect() {
         if (ptr_.load(std::memory_order_acquire)) return;
-        int fd = open(SHM_PATH, O_RDONLY);
+        // Read-write so we can publish readPos; fall back to read-only if the
+        // CLI couldn't make the file writable for us
+        int prot = PROT_READ | PROT_WRITE;
+        int fd = open(path_.c_str(), O_RDWR);
+        if (fd < 0) {
+            prot = PROT_READ;
+            fd = open(path_.c_str(), O_RDONLY);
+        }
         if (fd >= 0) {
             off_t size = lseek(fd, 0, SEEK_END);
             lseek(fd, 0, SEEK_SET);
             if (size > 0) {
                 bufferSize_ = static_cast<size_t>(size);
-                void* mapped = mmap(nullptr, bufferSize_, PROT_READ, MAP_SHARED, fd, 0);
+                void* mapped = mmap(nullptr, bufferSize_, prot, MAP_SHARED, fd, 0);
                 if (mapped != MAP_FAILED) {
                     fd_ = fd;
+                    writable_ = (prot & PROT_WRITE) != 0;
                     ptr_.store(mapped, std::memory_order_release);
                 } else {
                     close(fd);
@@ -116,13 +147,63 @@ public:
         return *static_cast<const uint32_t*>(p);
     }
 
+    // Claim a read position slot for one IO handler (-1 if all are taken)
+    int addReader() {
+        for (int i = 0; i < MAX_READERS; i++) {
+            bool expected = false;
+            if (readerUsed_[i].compare_exchange_strong(expected, true)) {
+                readerPos_[i].store(0, std::memory_order_relaxed);
+                return i;
+            }
+        }
+        return -1;
+    }
+
+    void removeReader(int slot) {
+        if (slot < 0) return;
+        readerPos_[slot].store(0, std::memory_order_relaxed);
+        readerUsed_[slot].store(false, std::memory_order_release);
+    }
+
+    // Record one handler's read position and publish the slowest current one,
+    // so the CLI's fill, latency and overruns follow the mic furthest behind.
+    // Readers a whole ring behind have stopped (their app closed) and don't count.
+    void setReadPos(int slot, uint32_t pos, uint32_t writePos) {
+        void* p = ptr_.load(std::memory_order_acquire);
+        if (!p || !writable_) return;
+        if (slot >= 0) readerPos_[slot].store(pos, std::memory_order_relaxed);
+
+        uint32_t slowest = pos;
+        for (int i = 0; i < MAX_READERS; i++) {
+            if (!readerUsed_[i].load(std::memory_order_acquire)) continue;
+            uint32_t other = readerPos_[i].load(std::memory_order_relaxed);
+            if (other == 0 || writePos - other > RING_BUFFER_FRAMES) continue;
+            if (writePos - other > writePos - slowest) slowest = other;
+        }
+        __atomic_store_n(&static_cast<uint32_t*>(p)[4], slowest, __ATOMIC_RELAXED);
+    }
+
 private:
+    static constexpr int MAX_READERS = 64;
+
+    std::string path_;
     std::atomic<void*> ptr_{nullptr};
+    std::atomic<bool> readerUsed_[MAX_READERS] = {};
+    std::atomic<uint32_t> readerPos_[MAX_READERS] = {};
+    bool writable_ = false;
     int fd_ = -1;
     size_t bufferSize_ = 0;
 };
 
-static SharedAudioBuffer g_sharedBuffer;
+// One buffer per source, created on first use and never freed, so IO
+// handlers can keep a plain pointer. Guarded by g_devicesMutex.
+static std::map<int, std::unique_ptr<SharedAudioBuffer>> g_sharedBuffers;
+
+SharedAudioBuffer* SharedBufferFor(int source) {
+    auto& buffer = g_sharedBuffers[source];
+    if (!buffer) buffer = std::make_unique<SharedAudioBuffer>(ShmPath(source));
+    return buffer.get();
+}
 
 inline SInt16 ConvertToSInt16(float sample) {
     // Clamp to [-1.0, 1.0] range first, then scale to SInt16
@@ -135,10 +216,12 @@ inline SInt16 ConvertToSInt16(float sample) {
 class DuomicIOHandler : public aspl::ControlRequestHandler, public aspl::IORequestHandler
 {
 public:
-    explicit DuomicIOHandler(int channelIndex)
-        : channelIndex_(channelIndex)
+    DuomicIOHandler(int channelIndex, SharedAudioBuffer* buffer)
+        : channelIndex_(channelIndex), buffer_(buffer), slot_(buffer->addReader())
     {}
 
+    ~DuomicIOHandler() { buffer_->removeReader(slot_); }
+
     void OnReadClientInput(const std::shared_ptr<aspl::Client>& client,
         const std::shared_ptr<aspl::Stream>& stream,
         Float64 zeroTimestamp,
@@ -147,24 +230,24 @@ public:
         UInt32 bytesCount) override
     {
         // Try to connect to shared memory (idempotent - returns immediately if already connected)
-        g_sharedBuffer.connect();
+        buffer_->connect();
 
         SInt16* samples = static_cast<SInt16*>(bytes);
         UInt32 numSamples = bytesCount / sizeof(SInt16) / ChannelCount;
 
-        if (!g_sharedBuffer.isActive()) {
+        if (!buffer_->isActive()) {
             std::memset(bytes, 0, bytesCount);
             return;
         }
 
-        const float* shmSamples = g_sharedBuffer.getSamples();
+        const float* shmSamples = buffer_->getSamples();
         if (!shmSamples) {
             std::memset(bytes, 0, bytesCount);
             return;
         }
 
-        uint32_t writePos = g_sharedBuffer.getWritePos();
-        uint32_t inputChannels = g_sharedBuffer.getChannelCount();
+        uint32_t writePos = buffer_->getWritePos();
+        uint32_t inputChannels = buffer_->getChannelCount();
 
         if (channelIndex_ >= (int)inputChannels) {
             std::memset(bytes, 0, bytesCount);
@@ -202,15 +285,29 @@ public:
         }
 
         readPos_ += samplesToRead;
+        buffer_->setReadPos(slot_, readPos_, writePos);
     }
 
 private:
     int channelIndex_;
+    SharedAudioBuffer* buffer_;
+    int slot_;
     uint32_t readPos_ = 0;
 };
 
+// Capture source from ADD metadata (0 without "source"), -1 if out of range
+int SourceOf(const std::map<std::string, std::string>& metadata) {
+    auto it = metadata.find("source");
+    if (it == metadata.end()) return 0;
+    char* end = nullptr;
+    long source = std::strtol(it->second.c_str(), &end, 10);
+    if (it->second.empty() || *end != '\0' || source < 0 || source >= MAX_SOURCES) return -1;
+    return (int)source;
+}
+
 // Add a new virtual device at runtime
-bool AddVirtualDevice(const std::string& name, int channel) {
+bool AddVirtualDevice(const std::string& name, int channel,
+                      const std::map<std::string, std::string>& metadata = {}) {
     std::lock_guard<std::mutex> lock(g_devicesMutex);
 
     // Check if device with this name already exists
@@ -229,13 +326,14 @@ bool AddVirtualDevice(const std::string& name, int channel) {
     auto device = std::make_shared<aspl::Device>(g_context, params);
     device->AddStreamWithControlsAsync(aspl::Direction::Input);
 
-    auto handler = std::make_shared<DuomicIOHandler>(channel);
+    // Extra capture devices write their own shared memory (checked by the callers)
+    auto handler = std::make_shared<DuomicIOHandler>(channel, SharedBufferFor(SourceOf(metadata)));
     device->SetControlHandler(handler);
     device->SetIOHandler(handler);
 
     g_plugin->AddDevice(device);
 
-    g_devices.push_back({name, channel, device, handler});
+    g_devices.push_back({name, channel, metadata, device, handler});
 
     return true;
 }
@@ -259,13 +357,212 @@ std::string ListDevices() {
     std::lock_guard<std::mutex> lock(g_devicesMutex);
     std::stringstream ss;
     for (const auto& dev : g_devices) {
-        ss << dev.name << ":" << dev.channel << "\n";
+        ss << dev.name << ":" << dev.channel;
+        for (const auto& [key, value] : dev.metadata) ss << ":" << key << "=" << value;
+        ss << "\n";
     }
     return ss.str();
 }
 
+// ---- JSON protocol (advertised as "json" in CAPS) ----
+// One object per line. Requests are flat apart from a string-valued
+// "metadata" object, so this reads exactly that and nothing more.
+
+struct JsonRequest {
+    std::map<std::string, std::string> strings;
+    std::map<std::string, long> numbers;
+    std::map<std::string, std::string> metadata;
+};
+
+void SkipSpace(const std::string& s, size_t& i) {
+    while (i < s.size() && isspace((unsigned char)s[i])) i++;
+}
+
+void AppendUtf8(std::string& out, unsigned code) {
+    if (code < 0x80) {
+        out += (char)code;
+    } else if (code < 0x800) {
+        out += (char)(0xC0 | (code >> 6));
+        out += (char)(0x80 | (code & 0x3F));
+    } else {
+        out += (char)(0xE0 | (code >> 12));
+        out += (char)(0x80 | ((code >> 6) & 0x3F));
+        out += (char)(0x80 | (code & 0x3F));
+    }
+}
+
+bool ParseJsonString(const std::string& s, size_t& i, std::string& out) {
+    if (i >= s.size() || s[i] != '"') return false;
+    i++;
+    out.clear();
+    while (i < s.size()) {
+        char c = s[i++];
+        if (c == '"') return true;
+        if (c != '\\') {
+            out += c;
+            continue;
+        }
+        if (i >= s.size()) return false;
+        char e = s[i++];
+        switch (e) {
+            case '"': case '\\': case '/': out += e; break;
+            case 'b': out += '\b'; break;
+            case 'f': out += '\f'; break;
+            case 'n': out += '\n'; break;
+            case 'r': out += '\r'; break;
+            case 't': out += '\t'; break;
+            case 'u': {
+                if (i + 4 > s.size()) return false;
+                unsigned code = (unsigned)std::strtoul(s.substr(i, 4).c_str(), nullptr, 16);
+                i += 4;
+                AppendUtf8(out, code);
+                break;
+            }
+            default: return false;
+        }
+    }
+    return false;
+}
+
+// Parse `{"key": "string" | integer | {"key": "string", ...}, ...}`
+bool ParseJsonRequest(const std::string& s, JsonRequest& request) {
+    size_t i = 0;
+    SkipSpace(s, i);
+    if (i >= s.size() || s[i++] != '{') return false;
+    SkipSpace(s, i);
+    if (i < s.size() && s[i] == '}') return true;
+
+    while (i < s.size()) {
+        std::string key;
+        SkipSpace(s, i);
+        if (!ParseJsonString(s, i, key)) return false;
+        SkipSpace(s, i);
+        if (i >= s.size() || s[i++] != ':') return false;
+        SkipSpace(s, i);
+        if (i >= s.size()) return false;
+
+        if (s[i] == '"') {
+            std::string value;
+            if (!ParseJsonString(s, i, value)) return false;
+            request.strings[key] = value;
+        } else if (s[i] == '{' && key == "metadata") {
+            i++;
+            SkipSpace(s, i);
+            while (i < s.size() && s[i] != '}') {
+                std::string metaKey, metaValue;
+                if (!ParseJsonString(s, i, metaKey)) return false;
+                SkipSpace(s, i);
+                if (i >= s.size() || s[i++] != ':') return false;
+                SkipSpace(s, i);
+                if (!ParseJsonString(s, i, metaValue)) return false;
+                request.metadata[metaKey] = metaValue;
+                SkipSpace(s, i);
+                if (i < s.size() && s[i] == ',') i++;
+                SkipSpace(s, i);
+            }
+            if (i >= s.size()) return false;
+            i++;
+        } else {
+            char* end = nullptr;
+            long value = std::strtol(s.c_str() + i, &end, 10);
+            if (end == s.c_str() + i) return false;
+            i = end - s.c_str();
+            request.numbers[key] = value;
+        }
+
+        SkipSpace(s, i);
+        if (i >= s.size()) return false;
+        if (s[i] == '}') return true;
+        if (s[i++] != ',') return false;
+    }
+    return false;
+}
+
+std::string JsonEscape(const std::string& s) {
+    std::string out = "\"";
+    for (char c : s) {
+        switch (c) {
+            case '"': out += "\\\""; break;
+            case '\\': out += "\\\\"; break;
+            case '\n': out += "\\n"; break;
+            case '\r': out += "\\r"; break;
+            case '\t': out += "\\t"; break;
+            default:
+                if ((unsigned char)c < 0x20) {
+                    char escaped[8];
+                    snprintf(escaped, sizeof(escaped), "\\u%04x", (unsigned char)c);
+                    out += escaped;
+                } else {
+                    out += c;
+                }
+        }
+    }
+    return out + "\"";
+}
+
+std::string JsonError(int code, const std::string& message) {
+    return "{\"ok\":false,\"error\":" + JsonEscape(message) + ",\"code\":" +
+           std::to_string(code) + "}\n";
+}
+
+std::string ListDevicesJson() {
+    std::lock_guard<std::mutex> lock(g_devicesMutex);
+    std::string out = "{\"ok\":true,\"devices\":[";
+    for (size_t d = 0; d < g_devices.size(); d++) {
+        const auto& dev = g_devices[d];
+        if (d > 0) out += ",";
+        out += "{\"name\":" + JsonEscape(dev.name) + ",\"channel\":" + std::to_string(dev.channel);
+        if (!dev.metadata.empty()) {
+            out += ",\"metadata\":{";
+            bool first = true;
+            for (const auto& [key, value] : dev.metadata) {
+                if (!first) out += ",";
+                out += JsonEscape(key) + ":" + JsonEscape(value);
+                first = false;
+            }
+            out += "}";
+        }
+        out += "}";
+    }
+    return out + "]}\n";
+}
+
+std::string HandleJsonCommand(const std::string& line) {
+    JsonRequest request;
+    if (!ParseJsonRequest(line, request)) return JsonError(400, "Malformed JSON request");
+
+    const std::string method = request.strings["method"];
+    if (method == "add") {
+        const std::string name = request.strings["name"];
+        if (name.empty()) return JsonError(400, "Invalid name");
+        auto channel = request.numbers.find("channel");
+        if (channel == request.numbers.end() || channel->second < 0 ||
+            channel->second >= (long)MAX_CHANNELS) {
+            return JsonError(400, "Invalid channel");
+        }
+        if (SourceOf(request.metadata) < 0) return JsonError(400, "Invalid source");
+        if (!AddVirtualDevice(name, (int)channel->second, request.metadata)) {
+            return JsonError(409, "Device already exists");
+        }
+        return "{\"ok\":true}\n";
+    }
+    if (method == "remove") {
+        const std::string name = request.strings["name"];
+        if (name.empty()) return JsonError(400, "Invalid name");
+        if (!RemoveVirtualDevice(name)) return JsonError(404, "Device not found");
+        return "{\"ok\":true}\n";
+    }
+    if (method == "list") return ListDevicesJson();
+    if (method == "ping") return "{\"ok\":true}\n";
+
+    return JsonError(400, "Unknown method");
+}
+
 // Handle IPC command
 std::string HandleCommand(const std::string& cmd) {
+    size_t start = cmd.find_first_not_of(" \t\r\n");
+    if (start != std::string::npos && cmd[start] == '{') return HandleJsonCommand(cmd);
+
     std::istringstream iss(cmd);
     std::string command;
     iss >> command;
@@ -276,10 +573,26 @@ std::string HandleCommand(const std::string& cmd) {
         std::getline(iss >> std::ws, name, ':');
         iss >> channel;
 
+        // Optional ":key=value[:key=value...]" after the channel
+        std::map<std::string, std::string> metadata;
+        if (iss.peek() == ':') {
+            iss.get();
+            std::string fields;
+            std::getline(iss, fields);
+            while (!fields.empty() && isspace((unsigned char)fields.back())) fields.pop_back();
+            std::istringstream fieldStream(fields);
+            std::string field;
+            while (std::getline(fieldStream, field, ':')) {
+                size_t eq = field.find('=');
+                if (eq != std::string::npos) metadata[field.substr(0, eq)] = field.substr(eq + 1);
+            }
+        }
+
         if (name.empty()) return "ERROR:Invalid name\n";
         if (channel < 0 || channel >= (int)MAX_CHANNELS) return "ERROR:Invalid channel\n";
+        if (SourceOf(metadata) < 0) return "ERROR:Invalid source\n";
 
-        if (AddVirtualDevice(name, channel)) {
+        if (AddVirtualDevice(name, channel, metadata)) {
             return "OK:Device added\n";
         } else {
             return "ERROR:Device already exists\n";
@@ -303,6 +616,9 @@ std::string HandleCommand(const std::string& cmd) {
     else if (command == "PING") {
         return "PONG\n";
     }
+    else if (command == "CAPS") {
+        return "OK:meta,json,sources\n";
+    }
 
     return "ERROR:Unknown command\n";
 }
@@ -310,7 +626,7 @@ std::string HandleCommand(const std::string& cmd) {
 // IPC thread function
 void IPCThread() {
     // Remove old socket
-    unlink(SOCKET_PATH);
+    unlink(SOCKET_PATH.c_str());
 
     // Create socket
     int serverFd = socket(AF_UNIX, SOCK_STREAM, 0);
@@ -319,7 +635,7 @@ void IPCThread() {
     struct sockaddr_un addr;
     memset(&addr, 0, sizeof(addr));
     addr.sun_family = AF_UNIX;
-    strncpy(addr.sun_path, SOCKET_PATH, sizeof(addr.sun_path) - 1);
+    strncpy(addr.sun_path, SOCKET_PATH.c_str(), sizeof(addr.sun_path) - 1);
 
     if (bind(serverFd, (struct sockaddr*)&addr, sizeof(addr)) < 0) {
         close(serverFd);
@@ -327,7 +643,7 @@ void IPCThread() {
     }
 
     // Make socket accessible
-    chmod(SOCKET_PATH, 0666);
+    chmod(SOCKET_PATH.c_str(), 0666);
 
     if (listen(serverFd, 5) < 0) {
         close(serverFd);
@@ -365,7 +681,7 @@ void IPCThread() {
     }
 
     close(serverFd);
-    unlink(SOCKET_PATH);
+    unlink(SOCKET_PATH.c_str());
 }
 
 // Read initial config file
@@ -407,7 +723,10 @@ std::shared_ptr<aspl::Driver> CreateDuomicDriver()
     g_plugin = std::make_shared<aspl::Plugin>(g_context);
 
     // Connect to shared memory
-    g_sharedBuffer.connect();
+    {
+        std::lock_guard<std::mutex> lock(g_devicesMutex);
+        SharedBufferFor(0)->connect();
+    }
 
     // Read initial config and create devices
     auto config = ReadConfig();
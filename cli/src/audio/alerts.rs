@@ -0,0 +1,133 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use crate::config::AlertsConfig;
+
+/// The same alert isn't repeated within this window (a sustained clip alerts once)
+pub const ALERT_REPEAT: Duration = Duration::from_secs(10);
+
+/// No new audio for this long counts as a disconnect (default for `[meters] silence_timeout_s`)
+pub const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Something worth interrupting an unattended recording for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alert {
+    Clip,
+    Disconnect,
+}
+
+/// Turns capture counters into debounced alerts
+///
+/// Feed it the capture's clip count and write position on every UI tick (or
+/// any periodic check in a headless caller). A disconnect is a write position
+/// that stops moving; cpal doesn't report unplugged devices reliably.
+#[derive(Debug)]
+pub struct AlertMonitor {
+    config: AlertsConfig,
+    silence_timeout: Duration,
+    clipped: u64,
+    write_pos: u32,
+    write_changed: Instant,
+    stalled: bool,
+    last_clip: Option<Instant>,
+    last_disconnect: Option<Instant>,
+}
+
+impl AlertMonitor {
+    pub fn new(config: &AlertsConfig, now: Instant) -> Self {
+        Self {
+            config: config.clone(),
+            silence_timeout: DISCONNECT_TIMEOUT,
+            clipped: 0,
+            write_pos: 0,
+            write_changed: now,
+            stalled: false,
+            last_clip: None,
+            last_disconnect: None,
+        }
+    }
+
+    /// Count a disconnect after `timeout` without new audio instead of [`DISCONNECT_TIMEOUT`]
+    pub fn silence_timeout(mut self, timeout: Duration) -> Self {
+        self.silence_timeout = timeout;
+        self
+    }
+
+    /// Whether any alert is enabled
+    pub fn enabled(&self) -> bool {
+        self.config.on_clip || self.config.on_disconnect
+    }
+
+    /// Check the latest counters; returns an alert to raise, logged at WARN
+    pub fn check(&mut self, clipped: u64, write_pos: u32, now: Instant) -> Option<Alert> {
+        let new_clips = clipped > self.clipped;
+        self.clipped = clipped;
+
+        if write_pos != self.write_pos {
+            self.write_pos = write_pos;
+            self.write_changed = now;
+            self.stalled = false;
+        }
+        let stalled = now.duration_since(self.write_changed) >= self.silence_timeout;
+        // Only the start of a stall alerts, however long it lasts
+        let disconnected = stalled && !self.stalled;
+        self.stalled = stalled;
+
+        let due =
+            |last: Option<Instant>| last.is_none_or(|t| now.duration_since(t) >= ALERT_REPEAT);
+
+        if self.config.on_disconnect && disconnected && due(self.last_disconnect) {
+            self.last_disconnect = Some(now);
+            tracing::warn!("No audio from the device for {:?}", self.silence_timeout);
+            return Some(Alert::Disconnect);
+        }
+        if self.config.on_clip && new_clips && due(self.last_clip) {
+            self.last_clip = Some(now);
+            tracing::warn!("Clipping detected");
+            return Some(Alert::Clip);
+        }
+        None
+    }
+}
+
+/// Ring the bell on the controlling terminal, if there is one
+pub fn ring_bell() {
+    if let Ok(mut tty) = OpenOptions::new().write(true).open("/dev/tty") {
+        let _ = tty.write_all(b"\x07");
+        let _ = tty.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alerts_are_debounced() {
+        let config = AlertsConfig {
+            on_clip: true,
+            on_disconnect: true,
+            flash: true,
+        };
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut monitor = AlertMonitor::new(&config, start);
+
+        assert_eq!(monitor.check(0, 100, at(50)), None);
+        assert_eq!(monitor.check(3, 200, at(100)), Some(Alert::Clip));
+        // Sustained clipping: no repeat within ALERT_REPEAT
+        assert_eq!(monitor.check(9, 300, at(150)), None);
+        assert_eq!(monitor.check(12, 400, at(10_200)), Some(Alert::Clip));
+
+        // Write position stops moving
+        assert_eq!(monitor.check(12, 400, at(11_000)), None);
+        assert_eq!(monitor.check(12, 400, at(12_300)), Some(Alert::Disconnect));
+        assert_eq!(monitor.check(12, 400, at(13_000)), None);
+
+        // Disabled alerts never fire
+        let mut quiet = AlertMonitor::new(&AlertsConfig::default(), start);
+        assert!(!quiet.enabled());
+        assert_eq!(quiet.check(5, 0, at(5_000)), None);
+    }
+}
@@ -0,0 +1,277 @@
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{FromSample, Sample, SampleFormat, SizedSample, StreamConfig};
+use std::any::Any;
+
+use super::to_f32;
+use crate::DuomicError;
+
+/// Callback receiving interleaved f32 sample blocks from a backend
+pub type BlockCallback = Box<dyn FnMut(&[f32]) + Send + 'static>;
+
+/// Callback receiving stream errors, outside the sample callback
+pub type FaultCallback = Box<dyn FnMut(StreamFault) + Send + 'static>;
+
+/// Stream error reported by a backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamFault {
+    /// Over/underrun or other transient backend error; blocks keep coming
+    Xrun(String),
+    /// The device went away; no more blocks will arrive
+    Disconnected(String),
+}
+
+/// Keeps a backend delivering blocks; delivery stops when dropped
+pub struct BackendStream {
+    _inner: Box<dyn Any>,
+}
+
+impl BackendStream {
+    fn new<S: 'static>(inner: S) -> Self {
+        Self {
+            _inner: Box::new(inner),
+        }
+    }
+}
+
+/// Source of interleaved sample blocks for `AudioCapture`
+///
+/// Blocks may end mid-frame; the capture carries partial frames over.
+pub trait CaptureBackend {
+    /// Channels per interleaved frame
+    fn channels(&self) -> u16;
+
+    /// Sample rate in Hz
+    fn sample_rate(&self) -> u32;
+
+    /// Start delivering blocks to `on_block` and stream errors to `on_fault`
+    fn start(&self, on_block: BlockCallback, on_fault: FaultCallback) -> Result<BackendStream>;
+}
+
+/// Input stream on a cpal device, converted to f32
+pub struct CpalBackend {
+    device: cpal::Device,
+    config: StreamConfig,
+    sample_format: SampleFormat,
+    /// Channels passed on per frame when the stream carries more than wanted
+    keep_channels: Option<u16>,
+}
+
+impl CpalBackend {
+    /// Use the device's default input config
+    pub fn new(device: &cpal::Device) -> Result<Self> {
+        let config = device
+            .default_input_config()
+            .context("Failed to get default input config")?;
+
+        Ok(Self {
+            device: device.clone(),
+            sample_format: config.sample_format(),
+            config: config.into(),
+            keep_channels: None,
+        })
+    }
+
+    /// Capture at most `max` channels (`[device] max_channels`)
+    ///
+    /// Opens a `max`-channel stream at the default rate when the device
+    /// offers one; otherwise keeps the full stream and passes on only the
+    /// first `max` channels of each frame.
+    pub fn limit_channels(mut self, max: Option<u16>) -> Self {
+        let Some(max) = max.filter(|&max| max > 0 && max < self.config.channels) else {
+            return self;
+        };
+
+        let rate = self.config.sample_rate;
+        let native = self
+            .device
+            .supported_input_configs()
+            .ok()
+            .and_then(|configs| {
+                configs
+                    .filter(|c| {
+                        c.channels() == max
+                            && c.min_sample_rate() <= rate
+                            && rate <= c.max_sample_rate()
+                    })
+                    // Prefer the default sample format
+                    .min_by_key(|c| c.sample_format() != self.sample_format)
+            });
+        match native {
+            Some(range) => {
+                self.sample_format = range.sample_format();
+                self.config.channels = max;
+            }
+            None => {
+                tracing::warn!(
+                    "Device has no {}-channel input config, capturing all {} and keeping the first {}",
+                    max,
+                    self.config.channels,
+                    max
+                );
+                self.keep_channels = Some(max);
+            }
+        }
+        self
+    }
+
+    /// Device sample format before conversion
+    pub fn sample_format(&self) -> SampleFormat {
+        self.sample_format
+    }
+
+    fn build_stream<T>(
+        &self,
+        mut on_block: BlockCallback,
+        mut on_fault: FaultCallback,
+    ) -> Result<cpal::Stream>
+    where
+        T: Sample + SizedSample,
+        f32: FromSample<T>,
+    {
+        let err_fn = move |err: cpal::StreamError| {
+            // Note: This is an error callback, not the audio callback
+            // Logging here is acceptable as errors are rare
+            tracing::warn!("Audio stream error: {}", err);
+            on_fault(match err {
+                cpal::StreamError::DeviceNotAvailable => StreamFault::Disconnected(err.to_string()),
+                cpal::StreamError::BackendSpecific { .. } => StreamFault::Xrun(err.to_string()),
+            });
+        };
+
+        // Pre-allocate conversion buffer
+        // Typical callback size is 256-1024 frames, we allocate for worst case
+        let mut converted: Vec<f32> = Vec::with_capacity(4096 * self.config.channels as usize);
+        let mut trim = self
+            .keep_channels
+            .map(|keep| FrameTrim::new(self.config.channels, keep));
+
+        let stream = self
+            .device
+            .build_input_stream(
+                &self.config,
+                move |data: &[T], _: &cpal::InputCallbackInfo| {
+                    converted.clear();
+                    let samples = data.iter().map(|s| to_f32(*s));
+                    match trim.as_mut() {
+                        Some(trim) => trim.extend(&mut converted, samples),
+                        None => converted.extend(samples),
+                    }
+                    on_block(&converted);
+                },
+                err_fn,
+                None,
+            )
+            .context("Failed to build input stream")?;
+
+        Ok(stream)
+    }
+}
+
+impl CaptureBackend for CpalBackend {
+    fn channels(&self) -> u16 {
+        self.keep_channels.unwrap_or(self.config.channels)
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.config.sample_rate.0
+    }
+
+    fn start(&self, on_block: BlockCallback, on_fault: FaultCallback) -> Result<BackendStream> {
+        let stream = match self.sample_format {
+            SampleFormat::F32 => self.build_stream::<f32>(on_block, on_fault)?,
+            SampleFormat::I16 => self.build_stream::<i16>(on_block, on_fault)?,
+            SampleFormat::U16 => self.build_stream::<u16>(on_block, on_fault)?,
+            format => return Err(DuomicError::Unsupported(format).into()),
+        };
+
+        stream.play().context("Failed to start audio stream")?;
+        Ok(BackendStream::new(stream))
+    }
+}
+
+/// Drops the trailing channels of each interleaved frame
+///
+/// Tracks the position within the frame across blocks, so blocks ending
+/// mid-frame are handled.
+#[derive(Debug, Clone)]
+pub struct FrameTrim {
+    channels: usize,
+    keep: usize,
+    position: usize,
+}
+
+impl FrameTrim {
+    /// Keep the first `keep` of `channels` channels
+    pub fn new(channels: u16, keep: u16) -> Self {
+        Self {
+            channels: channels.max(1) as usize,
+            keep: keep as usize,
+            position: 0,
+        }
+    }
+
+    /// Append the kept samples of `samples` to `out`
+    pub fn extend(&mut self, out: &mut Vec<f32>, samples: impl Iterator<Item = f32>) {
+        for sample in samples {
+            if self.position < self.keep {
+                out.push(sample);
+            }
+            self.position = (self.position + 1) % self.channels;
+        }
+    }
+}
+
+/// Replays a fixed interleaved buffer in `block_size`-sample blocks (for tests)
+///
+/// Delivery happens synchronously inside `start`, so everything has been
+/// processed by the time it returns.
+#[derive(Debug, Clone)]
+pub struct MockBackend {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub samples: Vec<f32>,
+    pub block_size: usize,
+    /// Reported after the blocks
+    pub faults: Vec<StreamFault>,
+}
+
+impl CaptureBackend for MockBackend {
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn start(
+        &self,
+        mut on_block: BlockCallback,
+        mut on_fault: FaultCallback,
+    ) -> Result<BackendStream> {
+        for block in self.samples.chunks(self.block_size.max(1)) {
+            on_block(block);
+        }
+        for fault in &self.faults {
+            on_fault(fault.clone());
+        }
+        Ok(BackendStream::new(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_trim_across_blocks() {
+        // 3 channels, keep 2; blocks split frames at odd places
+        let mut trim = FrameTrim::new(3, 2);
+        let mut out = Vec::new();
+        trim.extend(&mut out, [0.0, 1.0, 2.0, 3.0].into_iter());
+        trim.extend(&mut out, [4.0].into_iter());
+        trim.extend(&mut out, [5.0, 6.0, 7.0, 8.0].into_iter());
+        assert_eq!(out, [0.0, 1.0, 3.0, 4.0, 6.0, 7.0]);
+    }
+}
@@ -1,23 +1,569 @@
-use anyhow::{Context, Result};
-use cpal::traits::{DeviceTrait, StreamTrait};
-use cpal::{SampleFormat, StreamConfig};
+use anyhow::{bail, Context, Result};
+use cpal::{FromSample, Sample};
 use crossbeam_channel::{bounded, Receiver, Sender};
-use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crate::ipc::SharedAudioBuffer;
+use super::{
+    BackendStream, CaptureBackend, CpalBackend, GateSettings, HighPass, NoiseGate, StreamFault,
+};
+use crate::ipc::{ReaderWatch, SharedAudioBuffer};
+use crate::DuomicError;
 
-/// Maximum supported channels (matches driver)
-const MAX_CHANNELS: usize = 8;
+/// Frames per peak update sent to the UI
+const PEAK_INTERVAL_FRAMES: usize = 100;
+
+/// Peak updates queued for the UI before new ones are dropped
+const PEAK_QUEUE: usize = 16;
+
+/// How long `restart` waits for the stopped stream to hand back shared memory
+const PROCESSOR_RETURN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Highest linear clip threshold, so full-scale integer samples (32767/32768) still count
+const MAX_CLIP_LEVEL: f32 = 0.999;
+
+/// Time constant of the running sums behind the correlation meter
+const CORRELATION_TIME: Duration = Duration::from_millis(300);
+
+/// Mean square below which a channel counts as silent for correlation (-80 dBFS)
+const CORRELATION_SILENCE: f32 = 1e-8;
+
+/// Gain, mute, polarity and high-pass for one channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MixSettings {
+    pub gain_db: f32,
+    pub muted: bool,
+    pub invert: bool,
+    /// High-pass cutoff in Hz (applied before the gate and meters)
+    pub highpass_hz: Option<f32>,
+}
+
+impl Default for MixSettings {
+    fn default() -> Self {
+        Self {
+            gain_db: 0.0,
+            muted: false,
+            invert: false,
+            highpass_hz: None,
+        }
+    }
+}
+
+impl MixSettings {
+    /// Linear factor applied to each sample
+    pub fn factor(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else if self.invert {
+            -db_to_amplitude(self.gain_db)
+        } else {
+            db_to_amplitude(self.gain_db)
+        }
+    }
+}
+
+/// Live per-channel mix, shared between the UI and the capture callback
+///
+/// Each channel's settings are stored as atomics so the UI can change them
+/// while capturing without locking the audio thread.
+#[derive(Debug)]
+pub struct ChannelControls {
+    gain_db: Vec<AtomicU32>,
+    muted: Vec<AtomicBool>,
+    invert: Vec<AtomicBool>,
+    // Cutoff as f32 bits, 0.0 = off
+    highpass_hz: Vec<AtomicU32>,
+}
+
+impl ChannelControls {
+    fn new(channels: usize, initial: &[MixSettings]) -> Self {
+        let setting = |ch: usize| initial.get(ch).copied().unwrap_or_default();
+        Self {
+            gain_db: (0..channels)
+                .map(|ch| AtomicU32::new(setting(ch).gain_db.to_bits()))
+                .collect(),
+            muted: (0..channels)
+                .map(|ch| AtomicBool::new(setting(ch).muted))
+                .collect(),
+            invert: (0..channels)
+                .map(|ch| AtomicBool::new(setting(ch).invert))
+                .collect(),
+            highpass_hz: (0..channels)
+                .map(|ch| AtomicU32::new(setting(ch).highpass_hz.unwrap_or(0.0).to_bits()))
+                .collect(),
+        }
+    }
+
+    /// Current settings of `channel` (defaults if out of range)
+    pub fn get(&self, channel: usize) -> MixSettings {
+        match (
+            self.gain_db.get(channel),
+            self.muted.get(channel),
+            self.invert.get(channel),
+            self.highpass_hz.get(channel),
+        ) {
+            (Some(gain_db), Some(muted), Some(invert), Some(highpass_hz)) => MixSettings {
+                gain_db: f32::from_bits(gain_db.load(Ordering::Relaxed)),
+                muted: muted.load(Ordering::Relaxed),
+                invert: invert.load(Ordering::Relaxed),
+                highpass_hz: Some(f32::from_bits(highpass_hz.load(Ordering::Relaxed)))
+                    .filter(|&hz| hz > 0.0),
+            },
+            _ => MixSettings::default(),
+        }
+    }
+
+    /// Change `channel`'s settings; picked up by the next callback
+    pub fn set(&self, channel: usize, settings: MixSettings) {
+        if let (Some(gain_db), Some(muted), Some(invert), Some(highpass_hz)) = (
+            self.gain_db.get(channel),
+            self.muted.get(channel),
+            self.invert.get(channel),
+            self.highpass_hz.get(channel),
+        ) {
+            gain_db.store(settings.gain_db.to_bits(), Ordering::Relaxed);
+            muted.store(settings.muted, Ordering::Relaxed);
+            invert.store(settings.invert, Ordering::Relaxed);
+            highpass_hz.store(
+                settings.highpass_hz.unwrap_or(0.0).to_bits(),
+                Ordering::Relaxed,
+            );
+        }
+    }
+}
+
+/// RMS of each channel's latest callback block, after gain/mute/invert
+///
+/// Blocks are short (~5-20 ms), so callers average several readings for a
+/// steady value (see the dashboard's gain suggestion).
+#[derive(Debug)]
+pub struct RmsLevels {
+    // Mean square as f32 bits
+    mean_square: Vec<AtomicU32>,
+}
+
+impl RmsLevels {
+    fn new(channels: usize) -> Self {
+        Self {
+            mean_square: (0..channels).map(|_| AtomicU32::new(0)).collect(),
+        }
+    }
+
+    /// Linear RMS of `channel` (0.0 if out of range)
+    pub fn get(&self, channel: usize) -> f32 {
+        self.mean_square(channel).sqrt()
+    }
+
+    /// Mean square of `channel` (averages of these give a true RMS over time)
+    pub fn mean_square(&self, channel: usize) -> f32 {
+        self.mean_square
+            .get(channel)
+            .map_or(0.0, |ms| f32::from_bits(ms.load(Ordering::Relaxed)))
+    }
+
+    fn set(&self, channel: usize, mean_square: f32) {
+        if let Some(ms) = self.mean_square.get(channel) {
+            ms.store(mean_square.to_bits(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// Per-channel processing applied before samples reach shared memory
+#[derive(Debug, Clone)]
+pub struct CaptureOptions {
+    /// Noise gate per device channel (index = channel, `None` = no gate)
+    pub gates: Vec<Option<GateSettings>>,
+    /// Initial gain/mute/invert per device channel (missing = defaults)
+    pub mix: Vec<MixSettings>,
+    /// Linear level at or above which a sample counts as clipped (see `clip_threshold`)
+    pub clip_threshold: f32,
+    /// Device channels of a stereo pair to correlate (sent with each peak update)
+    pub correlation: Option<(usize, usize)>,
+    /// Record callback timing (see [`AudioCapture::timing`], for `duomic bench`)
+    pub measure_timing: bool,
+    /// Capture only the first N device channels (`[device] max_channels`)
+    pub max_channels: Option<u16>,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            gates: Vec::new(),
+            mix: Vec::new(),
+            clip_threshold: clip_threshold(0.0),
+            correlation: None,
+            measure_timing: false,
+            max_channels: None,
+        }
+    }
+}
+
+/// Running correlation coefficient of two channels, -1 (out of phase) to +1 (mono)
+///
+/// Exponentially weighted averages of L·R, L² and R² over [`CORRELATION_TIME`],
+/// updated per frame; the coefficient is `LR / sqrt(L² · R²)`.
+#[derive(Debug, Clone)]
+struct Correlator {
+    left: usize,
+    right: usize,
+    // Weight of each new frame
+    alpha: f32,
+    product: f32,
+    left_power: f32,
+    right_power: f32,
+}
+
+impl Correlator {
+    fn new((left, right): (usize, usize), sample_rate: u32) -> Self {
+        let frames = CORRELATION_TIME.as_secs_f32() * sample_rate.max(1) as f32;
+        Self {
+            left,
+            right,
+            alpha: 1.0 - (-1.0 / frames).exp(),
+            product: 0.0,
+            left_power: 0.0,
+            right_power: 0.0,
+        }
+    }
+
+    fn push(&mut self, frame: &[f32]) {
+        let (l, r) = (frame[self.left], frame[self.right]);
+        self.product += (l * r - self.product) * self.alpha;
+        self.left_power += (l * l - self.left_power) * self.alpha;
+        self.right_power += (r * r - self.right_power) * self.alpha;
+    }
+
+    /// Current coefficient, or `None` while either channel is silent
+    fn value(&self) -> Option<f32> {
+        if self.left_power < CORRELATION_SILENCE || self.right_power < CORRELATION_SILENCE {
+            return None;
+        }
+        Some((self.product / (self.left_power * self.right_power).sqrt()).clamp(-1.0, 1.0))
+    }
+}
+
+/// Overrun counters updated by the capture callback
+#[derive(Debug, Default)]
+pub struct CaptureStats {
+    dropped_peaks: AtomicU64,
+    write_errors: AtomicU64,
+    clipped_samples: AtomicU64,
+    xruns: AtomicU64,
+    shm_overruns: AtomicU64,
+    unread: AtomicBool,
+    device_lost: AtomicBool,
+    last_error: Mutex<Option<String>>,
+}
+
+impl CaptureStats {
+    /// Peak updates discarded because the UI channel was full
+    pub fn dropped_peaks(&self) -> u64 {
+        self.dropped_peaks.load(Ordering::Relaxed)
+    }
+
+    /// Callbacks whose shared memory write failed
+    pub fn write_errors(&self) -> u64 {
+        self.write_errors.load(Ordering::Relaxed)
+    }
+
+    /// Samples (any channel) at or above the clip threshold
+    pub fn clipped_samples(&self) -> u64 {
+        self.clipped_samples.load(Ordering::Relaxed)
+    }
+
+    /// Transient stream errors (over/underruns) reported by the backend
+    pub fn xruns(&self) -> u64 {
+        self.xruns.load(Ordering::Relaxed)
+    }
+
+    /// Times the driver fell a whole ring buffer behind and skipped audio
+    ///
+    /// Each one is a gap in what apps hear from the virtual mics.
+    pub fn shm_overruns(&self) -> u64 {
+        self.shm_overruns.load(Ordering::Relaxed)
+    }
+
+    /// Whether the driver stopped reading and unread audio is being overwritten
+    ///
+    /// Usual when no app has a virtual mic open; the audio is discarded.
+    pub fn unread(&self) -> bool {
+        self.unread.load(Ordering::Relaxed)
+    }
+
+    /// Whether the backend reported the device gone since the stream started
+    pub fn device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    /// Message of the most recent stream error
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().ok().and_then(|e| e.clone())
+    }
+
+    fn record_fault(&self, fault: StreamFault) {
+        let message = match fault {
+            StreamFault::Xrun(message) => {
+                self.xruns.fetch_add(1, Ordering::Relaxed);
+                message
+            }
+            StreamFault::Disconnected(message) => {
+                self.device_lost.store(true, Ordering::Relaxed);
+                message
+            }
+        };
+        if let Ok(mut last) = self.last_error.lock() {
+            *last = Some(message);
+        }
+    }
+}
+
+/// Callback timing accumulated by the capture callback when measuring
+///
+/// Plain atomic counters and running sums, so recording stays lock- and
+/// allocation-free; [`CallbackTiming::summary`] turns them into statistics.
+/// Intervals are kept in microseconds so their squares don't overflow.
+#[derive(Debug)]
+pub struct CallbackTiming {
+    callbacks: AtomicU64,
+    frames: AtomicU64,
+    intervals: AtomicU64,
+    interval_min_us: AtomicU64,
+    interval_max_us: AtomicU64,
+    interval_sum_us: AtomicU64,
+    interval_square_sum: AtomicU64,
+    process_sum_ns: AtomicU64,
+    process_max_ns: AtomicU64,
+    write_sum_ns: AtomicU64,
+    write_max_ns: AtomicU64,
+}
+
+/// Statistics over the callbacks seen so far
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimingSummary {
+    pub callbacks: u64,
+    /// Mean frames per callback
+    pub block_frames: f64,
+    /// Time between consecutive callbacks (zero until there are two)
+    pub interval_min: Duration,
+    pub interval_max: Duration,
+    pub interval_mean: Duration,
+    pub interval_stddev: Duration,
+    /// Time spent in the whole callback (gates, meters and the write)
+    pub process_mean: Duration,
+    pub process_max: Duration,
+    /// Time spent writing to shared memory
+    pub write_mean: Duration,
+    pub write_max: Duration,
+}
+
+impl Default for CallbackTiming {
+    fn default() -> Self {
+        Self {
+            callbacks: AtomicU64::new(0),
+            frames: AtomicU64::new(0),
+            intervals: AtomicU64::new(0),
+            interval_min_us: AtomicU64::new(u64::MAX),
+            interval_max_us: AtomicU64::new(0),
+            interval_sum_us: AtomicU64::new(0),
+            interval_square_sum: AtomicU64::new(0),
+            process_sum_ns: AtomicU64::new(0),
+            process_max_ns: AtomicU64::new(0),
+            write_sum_ns: AtomicU64::new(0),
+            write_max_ns: AtomicU64::new(0),
+        }
+    }
+}
+
+impl CallbackTiming {
+    fn record_interval(&self, interval: Duration) {
+        let us = interval.as_micros() as u64;
+        self.intervals.fetch_add(1, Ordering::Relaxed);
+        self.interval_min_us.fetch_min(us, Ordering::Relaxed);
+        self.interval_max_us.fetch_max(us, Ordering::Relaxed);
+        self.interval_sum_us.fetch_add(us, Ordering::Relaxed);
+        self.interval_square_sum
+            .fetch_add(us.saturating_mul(us), Ordering::Relaxed);
+    }
+
+    fn record_block(&self, frames: usize, process: Duration, write: Duration) {
+        let (process, write) = (process.as_nanos() as u64, write.as_nanos() as u64);
+        self.callbacks.fetch_add(1, Ordering::Relaxed);
+        self.frames.fetch_add(frames as u64, Ordering::Relaxed);
+        self.process_sum_ns.fetch_add(process, Ordering::Relaxed);
+        self.process_max_ns.fetch_max(process, Ordering::Relaxed);
+        self.write_sum_ns.fetch_add(write, Ordering::Relaxed);
+        self.write_max_ns.fetch_max(write, Ordering::Relaxed);
+    }
+
+    pub fn summary(&self) -> TimingSummary {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let callbacks = load(&self.callbacks);
+        if callbacks == 0 {
+            return TimingSummary::default();
+        }
+        let mean_ns = |sum: &AtomicU64| Duration::from_nanos(load(sum) / callbacks);
+
+        let mut summary = TimingSummary {
+            callbacks,
+            block_frames: load(&self.frames) as f64 / callbacks as f64,
+            process_mean: mean_ns(&self.process_sum_ns),
+            process_max: Duration::from_nanos(load(&self.process_max_ns)),
+            write_mean: mean_ns(&self.write_sum_ns),
+            write_max: Duration::from_nanos(load(&self.write_max_ns)),
+            ..TimingSummary::default()
+        };
+
+        let intervals = load(&self.intervals);
+        if intervals > 0 {
+            let mean = load(&self.interval_sum_us) as f64 / intervals as f64;
+            let variance = load(&self.interval_square_sum) as f64 / intervals as f64 - mean * mean;
+            summary.interval_min = Duration::from_micros(load(&self.interval_min_us));
+            summary.interval_max = Duration::from_micros(load(&self.interval_max_us));
+            summary.interval_mean = Duration::from_secs_f64(mean / 1e6);
+            summary.interval_stddev = Duration::from_secs_f64(variance.max(0.0).sqrt() / 1e6);
+        }
+        summary
+    }
+}
+
+/// Shared memory positions mirrored by the capture callback for UI display
+#[derive(Debug, Default)]
+struct BufferPositions {
+    write: AtomicU32,
+    /// Driver read position (0 until the driver publishes one)
+    read: AtomicU32,
+    /// Frames written since the capture started; unlike `write` it never wraps
+    frames: AtomicU64,
+}
+
+/// One peak update: the highest level per device channel since the last one
+///
+/// Buffers are sized to the device's channel count at `start` and come from a
+/// small pool; dropping this returns the buffer, so the audio callback never
+/// allocates. Compared to the old fixed `[f32; 8]` array this costs a second
+/// channel hop per update (~480/s at 48 kHz) and an atomic refcount bump, in
+/// exchange for any number of channels.
+pub struct PeakLevels {
+    levels: Box<[f32]>,
+    correlation: Option<f32>,
+    recycle: Sender<Box<[f32]>>,
+}
+
+impl PeakLevels {
+    /// Stereo pair correlation at the time of this update (see
+    /// `CaptureOptions::correlation`); `None` without a pair or while it's silent
+    pub fn correlation(&self) -> Option<f32> {
+        self.correlation
+    }
+}
+
+impl std::ops::Deref for PeakLevels {
+    type Target = [f32];
+
+    fn deref(&self) -> &[f32] {
+        &self.levels
+    }
+}
+
+impl Drop for PeakLevels {
+    fn drop(&mut self) {
+        // Fails only once capture has stopped; the buffer is simply freed then
+        let _ = self.recycle.try_send(std::mem::take(&mut self.levels));
+    }
+}
+
+/// Callback side of the peak channel: fills pooled buffers
+struct PeakSender {
+    sender: Sender<PeakLevels>,
+    pool: Receiver<Box<[f32]>>,
+    recycle: Sender<Box<[f32]>>,
+}
+
+impl PeakSender {
+    /// Queue `peaks` for the UI; false if the queue or the pool is exhausted
+    fn try_send(&self, peaks: &[f32], correlation: Option<f32>) -> bool {
+        let Ok(mut levels) = self.pool.try_recv() else {
+            return false;
+        };
+        levels.copy_from_slice(peaks);
+        // On failure the message is dropped here and its buffer recycled
+        self.sender
+            .try_send(PeakLevels {
+                levels,
+                correlation,
+                recycle: self.recycle.clone(),
+            })
+            .is_ok()
+    }
+}
+
+/// Peak channel for `channels` channels, with every buffer allocated up front
+fn peak_channel(channels: usize) -> (PeakSender, Receiver<PeakLevels>) {
+    let (sender, receiver) = bounded(PEAK_QUEUE);
+
+    // Enough for a full queue, one update held by the UI and one being filled
+    let pool_size = PEAK_QUEUE + 2;
+    let (recycle, pool) = bounded(pool_size);
+    for _ in 0..pool_size {
+        let _ = recycle.try_send(vec![0.0f32; channels].into_boxed_slice());
+    }
+
+    (
+        PeakSender {
+            sender,
+            pool,
+            recycle,
+        },
+        receiver,
+    )
+}
+
+/// Owns the processor inside the stream callback and hands it back on drop
+///
+/// Backends drop the callback when their stream is dropped, so stopping a
+/// stream returns the processor (and the shared memory it writes) to
+/// `AudioCapture` for the next stream, without any locking in the callback.
+struct ProcessorSlot {
+    processor: Option<BlockProcessor>,
+    home: Sender<BlockProcessor>,
+}
+
+impl Drop for ProcessorSlot {
+    fn drop(&mut self) {
+        if let Some(processor) = self.processor.take() {
+            let _ = self.home.try_send(processor);
+        }
+    }
+}
 
 /// Audio capture state
 pub struct AudioCapture {
-    stream: Option<cpal::Stream>,
+    stream: Option<BackendStream>,
     running: Arc<AtomicBool>,
-    peak_receiver: Receiver<[f32; MAX_CHANNELS]>,
+    /// Device to re-query on `restart` (None for custom backends)
+    device: Option<cpal::Device>,
+    /// Processor returned by a stopped stream, and the sender streams return it to
+    processor_return: Receiver<BlockProcessor>,
+    processor_home: Sender<BlockProcessor>,
+    peak_receiver: Receiver<PeakLevels>,
     channel_count: u16,
-    /// Shared write position for UI display (updated by callback)
-    write_pos: Arc<AtomicU32>,
+    /// Rate the stream actually opened at (also written to shared memory)
+    sample_rate: u32,
+    /// Ring buffer size of the shared memory the callback writes to
+    capacity_frames: usize,
+    /// Shared memory positions for UI display (updated by callback)
+    positions: Arc<BufferPositions>,
+    /// Overrun counters (updated by callback)
+    stats: Arc<CaptureStats>,
+    /// Live gain/mute/invert (read by callback)
+    controls: Arc<ChannelControls>,
+    /// Per-channel RMS (updated by callback)
+    rms: Arc<RmsLevels>,
+    /// Callback timing, if `measure_timing` was set (updated by callback)
+    timing: Option<Arc<CallbackTiming>>,
+    /// When the current stream started, and `frames_written` at that point
+    clock_start: (Instant, u64),
 }
 
 impl AudioCapture {
@@ -25,155 +571,180 @@ impl AudioCapture {
     ///
     /// Takes ownership of SharedAudioBuffer - the callback will own it directly
     /// to avoid mutex locking in the real-time audio thread.
-    pub fn start(device: &cpal::Device, shm: SharedAudioBuffer) -> Result<Self> {
-        let config = device
-            .default_input_config()
-            .context("Failed to get default input config")?;
-
-        let channel_count = config.channels();
-        let sample_format = config.sample_format();
-        let stream_config: StreamConfig = config.into();
+    pub fn start(
+        device: &cpal::Device,
+        shm: SharedAudioBuffer,
+        options: &CaptureOptions,
+    ) -> Result<Self, DuomicError> {
+        let backend = CpalBackend::new(device)?.limit_channels(options.max_channels);
 
         tracing::info!(
             "Starting audio capture: {} channels, {} Hz, {:?}",
-            channel_count,
-            stream_config.sample_rate.0,
-            sample_format
+            backend.channels(),
+            backend.sample_rate(),
+            backend.sample_format()
         );
 
+        let mut capture = Self::start_with(&backend, shm, options)?;
+        capture.device = Some(device.clone());
+        Ok(capture)
+    }
+
+    /// Start capturing from any backend (e.g. `MockBackend` in tests)
+    pub fn start_with(
+        backend: &dyn CaptureBackend,
+        mut shm: SharedAudioBuffer,
+        options: &CaptureOptions,
+    ) -> Result<Self> {
+        let channel_count = backend.channels();
+        let sample_rate = backend.sample_rate();
+        let capacity_frames = shm.capacity_frames();
+        // Readers (e.g. `duomic monitor`) take the rate from the header
+        if shm.sample_rate() != sample_rate {
+            tracing::info!(
+                "Stream opened at {} Hz instead of {} Hz",
+                sample_rate,
+                shm.sample_rate()
+            );
+            shm.set_sample_rate(sample_rate);
+        }
+
         let running = Arc::new(AtomicBool::new(true));
-        let running_clone = running.clone();
 
-        // Atomic write position for UI display
-        let write_pos = Arc::new(AtomicU32::new(0));
-        let write_pos_clone = write_pos.clone();
+        // Atomic buffer positions for UI display
+        let positions = Arc::new(BufferPositions::default());
+        let stats = Arc::new(CaptureStats::default());
 
-        // Channel for sending peak levels to the UI (fixed-size array, no allocation)
-        let (peak_sender, peak_receiver) = bounded::<[f32; MAX_CHANNELS]>(16);
+        // Channel for sending peak levels to the UI (pooled buffers, no allocation)
+        let (peak_sender, peak_receiver) = peak_channel(channel_count as usize);
 
-        let stream = match sample_format {
-            SampleFormat::F32 => Self::build_stream::<f32>(
-                device,
-                &stream_config,
-                shm,
-                running_clone,
-                peak_sender,
-                channel_count,
-                write_pos_clone,
-            )?,
-            SampleFormat::I16 => Self::build_stream::<i16>(
-                device,
-                &stream_config,
-                shm,
-                running_clone,
-                peak_sender,
-                channel_count,
-                write_pos_clone,
-            )?,
-            SampleFormat::U16 => Self::build_stream::<u16>(
-                device,
-                &stream_config,
-                shm,
-                running_clone,
-                peak_sender,
-                channel_count,
-                write_pos_clone,
-            )?,
-            _ => anyhow::bail!("Unsupported sample format: {:?}", sample_format),
-        };
+        let processor = BlockProcessor::new(
+            shm,
+            channel_count,
+            sample_rate,
+            options,
+            peak_sender,
+            positions.clone(),
+            stats.clone(),
+        );
+        let controls = processor.controls.clone();
+        let rms = processor.rms.clone();
+        let timing = processor.timing.clone();
 
-        stream.play().context("Failed to start audio stream")?;
+        let (processor_home, processor_return) = bounded(1);
+        let stream = Self::start_stream(backend, processor, &running, &processor_home, &stats)?;
 
         Ok(Self {
             stream: Some(stream),
             running,
+            device: None,
+            processor_return,
+            processor_home,
             peak_receiver,
             channel_count,
-            write_pos,
+            sample_rate,
+            capacity_frames,
+            positions,
+            stats,
+            controls,
+            rms,
+            timing,
+            clock_start: (Instant::now(), 0),
         })
     }
 
-    fn build_stream<T>(
-        device: &cpal::Device,
-        config: &StreamConfig,
-        mut shm: SharedAudioBuffer,
-        running: Arc<AtomicBool>,
-        peak_sender: Sender<[f32; MAX_CHANNELS]>,
-        channel_count: u16,
-        write_pos_atomic: Arc<AtomicU32>,
-    ) -> Result<cpal::Stream>
-    where
-        T: cpal::Sample + cpal::SizedSample + Into<f32>,
-    {
-        let err_fn = |err| {
-            // Note: This is an error callback, not the audio callback
-            // Logging here is acceptable as errors are rare
-            tracing::error!("Audio stream error: {}", err);
+    fn start_stream(
+        backend: &dyn CaptureBackend,
+        processor: BlockProcessor,
+        running: &Arc<AtomicBool>,
+        home: &Sender<BlockProcessor>,
+        stats: &Arc<CaptureStats>,
+    ) -> Result<BackendStream> {
+        let running = running.clone();
+        let mut slot = ProcessorSlot {
+            processor: Some(processor),
+            home: home.clone(),
         };
+        let stats = stats.clone();
+        backend.start(
+            Box::new(move |data: &[f32]| {
+                if !running.load(Ordering::Relaxed) {
+                    return;
+                }
+                if let Some(processor) = slot.processor.as_mut() {
+                    processor.process_block(data);
+                }
+            }),
+            Box::new(move |fault| stats.record_fault(fault)),
+        )
+    }
 
-        let channels = channel_count as usize;
-
-        // Pre-allocate peak buffer (fixed-size array on stack, no heap allocation)
-        let mut peaks = [0.0f32; MAX_CHANNELS];
-
-        // Pre-allocate sample conversion buffer
-        // Typical callback size is 256-1024 frames, we allocate for worst case
-        let mut sample_buffer: Vec<f32> = Vec::with_capacity(4096 * channels);
+    /// Rebuild the stream from the device's current config
+    ///
+    /// Keeps the shared memory mapped and the peak receiver, counters and
+    /// live mix intact, so a restart doesn't truncate the buffer under the
+    /// driver. Fails if the channel count changed (start a new capture then).
+    pub fn restart(&mut self, options: &CaptureOptions) -> Result<()> {
+        let device = self
+            .device
+            .clone()
+            .context("Capture was not started from a device")?;
+        let backend = CpalBackend::new(&device)?.limit_channels(options.max_channels);
 
-        // Frame counter for peak sending
-        let mut frame_counter: usize = 0;
+        tracing::info!(
+            "Restarting audio capture: {} channels, {} Hz, {:?}",
+            backend.channels(),
+            backend.sample_rate(),
+            backend.sample_format()
+        );
 
-        let stream = device
-            .build_input_stream(
-                config,
-                move |data: &[T], _: &cpal::InputCallbackInfo| {
-                    if !running.load(Ordering::Relaxed) {
-                        return;
-                    }
+        self.restart_with(&backend, options)
+    }
 
-                    // Reuse pre-allocated buffer (clear + extend avoids reallocation)
-                    sample_buffer.clear();
-                    sample_buffer.extend(data.iter().map(|s| (*s).into()));
-
-                    // Calculate peak levels per channel (no allocation)
-                    for chunk in sample_buffer.chunks(channels) {
-                        for (ch, &sample) in chunk.iter().enumerate() {
-                            if ch < MAX_CHANNELS {
-                                let abs = sample.abs();
-                                if abs > peaks[ch] {
-                                    peaks[ch] = abs;
-                                }
-                            }
-                        }
-
-                        frame_counter += 1;
-
-                        // Send peaks every ~100 frames (fixed-size array, no clone allocation)
-                        if frame_counter >= 100 {
-                            let _ = peak_sender.try_send(peaks);
-                            peaks = [0.0f32; MAX_CHANNELS];
-                            frame_counter = 0;
-                        }
-                    }
+    /// `restart` on any backend (e.g. `MockBackend` in tests)
+    pub fn restart_with(
+        &mut self,
+        backend: &dyn CaptureBackend,
+        options: &CaptureOptions,
+    ) -> Result<()> {
+        if backend.channels() != self.channel_count {
+            bail!(
+                "Device now has {} channels instead of {}",
+                backend.channels(),
+                self.channel_count
+            );
+        }
 
-                    // Write to shared memory (no mutex, callback owns shm)
-                    // Error handling: silently ignore errors to avoid blocking
-                    // The write_pos update will stall, which the driver handles gracefully
-                    let _ = shm.write_samples(&sample_buffer);
+        self.running.store(false, Ordering::Relaxed);
+        drop(self.stream.take());
+        let mut processor = self
+            .processor_return
+            .recv_timeout(PROCESSOR_RETURN_TIMEOUT)
+            .context("Stopped stream did not release the shared memory")?;
 
-                    // Update atomic write_pos for UI display
-                    write_pos_atomic.store(shm.write_pos(), Ordering::Relaxed);
-                },
-                err_fn,
-                None,
-            )
-            .context("Failed to build input stream")?;
+        processor.reconfigure(backend.sample_rate(), options);
+        for ch in 0..self.channel_count as usize {
+            self.controls
+                .set(ch, options.mix.get(ch).copied().unwrap_or_default());
+        }
 
-        Ok(stream)
+        self.running.store(true, Ordering::Relaxed);
+        self.stats.device_lost.store(false, Ordering::Relaxed);
+        let stream = Self::start_stream(
+            backend,
+            processor,
+            &self.running,
+            &self.processor_home,
+            &self.stats,
+        )?;
+        self.stream = Some(stream);
+        self.sample_rate = backend.sample_rate();
+        self.clock_start = (Instant::now(), self.frames_written());
+        Ok(())
     }
 
     /// Get the peak level receiver
-    pub fn peak_receiver(&self) -> &Receiver<[f32; MAX_CHANNELS]> {
+    pub fn peak_receiver(&self) -> &Receiver<PeakLevels> {
         &self.peak_receiver
     }
 
@@ -182,9 +753,63 @@ impl AudioCapture {
         self.channel_count
     }
 
+    /// Sample rate the stream is running at (may differ from the device default)
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Ring buffer size in frames (what `write_pos` and `read_pos` index)
+    pub fn capacity_frames(&self) -> usize {
+        self.capacity_frames
+    }
+
     /// Get current write position (for UI display)
     pub fn write_pos(&self) -> u32 {
-        self.write_pos.load(Ordering::Relaxed)
+        self.positions.write.load(Ordering::Relaxed)
+    }
+
+    /// Get the driver's last read position (0 if it never published one)
+    pub fn read_pos(&self) -> u32 {
+        self.positions.read.load(Ordering::Relaxed)
+    }
+
+    /// Total frames written to shared memory, across restarts
+    ///
+    /// `write_pos` wraps at `u32::MAX` (about a day at 48 kHz); this doesn't.
+    pub fn frames_written(&self) -> u64 {
+        self.positions.frames.load(Ordering::Relaxed)
+    }
+
+    /// Wall-clock minus sample-clock seconds since the stream (re)started
+    ///
+    /// The sample clock is the frames written since then at the nominal
+    /// rate. Stream startup shows up as a small constant offset; a skew that
+    /// keeps growing means the device clock runs slow (positive) or fast
+    /// (negative) against the system clock.
+    pub fn clock_skew(&self) -> f64 {
+        let (started, frames_at_start) = self.clock_start;
+        let frames = self.frames_written().saturating_sub(frames_at_start);
+        started.elapsed().as_secs_f64() - frames as f64 / self.sample_rate.max(1) as f64
+    }
+
+    /// Get per-channel RMS of the latest block
+    pub fn rms(&self) -> &RmsLevels {
+        &self.rms
+    }
+
+    /// Get overrun counters
+    pub fn stats(&self) -> &CaptureStats {
+        &self.stats
+    }
+
+    /// Live per-channel gain/mute/invert
+    pub fn controls(&self) -> &ChannelControls {
+        &self.controls
+    }
+
+    /// Callback timing, if started with `measure_timing`
+    pub fn timing(&self) -> Option<&CallbackTiming> {
+        self.timing.as_deref()
     }
 
     /// Stop capturing
@@ -208,12 +833,333 @@ impl Drop for AudioCapture {
     }
 }
 
-/// Convert linear amplitude to dB
-pub fn amplitude_to_db(amplitude: f32) -> f32 {
+/// Per-block work of the capture callback: gates, peak levels, shared memory write
+///
+/// Backend-agnostic and allocation-free once constructed, so it runs on the
+/// real-time audio thread as well as in tests.
+struct BlockProcessor {
+    shm: SharedAudioBuffer,
+    channels: usize,
+    // Partial frames left over from the previous block
+    carry: FrameCarry,
+    sample_buffer: Vec<f32>,
+    sample_rate: u32,
+    // High-pass per channel, following the cutoff in `controls`
+    highpass: Vec<Option<HighPass>>,
+    gates: Vec<Option<NoiseGate>>,
+    clip_threshold: f32,
+    correlation: Option<Correlator>,
+    controls: Arc<ChannelControls>,
+    // Per-channel mix factors, refreshed from `controls` each block
+    factors: Vec<f32>,
+    peaks: Vec<f32>,
+    // Sum of squares per channel in the current block
+    energy: Vec<f32>,
+    rms: Arc<RmsLevels>,
+    // Frame counter for peak sending
+    frame_counter: usize,
+    peak_sender: PeakSender,
+    positions: Arc<BufferPositions>,
+    stats: Arc<CaptureStats>,
+    timing: Option<Arc<CallbackTiming>>,
+    // Start of the previous callback, for intervals
+    last_callback: Option<Instant>,
+    reader: ReaderWatch,
+}
+
+impl BlockProcessor {
+    fn new(
+        shm: SharedAudioBuffer,
+        channel_count: u16,
+        sample_rate: u32,
+        options: &CaptureOptions,
+        peak_sender: PeakSender,
+        positions: Arc<BufferPositions>,
+        stats: Arc<CaptureStats>,
+    ) -> Self {
+        let channels = channel_count as usize;
+
+        Self {
+            shm,
+            channels,
+            carry: FrameCarry::new(channels),
+            // Typical callback size is 256-1024 frames, we allocate for worst case
+            sample_buffer: Vec::with_capacity(4096 * channels),
+            sample_rate,
+            highpass: Self::highpass(channels, sample_rate, options),
+            // Gate state lives in the processor (moved into the callback)
+            gates: Self::gates(channels, sample_rate, options),
+            clip_threshold: options.clip_threshold,
+            correlation: Self::correlator(channels, sample_rate, options),
+            controls: Arc::new(ChannelControls::new(channels, &options.mix)),
+            factors: vec![1.0f32; channels],
+            peaks: vec![0.0f32; channels],
+            energy: vec![0.0f32; channels],
+            rms: Arc::new(RmsLevels::new(channels)),
+            frame_counter: 0,
+            peak_sender,
+            positions,
+            stats,
+            timing: options
+                .measure_timing
+                .then(|| Arc::new(CallbackTiming::default())),
+            last_callback: None,
+            reader: ReaderWatch::default(),
+        }
+    }
+
+    fn gates(
+        channels: usize,
+        sample_rate: u32,
+        options: &CaptureOptions,
+    ) -> Vec<Option<NoiseGate>> {
+        (0..channels)
+            .map(|ch| {
+                options
+                    .gates
+                    .get(ch)
+                    .copied()
+                    .flatten()
+                    .map(|settings| NoiseGate::new(settings, sample_rate))
+            })
+            .collect()
+    }
+
+    fn highpass(
+        channels: usize,
+        sample_rate: u32,
+        options: &CaptureOptions,
+    ) -> Vec<Option<HighPass>> {
+        (0..channels)
+            .map(|ch| {
+                let cutoff = options.mix.get(ch).and_then(|mix| mix.highpass_hz);
+                cutoff.map(|hz| HighPass::new(hz, sample_rate))
+            })
+            .collect()
+    }
+
+    /// Correlator for the configured pair, if both channels exist
+    fn correlator(
+        channels: usize,
+        sample_rate: u32,
+        options: &CaptureOptions,
+    ) -> Option<Correlator> {
+        options
+            .correlation
+            .filter(|&(l, r)| l != r && l < channels && r < channels)
+            .map(|pair| Correlator::new(pair, sample_rate))
+    }
+
+    /// Prepare for a new stream: fresh gates and carry, new rate in the header
+    fn reconfigure(&mut self, sample_rate: u32, options: &CaptureOptions) {
+        self.gates = Self::gates(self.channels, sample_rate, options);
+        self.sample_rate = sample_rate;
+        self.highpass = Self::highpass(self.channels, sample_rate, options);
+        self.clip_threshold = options.clip_threshold;
+        self.correlation = Self::correlator(self.channels, sample_rate, options);
+        self.carry = FrameCarry::new(self.channels);
+        self.peaks.fill(0.0);
+        self.frame_counter = 0;
+        // The gap across a restart isn't a callback interval
+        self.last_callback = None;
+        self.shm.set_sample_rate(sample_rate);
+    }
+
+    /// Process one block of interleaved samples (may end mid-frame)
+    fn process_block(&mut self, data: &[f32]) {
+        let channels = self.channels;
+        let started = self.timing.is_some().then(Instant::now);
+        if let (Some(timing), Some(started)) = (&self.timing, started) {
+            if let Some(last) = self.last_callback.replace(started) {
+                timing.record_interval(started - last);
+            }
+        }
+
+        // Reuse pre-allocated buffer; only whole frames remain after align
+        self.carry
+            .align(data.iter().copied(), &mut self.sample_buffer);
+
+        // Follow live mix changes; a new cutoff only recomputes coefficients
+        let mut filtering = false;
+        for (ch, (factor, filter)) in self
+            .factors
+            .iter_mut()
+            .zip(self.highpass.iter_mut())
+            .enumerate()
+        {
+            let mix = self.controls.get(ch);
+            *factor = mix.factor();
+            match (mix.highpass_hz, filter.as_mut()) {
+                (None, _) => *filter = None,
+                (Some(hz), Some(existing)) if existing.cutoff_hz() == hz => {}
+                (Some(hz), Some(existing)) => existing.set_cutoff(hz, self.sample_rate),
+                (Some(hz), None) => *filter = Some(HighPass::new(hz, self.sample_rate)),
+            }
+            filtering |= filter.is_some();
+        }
+
+        // High-pass first, so rumble neither opens the gate nor shows on the meters
+        if filtering {
+            for chunk in self.sample_buffer.chunks_mut(channels) {
+                for (sample, filter) in chunk.iter_mut().zip(self.highpass.iter_mut()) {
+                    if let Some(filter) = filter {
+                        *sample = filter.process(*sample);
+                    }
+                }
+            }
+        }
+
+        // Apply per-channel noise gates
+        for chunk in self.sample_buffer.chunks_mut(channels) {
+            for (sample, gate) in chunk.iter_mut().zip(self.gates.iter_mut()) {
+                if let Some(gate) = gate {
+                    *sample = gate.process(*sample);
+                }
+            }
+        }
+
+        // Apply gain/mute/invert (after the gate, so its threshold ignores gain)
+        if self.factors.iter().any(|&f| f != 1.0) {
+            for chunk in self.sample_buffer.chunks_mut(channels) {
+                for (sample, factor) in chunk.iter_mut().zip(&self.factors) {
+                    *sample *= factor;
+                }
+            }
+        }
+
+        // Calculate peak levels per channel and count clipped samples (no allocation)
+        let mut clipped = 0;
+        self.energy.fill(0.0);
+        for chunk in self.sample_buffer.chunks(channels) {
+            if let Some(correlation) = &mut self.correlation {
+                correlation.push(chunk);
+            }
+            for (ch, &sample) in chunk.iter().enumerate() {
+                self.energy[ch] += sample * sample;
+                let abs = sample.abs();
+                if abs >= self.clip_threshold {
+                    clipped += 1;
+                }
+                if abs > self.peaks[ch] {
+                    self.peaks[ch] = abs;
+                }
+            }
+
+            self.frame_counter += 1;
+
+            // Send peaks every ~100 frames (copied into a pooled buffer)
+            if self.frame_counter >= PEAK_INTERVAL_FRAMES {
+                let correlation = self.correlation.as_ref().and_then(Correlator::value);
+                if !self.peak_sender.try_send(&self.peaks, correlation) {
+                    self.stats.dropped_peaks.fetch_add(1, Ordering::Relaxed);
+                }
+                self.peaks.fill(0.0);
+                self.frame_counter = 0;
+            }
+        }
+
+        let frames = self.sample_buffer.len() / channels;
+        if frames > 0 {
+            for (ch, energy) in self.energy.iter().enumerate() {
+                self.rms.set(ch, energy / frames as f32);
+            }
+        }
+
+        if clipped > 0 {
+            self.stats
+                .clipped_samples
+                .fetch_add(clipped, Ordering::Relaxed);
+        }
+
+        // Write to shared memory (no mutex, callback owns shm)
+        // Error handling: count errors instead of blocking
+        // The write_pos update will stall, which the driver handles gracefully
+        let write_started = started.map(|_| Instant::now());
+        if self.shm.write_samples(&self.sample_buffer).is_err() {
+            self.stats.write_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        if let (Some(timing), Some(started), Some(write_started)) =
+            (&self.timing, started, write_started)
+        {
+            let now = Instant::now();
+            timing.record_block(frames, now - started, now - write_started);
+        }
+
+        // Update atomic positions for UI display
+        self.positions
+            .write
+            .store(self.shm.write_pos(), Ordering::Relaxed);
+        self.positions
+            .frames
+            .fetch_add(frames as u64, Ordering::Relaxed);
+        self.positions
+            .read
+            .store(self.shm.read_pos(), Ordering::Relaxed);
+
+        if self
+            .reader
+            .observe(self.shm.write_pos(), self.shm.read_pos())
+        {
+            self.stats.shm_overruns.fetch_add(1, Ordering::Relaxed);
+        }
+        self.stats
+            .unread
+            .store(self.reader.lapped(), Ordering::Relaxed);
+    }
+}
+
+/// Carry-over for samples that don't complete a frame
+///
+/// Invariant: after `align`, the output holds whole interleaved frames only,
+/// so `output[i]` always belongs to channel `i % channels`. Any trailing
+/// partial frame is held back and prepended to the next callback's data.
+struct FrameCarry {
+    channels: usize,
+    pending: Vec<f32>,
+}
+
+impl FrameCarry {
+    fn new(channels: usize) -> Self {
+        Self {
+            channels: channels.max(1),
+            pending: Vec::with_capacity(channels),
+        }
+    }
+
+    /// Fill `output` with carried samples followed by `input`, keeping back the remainder
+    fn align(&mut self, input: impl Iterator<Item = f32>, output: &mut Vec<f32>) {
+        output.clear();
+        output.append(&mut self.pending);
+        output.extend(input);
+
+        let remainder = output.len() % self.channels;
+        if remainder > 0 {
+            self.pending
+                .extend(output.drain(output.len() - remainder..));
+        }
+    }
+}
+
+/// Normalize a device sample to f32 in [-1.0, 1.0]
+///
+/// A plain `Into<f32>` keeps the integer range (0..65535 for U16), so go
+/// through cpal's sample conversion which recenters unsigned formats.
+pub fn to_f32<T>(sample: T) -> f32
+where
+    f32: FromSample<T>,
+{
+    f32::from_sample(sample)
+}
+
+/// Default lower bound for dB conversions and meters
+pub const DEFAULT_FLOOR_DB: f32 = -60.0;
+
+/// Convert linear amplitude to dB, clamped at `floor_db`
+pub fn amplitude_to_db(amplitude: f32, floor_db: f32) -> f32 {
     if amplitude <= 0.0 {
-        -60.0
+        floor_db
     } else {
-        20.0 * amplitude.log10()
+        (20.0 * amplitude.log10()).max(floor_db)
     }
 }
 
@@ -222,14 +1168,456 @@ pub fn db_to_amplitude(db: f32) -> f32 {
     10.0f32.powf(db / 20.0)
 }
 
+/// Linear clip threshold for a dBFS setting (clamped to at most 0 dB)
+///
+/// 0 dB maps to just under full scale, since integer formats never reach 1.0.
+pub fn clip_threshold(threshold_db: f32) -> f32 {
+    db_to_amplitude(threshold_db.min(0.0)).min(MAX_CLIP_LEVEL)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::audio::{MockBackend, DEFAULT_HIGHPASS_HZ};
 
     #[test]
     fn test_db_conversion() {
-        assert!((amplitude_to_db(1.0) - 0.0).abs() < 0.001);
-        assert!((amplitude_to_db(0.5) - (-6.02)).abs() < 0.1);
-        assert!((amplitude_to_db(0.0) - (-60.0)).abs() < 0.001);
+        assert!((amplitude_to_db(1.0, DEFAULT_FLOOR_DB) - 0.0).abs() < 0.001);
+        assert!((amplitude_to_db(0.5, DEFAULT_FLOOR_DB) - (-6.02)).abs() < 0.1);
+        assert!((amplitude_to_db(0.0, DEFAULT_FLOOR_DB) - (-60.0)).abs() < 0.001);
+        assert!((amplitude_to_db(0.0001, DEFAULT_FLOOR_DB) - (-60.0)).abs() < 0.001);
+        assert!((amplitude_to_db(0.0001, -100.0) - (-80.0)).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_sample_normalization() {
+        // U16: midpoint is silence, extremes are full scale
+        assert_eq!(to_f32(32768u16), 0.0);
+        assert_eq!(to_f32(0u16), -1.0);
+        assert!((to_f32(65535u16) - 1.0).abs() < 0.001);
+        assert!((to_f32(49152u16) - 0.5).abs() < 0.001);
+
+        // I16: divide by 32768
+        assert_eq!(to_f32(0i16), 0.0);
+        assert_eq!(to_f32(-32768i16), -1.0);
+        assert_eq!(to_f32(16384i16), 0.5);
+        assert!((to_f32(32767i16) - 1.0).abs() < 0.001);
+
+        // F32 passes through
+        assert_eq!(to_f32(-0.25f32), -0.25);
+    }
+
+    /// Shared memory at a per-test temp path, plus the path for cleanup
+    fn test_shm(name: &str, channels: u32) -> (SharedAudioBuffer, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!(
+            "duomic_capture_test_{}_{}",
+            std::process::id(),
+            name
+        ));
+        let shm = SharedAudioBuffer::open_path(&path, channels, 48000).unwrap();
+        (shm, path)
+    }
+
+    /// Run `samples` through a processor in odd-sized blocks; returns the
+    /// highest peak seen per channel and the samples read back from shared memory
+    fn run_blocks(name: &str, channels: u16, samples: &[f32]) -> (Vec<f32>, Vec<f32>) {
+        let (shm, path) = test_shm(name, channels as u32);
+        let (sender, receiver) = peak_channel(channels as usize);
+        let stats = Arc::new(CaptureStats::default());
+        let mut processor = BlockProcessor::new(
+            shm,
+            channels,
+            48000,
+            &CaptureOptions::default(),
+            sender,
+            Arc::new(BufferPositions::default()),
+            stats.clone(),
+        );
+
+        for block in samples.chunks(37) {
+            processor.process_block(block);
+        }
+
+        let mut peaks = vec![0.0f32; channels as usize];
+        for sent in receiver.try_iter() {
+            for (peak, level) in peaks.iter_mut().zip(sent.iter()) {
+                *peak = peak.max(*level);
+            }
+        }
+
+        let reader = SharedAudioBuffer::open_readonly_path(&path).unwrap();
+        let mut written = vec![0.0f32; samples.len()];
+        let frames = reader.read_samples(0, &mut written);
+        written.truncate(frames * channels as usize);
+
+        assert_eq!(stats.write_errors(), 0);
+        drop(processor);
+        let _ = std::fs::remove_file(&path);
+        (peaks, written)
+    }
+
+    #[test]
+    fn test_process_block_sine_at_minus_6_db() {
+        // 1 kHz at 48 kHz on channel 0, silence on channel 1
+        let samples: Vec<f32> = (0..480)
+            .flat_map(|i| {
+                let phase = 2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0;
+                [0.5 * phase.sin(), 0.0]
+            })
+            .collect();
+
+        let (peaks, written) = run_blocks("sine", 2, &samples);
+        assert!((amplitude_to_db(peaks[0], DEFAULT_FLOOR_DB) - (-6.02)).abs() < 0.05);
+        assert_eq!(peaks[1], 0.0);
+        assert_eq!(written, samples);
+    }
+
+    #[test]
+    fn test_process_block_full_scale_square_and_silence() {
+        let samples: Vec<f32> = (0..300)
+            .flat_map(|i| {
+                let square = if (i / 24) % 2 == 0 { 1.0 } else { -1.0 };
+                [square, 0.0, square * 0.25]
+            })
+            .collect();
+
+        let (peaks, written) = run_blocks("square", 3, &samples);
+        assert_eq!(peaks, vec![1.0, 0.0, 0.25]);
+        assert_eq!(written, samples);
+    }
+
+    #[test]
+    fn test_process_block_more_than_eight_channels() {
+        // 12 channels, channel n peaks at (n + 1) / 16
+        let channels = 12;
+        let samples: Vec<f32> = (0..200 * channels)
+            .map(|i| ((i % channels) + 1) as f32 / 16.0)
+            .collect();
+
+        let (peaks, written) = run_blocks("wide", channels as u16, &samples);
+        let expected: Vec<f32> = (0..channels).map(|ch| (ch + 1) as f32 / 16.0).collect();
+        assert_eq!(peaks, expected);
+        assert_eq!(written, samples);
+    }
+
+    #[test]
+    fn test_correlation_of_stereo_pair() {
+        // Half a second of 1 kHz on channels 0 and 2 (channel 1 is ignored);
+        // returns the correlation sent with the last peak update
+        let correlate = |name: &str, right: fn(f32) -> f32| {
+            let (shm, path) = test_shm(name, 3);
+            let (sender, receiver) = peak_channel(3);
+            let options = CaptureOptions {
+                correlation: Some((0, 2)),
+                ..CaptureOptions::default()
+            };
+            let mut processor = BlockProcessor::new(
+                shm,
+                3,
+                48000,
+                &options,
+                sender,
+                Arc::new(BufferPositions::default()),
+                Arc::new(CaptureStats::default()),
+            );
+            let samples: Vec<f32> = (0..24000)
+                .flat_map(|i| {
+                    let phase = 2.0 * std::f32::consts::PI * 1000.0 * i as f32 / 48000.0;
+                    [0.5 * phase.sin(), 0.9, right(phase)]
+                })
+                .collect();
+            let mut last = None;
+            for block in samples.chunks(512 * 3) {
+                processor.process_block(block);
+                // Keep the queue drained like the UI does
+                for sent in receiver.try_iter() {
+                    last = Some(sent.correlation());
+                }
+            }
+            drop(processor);
+            let _ = std::fs::remove_file(&path);
+            last.expect("no peak updates")
+        };
+
+        let mono = correlate("corr_mono", |p| 0.25 * p.sin()).unwrap();
+        assert!(mono > 0.99);
+        let inverted = correlate("corr_inv", |p| -0.5 * p.sin()).unwrap();
+        assert!(inverted < -0.99);
+        let quadrature = correlate("corr_quad", |p| 0.5 * p.cos()).unwrap();
+        assert!(quadrature.abs() < 0.05);
+        // Silence on one side: nothing to measure
+        assert_eq!(correlate("corr_silent", |_| 0.0), None);
+    }
+
+    #[test]
+    fn test_peak_buffers_are_recycled() {
+        let (sender, receiver) = peak_channel(3);
+
+        // Far more updates than the pool holds, as long as the UI keeps up
+        for i in 0..100 {
+            assert!(sender.try_send(&[i as f32, 0.0, 1.0], None));
+            let levels = receiver.try_recv().unwrap();
+            assert_eq!(&levels[..], &[i as f32, 0.0, 1.0]);
+        }
+
+        // A stalled UI drops updates instead of allocating more buffers
+        let sent = (0..100)
+            .filter(|_| sender.try_send(&[0.0; 3], None))
+            .count();
+        assert_eq!(sent, PEAK_QUEUE);
+    }
+
+    #[test]
+    fn test_mix_gain_mute_invert() {
+        let (shm, path) = test_shm("mix", 3);
+        let backend = MockBackend {
+            channels: 3,
+            sample_rate: 48000,
+            samples: [0.25, 0.5, 0.5].repeat(50),
+            block_size: 30,
+            faults: Vec::new(),
+        };
+        let options = CaptureOptions {
+            mix: vec![
+                MixSettings {
+                    gain_db: 6.0206,
+                    ..MixSettings::default()
+                },
+                MixSettings {
+                    muted: true,
+                    ..MixSettings::default()
+                },
+                MixSettings {
+                    invert: true,
+                    ..MixSettings::default()
+                },
+            ],
+            ..CaptureOptions::default()
+        };
+        let capture = AudioCapture::start_with(&backend, shm, &options).unwrap();
+        assert!(capture.controls().get(1).muted);
+
+        let reader = SharedAudioBuffer::open_readonly_path(&path).unwrap();
+        let mut written = vec![0.0f32; 3];
+        reader.read_samples(0, &mut written);
+        assert!((written[0] - 0.5).abs() < 0.0001);
+        assert_eq!(written[1], 0.0);
+        assert_eq!(written[2], -0.5);
+
+        // RMS is measured after the mix
+        assert!((capture.rms().get(0) - 0.5).abs() < 0.0001);
+        assert_eq!(capture.rms().get(1), 0.0);
+        assert!((capture.rms().get(2) - 0.5).abs() < 0.0001);
+
+        drop(capture);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_highpass_follows_live_mix() {
+        let (shm, path) = test_shm("highpass", 2);
+        let (sender, _receiver) = peak_channel(2);
+        let options = CaptureOptions {
+            mix: vec![MixSettings {
+                highpass_hz: Some(80.0),
+                ..MixSettings::default()
+            }],
+            ..CaptureOptions::default()
+        };
+        let mut processor = BlockProcessor::new(
+            shm,
+            2,
+            48000,
+            &options,
+            sender,
+            Arc::new(BufferPositions::default()),
+            Arc::new(CaptureStats::default()),
+        );
+
+        // A DC offset on both channels: only the filtered one settles to silence
+        let block = [0.5f32; 960];
+        for _ in 0..100 {
+            processor.process_block(&block);
+        }
+        assert!(processor.rms.get(0) < 1e-3);
+        assert!((processor.rms.get(1) - 0.5).abs() < 1e-4);
+
+        // Switching it off (and on elsewhere) applies from the next block
+        processor.controls.set(0, MixSettings::default());
+        processor.controls.set(
+            1,
+            MixSettings {
+                highpass_hz: Some(DEFAULT_HIGHPASS_HZ),
+                ..MixSettings::default()
+            },
+        );
+        processor.process_block(&block);
+        assert!((processor.rms.get(0) - 0.5).abs() < 1e-4);
+        assert!(processor.highpass[0].is_none() && processor.highpass[1].is_some());
+
+        drop(processor);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_clip_threshold() {
+        assert_eq!(clip_threshold(0.0), MAX_CLIP_LEVEL);
+        assert_eq!(clip_threshold(3.0), MAX_CLIP_LEVEL);
+        assert!((clip_threshold(-0.1) - 0.98855).abs() < 0.0001);
+        assert!(to_f32(i16::MAX) >= clip_threshold(0.0));
+    }
+
+    #[test]
+    fn test_capture_with_mock_backend() {
+        let (shm, path) = test_shm("mock", 2);
+        let backend = MockBackend {
+            channels: 2,
+            sample_rate: 48000,
+            samples: (0..400)
+                .map(|i| if i % 2 == 0 { 0.5 } else { -0.25 })
+                .collect(),
+            block_size: 33,
+            faults: vec![
+                StreamFault::Xrun("input overflow".into()),
+                StreamFault::Xrun("input overflow".into()),
+                StreamFault::Disconnected("unplugged".into()),
+            ],
+        };
+
+        let options = CaptureOptions {
+            clip_threshold: clip_threshold(-6.5),
+            ..CaptureOptions::default()
+        };
+        let capture = AudioCapture::start_with(&backend, shm, &options).unwrap();
+        assert_eq!(capture.channel_count(), 2);
+        assert_eq!(capture.write_pos(), 200);
+        assert_eq!(capture.frames_written(), 200);
+        // Channel 0 sits at -6 dB, above the -6.5 dB threshold
+        assert_eq!(capture.stats().clipped_samples(), 200);
+
+        let peaks = capture.peak_receiver().try_recv().unwrap();
+        assert_eq!(&peaks[..2], &[0.5, 0.25]);
+
+        // Transient errors are counted; a lost device is flagged separately
+        assert_eq!(capture.stats().xruns(), 2);
+        assert!(capture.stats().device_lost());
+        assert_eq!(capture.stats().last_error().as_deref(), Some("unplugged"));
+
+        drop(capture);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_restart_keeps_buffer_and_receiver() {
+        let (shm, path) = test_shm("restart", 2);
+        let backend = |sample_rate, level| MockBackend {
+            channels: 2,
+            sample_rate,
+            samples: vec![level; 400],
+            block_size: 33,
+            faults: Vec::new(),
+        };
+
+        let mut capture =
+            AudioCapture::start_with(&backend(48000, 0.5), shm, &CaptureOptions::default())
+                .unwrap();
+        assert_eq!(capture.write_pos(), 200);
+        let peak_receiver = capture.peak_receiver().clone();
+        peak_receiver.try_iter().for_each(drop);
+
+        // Reopening would recreate the file; the restarted stream must keep
+        // writing the same mapping instead
+        std::fs::remove_file(&path).unwrap();
+        capture
+            .restart_with(&backend(44100, 0.25), &CaptureOptions::default())
+            .unwrap();
+        assert!(!path.exists());
+        assert!(capture.is_running());
+        assert_eq!(capture.channel_count(), 2);
+        assert_eq!(capture.sample_rate(), 44100);
+        // The ring and the frame count carry on where the first stream stopped
+        assert_eq!(capture.write_pos(), 400);
+        assert_eq!(capture.frames_written(), 400);
+        let peaks = peak_receiver.try_recv().unwrap();
+        assert_eq!(&peaks[..2], &[0.25, 0.25]);
+
+        // A different channel count needs a new capture; the old one is untouched
+        let mono = MockBackend {
+            channels: 1,
+            ..backend(48000, 0.5)
+        };
+        assert!(capture
+            .restart_with(&mono, &CaptureOptions::default())
+            .is_err());
+        assert_eq!(capture.write_pos(), 400);
+    }
+
+    #[test]
+    fn test_callback_timing() {
+        let (shm, path) = test_shm("timing", 2);
+        let backend = MockBackend {
+            channels: 2,
+            sample_rate: 48000,
+            samples: vec![0.1; 400],
+            block_size: 80,
+            faults: Vec::new(),
+        };
+        let capture = AudioCapture::start_with(&backend, shm, &CaptureOptions::default()).unwrap();
+        assert!(capture.timing().is_none());
+        drop(capture);
+
+        let shm = SharedAudioBuffer::open_path(&path, 2, 48000).unwrap();
+        let options = CaptureOptions {
+            measure_timing: true,
+            ..CaptureOptions::default()
+        };
+        let capture = AudioCapture::start_with(&backend, shm, &options).unwrap();
+        let summary = capture.timing().unwrap().summary();
+        assert_eq!(summary.callbacks, 5);
+        assert_eq!(summary.block_frames, 40.0);
+        assert!(summary.write_max <= summary.process_max);
+        drop(capture);
+        let _ = std::fs::remove_file(&path);
+
+        // Statistics over known intervals: 1, 2 and 3 ms
+        let timing = CallbackTiming::default();
+        for ms in 1..=3 {
+            timing.record_interval(Duration::from_millis(ms));
+            timing.record_block(256, Duration::from_micros(ms * 10), Duration::ZERO);
+        }
+        let summary = timing.summary();
+        assert_eq!(summary.interval_min, Duration::from_millis(1));
+        assert_eq!(summary.interval_max, Duration::from_millis(3));
+        assert_eq!(summary.interval_mean, Duration::from_millis(2));
+        assert!((summary.interval_stddev.as_secs_f64() * 1e3 - 0.8165).abs() < 1e-3);
+        assert_eq!(summary.process_mean, Duration::from_micros(20));
+        assert_eq!(summary.process_max, Duration::from_micros(30));
+    }
+
+    #[test]
+    fn test_frame_carry_keeps_channel_alignment() {
+        // 3 channels, samples encode their channel as the value
+        let channels = 3;
+        let stream: Vec<f32> = (0..60).map(|i| (i % channels) as f32).collect();
+
+        let mut carry = FrameCarry::new(channels);
+        let mut output = Vec::new();
+        let mut total = 0;
+
+        let mut offset = 0;
+        for size in [4, 7, 1, 2, 11, 5, 30] {
+            carry.align(stream[offset..offset + size].iter().copied(), &mut output);
+            offset += size;
+
+            assert_eq!(output.len() % channels, 0);
+            for (i, sample) in output.iter().enumerate() {
+                assert_eq!(*sample, (i % channels) as f32);
+            }
+            total += output.len();
+        }
+
+        // Nothing lost once the stream ends on a frame boundary
+        assert_eq!(offset, stream.len());
+        assert_eq!(total, stream.len());
     }
 }
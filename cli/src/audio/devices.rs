@@ -9,10 +9,22 @@ use crate::ipc::DriverClient;
 pub struct AudioDevice {
     pub name: String,
     pub channels: u16,
-    pub sample_rate: u32,
+    /// Rate of the default input config; the stream may open at another
+    /// (see `AudioCapture::sample_rate`)
+    pub default_sample_rate: u32,
     pub index: usize,
 }
 
+impl AudioDevice {
+    /// Whether the device can be captured at all
+    ///
+    /// Some aggregate/virtual devices report 0 channels or a 0 Hz default
+    /// config; selecting one would leave nothing to pick on the channel screen.
+    pub fn is_usable(&self) -> bool {
+        self.channels > 0 && self.default_sample_rate > 0
+    }
+}
+
 impl std::fmt::Display for AudioDevice {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} ({} channels)", self.name, self.channels)
@@ -88,12 +100,22 @@ pub fn list_input_devices() -> Result<Vec<AudioDevice>> {
 
         // Get default config to determine channels and sample rate
         if let Ok(config) = device.default_input_config() {
-            devices.push(AudioDevice {
+            let device = AudioDevice {
                 name,
                 channels: config.channels(),
-                sample_rate: config.sample_rate().0,
+                default_sample_rate: config.sample_rate().0,
                 index,
-            });
+            };
+            if device.is_usable() {
+                devices.push(device);
+            } else {
+                tracing::debug!(
+                    "Skipping {}: {} channels at {} Hz",
+                    device.name,
+                    device.channels,
+                    device.default_sample_rate
+                );
+            }
         }
     }
 
@@ -101,34 +123,221 @@ pub fn list_input_devices() -> Result<Vec<AudioDevice>> {
     Ok(devices)
 }
 
+/// Drop `hidden` devices and move `pinned` ones to the top
+///
+/// Patterns match case-insensitively as substrings. Pinned devices keep the
+/// order of the `pinned` list; everything else keeps its original order.
+pub fn filter_devices(
+    devices: Vec<AudioDevice>,
+    hidden: &[String],
+    pinned: &[String],
+) -> Vec<AudioDevice> {
+    let hidden: Vec<String> = hidden.iter().map(|h| h.to_lowercase()).collect();
+    let pinned: Vec<String> = pinned.iter().map(|p| p.to_lowercase()).collect();
+
+    let mut devices: Vec<AudioDevice> = devices
+        .into_iter()
+        .filter(|d| {
+            let name = d.name.to_lowercase();
+            let is_hidden = hidden.iter().any(|h| name.contains(h.as_str()));
+            if is_hidden {
+                tracing::debug!("Hiding device: {}", d.name);
+            }
+            !is_hidden
+        })
+        .collect();
+
+    // Stable sort: pinned by pattern position, unpinned after in original order
+    devices.sort_by_key(|d| {
+        let name = d.name.to_lowercase();
+        pinned
+            .iter()
+            .position(|p| name.contains(p.as_str()))
+            .unwrap_or(pinned.len())
+    });
+
+    devices
+}
+
+/// How a device name given by the user or config matched the device list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchResult {
+    /// One device has this name (ignoring case)
+    Exact(usize),
+    /// No exact name, but one device contains it
+    Unique(usize),
+    /// Several devices have this name, or several contain it (no exact one)
+    Ambiguous(Vec<usize>),
+    None,
+}
+
+impl MatchResult {
+    /// The matched position; among several, the first that `prefer`s, else the first
+    pub fn pick(&self, prefer: impl Fn(usize) -> bool) -> Option<usize> {
+        match self {
+            MatchResult::Exact(i) | MatchResult::Unique(i) => Some(*i),
+            MatchResult::Ambiguous(all) => all
+                .iter()
+                .copied()
+                .find(|&i| prefer(i))
+                .or(all.first().copied()),
+            MatchResult::None => None,
+        }
+    }
+}
+
+/// Match `name` against device names (case-insensitive)
+///
+/// Every lookup by name goes through this, so the device picked from the
+/// list is the one that gets opened. Exact names win over substrings: "Mic"
+/// picks "Mic" even when "USB Mic" is listed first.
+pub fn match_device<S: AsRef<str>>(name: &str, candidates: &[S]) -> MatchResult {
+    let name = name.to_lowercase();
+    let lowered: Vec<String> = candidates
+        .iter()
+        .map(|c| c.as_ref().to_lowercase())
+        .collect();
+
+    let exact: Vec<usize> = (0..lowered.len()).filter(|&i| lowered[i] == name).collect();
+    if let [i] = exact[..] {
+        return MatchResult::Exact(i);
+    }
+    if !exact.is_empty() {
+        return MatchResult::Ambiguous(exact);
+    }
+
+    let partial: Vec<usize> = (0..lowered.len())
+        .filter(|&i| lowered[i].contains(&name))
+        .collect();
+    match partial[..] {
+        [] => MatchResult::None,
+        [i] => MatchResult::Unique(i),
+        _ => MatchResult::Ambiguous(partial),
+    }
+}
+
 /// Find a device by name (partial match)
 pub fn find_device_by_name(name: &str) -> Result<Option<AudioDevice>> {
+    find_device(name, None)
+}
+
+/// Find a device by name (partial match), using `index` to pick among duplicates
+pub fn find_device(name: &str, index: Option<usize>) -> Result<Option<AudioDevice>> {
     let devices = list_input_devices()?;
-    let name_lower = name.to_lowercase();
+    Ok(resolve_device(&devices, name, index).cloned())
+}
 
-    Ok(devices
-        .into_iter()
-        .find(|d| d.name.to_lowercase().contains(&name_lower)))
+/// Pick the device matching `name` (see [`match_device`])
+///
+/// When several match (e.g. two identical USB mics), the one with
+/// enumeration `index` wins if it's among them; otherwise the first match.
+/// Indices can shift when devices are plugged in or removed, so the index
+/// only breaks ties and never overrides the name.
+pub fn resolve_device<'a>(
+    devices: &'a [AudioDevice],
+    name: &str,
+    index: Option<usize>,
+) -> Option<&'a AudioDevice> {
+    let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+    match_device(name, &names)
+        .pick(|i| Some(devices[i].index) == index)
+        .map(|i| &devices[i])
 }
 
-/// Get the cpal device by name
-pub fn get_cpal_device(name: &str) -> Result<cpal::Device> {
+/// Get the cpal device for a listed device, by its enumeration index
+///
+/// Falls back to a name lookup if the devices were re-enumerated in a
+/// different order since `device` was listed.
+pub fn get_cpal_device_for(device: &AudioDevice) -> Result<cpal::Device> {
     let host = cpal::default_host();
-    let input_devices = host
+    let at_index = host
         .input_devices()
-        .context("Failed to enumerate input devices")?;
+        .context("Failed to enumerate input devices")?
+        .nth(device.index)
+        .filter(|d| d.name().is_ok_and(|name| name == device.name));
 
-    let name_lower = name.to_lowercase();
+    match at_index {
+        Some(cpal_device) => Ok(cpal_device),
+        None => cpal_input_named(&device.name),
+    }
+}
 
-    for device in input_devices {
-        if let Ok(device_name) = device.name() {
-            if device_name.to_lowercase().contains(&name_lower) {
-                return Ok(device);
-            }
+/// Get the cpal device by name, matched like the device list
+///
+/// Virtual and unusable devices are skipped as in [`list_input_devices`],
+/// so a name never opens a device the selector wouldn't show.
+pub fn get_cpal_device(name: &str) -> Result<cpal::Device> {
+    let devices = list_input_devices()?;
+    let device = resolve_device(&devices, name, None)
+        .with_context(|| format!("Device not found: {}", name))?;
+    get_cpal_device_for(device)
+}
+
+/// The cpal input device called exactly `name`
+fn cpal_input_named(name: &str) -> Result<cpal::Device> {
+    cpal::default_host()
+        .input_devices()
+        .context("Failed to enumerate input devices")?
+        .find(|d| d.name().is_ok_and(|n| n == name))
+        .with_context(|| format!("Device not found: {}", name))
+}
+
+/// Get list of available output devices
+pub fn list_output_devices() -> Result<Vec<AudioDevice>> {
+    let host = cpal::default_host();
+    let mut devices = Vec::new();
+
+    let output_devices = host
+        .output_devices()
+        .context("Failed to enumerate output devices")?;
+
+    for (index, device) in output_devices.enumerate() {
+        let name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+
+        if let Ok(config) = device.default_output_config() {
+            devices.push(AudioDevice {
+                name,
+                channels: config.channels(),
+                default_sample_rate: config.sample_rate().0,
+                index,
+            });
         }
     }
 
-    anyhow::bail!("Device not found: {}", name)
+    tracing::debug!("Found {} output devices", devices.len());
+    Ok(devices)
+}
+
+/// Get the cpal output device by name (see [`match_device`])
+pub fn get_output_device_by_name(name: &str) -> Result<cpal::Device> {
+    let host = cpal::default_host();
+    let mut output_devices: Vec<cpal::Device> = host
+        .output_devices()
+        .context("Failed to enumerate output devices")?
+        .collect();
+    let names: Vec<String> = output_devices
+        .iter()
+        .map(|d| d.name().unwrap_or_default())
+        .collect();
+
+    match match_device(name, &names).pick(|_| false) {
+        Some(i) => Ok(output_devices.swap_remove(i)),
+        None => anyhow::bail!("Output device not found: {}", name),
+    }
+}
+
+/// Output device by name, falling back to the default output if unset or missing
+pub fn resolve_output_device(name: Option<&str>) -> Result<cpal::Device> {
+    if let Some(name) = name {
+        match get_output_device_by_name(name) {
+            Ok(device) => return Ok(device),
+            Err(e) => tracing::warn!("{}, using the default output", e),
+        }
+    }
+
+    cpal::default_host()
+        .default_output_device()
+        .context("No default output device available")
 }
 
 /// Get default input device
@@ -138,6 +347,13 @@ pub fn get_default_input_device() -> Result<cpal::Device> {
         .context("No default input device available")
 }
 
+/// Name of the system default input device
+pub fn default_input_device_name() -> Result<String> {
+    get_default_input_device()?
+        .name()
+        .context("Failed to read the default input device name")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,4 +365,86 @@ mod tests {
         // Just check it doesn't panic
         let _ = result;
     }
+
+    fn device(name: &str, index: usize) -> AudioDevice {
+        AudioDevice {
+            name: name.to_string(),
+            channels: 2,
+            default_sample_rate: 48000,
+            index,
+        }
+    }
+
+    #[test]
+    fn test_filter_devices_hidden_and_pinned() {
+        let devices = vec![
+            device("MacBook Pro Microphone", 0),
+            device("BlackHole 2ch", 1),
+            device("BOYALINK", 2),
+            device("ZoomAudioDevice", 3),
+        ];
+
+        let hidden = vec!["blackhole".to_string(), "zoom".to_string()];
+        let pinned = vec!["boya".to_string()];
+        let names: Vec<String> = filter_devices(devices, &hidden, &pinned)
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+
+        assert_eq!(names, ["BOYALINK", "MacBook Pro Microphone"]);
+    }
+
+    #[test]
+    fn test_zero_channel_or_rate_devices_are_unusable() {
+        assert!(device("USB Mic", 0).is_usable());
+
+        let mut silent = device("Aggregate", 1);
+        silent.channels = 0;
+        assert!(!silent.is_usable());
+
+        let mut no_rate = device("Broken", 2);
+        no_rate.default_sample_rate = 0;
+        assert!(!no_rate.is_usable());
+    }
+
+    #[test]
+    fn test_match_device() {
+        let names = [
+            "USB Mic",
+            "Mic",
+            "MacBook Pro Microphone",
+            "Scarlett 2i2",
+            "USB Mic",
+        ];
+
+        // An exact name beats devices containing it, whatever the order
+        assert_eq!(match_device("mic", &names), MatchResult::Exact(1));
+        assert_eq!(match_device("scar", &names), MatchResult::Unique(3));
+        assert_eq!(
+            match_device("usb mic", &names),
+            MatchResult::Ambiguous(vec![0, 4])
+        );
+        assert_eq!(match_device("pro", &names), MatchResult::Unique(2));
+        assert_eq!(match_device("micro", &names), MatchResult::Unique(2));
+        assert_eq!(match_device("m", &names).pick(|_| false), Some(0));
+        assert_eq!(match_device("zoom", &names), MatchResult::None);
+        assert_eq!(MatchResult::Ambiguous(vec![0, 4]).pick(|i| i == 4), Some(4));
+    }
+
+    #[test]
+    fn test_resolve_device_prefers_index_among_duplicates() {
+        let devices = vec![
+            device("MacBook Pro Microphone", 0),
+            device("USB Mic", 2),
+            device("USB Mic", 3),
+        ];
+        let index_of = |name, index| resolve_device(&devices, name, index).map(|d| d.index);
+
+        assert_eq!(index_of("usb", None), Some(2));
+        assert_eq!(index_of("usb", Some(3)), Some(3));
+        // An index that doesn't match the name never wins
+        assert_eq!(index_of("usb", Some(0)), Some(2));
+        assert_eq!(index_of("macbook", Some(3)), Some(0));
+        assert_eq!(index_of("missing", Some(2)), None);
+    }
 }
@@ -0,0 +1,107 @@
+use std::f64::consts::PI;
+
+/// Cutoff used when a high-pass is switched on from the dashboard
+pub const DEFAULT_HIGHPASS_HZ: f32 = 80.0;
+
+/// Lowest cutoff accepted; below this the filter would only remove DC
+const MIN_CUTOFF_HZ: f32 = 5.0;
+
+/// Butterworth (Q = 1/√2) high-pass biquad for one channel, 12 dB/octave
+///
+/// Coefficients are computed once for a cutoff and sample rate, and the
+/// state is kept in `f64` so low cutoffs stay stable. Runs inside the audio
+/// callback: `process` does no allocation or locking.
+#[derive(Debug, Clone)]
+pub struct HighPass {
+    cutoff_hz: f32,
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    // Transposed direct form II state
+    z1: f64,
+    z2: f64,
+}
+
+impl HighPass {
+    pub fn new(cutoff_hz: f32, sample_rate: u32) -> Self {
+        let mut filter = Self {
+            cutoff_hz,
+            b0: 1.0,
+            b1: 0.0,
+            b2: 0.0,
+            a1: 0.0,
+            a2: 0.0,
+            z1: 0.0,
+            z2: 0.0,
+        };
+        filter.set_cutoff(cutoff_hz, sample_rate);
+        filter
+    }
+
+    /// Cutoff this filter was built for (before clamping to the sample rate)
+    pub fn cutoff_hz(&self) -> f32 {
+        self.cutoff_hz
+    }
+
+    /// Recompute the coefficients, keeping the state so the change doesn't click
+    pub fn set_cutoff(&mut self, cutoff_hz: f32, sample_rate: u32) {
+        let sample_rate = sample_rate.max(1) as f32;
+        let hz = cutoff_hz.clamp(MIN_CUTOFF_HZ, sample_rate * 0.45) as f64;
+        let w0 = 2.0 * PI * hz / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        // alpha = sin(w0) / (2Q) with Q = 1/√2
+        let alpha = sin * std::f64::consts::FRAC_1_SQRT_2;
+        let a0 = 1.0 + alpha;
+
+        self.cutoff_hz = cutoff_hz;
+        self.b0 = (1.0 + cos) / 2.0 / a0;
+        self.b1 = -(1.0 + cos) / a0;
+        self.b2 = self.b0;
+        self.a1 = -2.0 * cos / a0;
+        self.a2 = (1.0 - alpha) / a0;
+    }
+
+    /// Filter one sample
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let x = sample as f64;
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Peak output of the filter for a sine at `hz`, after it settled
+    fn gain_at(hz: f32) -> f32 {
+        let mut filter = HighPass::new(DEFAULT_HIGHPASS_HZ, 48000);
+        let mut peak = 0.0f32;
+        for i in 0..48000 {
+            let phase = 2.0 * std::f32::consts::PI * hz * i as f32 / 48000.0;
+            let out = filter.process(phase.sin());
+            if i >= 24000 {
+                peak = peak.max(out.abs());
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn test_highpass_response() {
+        // DC is removed entirely
+        let mut filter = HighPass::new(DEFAULT_HIGHPASS_HZ, 48000);
+        let settled = (0..48000).map(|_| filter.process(0.5)).last().unwrap();
+        assert!(settled.abs() < 1e-4);
+
+        // -3 dB at the cutoff, ~-24 dB two octaves below, flat above
+        assert!((gain_at(80.0) - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.01);
+        assert!(gain_at(20.0) < 0.07);
+        assert!((gain_at(1000.0) - 1.0).abs() < 0.01);
+        assert!((gain_at(5000.0) - 1.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,136 @@
+/// Time constant of the level detector feeding the gate decision
+const DETECTOR_RELEASE_MS: f32 = 10.0;
+
+/// Noise gate parameters for one channel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GateSettings {
+    /// Level below which the gate closes (dBFS)
+    pub threshold_db: f32,
+    /// Time for the gate to fade in once the level crosses the threshold
+    pub attack_ms: f32,
+    /// Time for the gate to fade out once the level drops below the threshold
+    pub release_ms: f32,
+}
+
+/// Per-channel noise gate with attack/release smoothing
+///
+/// Runs inside the audio callback: `process` does no allocation or locking.
+#[derive(Debug, Clone)]
+pub struct NoiseGate {
+    threshold: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    detector_coeff: f32,
+    envelope: f32,
+    gain: f32,
+}
+
+impl NoiseGate {
+    pub fn new(settings: GateSettings, sample_rate: u32) -> Self {
+        Self {
+            threshold: super::db_to_amplitude(settings.threshold_db),
+            attack_coeff: smoothing_coeff(settings.attack_ms, sample_rate),
+            release_coeff: smoothing_coeff(settings.release_ms, sample_rate),
+            detector_coeff: smoothing_coeff(DETECTOR_RELEASE_MS, sample_rate),
+            envelope: 0.0,
+            // Start closed so noise before the first loud sample is suppressed
+            gain: 0.0,
+        }
+    }
+
+    /// Apply the gate to one sample
+    pub fn process(&mut self, sample: f32) -> f32 {
+        // Peak detector: instant rise, short exponential fall
+        let abs = sample.abs();
+        if abs > self.envelope {
+            self.envelope = abs;
+        } else {
+            self.envelope -= (self.envelope - abs) * self.detector_coeff;
+        }
+
+        let target = if self.is_open() { 1.0 } else { 0.0 };
+        let coeff = if target > self.gain {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.gain += (target - self.gain) * coeff;
+
+        sample * self.gain
+    }
+
+    /// Whether the detected level is currently above the threshold
+    pub fn is_open(&self) -> bool {
+        self.envelope >= self.threshold
+    }
+
+    /// Current gain applied to samples (0.0 closed, 1.0 open)
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+}
+
+/// One-pole smoothing coefficient reaching ~63% of a step after `ms`
+fn smoothing_coeff(ms: f32, sample_rate: u32) -> f32 {
+    let samples = ms * sample_rate as f32 / 1000.0;
+    if samples <= 1.0 {
+        1.0
+    } else {
+        1.0 - (-1.0 / samples).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48000;
+
+    fn gate() -> NoiseGate {
+        NoiseGate::new(
+            GateSettings {
+                threshold_db: -40.0,
+                attack_ms: 1.0,
+                release_ms: 20.0,
+            },
+            RATE,
+        )
+    }
+
+    fn feed(gate: &mut NoiseGate, amplitude: f32, frames: usize) -> f32 {
+        let mut last = 0.0;
+        for i in 0..frames {
+            let sample = if i % 2 == 0 { amplitude } else { -amplitude };
+            last = gate.process(sample);
+        }
+        last
+    }
+
+    #[test]
+    fn test_gate_stays_closed_on_noise() {
+        let mut gate = gate();
+        // -60 dB noise, well under the -40 dB threshold
+        let out = feed(&mut gate, 0.001, 4800);
+        assert!(!gate.is_open());
+        assert!(out.abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_gate_opens_and_releases() {
+        let mut gate = gate();
+
+        // Loud block opens the gate after the attack
+        let out = feed(&mut gate, 0.5, 480);
+        assert!(gate.is_open());
+        assert!((out.abs() - 0.5).abs() < 0.01);
+
+        // A short dip is bridged by the release, avoiding chatter
+        feed(&mut gate, 0.001, 48);
+        assert!(gate.gain() > 0.9);
+
+        // Sustained quiet closes it
+        let out = feed(&mut gate, 0.001, 9600);
+        assert!(!gate.is_open());
+        assert!(out.abs() < 1e-4);
+    }
+}
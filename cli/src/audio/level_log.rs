@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Buffered rows are written out at least this often
+pub const LEVEL_LOG_FLUSH: Duration = Duration::from_secs(1);
+
+/// CSV time series of per-channel levels (`duomic run --log-levels`)
+///
+/// One row per call to [`LevelLog::record`]: Unix time in seconds, then
+/// peak and RMS per device channel as linear amplitudes (0.0 to 1.0). The
+/// header is written with the first row, once the channel count is known;
+/// later rows are padded or cut to that many channels.
+pub struct LevelLog<W: Write = BufWriter<File>> {
+    writer: W,
+    channels: Option<usize>,
+    last_flush: Instant,
+}
+
+impl LevelLog {
+    /// Create (or truncate) the CSV file at `path`
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create level log {}", path.display()))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> LevelLog<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            channels: None,
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Append a row of `peaks` and `rms` (per device channel) taken at `time`
+    pub fn record(&mut self, time: SystemTime, peaks: &[f32], rms: &[f32]) -> Result<()> {
+        let channels = match self.channels {
+            Some(channels) => channels,
+            None => {
+                let mut header = String::from("timestamp");
+                for ch in 0..peaks.len() {
+                    header.push_str(&format!(",ch{}_peak,ch{}_rms", ch, ch));
+                }
+                writeln!(self.writer, "{}", header)?;
+                *self.channels.insert(peaks.len())
+            }
+        };
+
+        let seconds = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+        let mut row = format!("{:.3}", seconds);
+        for ch in 0..channels {
+            let peak = peaks.get(ch).copied().unwrap_or(0.0);
+            let rms = rms.get(ch).copied().unwrap_or(0.0);
+            row.push_str(&format!(",{:.6},{:.6}", peak, rms));
+        }
+        writeln!(self.writer, "{}", row)?;
+
+        if self.last_flush.elapsed() >= LEVEL_LOG_FLUSH {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write out buffered rows
+    pub fn flush(&mut self) -> Result<()> {
+        self.last_flush = Instant::now();
+        self.writer.flush().context("Failed to write level log")
+    }
+
+    /// Flush and hand back the writer
+    pub fn finish(mut self) -> Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_log_rows() {
+        let mut log = LevelLog::new(Vec::new());
+        let at = |ms| UNIX_EPOCH + Duration::from_millis(ms);
+        log.record(at(1_500), &[0.5, 0.25], &[0.1, 0.05]).unwrap();
+        // Rows follow the header's channel count
+        log.record(at(1_600), &[1.0], &[0.2]).unwrap();
+        log.record(at(1_700), &[0.0, 0.0, 0.9], &[]).unwrap();
+
+        let csv = String::from_utf8(log.finish().unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "timestamp,ch0_peak,ch0_rms,ch1_peak,ch1_rms",
+                "1.500,0.500000,0.100000,0.250000,0.050000",
+                "1.600,1.000000,0.200000,0.000000,0.000000",
+                "1.700,0.000000,0.000000,0.000000,0.000000",
+            ]
+        );
+    }
+}
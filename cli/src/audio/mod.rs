@@ -1,8 +1,20 @@
 // Audio library - some methods are prepared for future use
 #![allow(dead_code)]
 
+mod alerts;
+mod backend;
 mod capture;
 mod devices;
+mod filter;
+mod gate;
+mod level_log;
+mod monitor;
 
+pub use alerts::*;
+pub use backend::*;
 pub use capture::*;
 pub use devices::*;
+pub use filter::*;
+pub use gate::*;
+pub use level_log::*;
+pub use monitor::*;
@@ -0,0 +1,164 @@
+use anyhow::{bail, Context, Result};
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, StreamConfig};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::resolve_output_device;
+use crate::ipc::SharedAudioBuffer;
+
+/// How far behind write_pos playback starts (and resyncs to), in frames
+///
+/// 2048 frames is ~43ms at 48kHz, on top of the output device's own buffer.
+pub const MONITOR_LATENCY_FRAMES: u32 = 2048;
+
+/// Plays one channel of the shared memory ring buffer on an output device
+///
+/// Reads through a read-only mapping, so it works alongside any capture that
+/// writes the buffer (the `run` preview, a full session, or another process).
+pub struct ChannelMonitor {
+    _stream: cpal::Stream,
+    channel: Arc<AtomicUsize>,
+    channel_count: usize,
+    device_name: String,
+    output_rate: u32,
+    capture_rate: u32,
+}
+
+impl ChannelMonitor {
+    /// Start playing `channel` (0-based) on the named output (partial match),
+    /// or the default output if unset or not found
+    pub fn start(channel: usize, output: Option<&str>) -> Result<Self> {
+        let device = resolve_output_device(output)?;
+        Self::start_on(&device, channel)
+    }
+
+    /// Start playing `channel` (0-based) on a specific output device
+    pub fn start_on(device: &cpal::Device, channel: usize) -> Result<Self> {
+        let shm = SharedAudioBuffer::open_readonly()
+            .context("Shared memory not found - is `duomic run` capturing?")?;
+
+        let channel_count = shm.channel_count() as usize;
+        if channel >= channel_count {
+            bail!(
+                "Channel {} out of range (shared memory has {} channels)",
+                channel,
+                channel_count
+            );
+        }
+
+        let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+
+        let config = device
+            .default_output_config()
+            .context("Failed to get default output config")?;
+        let sample_format = config.sample_format();
+        let stream_config: StreamConfig = config.into();
+        let output_rate = stream_config.sample_rate.0;
+        let capture_rate = shm.sample_rate();
+
+        let channel = Arc::new(AtomicUsize::new(channel));
+        let stream = match sample_format {
+            SampleFormat::F32 => build_stream::<f32>(device, &stream_config, shm, channel.clone())?,
+            SampleFormat::I16 => build_stream::<i16>(device, &stream_config, shm, channel.clone())?,
+            SampleFormat::U16 => build_stream::<u16>(device, &stream_config, shm, channel.clone())?,
+            _ => bail!("Unsupported output sample format: {:?}", sample_format),
+        };
+        stream.play().context("Failed to start output stream")?;
+
+        tracing::debug!("Monitoring channel {:?} on {}", channel, device_name);
+
+        Ok(Self {
+            _stream: stream,
+            channel,
+            channel_count,
+            device_name,
+            output_rate,
+            capture_rate,
+        })
+    }
+
+    /// Switch to another channel (out-of-range channels are ignored)
+    pub fn set_channel(&self, channel: usize) {
+        if channel < self.channel_count {
+            self.channel.store(channel, Ordering::Relaxed);
+        }
+    }
+
+    /// Channel currently playing
+    pub fn channel(&self) -> usize {
+        self.channel.load(Ordering::Relaxed)
+    }
+
+    /// Output device name
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// Output sample rate (no resampling: pitch shifts if it differs from capture)
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    /// Capture sample rate from the shared memory header
+    pub fn capture_rate(&self) -> u32 {
+        self.capture_rate
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    shm: SharedAudioBuffer,
+    channel: Arc<AtomicUsize>,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let out_channels = config.channels as usize;
+    let shm_channels = shm.channel_count() as usize;
+    let capacity = shm.capacity_frames() as u32;
+
+    let mut read_pos = shm.write_pos().wrapping_sub(MONITOR_LATENCY_FRAMES);
+
+    // Pre-allocated interleaved scratch buffer (no allocation in the callback)
+    let mut scratch = vec![0.0f32; 4096 * shm_channels];
+
+    let err_fn = |err| {
+        tracing::error!("Output stream error: {}", err);
+    };
+
+    let stream = device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                // Fell too far behind: the frames at read_pos were overwritten
+                let lag = shm.write_pos().wrapping_sub(read_pos);
+                if lag > capacity - MONITOR_LATENCY_FRAMES {
+                    read_pos = shm.write_pos().wrapping_sub(MONITOR_LATENCY_FRAMES);
+                }
+
+                let channel = channel.load(Ordering::Relaxed);
+                let wanted = (data.len() / out_channels).min(scratch.len() / shm_channels);
+                let frames = shm.read_samples(read_pos, &mut scratch[..wanted * shm_channels]);
+                read_pos = read_pos.wrapping_add(frames as u32);
+
+                for (i, out_frame) in data.chunks_mut(out_channels).enumerate() {
+                    // Underrun (or callback larger than scratch): pad with silence
+                    let sample = if i < frames {
+                        scratch[i * shm_channels + channel]
+                    } else {
+                        0.0
+                    };
+                    for out in out_frame.iter_mut() {
+                        *out = T::from_sample(sample);
+                    }
+                }
+            },
+            err_fn,
+            None,
+        )
+        .context("Failed to build output stream")?;
+
+    Ok(stream)
+}
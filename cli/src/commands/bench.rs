@@ -0,0 +1,170 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::paint;
+use duomic::audio::{
+    default_input_device_name, find_device, get_cpal_device_for, AudioCapture, CaptureStats,
+    TimingSummary,
+};
+use duomic::capture_options;
+use duomic::config::Config;
+use duomic::ipc::{SharedAudioBuffer, StatsSnapshot};
+
+/// How often the peak queue is drained, like a UI tick
+const DRAIN_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Capture for `seconds` and print callback timing statistics
+///
+/// Uses `device`, else the configured device, else the default input, with
+/// the config's gates and mix so the processing matches `duomic run`.
+pub fn execute(seconds: u64, device: Option<String>, config_path: &Path) -> Result<()> {
+    if StatsSnapshot::read().ok().flatten().is_some() {
+        bail!("`duomic run` is capturing, stop it before benchmarking");
+    }
+
+    let config = Config::load_from(config_path).unwrap_or_default();
+    let (name, index) = match device {
+        Some(name) => (name, None),
+        None => match &config.device.name {
+            Some(name) => (name.clone(), config.device.index),
+            None => (default_input_device_name()?, None),
+        },
+    };
+    let device =
+        find_device(&name, index)?.with_context(|| format!("Device not found: {}", name))?;
+
+    let channels = config.device.capture_channels(device.channels);
+    let buffer = SharedAudioBuffer::open(channels as u32, device.default_sample_rate)?;
+    let mut options = capture_options(&config, channels);
+    options.measure_timing = true;
+    let capture = AudioCapture::start(&get_cpal_device_for(&device)?, buffer, &options)?;
+
+    println!(
+        "Benchmarking {} ({} ch, {} Hz) for {} s - press Ctrl+C to stop early",
+        device.name,
+        capture.channel_count(),
+        capture.sample_rate(),
+        seconds
+    );
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_handler = stop.clone();
+    ctrlc::set_handler(move || {
+        stop_handler.store(true, Ordering::SeqCst);
+    })
+    .ok();
+
+    let started = Instant::now();
+    let duration = Duration::from_secs(seconds);
+    while started.elapsed() < duration && !stop.load(Ordering::SeqCst) {
+        capture.peak_receiver().try_iter().for_each(drop);
+        thread::sleep(DRAIN_INTERVAL);
+    }
+
+    let summary = capture
+        .timing()
+        .map(|timing| timing.summary())
+        .unwrap_or_default();
+    print_summary(
+        &summary,
+        capture.sample_rate(),
+        capture.stats(),
+        started.elapsed(),
+    );
+    Ok(())
+}
+
+fn print_summary(
+    summary: &TimingSummary,
+    sample_rate: u32,
+    stats: &CaptureStats,
+    elapsed: Duration,
+) {
+    println!();
+    if summary.callbacks == 0 {
+        println!(
+            "{}",
+            paint(
+                "31",
+                format!("No callbacks in {:.1} s", elapsed.as_secs_f64())
+            )
+        );
+        return;
+    }
+
+    let ms = |d: Duration| format!("{:.3} ms", d.as_secs_f64() * 1e3);
+    let us = |d: Duration| format!("{:.1} µs", d.as_secs_f64() * 1e6);
+    println!(
+        "Callbacks:  {} in {:.1} s, {:.1} frames each (expected interval {})",
+        summary.callbacks,
+        elapsed.as_secs_f64(),
+        summary.block_frames,
+        ms(block_period(summary, sample_rate))
+    );
+    println!(
+        "Interval:   min {}  mean {}  max {}  stddev {}",
+        ms(summary.interval_min),
+        ms(summary.interval_mean),
+        ms(summary.interval_max),
+        ms(summary.interval_stddev)
+    );
+    println!(
+        "Processing: mean {}  max {}  ({:.2}% of the block period)",
+        us(summary.process_mean),
+        us(summary.process_max),
+        budget_used(summary, sample_rate) * 100.0
+    );
+    println!(
+        "SHM write:  mean {}  max {}",
+        us(summary.write_mean),
+        us(summary.write_max)
+    );
+
+    let problems = [
+        ("Xruns:", stats.xruns()),
+        ("Dropped peak updates:", stats.dropped_peaks()),
+        ("Write errors:", stats.write_errors()),
+    ];
+    for (label, count) in problems {
+        let line = format!("{} {}", label, count);
+        println!("{}", if count > 0 { paint("33", line) } else { line });
+    }
+}
+
+/// Audio time covered by an average callback
+fn block_period(summary: &TimingSummary, sample_rate: u32) -> Duration {
+    Duration::from_secs_f64(summary.block_frames / sample_rate.max(1) as f64)
+}
+
+/// Share of the block period the callback spends on average (0.0 to 1.0+)
+fn budget_used(summary: &TimingSummary, sample_rate: u32) -> f64 {
+    let period = block_period(summary, sample_rate).as_secs_f64();
+    if period > 0.0 {
+        summary.process_mean.as_secs_f64() / period
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_used() {
+        // 480 frames at 48 kHz is 10 ms; 1 ms of work is 10% of it
+        let summary = TimingSummary {
+            callbacks: 100,
+            block_frames: 480.0,
+            process_mean: Duration::from_millis(1),
+            ..TimingSummary::default()
+        };
+        assert_eq!(block_period(&summary, 48000), Duration::from_millis(10));
+        assert!((budget_used(&summary, 48000) - 0.1).abs() < 1e-9);
+        assert_eq!(budget_used(&TimingSummary::default(), 48000), 0.0);
+    }
+}
@@ -0,0 +1,333 @@
+use anyhow::{bail, Result};
+use cpal::traits::HostTrait;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use super::paint;
+use duomic::audio::list_input_devices;
+use duomic::config::Config;
+use duomic::ipc::{runtime_paths, DriverClient, SocketState};
+use duomic::DuomicError;
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// One diagnostic line: what was checked, what was found, how to fix it
+#[derive(Debug, Serialize)]
+struct Check {
+    name: &'static str,
+    status: CheckStatus,
+    detail: String,
+    /// A failing critical check makes `doctor` exit non-zero
+    critical: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hint: Option<&'static str>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            critical: true,
+            hint: None,
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: &'static str) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            critical: true,
+            hint: Some(hint),
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: &'static str) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            critical: false,
+            hint: Some(hint),
+        }
+    }
+
+    fn is_critical_failure(&self) -> bool {
+        self.critical && self.status == CheckStatus::Fail
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DoctorReport {
+    ok: bool,
+    checks: Vec<Check>,
+}
+
+/// Run all environment checks; errors out if a critical one fails
+pub fn execute(json: bool, config_path: &Path) -> Result<()> {
+    let paths = runtime_paths();
+    let checks = vec![
+        check_audio_host(),
+        check_input_devices(),
+        check_driver_socket(),
+        check_driver_ping(),
+        check_tmp_dir(&paths.dir, paths.is_default()),
+        check_shared_memory(&paths.shm),
+        check_config(config_path),
+    ];
+    let failed = checks.iter().filter(|c| c.is_critical_failure()).count();
+    let report = DoctorReport {
+        ok: failed == 0,
+        checks,
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_report(&report);
+    }
+
+    if failed > 0 {
+        bail!("{} critical check(s) failed", failed);
+    }
+    Ok(())
+}
+
+fn print_report(report: &DoctorReport) {
+    println!();
+    println!("duomic doctor");
+    println!();
+
+    for check in &report.checks {
+        let (symbol, color) = match check.status {
+            CheckStatus::Pass => ("✓", "32"),
+            CheckStatus::Warn => ("!", "33"),
+            CheckStatus::Fail => ("✗", "31"),
+        };
+        println!(
+            "  {} {:<16} {}",
+            paint(color, symbol),
+            check.name,
+            check.detail
+        );
+        if let (Some(hint), true) = (check.hint, check.status != CheckStatus::Pass) {
+            println!("  {:<18} {}", "", paint("36", hint));
+        }
+    }
+
+    println!();
+    if report.ok {
+        println!("{}", paint("32", "All critical checks passed"));
+    } else {
+        println!(
+            "{}",
+            paint("31", "Some critical checks failed, see the hints above")
+        );
+    }
+}
+
+fn check_audio_host() -> Check {
+    const NAME: &str = "CoreAudio host";
+    let host = cpal::default_host();
+    match host.input_devices() {
+        Ok(_) => Check::pass(NAME, format!("{:?} host reachable", host.id())),
+        Err(e) => Check::fail(
+            NAME,
+            format!("Cannot enumerate devices: {}", e),
+            "Restart CoreAudio: sudo killall coreaudiod",
+        ),
+    }
+}
+
+fn check_input_devices() -> Check {
+    const NAME: &str = "Input devices";
+    match list_input_devices() {
+        Ok(devices) if !devices.is_empty() => {
+            let names: Vec<String> = devices
+                .iter()
+                .map(|d| format!("[{}] {} ({} ch)", d.index, d.name, d.channels))
+                .collect();
+            Check::pass(NAME, names.join(", "))
+        }
+        Ok(_) => Check::fail(
+            NAME,
+            "No input devices found",
+            "Make sure the device is connected and allowed in System Settings → Privacy → Microphone",
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            e.to_string(),
+            "Restart CoreAudio: sudo killall coreaudiod",
+        ),
+    }
+}
+
+fn check_driver_socket() -> Check {
+    const NAME: &str = "Driver socket";
+    let socket = &runtime_paths().socket;
+    match DriverClient::socket_state() {
+        SocketState::Present => Check::pass(NAME, format!("{} exists", socket.display())),
+        SocketState::Stale => Check::fail(
+            NAME,
+            format!("{} is stale (connection refused)", socket.display()),
+            "Restart the driver (sudo killall coreaudiod) or delete the leftover socket file",
+        ),
+        SocketState::Missing => Check::fail(
+            NAME,
+            format!("{} not found", socket.display()),
+            "Install the driver (sudo ./install.sh), then: sudo killall coreaudiod",
+        ),
+    }
+}
+
+fn check_driver_ping() -> Check {
+    const NAME: &str = "Driver PING";
+    let mut client = DriverClient::new();
+    match client.ping() {
+        Ok(true) => Check::pass(NAME, "Driver responded"),
+        Ok(false) => Check::fail(
+            NAME,
+            "Unexpected reply",
+            "Restart the driver: sudo killall coreaudiod",
+        ),
+        Err(e @ DuomicError::DriverTimeout(_)) => Check::fail(
+            NAME,
+            format!("{:#}", e),
+            "The driver is loaded but hung; restart it: sudo killall coreaudiod",
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            format!("{:#}", e),
+            "Restart the driver: sudo killall coreaudiod",
+        ),
+    }
+}
+
+/// The socket and shared memory live in `dir`; a `shared` one (/tmp) must be
+/// world-writable and sticky, a custom `DUOMIC_RUNTIME_DIR` just writable
+fn check_tmp_dir(dir: &Path, shared: bool) -> Check {
+    const NAME: &str = "Runtime directory";
+    let mode = match fs::metadata(dir) {
+        Ok(meta) => meta.permissions().mode() & 0o7777,
+        Err(e) => {
+            return Check::fail(
+                NAME,
+                format!("{}: {}", dir.display(), e),
+                "Check that the directory exists",
+            )
+        }
+    };
+
+    let probe = dir.join(format!(".duomic-doctor-{}", std::process::id()));
+    let writable = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .is_ok();
+    let _ = fs::remove_file(&probe);
+
+    if !writable {
+        Check::fail(
+            NAME,
+            format!("{} is not writable (mode {:o})", dir.display(), mode),
+            "Restore the default permissions: sudo chmod 1777 /tmp",
+        )
+    } else if shared && mode & 0o1777 != 0o1777 {
+        Check::warn(
+            NAME,
+            format!("{} has mode {:o}, expected 1777", dir.display(), mode),
+            "The driver may not be able to reach the socket: sudo chmod 1777 /tmp",
+        )
+    } else {
+        Check::pass(
+            NAME,
+            format!("{} writable (mode {:o})", dir.display(), mode),
+        )
+    }
+}
+
+/// The capture buffer file must be writable by us (it's created on first run)
+fn check_shared_memory(path: &Path) -> Check {
+    const NAME: &str = "Shared memory";
+    if !path.exists() {
+        return Check::pass(
+            NAME,
+            format!("{} not created yet (created on first run)", path.display()),
+        );
+    }
+
+    match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(_) => Check::pass(NAME, format!("{} writable", path.display())),
+        Err(e) => Check::fail(
+            NAME,
+            format!("{}: {}", path.display(), e),
+            "Remove the stale file (another user may own it; one user at a time can run duomic)",
+        ),
+    }
+}
+
+fn check_config(path: &Path) -> Check {
+    const NAME: &str = "Config";
+    if !path.exists() {
+        return Check::warn(
+            NAME,
+            format!("{} not created yet", path.display()),
+            "Run duomic to set up virtual mics",
+        );
+    }
+
+    match Config::load_from(path) {
+        Ok(config) => Check::pass(
+            NAME,
+            format!(
+                "{} ({} virtual mics)",
+                path.display(),
+                config.virtual_mics.len()
+            ),
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            format!("{:#}", e),
+            "Fix the TOML syntax, or delete the file to start over",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_memory_and_tmp_checks() {
+        let dir = std::env::temp_dir().join(format!("duomic-doctor-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let shm = dir.join("audio");
+        assert_eq!(check_shared_memory(&shm).status, CheckStatus::Pass);
+        fs::write(&shm, b"").unwrap();
+        assert_eq!(check_shared_memory(&shm).status, CheckStatus::Pass);
+
+        // Writable but not sticky/world-writable: a warning, not a failure
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).unwrap();
+        let check = check_tmp_dir(&dir, true);
+        assert_eq!(check.status, CheckStatus::Warn);
+        assert!(!check.is_critical_failure());
+        // Fine for a custom runtime directory
+        assert_eq!(check_tmp_dir(&dir, false).status, CheckStatus::Pass);
+
+        assert!(check_tmp_dir(&dir.join("missing"), true).is_critical_failure());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,2 +1,55 @@
+pub mod bench;
+pub mod doctor;
+pub mod monitor;
+pub mod reset;
 pub mod run;
 pub mod status;
+pub mod sync;
+
+/// Whether printed output may use ANSI colors
+///
+/// `--no-color` and `[ui] color = false` are folded into `NO_COLOR` at startup.
+pub fn color_enabled() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+/// Wrap `text` in an SGR color code (e.g. "32" for green) when colors are on
+pub fn paint(code: &str, text: impl std::fmt::Display) -> String {
+    if color_enabled() {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Terminal color for a mic's `color` tag ("red", "light-blue", "#ff8800" or an index)
+pub fn tag_color(tag: &str) -> Option<ratatui::style::Color> {
+    tag.parse().ok()
+}
+
+/// SGR code for a mic's `color` tag, for `paint`
+pub fn tag_sgr(tag: &str) -> Option<String> {
+    use ratatui::style::Color;
+    let code = match tag_color(tag)? {
+        Color::Black => "30",
+        Color::Red => "31",
+        Color::Green => "32",
+        Color::Yellow => "33",
+        Color::Blue => "34",
+        Color::Magenta => "35",
+        Color::Cyan => "36",
+        Color::Gray => "37",
+        Color::DarkGray => "90",
+        Color::LightRed => "91",
+        Color::LightGreen => "92",
+        Color::LightYellow => "93",
+        Color::LightBlue => "94",
+        Color::LightMagenta => "95",
+        Color::LightCyan => "96",
+        Color::White => "97",
+        Color::Rgb(r, g, b) => return Some(format!("38;2;{};{};{}", r, g, b)),
+        Color::Indexed(i) => return Some(format!("38;5;{}", i)),
+        Color::Reset => return None,
+    };
+    Some(code.to_string())
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use duomic::audio::ChannelMonitor;
+use duomic::config::Config;
+
+/// Play one channel of the shared memory ring buffer on an output device
+///
+/// `output` overrides `[monitor] output_device`; the default output is used
+/// when neither is set or the device isn't found.
+pub fn execute(channel: u32, output: Option<String>, config_path: &Path) -> Result<()> {
+    let config = Config::load_from(config_path).unwrap_or_default();
+    let output = output.or(config.monitor.output_device);
+    let monitor = ChannelMonitor::start(channel as usize, output.as_deref())?;
+
+    if monitor.output_rate() != monitor.capture_rate() {
+        eprintln!(
+            "Warning: output runs at {} Hz but capture is {} Hz (no resampling, pitch will shift)",
+            monitor.output_rate(),
+            monitor.capture_rate()
+        );
+    }
+
+    println!(
+        "Monitoring channel {} ({} Hz) on {} - press Ctrl+C to stop",
+        channel,
+        monitor.capture_rate(),
+        monitor.device_name()
+    );
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_handler = stop.clone();
+    ctrlc::set_handler(move || {
+        stop_handler.store(true, Ordering::SeqCst);
+    })
+    .ok();
+
+    while !stop.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    drop(monitor);
+    println!();
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use super::paint;
+use duomic::config::MAX_EXTRA_DEVICES;
+use duomic::ipc::{runtime_paths, DriverClient, SocketState, StatsSnapshot};
+
+/// What `duomic reset` should remove
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResetOptions {
+    /// Virtual mics registered with the driver
+    pub devices: bool,
+    /// Config file
+    pub config: bool,
+    /// Shared memory, stats file and a stale socket
+    pub shm: bool,
+    /// Everything above
+    pub all: bool,
+    /// Don't ask before removing
+    pub force: bool,
+}
+
+impl ResetOptions {
+    /// The flags with `--all` expanded
+    fn targets(self) -> Self {
+        Self {
+            devices: self.devices || self.all,
+            config: self.config || self.all,
+            shm: self.shm || self.all,
+            ..self
+        }
+    }
+}
+
+pub fn execute(options: ResetOptions, config_path: &Path) -> Result<()> {
+    let options = options.targets();
+    if !(options.devices || options.config || options.shm) {
+        bail!("Nothing to reset: pass --devices, --shm, --config-file or --all");
+    }
+
+    let paths = runtime_paths();
+
+    println!("This will remove:");
+    if options.devices {
+        println!("  - all virtual mics registered with the driver");
+    }
+    if options.shm {
+        println!(
+            "  - {} and {} (and {} if stale)",
+            paths.shm.display(),
+            paths.stats.display(),
+            paths.socket.display()
+        );
+    }
+    if options.config {
+        println!("  - {}", config_path.display());
+    }
+
+    if !options.force && !confirm("Continue? [y/N] ")? {
+        println!("Nothing removed.");
+        return Ok(());
+    }
+    println!();
+
+    if options.devices {
+        reset_devices();
+    }
+    if options.shm {
+        if StatsSnapshot::read().ok().flatten().is_some() {
+            println!(
+                "{}",
+                paint(
+                    "33",
+                    "Skipped shared memory: `duomic run` is capturing, stop it first"
+                )
+            );
+        } else {
+            remove_file("Shared memory", &paths.shm)?;
+            // `[[extra_devices]]` buffers, only mentioned when present
+            for source in 1..=MAX_EXTRA_DEVICES as u32 {
+                let path = paths.shm_for(source);
+                if path.exists() {
+                    remove_file("Shared memory", &path)?;
+                }
+            }
+            remove_file("Stats file", &paths.stats)?;
+        }
+        match DriverClient::remove_stale_socket()? {
+            true => println!("Removed stale socket {}", paths.socket.display()),
+            false if DriverClient::socket_state() == SocketState::Present => {
+                println!(
+                    "Kept driver socket {} (driver is running)",
+                    paths.socket.display()
+                )
+            }
+            false => {}
+        }
+    }
+    if options.config {
+        remove_file("Config", config_path)?;
+    }
+
+    Ok(())
+}
+
+fn reset_devices() {
+    if DriverClient::socket_state() != SocketState::Present {
+        println!("Driver not running, no virtual mics to remove");
+        return;
+    }
+    match DriverClient::new().remove_all_devices() {
+        Ok(count) => println!("Removed {} virtual mic(s) from the driver", count),
+        Err(e) => println!(
+            "{}",
+            paint("31", format!("Failed to remove virtual mics: {:#}", e))
+        ),
+    }
+}
+
+/// Delete `path`, reporting what happened under `label`
+fn remove_file(label: &str, path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => println!("Removed {} {}", label.to_lowercase(), path.display()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            println!(
+                "{}",
+                paint("90", format!("{} {} not found", label, path.display()))
+            )
+        }
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to remove {}", path.display()));
+        }
+    }
+    Ok(())
+}
+
+/// Ask on stdin; only "y" or "yes" agrees (EOF counts as no)
+fn confirm(prompt: &str) -> Result<bool> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(is_yes(&answer))
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_targets_and_confirmation() {
+        let all = ResetOptions {
+            all: true,
+            ..ResetOptions::default()
+        }
+        .targets();
+        assert!(all.devices && all.config && all.shm);
+
+        let shm = ResetOptions {
+            shm: true,
+            ..ResetOptions::default()
+        }
+        .targets();
+        assert!(shm.shm && !shm.devices && !shm.config);
+
+        assert!(is_yes("y\n") && is_yes(" YES "));
+        assert!(!is_yes("") && !is_yes("n") && !is_yes("yep"));
+    }
+
+    #[test]
+    fn test_remove_file_ignores_missing() {
+        let path = std::env::temp_dir().join(format!("duomic-reset-test-{}", std::process::id()));
+        fs::write(&path, b"").unwrap();
+        remove_file("Test", &path).unwrap();
+        assert!(!path.exists());
+        remove_file("Test", &path).unwrap();
+    }
+}
@@ -1,38 +1,115 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use cpal::traits::DeviceTrait;
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Clear, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
 };
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-use crate::audio::{get_cpal_device, list_input_devices, AudioCapture, AudioDevice};
-use crate::config::{Config, VirtualMicConfig};
-use crate::ipc::{DeviceInfo, DriverClient, SharedAudioBuffer};
-use crate::tui::{
-    widgets::{DeviceList, HelpBar, LevelMeter},
-    AppEvent, EventHandler, KeyAction, Terminal,
+use duomic::audio::{
+    amplitude_to_db, clip_threshold, default_input_device_name, filter_devices,
+    get_cpal_device_for, list_input_devices, match_device, resolve_device, resolve_output_device,
+    ring_bell, Alert, AlertMonitor, AudioCapture, AudioDevice, CaptureOptions, ChannelMonitor,
+    LevelLog, MatchResult, DEFAULT_FLOOR_DB, DEFAULT_HIGHPASS_HZ, MONITOR_LATENCY_FRAMES,
+};
+use duomic::config::{
+    channel_label, default_mic_name, unique_name, Config, ConfigSaver, ConfigSource, ImportedNames,
+    MeterParams, VirtualMicConfig,
+};
+use duomic::ipc::{
+    buffer_fill, runtime_paths, DeviceInfo, DriverClient, DriverError, DriverErrorKind, DriverOp,
+    DriverTimeout, DriverWorker, Metrics, MetricsServer, MicMetrics, SharedAudioBuffer,
+    SocketState, StaleSocket, StatsSnapshot, SyncState, RESTART_DRIVER_COMMAND,
+};
+use duomic::{
+    channel_mix, correlation_pair, expected_mics, DeviceEvent, DeviceWatcher, DuomicError,
+    ExtraSource, RunningSession, Session,
 };
 
-/// Ring buffer size (must match shm.rs and Driver)
-const RING_BUFFER_FRAMES: u32 = 8192;
+use super::status::{build_report, spawn_driver_poller, DriverState, StatusReport};
+use super::{color_enabled, paint, tag_color};
+use crate::tui::{
+    is_interrupt,
+    widgets::{
+        truncate_to_width, CorrelationMeter, DeviceList, GainSlider, HelpBar, LevelMeter, TextInput,
+    },
+    AppEvent, EventHandler, KeyAction, KeyMap, Terminal,
+};
 
 /// Global flag for signal-triggered cleanup
 static CLEANUP_REQUESTED: AtomicBool = AtomicBool::new(false);
 
+/// How long a notice banner stays on screen
+const NOTICE_DURATION: Duration = Duration::from_secs(6);
+
+/// Digits typed within this window combine into one number (e.g. "1" "2" -> 12)
+const JUMP_DIGIT_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// How often devices and the driver are re-checked (plug/unplug, driver restarts)
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Tick rate on screens without meters (menus, name entry, errors)
+const IDLE_TICK: Duration = Duration::from_millis(250);
+
+/// Fastest allowed `[ui] tick_ms`
+const MIN_TICK_MS: u64 = 10;
+
+/// First and longest wait before kiosk mode retries a failed capture
+const KIOSK_RETRY_MIN: Duration = Duration::from_secs(1);
+const KIOSK_RETRY_MAX: Duration = Duration::from_secs(30);
+
+/// How long the dashboard header stays red after an alert
+const ALERT_FLASH: Duration = Duration::from_secs(1);
+
+/// How long the stats line stays red after a shared memory overrun
+const OVERRUN_FLAG: Duration = Duration::from_secs(5);
+
+/// Channel picker meters fall to this fraction every `PREVIEW_DECAY_STEP`
+const PREVIEW_DECAY: f32 = 0.92;
+const PREVIEW_DECAY_STEP: Duration = Duration::from_millis(50);
+
+/// Channels quieter than this during calibration get no suggestion
+const CALIBRATION_SILENCE_DB: f32 = -60.0;
+
+/// Gain step and range for the dashboard's +/- keys
+const GAIN_STEP_DB: f32 = 1.0;
+const MAX_GAIN_DB: f32 = 24.0;
+
+/// Columns for `[ui] gain_slider` after each meter, and the narrowest row
+/// that still gets one
+const GAIN_SLIDER_WIDTH: u16 = 13;
+const GAIN_SLIDER_MIN_ROW: u16 = 40;
+
+/// Longest mic name accepted while typing, in terminal columns (wide
+/// characters count twice); longer names from the config are shown truncated
+const MAX_NAME_WIDTH: usize = 32;
+
+/// Upper bound on how long the event loop waits before re-checking the signal flag
+const SIGNAL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Unified application state machine
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum AppState {
+    /// No input devices yet (waiting for one to be plugged in)
+    NoDevices,
     /// Initial: Check if config exists and ask user
     AskAction,
     /// Select input device
     SelectDevice,
     /// Multi-select channels to use
     SelectChannels,
+    /// Naming the channel just toggled on (`[ui] inline_naming`; typed into name_input)
+    NameChannel,
     /// Enter names for selected channels
     EnterNames,
     /// Running with dashboard
     Running,
+    /// Renaming or moving one mic from the dashboard (capture keeps running)
+    EditMic,
     /// Error state
     Error(String),
     /// Quit
@@ -42,19 +119,32 @@ enum AppState {
 struct App {
     state: AppState,
     config: Config,
+    // Config file from `main` and what chose it, shown under the action menu;
+    // None (as in tests) writes nothing
+    config_location: Option<(PathBuf, ConfigSource)>,
+    // Live edits are saved; off while running an unsaved one-shot setup, so
+    // its device and mics never replace the saved ones
+    persist_edits: bool,
+    keymap: KeyMap,
 
     // Device selection
     devices: Vec<AudioDevice>,
     selected_device_idx: usize,
     current_device: Option<AudioDevice>,
+    follows_default: bool, // Set up for the system default input (`--default`, see `default_input_changed`)
+    // Default input capture moved to; replaces `config.device` for this run only
+    capture_device: Option<AudioDevice>,
 
     // Channel selection (multi-select)
-    channel_selected: Vec<bool>, // Which channels are selected
-    channel_cursor: usize,       // Current cursor position
-    channel_levels: Vec<f32>,    // Real-time levels for preview
+    channel_selected: Vec<bool>,         // Which channels are selected
+    channel_cursor: usize,               // Current cursor position
+    channel_levels: Vec<f32>,            // Real-time levels for preview
+    preview_decayed_at: Option<Instant>, // Last `decay_preview`
+    monitoring: bool,                    // Cursor channel playing on the default output
 
     // Name entry
     channel_names: Vec<String>, // Names for selected channels
+    named_channels: Vec<usize>, // Channel of each entry in channel_names
     name_cursor: usize,         // Which channel name we're editing
     name_input: String,         // Current input buffer
 
@@ -63,52 +153,310 @@ struct App {
 
     // Dashboard
     dashboard_levels: Vec<f32>,
+    dashboard_peaks: Vec<f32>, // Loudest input per row since the last meter step
+    dashboard_holds: Vec<(f32, u32)>, // Peak-hold level and ticks left to hold it
+    dashboard_clips: Vec<u32>, // Ticks left to show a clip
     dashboard_labels: Vec<String>,
+    dashboard_channels: Vec<usize>, // Device channel of each row
+    dashboard_cursor: usize,        // Row whose gain/mute/invert the keys change
+    correlation: Option<f32>,       // Stereo pair correlation (None while silent)
+    dashboard_mics: Vec<usize>,     // Index in config.virtual_mics of each row
+    device_channels: usize,         // Channel count of the running device
+
+    // Mic edit (for EditMic state; the name is typed into name_input)
+    edit_mic: usize,       // Index in config.virtual_mics
+    edit_channel: u32,     // New source channel
+    edit_on_channel: bool, // Cursor on the channel field instead of the name
     start_time: Option<Instant>,
     buffer_usage: f32,
+    buffer_latency_ms: Option<f32>, // None until the driver publishes readPos
+    live_sample_rate: Option<u32>,  // Rate the stream opened at (None before capture)
+    dropped_peaks: u64,
+    write_errors: u64,
+    clipped_samples: u64,
+    xruns: u64,
+    stream_error: Option<String>, // Latest backend stream error
+    shm_overruns: u64,
+    last_overrun: Option<Instant>, // Flags BUFFER OVERRUN on the stats line
+    buffer_unread: bool,           // Driver stopped reading, audio is discarded
+
+    // Sample clock for the d overlay: frames written, and skew against the wall clock
+    frames_written: u64,
+    clock_skew: Option<f64>, // Seconds, None before capture
+    clock_overlay: bool,
+
+    // Per-mic peaks and clips for the exit summary
+    summary: SessionSummary,
+
+    // Transient banner (message, shown at)
+    notice: Option<(String, Instant)>,
+
+    // Gain suggestion: RMS readings being collected, then gains awaiting y/n
+    calibration: Option<Calibration>,
+    gain_suggestion: Option<Vec<(usize, f32)>>, // (device channel, suggested gain_db)
+
+    // Mics skipped for channels the device lacks, and whether to ask about removing them
+    out_of_range: Vec<DeviceInfo>,
+    out_of_range_prompt: bool,
+
+    // Running device with another channel count than at setup (asks to remap)
+    layout_change: Option<LayoutChange>,
+
+    // `[[extra_devices]]` captured alongside, a meter group each
+    extra_groups: Vec<ExtraGroup>,
+
+    // `--names-file` names, used by the first names step (or inline prompts)
+    imported_names: Option<ImportedNames>,
+
+    // `[alerts]` on clip/disconnect, and when the header last flashed
+    alerts: AlertMonitor,
+    alert_flash: Option<Instant>,
+
+    // Numeric jump (digits typed so far, time of last digit)
+    jump_digits: String,
+    jump_last: Option<Instant>,
+
+    // Unsaved `[ui]` preference, mix or mic edits, written on Tick
+    config_saver: ConfigSaver,
+
+    // Driver error behind the current Error state, for tailored suggestions
+    error_kind: Option<DriverErrorKind>,
+    // Driver socket as last probed on an error (a stale one can be deleted)
+    driver_socket: SocketState,
+    // Whether the driver answered PING when last checked with c (None = not checked)
+    driver_ping: Option<bool>,
+    // Step of error screen `c` waiting on the driver worker
+    recovery: Option<Recovery>,
+
+    // Driver commands still running on the worker thread (spinner frame)
+    driver_busy: bool,
+    spinner: usize,
+
+    // `[behavior] confirm_quit` overlay is up, waiting for a second key
+    confirm_quit: bool,
+
+    // `--kiosk`: no setup, no prompts, and the error screen retries by itself
+    kiosk: bool,
+    kiosk_retries: u32,
+    kiosk_retry_at: Option<Instant>,
+
+    // `--dashboard`: driver/config status above the meters (None until polled)
+    flat: bool,
+    status: Option<StatusReport>,
 }
 
 impl App {
     fn new(devices: Vec<AudioDevice>, config: Config) -> Self {
-        let has_config = config.device.name.is_some() && !config.virtual_mics.is_empty();
-        let initial_state = if has_config {
-            AppState::AskAction
-        } else {
-            AppState::SelectDevice
-        };
+        let initial_state = Self::initial_state(&devices, &config);
+        let selected_device_idx = Self::last_device_index(&devices, &config);
+
+        let (mut keymap, _) = KeyMap::from_config(&config.keybindings);
+        keymap.set_vim_keys(config.ui.vim_keys);
+        let alerts = Self::alert_monitor(&config);
 
         Self {
             state: initial_state,
+            keymap,
             config,
+            config_location: None,
+            persist_edits: true,
             devices,
-            selected_device_idx: 0,
+            selected_device_idx,
             current_device: None,
+            follows_default: false,
+            capture_device: None,
             channel_selected: Vec::new(),
             channel_cursor: 0,
             channel_levels: Vec::new(),
+            preview_decayed_at: None,
+            monitoring: false,
             channel_names: Vec::new(),
+            named_channels: Vec::new(),
             name_cursor: 0,
             name_input: String::new(),
             action_cursor: 0,
             dashboard_levels: Vec::new(),
+            dashboard_peaks: Vec::new(),
+            dashboard_holds: Vec::new(),
+            dashboard_clips: Vec::new(),
+            correlation: None,
             dashboard_labels: Vec::new(),
+            dashboard_channels: Vec::new(),
+            dashboard_cursor: 0,
+            dashboard_mics: Vec::new(),
+            device_channels: 0,
+            edit_mic: 0,
+            edit_channel: 0,
+            edit_on_channel: false,
             start_time: None,
             buffer_usage: 0.0,
+            buffer_latency_ms: None,
+            live_sample_rate: None,
+            dropped_peaks: 0,
+            write_errors: 0,
+            clipped_samples: 0,
+            xruns: 0,
+            stream_error: None,
+            shm_overruns: 0,
+            last_overrun: None,
+            buffer_unread: false,
+            frames_written: 0,
+            clock_skew: None,
+            clock_overlay: false,
+            summary: SessionSummary::default(),
+            notice: None,
+            calibration: None,
+            gain_suggestion: None,
+            out_of_range: Vec::new(),
+            out_of_range_prompt: false,
+            layout_change: None,
+            extra_groups: Vec::new(),
+            imported_names: None,
+            alerts,
+            alert_flash: None,
+            jump_digits: String::new(),
+            jump_last: None,
+            config_saver: ConfigSaver::default(),
+            error_kind: None,
+            driver_socket: SocketState::Present,
+            driver_ping: None,
+            recovery: None,
+            driver_busy: false,
+            spinner: 0,
+            confirm_quit: false,
+            kiosk: false,
+            kiosk_retries: 0,
+            kiosk_retry_at: None,
+            flat: false,
+            status: None,
+        }
+    }
+
+    fn initial_state(devices: &[AudioDevice], config: &Config) -> AppState {
+        let has_config = config.device.name.is_some() && !config.virtual_mics.is_empty();
+        if devices.is_empty() {
+            AppState::NoDevices
+        } else if has_config {
+            AppState::AskAction
+        } else {
+            AppState::SelectDevice
+        }
+    }
+
+    /// Replace the device list, leaving the "no devices" screen once one appears
+    fn set_devices(&mut self, devices: Vec<AudioDevice>) {
+        self.devices = devices;
+        // The list may have shrunk under the selector's cursor
+        self.selected_device_idx = self
+            .selected_device_idx
+            .min(self.devices.len().saturating_sub(1));
+        if self.state == AppState::NoDevices && !self.devices.is_empty() {
+            self.selected_device_idx = Self::last_device_index(&self.devices, &self.config);
+            self.state = Self::initial_state(&self.devices, &self.config);
+        }
+    }
+
+    /// Tip for the device selector when an Aggregate Device would help
+    ///
+    /// Shown when the highlighted device has fewer channels than the saved mics
+    /// use, or when several single-channel inputs are connected (one device
+    /// per mic, which duomic can't capture together).
+    fn aggregate_hint(&self) -> Option<String> {
+        let wanted = self
+            .config
+            .virtual_mics
+            .iter()
+            .map(|m| m.channel as usize + 1)
+            .max()
+            .unwrap_or(0);
+        let mono = self.devices.iter().filter(|d| d.channels == 1).count();
+
+        let reason = match self.devices.get(self.selected_device_idx) {
+            Some(device) if (device.channels as usize) < wanted => format!(
+                "{} has {} channel(s), your saved mics use {}.",
+                device.name, device.channels, wanted
+            ),
+            _ if mono >= 2 => format!("{} single-channel inputs are connected.", mono),
+            _ => return None,
+        };
+        let refresh = match self.keymap.label(KeyAction::Restart).as_str() {
+            "-" => String::new(),
+            key => format!("; {} refreshes the list", key),
+        };
+        Some(format!(
+            "Tip: {} To capture several inputs as one device, combine them into an \
+             Aggregate Device (Audio MIDI Setup → + → Create Aggregate Device). \
+             It shows up here once created{}.",
+            reason, refresh
+        ))
+    }
+
+    /// Index of the last-used device in `devices`, or 0 if it's no longer present
+    ///
+    /// With several devices of that name, the saved enumeration index picks one.
+    fn last_device_index(devices: &[AudioDevice], config: &Config) -> usize {
+        let Some(name) = config.device.last_device.as_ref() else {
+            return 0;
+        };
+        let same_name = || devices.iter().position(|d| &d.name == name);
+        devices
+            .iter()
+            .position(|d| &d.name == name && Some(d.index) == config.device.index)
+            .or_else(same_name)
+            .unwrap_or(0)
+    }
+
+    /// Feed a typed digit and return the 0-based index to jump to
+    ///
+    /// Digits typed in quick succession form a multi-digit 1-based number. If
+    /// that number is out of range the latest digit alone is used instead.
+    fn jump_index(&mut self, digit: char, len: usize) -> Option<usize> {
+        let continuing = self
+            .jump_last
+            .is_some_and(|t| t.elapsed() < JUMP_DIGIT_TIMEOUT);
+        if !continuing {
+            self.jump_digits.clear();
+        }
+        self.jump_digits.push(digit);
+        self.jump_last = Some(Instant::now());
+
+        let in_range = |n: usize| (1..=len).contains(&n).then(|| n - 1);
+
+        if let Some(idx) = self.jump_digits.parse().ok().and_then(in_range) {
+            return Some(idx);
         }
+
+        self.jump_digits.clear();
+        self.jump_digits.push(digit);
+        digit.to_digit(10).and_then(|n| in_range(n as usize))
     }
 
     fn handle_key(&mut self, action: KeyAction) -> Option<AppAction> {
         match &self.state {
+            AppState::NoDevices => self.handle_no_devices(action),
             AppState::AskAction => self.handle_ask_action(action),
             AppState::SelectDevice => self.handle_select_device(action),
             AppState::SelectChannels => self.handle_select_channels(action),
+            AppState::NameChannel => self.handle_name_channel(action),
             AppState::EnterNames => self.handle_enter_names(action),
             AppState::Running => self.handle_running(action),
+            AppState::EditMic => self.handle_edit_mic(action),
             AppState::Error(_) => self.handle_error(action),
             AppState::Quit => None,
         }
     }
 
+    fn handle_no_devices(&mut self, action: KeyAction) -> Option<AppAction> {
+        match action {
+            KeyAction::Restart | KeyAction::Select => Some(AppAction::RefreshDevices),
+            KeyAction::Quit | KeyAction::Cancel => {
+                self.state = AppState::Quit;
+                None
+            }
+            _ => None,
+        }
+    }
+
     fn handle_ask_action(&mut self, action: KeyAction) -> Option<AppAction> {
         match action {
             KeyAction::Up | KeyAction::Down => {
@@ -121,6 +469,7 @@ impl App {
                     Some(AppAction::StartWithConfig)
                 } else {
                     // New configuration
+                    self.selected_device_idx = Self::last_device_index(&self.devices, &self.config);
                     self.state = AppState::SelectDevice;
                     None
                 }
@@ -147,15 +496,24 @@ impl App {
                 }
                 None
             }
+            KeyAction::Char(c) if c.is_ascii_digit() => {
+                if let Some(idx) = self.jump_index(c, self.devices.len()) {
+                    self.selected_device_idx = idx;
+                }
+                None
+            }
+            // e.g. after creating an Aggregate Device (hotplug usually beats this)
+            KeyAction::Restart => Some(AppAction::RefreshDevices),
             KeyAction::Select => {
                 if let Some(device) = self.devices.get(self.selected_device_idx).cloned() {
-                    let channels = device.channels as usize;
-                    self.current_device = Some(device);
-                    self.channel_selected = vec![false; channels];
-                    self.channel_cursor = 0;
-                    self.channel_levels = vec![0.0; channels];
-                    self.state = AppState::SelectChannels;
-                    Some(AppAction::StartPreview)
+                    if !device.is_usable() {
+                        self.set_notice(format!(
+                            "{} reports no usable input ({} channels at {} Hz), pick another device",
+                            device.name, device.channels, device.default_sample_rate
+                        ));
+                        return None;
+                    }
+                    Some(self.open_channel_select(device))
                 } else {
                     None
                 }
@@ -184,37 +542,62 @@ impl App {
                 }
                 None
             }
-            KeyAction::Char(' ') => {
+            KeyAction::Char(c) if c.is_ascii_digit() => {
+                if let Some(idx) = self.jump_index(c, channel_count) {
+                    self.channel_cursor = idx;
+                }
+                None
+            }
+            KeyAction::Char('m') => {
+                // Listen to the cursor channel (follows the cursor)
+                self.monitoring = !self.monitoring;
+                Some(if self.monitoring {
+                    AppAction::StartMonitor
+                } else {
+                    AppAction::StopMonitor
+                })
+            }
+            KeyAction::Toggle => {
                 // Toggle selection
-                if self.channel_cursor < self.channel_selected.len() {
-                    self.channel_selected[self.channel_cursor] =
-                        !self.channel_selected[self.channel_cursor];
+                if let Some(selected) = self.channel_selected.get_mut(self.channel_cursor) {
+                    *selected = !*selected;
+                    if *selected && self.config.ui.inline_naming {
+                        let channel = self.channel_cursor;
+                        self.name_input = self
+                            .inline_name(channel)
+                            .or_else(|| self.imported_name(channel))
+                            .unwrap_or_default()
+                            .to_string();
+                        self.state = AppState::NameChannel;
+                    }
                 }
                 None
             }
+            KeyAction::Char('a') => {
+                self.channel_selected.fill(true);
+                None
+            }
+            KeyAction::Char('A') | KeyAction::Char('d') => {
+                self.channel_selected.fill(false);
+                None
+            }
+            KeyAction::Char('i') => {
+                self.channel_selected.iter_mut().for_each(|s| *s = !*s);
+                None
+            }
             KeyAction::Select => {
                 // Confirm selection - at least one channel must be selected
                 let selected_count = self.channel_selected.iter().filter(|&&s| s).count();
-                if selected_count > 0 {
-                    // Prepare name entry
-                    self.channel_names = self
-                        .channel_selected
-                        .iter()
-                        .filter_map(
-                            |&selected| {
-                                if selected {
-                                    Some(String::new())
-                                } else {
-                                    None
-                                }
-                            },
-                        )
-                        .collect();
-                    self.name_cursor = 0;
-                    self.name_input.clear();
-                    self.state = AppState::EnterNames;
+                if selected_count == 0 {
+                    None
+                } else if self.config.ui.inline_naming {
+                    // Names were typed while toggling; defaults for the rest
+                    self.enter_names();
+                    self.handle_enter_names(KeyAction::AcceptAll)
+                } else {
+                    self.enter_names();
+                    None
                 }
-                None
             }
             KeyAction::Cancel => {
                 self.state = AppState::SelectDevice;
@@ -228,10 +611,104 @@ impl App {
         }
     }
 
+    fn handle_name_channel(&mut self, action: KeyAction) -> Option<AppAction> {
+        match action {
+            KeyAction::Char(c) => {
+                if self.name_input.width() + c.width().unwrap_or(0) <= MAX_NAME_WIDTH {
+                    self.name_input.push(c);
+                }
+                None
+            }
+            KeyAction::Backspace => {
+                self.name_input.pop();
+                None
+            }
+            KeyAction::Select => {
+                self.commit_inline_name();
+                self.state = AppState::SelectChannels;
+                if self.channel_cursor + 1 < self.channel_selected.len() {
+                    self.channel_cursor += 1;
+                }
+                None
+            }
+            KeyAction::Cancel => {
+                // Undo the toggle that opened the prompt
+                if let Some(selected) = self.channel_selected.get_mut(self.channel_cursor) {
+                    *selected = false;
+                }
+                self.state = AppState::SelectChannels;
+                None
+            }
+            KeyAction::Quit => {
+                self.state = AppState::Quit;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// `--names-file` name for `channel` with the current selection
+    fn imported_name(&self, channel: usize) -> Option<&str> {
+        let position = self
+            .selected_channels()
+            .iter()
+            .position(|&ch| ch == channel)?;
+        self.imported_names
+            .as_ref()?
+            .name_for(channel as u32, position)
+    }
+
+    /// Name kept for `channel` from inline naming or an earlier pass
+    fn inline_name(&self, channel: usize) -> Option<&str> {
+        self.named_channels
+            .iter()
+            .position(|&named| named == channel)
+            .and_then(|i| self.channel_names.get(i))
+            .map(String::as_str)
+    }
+
+    /// Store the typed name (or the default) for the channel at `channel_cursor`
+    fn commit_inline_name(&mut self) {
+        let channel = self.channel_cursor;
+        let name = if self.name_input.is_empty() {
+            let index = self
+                .selected_channels()
+                .iter()
+                .position(|&ch| ch == channel)
+                .unwrap_or(0);
+            self.generate_default_name(index)
+        } else {
+            self.name_input.clone()
+        };
+
+        // Same rule as the names step: other selected channels keep theirs
+        let unique = unique_name(&name, |candidate| {
+            self.named_channels
+                .iter()
+                .zip(&self.channel_names)
+                .any(|(&ch, n)| {
+                    ch != channel && self.channel_selected.get(ch) == Some(&true) && n == candidate
+                })
+        });
+        if unique != name {
+            self.set_notice(format!(
+                "\"{}\" is already used, renamed to \"{}\"",
+                name, unique
+            ));
+        }
+        match self.named_channels.iter().position(|&ch| ch == channel) {
+            Some(i) => self.channel_names[i] = unique,
+            None => {
+                self.named_channels.push(channel);
+                self.channel_names.push(unique);
+            }
+        }
+    }
+
     fn handle_enter_names(&mut self, action: KeyAction) -> Option<AppAction> {
         match action {
             KeyAction::Char(c) => {
-                if self.name_input.len() < 32 {
+                if self.name_input.width() + c.width().unwrap_or(0) <= MAX_NAME_WIDTH {
                     self.name_input.push(c);
                 }
                 None
@@ -242,21 +719,26 @@ impl App {
             }
             KeyAction::Select => {
                 // Save current name and move to next or finish
-                self.channel_names[self.name_cursor] = if self.name_input.is_empty() {
-                    // Auto-generate name
-                    self.generate_default_name(self.name_cursor)
-                } else {
-                    self.name_input.clone()
-                };
+                self.commit_name();
 
                 if self.name_cursor + 1 < self.channel_names.len() {
                     self.name_cursor += 1;
-                    self.name_input.clear();
+                    // Pre-fill a name kept from an earlier pass
+                    self.name_input = self.channel_names[self.name_cursor].clone();
                     None
                 } else {
-                    // All names entered, save config and start
-                    Some(AppAction::SaveAndStart)
+                    self.finish_names()
+                }
+            }
+            KeyAction::AcceptAll => {
+                // This name as typed, then defaults for every channel still unnamed
+                self.commit_name();
+                while self.name_cursor + 1 < self.channel_names.len() {
+                    self.name_cursor += 1;
+                    self.name_input = self.channel_names[self.name_cursor].clone();
+                    self.commit_name();
                 }
+                self.finish_names()
             }
             KeyAction::Cancel => {
                 if self.name_cursor > 0 {
@@ -276,24 +758,202 @@ impl App {
     }
 
     fn handle_running(&mut self, action: KeyAction) -> Option<AppAction> {
+        if self.confirm_quit {
+            match action {
+                KeyAction::Quit | KeyAction::Yes | KeyAction::Select => {
+                    self.state = AppState::Quit;
+                }
+                KeyAction::Cancel | KeyAction::No => self.confirm_quit = false,
+                _ => {}
+            }
+            return None;
+        }
+
         match action {
             KeyAction::Quit => {
-                self.state = AppState::Quit;
+                // Quitting takes the virtual mics away from any app using them
+                if self.kiosk
+                    || (self.config.behavior.confirm_quit && !self.dashboard_mics.is_empty())
+                {
+                    self.confirm_quit = true;
+                } else {
+                    self.state = AppState::Quit;
+                }
                 None
             }
             KeyAction::Restart => Some(AppAction::Restart),
+            KeyAction::Setup if self.kiosk => None,
             KeyAction::Setup => {
+                // Capture keeps running: picking the same device reuses it for the preview
+                self.selected_device_idx = Self::last_device_index(&self.devices, &self.config);
                 self.state = AppState::SelectDevice;
-                Some(AppAction::StopCapture)
+                None
+            }
+            KeyAction::Up => {
+                self.dashboard_cursor = self.dashboard_cursor.saturating_sub(1);
+                None
+            }
+            KeyAction::Down => {
+                if self.dashboard_cursor + 1 < self.dashboard_channels.len() {
+                    self.dashboard_cursor += 1;
+                }
+                None
+            }
+            // ←/→ move the gain slider, so only while it's drawn
+            KeyAction::Char('+') | KeyAction::Char('=') => self.change_mix(|mic| {
+                mic.gain_db = (mic.gain_db + GAIN_STEP_DB).min(MAX_GAIN_DB);
+            }),
+            KeyAction::Right if self.config.ui.gain_slider => self.change_mix(|mic| {
+                mic.gain_db = (mic.gain_db + GAIN_STEP_DB).min(MAX_GAIN_DB);
+            }),
+            KeyAction::Char('-') => self.change_mix(|mic| {
+                mic.gain_db = (mic.gain_db - GAIN_STEP_DB).max(-MAX_GAIN_DB);
+            }),
+            KeyAction::Left if self.config.ui.gain_slider => self.change_mix(|mic| {
+                mic.gain_db = (mic.gain_db - GAIN_STEP_DB).max(-MAX_GAIN_DB);
+            }),
+            KeyAction::Yes if self.gain_suggestion.is_some() => {
+                let suggestion = self.gain_suggestion.take()?;
+                for (channel, gain_db) in &suggestion {
+                    for mic in &mut self.config.virtual_mics {
+                        if mic.channel as usize == *channel {
+                            mic.gain_db = *gain_db;
+                        }
+                    }
+                }
+                self.config_saver.mark_dirty();
+                self.set_notice(format!("Applied gain to {} channel(s)", suggestion.len()));
+                Some(AppAction::ApplyMixAll)
+            }
+            KeyAction::No | KeyAction::Cancel if self.gain_suggestion.is_some() => {
+                self.gain_suggestion = None;
+                None
+            }
+            KeyAction::Yes if self.out_of_range_prompt => {
+                self.remove_out_of_range_mics();
+                None
+            }
+            KeyAction::No | KeyAction::Cancel if self.out_of_range_prompt => {
+                self.out_of_range_prompt = false;
+                None
+            }
+            KeyAction::Yes if self.layout_change.is_some() => {
+                let change = self.layout_change.take()?;
+                Some(self.remap_channels(change.device))
+            }
+            KeyAction::No | KeyAction::Cancel if self.layout_change.is_some() => {
+                // Take the new layout as the setup, so this isn't asked again
+                let change = self.layout_change.take()?;
+                let mics = self.config.virtual_mics.clone();
+                self.config
+                    .remember_channels(&change.device.name, change.device.channels, &mics);
+                self.config_saver.mark_dirty();
+                None
+            }
+            KeyAction::Char('g') => {
+                self.gain_suggestion = None;
+                self.calibration = Some(Calibration::new(Instant::now()));
+                None
+            }
+            KeyAction::Char('e') => {
+                let idx = *self.dashboard_mics.get(self.dashboard_cursor)?;
+                let mic = self.config.virtual_mics.get(idx)?;
+                self.name_input = mic.name.clone();
+                self.edit_channel = mic.channel;
+                self.edit_mic = idx;
+                self.edit_on_channel = false;
+                self.state = AppState::EditMic;
+                None
+            }
+            KeyAction::Char('m') => self.change_mix(|mic| mic.muted = !mic.muted),
+            KeyAction::Char('i') => self.change_mix(|mic| mic.invert = !mic.invert),
+            KeyAction::Char('d') => {
+                self.clock_overlay = !self.clock_overlay;
+                None
+            }
+            KeyAction::Char('f') => {
+                let channel = *self.dashboard_channels.get(self.dashboard_cursor)?;
+                let highpass_hz = match channel_mix(&self.config, channel).highpass_hz {
+                    Some(_) => None,
+                    None => Some(DEFAULT_HIGHPASS_HZ),
+                };
+                self.set_notice(match highpass_hz {
+                    Some(hz) => format!("High-pass at {:.0} Hz on Channel {}", hz, channel),
+                    None => format!("High-pass off on Channel {}", channel),
+                });
+                self.change_mix(|mic| mic.highpass_hz = highpass_hz)
+            }
+            KeyAction::Char('v') => {
+                self.config.ui.meter_style = self.config.ui.meter_style.next();
+                self.set_notice(format!("Meter style: {:?}", self.config.ui.meter_style));
+                self.config_saver.mark_dirty();
+                None
+            }
+            KeyAction::Char('a') => {
+                self.config.ui.ascii_only = !self.config.ui.ascii_only;
+                let state = if self.config.ui.ascii_only {
+                    "on"
+                } else {
+                    "off"
+                };
+                self.set_notice(format!("ASCII meters {}", state));
+                self.config_saver.mark_dirty();
+                None
+            }
+            KeyAction::Char('V') => {
+                self.config.ui.vim_keys = !self.config.ui.vim_keys;
+                self.keymap.set_vim_keys(self.config.ui.vim_keys);
+                let state = if self.config.ui.vim_keys { "on" } else { "off" };
+                self.set_notice(format!("Vim keys (h/j/k/l) {}", state));
+                self.config_saver.mark_dirty();
+                None
             }
             _ => None,
         }
     }
 
-    fn handle_error(&mut self, action: KeyAction) -> Option<AppAction> {
+    fn handle_edit_mic(&mut self, action: KeyAction) -> Option<AppAction> {
         match action {
-            KeyAction::Char('r') | KeyAction::Restart => Some(AppAction::Retry),
-            KeyAction::Quit | KeyAction::Cancel => {
+            KeyAction::Up | KeyAction::Down => {
+                self.edit_on_channel = !self.edit_on_channel;
+                None
+            }
+            KeyAction::Left if self.edit_on_channel => {
+                self.edit_channel = self.edit_channel.saturating_sub(1);
+                None
+            }
+            KeyAction::Right if self.edit_on_channel => {
+                if (self.edit_channel as usize + 1) < self.device_channels {
+                    self.edit_channel += 1;
+                }
+                None
+            }
+            KeyAction::Char(c) if self.edit_on_channel => {
+                // Digits pick a channel directly (1-based, like channel selection)
+                if let Some(idx) = c
+                    .to_digit(10)
+                    .and_then(|_| self.jump_index(c, self.device_channels))
+                {
+                    self.edit_channel = idx as u32;
+                }
+                None
+            }
+            KeyAction::Char(c) => {
+                if self.name_input.width() + c.width().unwrap_or(0) <= MAX_NAME_WIDTH {
+                    self.name_input.push(c);
+                }
+                None
+            }
+            KeyAction::Backspace if !self.edit_on_channel => {
+                self.name_input.pop();
+                None
+            }
+            KeyAction::Select => self.finish_mic_edit(),
+            KeyAction::Cancel => {
+                self.state = AppState::Running;
+                None
+            }
+            KeyAction::Quit => {
                 self.state = AppState::Quit;
                 None
             }
@@ -301,762 +961,4802 @@ impl App {
         }
     }
 
-    fn generate_default_name(&self, name_index: usize) -> String {
-        let device_name = self
-            .current_device
-            .as_ref()
-            .map(|d| d.name.as_str())
-            .unwrap_or("Device");
-
-        // Find which channel this name_index corresponds to
-        let channel_num = self
-            .channel_selected
+    /// Validate and apply the mic edit, returning the driver change to make
+    ///
+    /// Stays on the edit screen with a notice when the name is empty or taken
+    /// (the driver keys devices by name) or the channel doesn't exist.
+    fn finish_mic_edit(&mut self) -> Option<AppAction> {
+        let name = self.name_input.trim().to_string();
+        let idx = self.edit_mic;
+        if name.is_empty() {
+            self.set_notice("Name can't be empty".into());
+            return None;
+        }
+        let taken = self
+            .config
+            .virtual_mics
             .iter()
             .enumerate()
-            .filter(|(_, &selected)| selected)
-            .nth(name_index)
-            .map(|(i, _)| i)
-            .unwrap_or(name_index);
+            .any(|(i, m)| i != idx && m.name == name);
+        if taken {
+            self.set_notice(format!("\"{}\" is already used by another mic", name));
+            return None;
+        }
+        if self.edit_channel as usize >= self.device_channels {
+            self.set_notice(format!(
+                "Channel {} doesn't exist (device has {})",
+                self.edit_channel, self.device_channels
+            ));
+            return None;
+        }
 
-        format!("{} Ch{}", device_name, channel_num)
-    }
+        self.state = AppState::Running;
+        let mic = self.config.virtual_mics.get_mut(idx)?;
+        if mic.name == name && mic.channel == self.edit_channel {
+            return None;
+        }
+        let old = DeviceInfo {
+            name: std::mem::replace(&mut mic.name, name),
+            channel: std::mem::replace(&mut mic.channel, self.edit_channel),
+            color: mic.color.clone(),
+            source: 0,
+        };
+        let new = DeviceInfo {
+            name: mic.name.clone(),
+            channel: mic.channel,
+            color: mic.color.clone(),
+            source: 0,
+        };
+        let label = mic_label(mic);
 
-    fn selected_channels(&self) -> Vec<usize> {
-        self.channel_selected
-            .iter()
-            .enumerate()
-            .filter_map(|(i, &selected)| if selected { Some(i) } else { None })
-            .collect()
+        if let Some(row) = self.dashboard_mics.iter().position(|&i| i == idx) {
+            self.dashboard_labels[row] = label;
+            self.dashboard_channels[row] = new.channel as usize;
+        }
+        self.config_saver.mark_dirty();
+        Some(AppAction::UpdateMic { old, new })
     }
 
-    fn selected_count(&self) -> usize {
-        self.channel_selected.iter().filter(|&&s| s).count()
+    /// Apply `change` to every mic on the cursor row's channel and queue a save
+    ///
+    /// The mix is per device channel, so mics sharing a channel change together.
+    fn change_mix(&mut self, change: impl Fn(&mut VirtualMicConfig)) -> Option<AppAction> {
+        let channel = *self.dashboard_channels.get(self.dashboard_cursor)?;
+        let mut changed = false;
+        for mic in &mut self.config.virtual_mics {
+            if mic.channel as usize == channel {
+                change(mic);
+                changed = true;
+            }
+        }
+        if !changed {
+            return None;
+        }
+        self.config_saver.mark_dirty();
+        Some(AppAction::ApplyMix(channel))
     }
 
-    fn build_virtual_mics(&self) -> Vec<VirtualMicConfig> {
-        let selected_channels = self.selected_channels();
-
-        self.channel_names
-            .iter()
-            .zip(selected_channels.iter())
-            .map(|(name, &channel)| VirtualMicConfig {
-                name: name.clone(),
-                channel: channel as u32,
-            })
-            .collect()
+    /// Run `mics` on `device` (`--map`, `--default`); saved only with `save`
+    fn use_one_shot(
+        &mut self,
+        device: &AudioDevice,
+        mics: Vec<VirtualMicConfig>,
+        save: bool,
+    ) -> Result<()> {
+        self.config.virtual_mics = mics;
+        self.config.dedupe_virtual_mic_names();
+        use_device(&mut self.config, device)?;
+        self.config.device.system_default = self.follows_default;
+        self.persist_edits = save;
+        if save {
+            self.write_config(&self.config)?;
+        }
+        Ok(())
     }
 
-    fn update_levels(&mut self, levels: &[f32]) {
-        match &self.state {
-            AppState::SelectChannels => {
-                for (i, level) in levels.iter().enumerate() {
-                    if i < self.channel_levels.len() {
-                        self.channel_levels[i] = self.channel_levels[i].max(*level) * 0.92;
-                    }
-                }
-            }
-            AppState::Running => {
-                for (i, level) in levels.iter().enumerate() {
-                    if i < self.dashboard_levels.len() {
-                        let current = self.dashboard_levels[i];
-                        self.dashboard_levels[i] = if *level > current {
-                            *level
-                        } else {
-                            current * 0.92
-                        };
-                    }
-                }
-            }
-            _ => {}
+    /// Write `config` to the config file
+    fn write_config(&self, config: &Config) -> Result<()> {
+        match &self.config_location {
+            Some((path, _)) => config.save_to(path),
+            None => Ok(()),
         }
     }
 
-    fn start_running(&mut self) {
-        let selected_channels = self.selected_channels();
-
-        self.dashboard_levels = vec![0.0; selected_channels.len()];
-        self.dashboard_labels = self.channel_names.clone();
-        self.start_time = Some(Instant::now());
-        self.state = AppState::Running;
+    /// Save pending live edits once they're due (or now, if `force`)
+    fn save_prefs(&mut self, force: bool) {
+        let Some((path, _)) = self.config_location.as_ref().filter(|_| self.persist_edits) else {
+            return;
+        };
+        if let Err(e) = self.config_saver.flush(&self.config, path, force) {
+            tracing::warn!("Failed to save UI preferences: {}", e);
+        }
     }
 
-    fn start_with_existing_config(&mut self) {
-        self.dashboard_levels = vec![0.0; self.config.virtual_mics.len()];
-        self.dashboard_labels = self
-            .config
-            .virtual_mics
-            .iter()
-            .map(|m| format!("{} [Ch {}]", m.name, m.channel))
-            .collect();
-        self.start_time = Some(Instant::now());
-        self.state = AppState::Running;
+    fn handle_error(&mut self, action: KeyAction) -> Option<AppAction> {
+        match action {
+            KeyAction::Restart => Some(AppAction::Retry),
+            KeyAction::Char('d') if self.driver_socket == SocketState::Stale => {
+                Some(AppAction::RemoveStaleSocket)
+            }
+            KeyAction::Char('c') => Some(AppAction::RecoverDriver),
+            // Unattended: only Ctrl+C leaves
+            KeyAction::Quit | KeyAction::Cancel if self.kiosk => None,
+            KeyAction::Quit | KeyAction::Cancel => {
+                self.state = AppState::Quit;
+                None
+            }
+            _ => None,
+        }
     }
 
-    fn set_error(&mut self, message: String) {
-        self.state = AppState::Error(message);
+    /// Store the typed name (or the default) for the channel at `name_cursor`
+    fn commit_name(&mut self) {
+        let name = if self.name_input.is_empty() {
+            // Auto-generate name
+            self.generate_default_name(self.name_cursor)
+        } else {
+            self.name_input.clone()
+        };
+
+        // Names collide in the driver, so suffix duplicates of other channels
+        let cursor = self.name_cursor;
+        let unique = unique_name(&name, |candidate| {
+            self.channel_names
+                .iter()
+                .enumerate()
+                .any(|(i, n)| i != cursor && n == candidate)
+        });
+        if unique != name {
+            self.set_notice(format!(
+                "\"{}\" is already used, renamed to \"{}\"",
+                name, unique
+            ));
+        }
+        self.channel_names[self.name_cursor] = unique;
     }
 
-    fn uptime(&self) -> Duration {
-        self.start_time
-            .map(|t| t.elapsed())
-            .unwrap_or(Duration::ZERO)
+    /// After the last name: start, unless the selection changed meanwhile
+    fn finish_names(&mut self) -> Option<AppAction> {
+        if self.named_channels != self.selected_channels() {
+            // Selection changed under us: redo the names rather than drop mics
+            self.enter_names();
+            self.set_notice("Channel selection changed, please check the names".into());
+            None
+        } else {
+            // All names entered, save config and start
+            Some(AppAction::SaveAndStart)
+        }
     }
-}
 
-enum AppAction {
-    StartWithConfig,
-    StartPreview,
-    StopPreview,
-    SaveAndStart,
-    StopCapture,
-    Restart,
-    Retry,
-}
+    fn generate_default_name(&self, name_index: usize) -> String {
+        let device_name = self
+            .current_device
+            .as_ref()
+            .map(|d| d.name.as_str())
+            .unwrap_or("Device");
 
-pub fn execute(device_name: Option<String>) -> Result<()> {
-    let config = Config::load().unwrap_or_default();
-    let devices = list_input_devices()?;
+        // Find which channel this name_index corresponds to
+        let channel_num = self
+            .channel_selected
+            .iter()
+            .enumerate()
+            .filter(|(_, &selected)| selected)
+            .nth(name_index)
+            .map(|(i, _)| i)
+            .unwrap_or(name_index);
 
-    if devices.is_empty() {
-        anyhow::bail!("No input devices found");
+        default_mic_name(
+            &self.config.naming.template,
+            device_name,
+            channel_num,
+            name_index + 1,
+        )
     }
 
-    // Setup Ctrl+C handler
-    ctrlc::set_handler(|| {
-        CLEANUP_REQUESTED.store(true, Ordering::SeqCst);
-    })
-    .ok();
+    /// Start name entry for the current selection
+    ///
+    /// Names already entered for channels that are still selected are kept, so
+    /// going back to toggle a channel doesn't lose them or shift them onto
+    /// other channels.
+    fn enter_names(&mut self) {
+        let selected = self.selected_channels();
+        self.channel_names = selected
+            .iter()
+            .map(|ch| {
+                self.named_channels
+                    .iter()
+                    .position(|named| named == ch)
+                    .and_then(|i| self.channel_names.get(i).cloned())
+                    .unwrap_or_default()
+            })
+            .collect();
+        self.named_channels = selected;
+        if !self.config.ui.inline_naming {
+            self.apply_imported_names();
+        }
+        self.name_cursor = 0;
+        self.name_input = self.channel_names.first().cloned().unwrap_or_default();
+        self.state = AppState::EnterNames;
+    }
 
-    // Initial cleanup: remove orphan devices from driver
-    cleanup_orphan_devices(&config);
+    /// Fill the names step from `--names-file`, once (later passes keep what was typed)
+    fn apply_imported_names(&mut self) {
+        let Some(imported) = self.imported_names.take() else {
+            return;
+        };
+        let selected: Vec<u32> = self.named_channels.iter().map(|&ch| ch as u32).collect();
+        for (position, (&channel, name)) in selected.iter().zip(&mut self.channel_names).enumerate()
+        {
+            if let Some(imported) = imported.name_for(channel, position) {
+                *name = imported.to_string();
+            }
+        }
+        if let Some(mismatch) = imported.mismatch(&selected) {
+            self.set_notice(format!("Names file: {}", mismatch));
+        }
+    }
 
-    let mut app = App::new(devices.clone(), config);
+    /// Show the channels of `device` for selection, with the last setup pre-selected
+    fn open_channel_select(&mut self, device: AudioDevice) -> AppAction {
+        let channels = self.config.device.capture_channels(device.channels) as usize;
+        self.remember_device(&device.name);
+        self.channel_selected = vec![false; channels];
+        self.recall_channels(&device);
+        self.current_device = Some(device);
+        self.channel_cursor = 0;
+        self.channel_levels = vec![0.0; channels];
+        self.state = AppState::SelectChannels;
+        AppAction::StartPreview
+    }
 
-    // If device specified via CLI, skip to that device
-    if let Some(ref name) = device_name {
-        if let Some(idx) = devices
-            .iter()
-            .position(|d| d.name.to_lowercase().contains(&name.to_lowercase()))
-        {
-            app.selected_device_idx = idx;
-            app.state = AppState::SelectDevice;
+    /// Pre-select `name` in the next setup's device list (saved with the live edits)
+    fn remember_device(&mut self, name: &str) {
+        if self.config.device.last_device.as_deref() != Some(name) {
+            self.config.device.last_device = Some(name.to_string());
+            self.config_saver.mark_dirty();
         }
     }
 
-    let mut terminal = Terminal::new()?;
-    let events = EventHandler::new(Duration::from_millis(50));
+    /// Ask to re-pick channels if `device` has another channel count than at setup
+    ///
+    /// Not asked while the out-of-range prompt is up, which covers a shrink.
+    fn check_channel_layout(&mut self, device: &AudioDevice) {
+        self.layout_change = self
+            .config
+            .channel_count_change(&device.name, device.channels)
+            .filter(|_| !self.out_of_range_prompt && !self.kiosk)
+            .map(|was| LayoutChange {
+                device: device.clone(),
+                was,
+            });
+    }
 
-    let mut audio_capture: Option<AudioCapture> = None;
-    let mut driver_client: Option<DriverClient> = None;
+    /// Channel selection for `device`, keeping the configured mics on channels it still has
+    fn remap_channels(&mut self, device: AudioDevice) -> AppAction {
+        let action = self.open_channel_select(device);
+        self.channel_selected.fill(false);
+        self.named_channels.clear();
+        self.channel_names.clear();
+        for mic in &self.config.virtual_mics {
+            let channel = mic.channel as usize;
+            if let Some(selected) = self.channel_selected.get_mut(channel).filter(|s| !**s) {
+                *selected = true;
+                self.named_channels.push(channel);
+                self.channel_names.push(mic.name.clone());
+            }
+        }
+        action
+    }
 
-    loop {
-        // Check if cleanup was requested via signal
-        if CLEANUP_REQUESTED.load(Ordering::SeqCst) {
-            app.state = AppState::Quit;
+    /// Pre-select the channels and names last set up on `device`, if any
+    fn recall_channels(&mut self, device: &AudioDevice) {
+        self.named_channels.clear();
+        self.channel_names.clear();
+        let Some(memory) = self.config.recall_channels(&device.name, device.channels) else {
+            return;
+        };
+        for mic in &memory.mics {
+            if let Some(selected) = self.channel_selected.get_mut(mic.channel as usize) {
+                *selected = true;
+                self.named_channels.push(mic.channel as usize);
+                self.channel_names.push(mic.name.clone());
+            }
         }
-        // Draw UI
-        terminal.draw(|frame| {
-            draw_ui(frame, &app);
-        })?;
+    }
 
-        // Handle events
-        match events.next()? {
-            AppEvent::Key(key) => {
-                // Use text input mode when entering names (allows all chars like 's', 'n', etc.)
-                let action = if app.state == AppState::EnterNames {
-                    KeyAction::from_text_input(key)
-                } else {
-                    KeyAction::from_navigation(key)
-                };
-                if let Some(app_action) = app.handle_key(action) {
-                    match app_action {
-                        AppAction::StartWithConfig => {
-                            // Start with existing config
-                            if let Some(ref _device_name) = app.config.device.name {
-                                match start_capture_from_config(&app.config, &devices) {
-                                    Ok((capture, client)) => {
-                                        app.start_with_existing_config();
-                                        audio_capture = Some(capture);
-                                        driver_client = client;
-                                    }
-                                    Err(e) => {
-                                        app.set_error(format!("Failed to start: {}", e));
-                                    }
-                                }
-                            }
-                        }
-                        AppAction::StartPreview => {
-                            // Start audio preview for channel selection
-                            if let Some(device) = &app.current_device {
-                                if let Ok(cpal_device) = get_cpal_device(&device.name) {
-                                    if let Ok(buffer) = SharedAudioBuffer::open(
-                                        device.channels as u32,
-                                        device.sample_rate,
-                                    ) {
-                                        if let Ok(capture) =
-                                            AudioCapture::start(&cpal_device, buffer)
-                                        {
-                                            audio_capture = Some(capture);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        AppAction::StopPreview | AppAction::StopCapture => {
-                            drop(audio_capture.take());
-                            drop(driver_client.take());
-                        }
-                        AppAction::SaveAndStart => {
-                            // Build and save config
-                            let virtual_mics = app.build_virtual_mics();
-                            let mut new_config = Config::default();
+    fn selected_channels(&self) -> Vec<usize> {
+        self.channel_selected
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &selected)| if selected { Some(i) } else { None })
+            .collect()
+    }
 
-                            if let Some(device) = &app.current_device {
-                                new_config.device.name = Some(device.name.clone());
-                                new_config.device.sample_rate = device.sample_rate;
-                            }
-                            new_config.virtual_mics = virtual_mics;
+    fn selected_count(&self) -> usize {
+        self.channel_selected.iter().filter(|&&s| s).count()
+    }
 
-                            if let Err(e) = new_config.save() {
-                                tracing::warn!("Failed to save config: {}", e);
-                            }
+    /// One mic per selected channel (never fewer, even if names are out of sync)
+    fn build_virtual_mics(&self) -> Vec<VirtualMicConfig> {
+        let selected_channels = self.selected_channels();
+        debug_assert_eq!(
+            self.named_channels, selected_channels,
+            "names prepared for a different channel selection"
+        );
 
-                            // Sync driver devices: remove old ones, add new ones
-                            if DriverClient::is_driver_available() {
-                                let mut client = DriverClient::new();
-                                if client.connect().is_ok() {
-                                    // Build expected device list
-                                    let expected: Vec<DeviceInfo> = new_config
-                                        .virtual_mics
-                                        .iter()
-                                        .map(|m| DeviceInfo {
-                                            name: m.name.clone(),
-                                            channel: m.channel,
-                                        })
-                                        .collect();
-
-                                    // Sync: removes orphans, adds missing
-                                    if let Err(e) = client.sync_devices(&expected) {
-                                        tracing::warn!("Failed to sync devices: {}", e);
-                                    }
-                                    driver_client = Some(client);
-                                }
-                            }
+        let same_device = self
+            .current_device
+            .as_ref()
+            .is_some_and(|d| self.config.device.name.as_ref() == Some(&d.name));
 
-                            app.config = new_config;
-                            app.start_running();
-                        }
-                        AppAction::Restart | AppAction::Retry => {
-                            drop(audio_capture.take());
-                            drop(driver_client.take());
+        selected_channels
+            .iter()
+            .enumerate()
+            .map(|(i, &channel)| {
+                let name = self
+                    .named_channels
+                    .iter()
+                    .position(|&named| named == channel)
+                    .and_then(|idx| self.channel_names.get(idx))
+                    .filter(|name| !name.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| self.generate_default_name(i));
+                // Keep gate and mix settings from an earlier setup of the same device
+                let previous = self
+                    .config
+                    .virtual_mics
+                    .iter()
+                    .filter(|_| same_device)
+                    .rfind(|m| m.channel as usize == channel);
+                match previous {
+                    Some(previous) => VirtualMicConfig {
+                        name,
+                        ..previous.clone()
+                    },
+                    None => VirtualMicConfig::new(name, channel as u32),
+                }
+            })
+            .collect()
+    }
 
-                            match start_capture_from_config(&app.config, &devices) {
-                                Ok((capture, client)) => {
-                                    app.start_with_existing_config();
-                                    audio_capture = Some(capture);
-                                    driver_client = client;
-                                }
-                                Err(e) => {
-                                    app.set_error(format!("Failed to restart: {}", e));
-                                }
-                            }
-                        }
-                    }
+    fn update_levels(&mut self, levels: &[f32]) {
+        match &self.state {
+            // Decay happens per tick in `decay_preview`, not per message
+            AppState::SelectChannels | AppState::NameChannel => {
+                for (shown, &level) in self.channel_levels.iter_mut().zip(levels) {
+                    *shown = shown.max(level);
                 }
             }
-            AppEvent::Tick => {
-                // Update audio levels and buffer usage from capture
-                if let Some(ref capture) = audio_capture {
-                    while let Ok(levels) = capture.peak_receiver().try_recv() {
-                        app.update_levels(&levels);
-                    }
-
-                    // Update buffer usage from atomic write_pos
-                    let write_pos = capture.write_pos() as f32;
-                    let capacity = RING_BUFFER_FRAMES as f32;
-                    app.buffer_usage = (write_pos % capacity) / capacity;
+            AppState::Running => {
+                // Rows may be sorted, so pick each row's device channel
+                let row_levels: Vec<f32> = self
+                    .dashboard_channels
+                    .iter()
+                    .map(|&ch| levels.get(ch).copied().unwrap_or(0.0))
+                    .collect();
+                self.summary.record(&row_levels);
+                for (peak, &level) in self.dashboard_peaks.iter_mut().zip(&row_levels) {
+                    *peak = peak.max(level);
                 }
             }
-            AppEvent::Resize(_, _) => {}
+            _ => {}
         }
+    }
 
-        if app.state == AppState::Quit {
-            break;
+    /// Let the channel picker's meters fall back by the time since the last call
+    ///
+    /// Called once per tick, so how fast they fall depends neither on how
+    /// many peak messages the device sends nor on `[ui] tick_ms`.
+    fn decay_preview(&mut self, now: Instant) {
+        let Some(last) = self.preview_decayed_at.replace(now) else {
+            return;
+        };
+        if !matches!(self.state, AppState::SelectChannels | AppState::NameChannel) {
+            return;
+        }
+        let steps =
+            now.saturating_duration_since(last).as_secs_f32() / PREVIEW_DECAY_STEP.as_secs_f32();
+        let factor = PREVIEW_DECAY.powf(steps);
+        for level in &mut self.channel_levels {
+            *level *= factor;
         }
     }
 
-    // Cleanup: remove all virtual devices from driver on exit
-    drop(audio_capture);
-    drop(driver_client);
-    cleanup_all_devices();
+    /// Move the dashboard meters one tick towards the peaks received since the last step
+    ///
+    /// Attack/release, peak hold and clip latch follow `[meters]` (see
+    /// [`MeterParams::resolve`]) at the current tick rate. Runs on every
+    /// Running tick, data or not, so a gap in the capture fades the bars out
+    /// instead of leaving them stuck.
+    fn step_meters(&mut self) {
+        if self.state != AppState::Running {
+            return;
+        }
+        let params = self.meter_params();
+        let clip_level = clip_threshold(self.config.meters.clip_threshold_db);
 
-    Ok(())
-}
+        for (i, (current, peak)) in self
+            .dashboard_levels
+            .iter_mut()
+            .zip(&mut self.dashboard_peaks)
+            .enumerate()
+        {
+            *current = params.step(*current, *peak);
 
-/// Remove orphan devices that exist in driver but not in config
-fn cleanup_orphan_devices(config: &Config) {
-    if !DriverClient::is_driver_available() {
-        return;
+            if let Some((hold, ticks)) = self.dashboard_holds.get_mut(i) {
+                let level = current.max(*peak);
+                if level >= *hold {
+                    *hold = level;
+                    *ticks = params.hold_ticks;
+                } else if *ticks > 0 {
+                    *ticks -= 1;
+                } else {
+                    *hold = level;
+                }
+            }
+            if let Some(latch) = self.dashboard_clips.get_mut(i) {
+                *latch = if *peak >= clip_level {
+                    params.clip_latch_ticks.max(1)
+                } else {
+                    latch.saturating_sub(1)
+                };
+            }
+            *peak = 0.0;
+        }
     }
 
-    let mut client = DriverClient::new();
-    if client.connect().is_err() {
-        return;
+    /// `[meters]` timing at the current tick rate
+    fn meter_params(&self) -> MeterParams {
+        MeterParams::resolve(&self.config.meters, self.tick_rate())
     }
 
-    // Build expected device list from config
-    let expected: Vec<DeviceInfo> = config
-        .virtual_mics
-        .iter()
-        .map(|m| DeviceInfo {
-            name: m.name.clone(),
-            channel: m.channel,
-        })
-        .collect();
+    /// Alerts with `[meters] silence_timeout_s`, starting now
+    fn alert_monitor(config: &Config) -> AlertMonitor {
+        let params = MeterParams::resolve(&config.meters, IDLE_TICK);
+        AlertMonitor::new(&config.alerts, Instant::now()).silence_timeout(params.silence_timeout)
+    }
+
+    /// Show the dashboard with one row per configured mic, in `[ui] sort_mics` order
+    fn start_with_existing_config(&mut self) {
+        // Mics skipped for channels the device lacks get no row
+        let skipped = |mic: &VirtualMicConfig| {
+            self.out_of_range
+                .iter()
+                .any(|m| m.name == mic.name && m.channel == mic.channel)
+        };
+        let mut rows: Vec<(&VirtualMicConfig, usize)> = self
+            .config
+            .virtual_mics
+            .iter()
+            .zip(0..)
+            .filter(|(mic, _)| !skipped(mic))
+            .collect();
+        self.config
+            .ui
+            .sort_mics
+            .apply(&mut rows, |(mic, _)| (&mic.name, mic.channel));
 
-    if let Err(e) = client.sync_devices(&expected) {
-        tracing::warn!("Failed to sync devices: {}", e);
+        self.dashboard_labels = rows.iter().map(|(mic, _)| mic_label(mic)).collect();
+        self.dashboard_channels = rows.iter().map(|(mic, _)| mic.channel as usize).collect();
+        self.dashboard_mics = rows.iter().map(|&(_, idx)| idx).collect();
+        self.dashboard_levels = vec![0.0; rows.len()];
+        self.dashboard_peaks = vec![0.0; rows.len()];
+        self.dashboard_holds = vec![(0.0, 0); rows.len()];
+        self.dashboard_clips = vec![0; rows.len()];
+        self.summary = SessionSummary::new(
+            rows.len(),
+            clip_threshold(self.config.meters.clip_threshold_db),
+        );
+        self.dashboard_cursor = 0;
+        self.alerts = Self::alert_monitor(&self.config);
+        self.start_time = Some(Instant::now());
+        self.state = AppState::Running;
     }
-}
 
-/// Remove all virtual devices from driver (called on exit)
-fn cleanup_all_devices() {
-    if !DriverClient::is_driver_available() {
-        return;
+    /// Remember the mics a session skipped and ask whether to drop them from the config
+    fn set_out_of_range(&mut self, mics: &[DeviceInfo]) {
+        self.out_of_range_prompt =
+            !self.kiosk && !mics.is_empty() && mics != self.out_of_range.as_slice();
+        self.out_of_range = mics.to_vec();
     }
 
-    let mut client = DriverClient::new();
-    if client.connect().is_err() {
-        return;
+    /// Meter groups for the session's `[[extra_devices]]`
+    fn set_extra_sources(&mut self, sources: &[ExtraSource]) {
+        self.extra_groups = sources
+            .iter()
+            .map(|extra| ExtraGroup {
+                device: extra.device().name.clone(),
+                mics: extra
+                    .virtual_mics()
+                    .iter()
+                    .map(|m| (m.name.clone(), m.channel as usize))
+                    .collect(),
+                levels: vec![0.0; extra.capture().channel_count() as usize],
+                lost: false,
+            })
+            .collect();
     }
 
-    match client.remove_all_devices() {
-        Ok(count) => {
-            if count > 0 {
-                tracing::info!("Cleaned up {} virtual devices on exit", count);
+    /// Follow the extra devices' peaks with the same attack/release as the
+    /// primary meters
+    fn update_extra_levels(&mut self, sources: &mut [ExtraSource]) {
+        let params = self.meter_params();
+        for (group, extra) in self.extra_groups.iter_mut().zip(sources) {
+            for (shown, &peak) in group.levels.iter_mut().zip(extra.levels()) {
+                *shown = params.step(*shown, peak);
             }
-        }
-        Err(e) => {
-            tracing::warn!("Failed to cleanup devices: {}", e);
+            group.lost = extra.capture().stats().device_lost();
         }
     }
-}
 
-fn start_capture_from_config(
-    config: &Config,
-    devices: &[AudioDevice],
-) -> Result<(AudioCapture, Option<DriverClient>)> {
-    let device_name = config
-        .device
-        .name
-        .as_ref()
-        .ok_or_else(|| anyhow::anyhow!("No device configured"))?;
+    /// Delete the skipped mics from the config (saved like other edits)
+    fn remove_out_of_range_mics(&mut self) {
+        let removed = std::mem::take(&mut self.out_of_range);
+        self.out_of_range_prompt = false;
+        self.config.virtual_mics.retain(|mic| {
+            !removed
+                .iter()
+                .any(|m| m.name == mic.name && m.channel == mic.channel)
+        });
+        self.config_saver.mark_dirty();
+        self.set_notice(format!("Removed {} mic(s) from the config", removed.len()));
+        self.start_with_existing_config();
+    }
 
-    let device = devices
-        .iter()
-        .find(|d| d.name.to_lowercase().contains(&device_name.to_lowercase()))
-        .ok_or_else(|| anyhow::anyhow!("Device not found: {}", device_name))?;
+    /// Feed RMS readings (mean square per device channel) to a running calibration
+    ///
+    /// After `[meters] calibration_s` this suggests, per dashboard channel with a
+    /// signal, the gain that brings its average level to `[meters] target_rms_db`.
+    fn record_rms(&mut self, mean_squares: &[f32], now: Instant) {
+        let duration = self.meter_params().calibration;
+        let Some(calibration) = &mut self.calibration else {
+            return;
+        };
+        calibration.add(mean_squares);
+        if now.duration_since(calibration.started) < duration {
+            return;
+        }
+        let averages = calibration.averages();
+        self.calibration = None;
 
-    let buffer = SharedAudioBuffer::open(device.channels as u32, device.sample_rate)?;
-    let cpal_device = get_cpal_device(&device.name)?;
-    let capture = AudioCapture::start(&cpal_device, buffer)?;
+        let target = self.config.meters.target_rms_db;
+        let mut channels = self.dashboard_channels.clone();
+        channels.sort_unstable();
+        channels.dedup();
 
-    let mut driver_client = None;
-    if DriverClient::is_driver_available() {
-        let mut client = DriverClient::new();
-        if client.connect().is_ok() {
-            for mic in &config.virtual_mics {
-                let _ = client.add_device(&mic.name, mic.channel);
+        let mut silent = 0;
+        let mut suggestion = Vec::new();
+        for channel in channels {
+            let mean_square = averages.get(channel).copied().unwrap_or(0.0);
+            let rms_db = 10.0 * mean_square.max(f32::MIN_POSITIVE).log10();
+            if rms_db < CALIBRATION_SILENCE_DB {
+                silent += 1;
+                continue;
+            }
+            // RMS is measured after the current gain, so adjust relative to it
+            let current = channel_mix(&self.config, channel).gain_db;
+            let suggested = (current + target - rms_db)
+                .round()
+                .clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+            if suggested != current {
+                suggestion.push((channel, suggested));
             }
-            driver_client = Some(client);
         }
-    }
 
-    Ok((capture, driver_client))
-}
+        if !suggestion.is_empty() {
+            self.gain_suggestion = Some(suggestion);
+        } else if silent > 0 {
+            self.set_notice(format!(
+                "No signal on {} channel(s), nothing to suggest",
+                silent
+            ));
+        } else {
+            self.set_notice(format!("Levels already sit around {:.0} dBFS", target));
+        }
+    }
+
+    /// Show an alert in the notice banner and flash the header (if `[alerts] flash`)
+    fn raise_alert(&mut self, alert: Alert) {
+        self.set_notice(alert_message(alert).to_string());
+        if self.config.alerts.flash {
+            self.alert_flash = Some(Instant::now());
+        }
+    }
+
+    /// Surface backend stream errors from the capture counters
+    ///
+    /// New xruns only raise a notice; a lost device ends the dashboard in the
+    /// error screen, where retry restarts capture.
+    fn record_stream_faults(&mut self, xruns: u64, device_lost: bool, last_error: Option<String>) {
+        if xruns > self.xruns {
+            if let Some(error) = &last_error {
+                self.set_notice(format!("Audio glitch: {}", error));
+            }
+        }
+        self.xruns = xruns;
+        self.stream_error = last_error;
+
+        if device_lost && self.state == AppState::Running {
+            let error = anyhow::anyhow!(self
+                .stream_error
+                .clone()
+                .unwrap_or_else(|| "device disconnected".to_string()));
+            self.set_error("Audio device lost", &error);
+        }
+    }
+
+    /// Whether capture should move to `name`, the new system default input
+    ///
+    /// Only for a setup made for the default input (`--default`). With
+    /// `[behavior] follow_default` capture moves to the new device for this
+    /// run, leaving the saved device alone, and the caller restarts capture on
+    /// it (shared memory is resized to its channels); otherwise a notice says
+    /// capture stays where it is.
+    fn default_input_changed(&mut self, name: &str) -> bool {
+        if self.state != AppState::Running || !self.follows_default {
+            return false;
+        }
+        let current = self.capture_device_name().unwrap_or_default().to_string();
+        // Hidden, virtual or gone already: nothing to switch to
+        let Some(device) = self.devices.iter().find(|d| d.name == name).cloned() else {
+            return false;
+        };
+        if device.name == current {
+            return false;
+        }
+        if !self.config.behavior.follow_default {
+            self.set_notice(format!(
+                "Default input is now {}, still capturing {} ([behavior] follow_default switches)",
+                device.name, current
+            ));
+            return false;
+        }
+
+        self.set_notice(format!(
+            "Default input is now {}, moving capture from {}",
+            device.name, current
+        ));
+        // Mics on channels the new device lacks are skipped like any out-of-range mic
+        let saved = self.config.device.name.as_ref() == Some(&device.name)
+            && !matches!(self.config.device.index, Some(i) if i != device.index);
+        self.capture_device = (!saved).then_some(device);
+        true
+    }
+
+    /// Name of the device being captured
+    fn capture_device_name(&self) -> Option<&str> {
+        match &self.capture_device {
+            Some(device) => Some(&device.name),
+            None => self.config.device.name.as_deref(),
+        }
+    }
+
+    /// The config to capture: `config` with the followed default input, if any
+    fn capture_config(&self) -> Config {
+        let mut config = self.config.clone();
+        if let Some(device) = &self.capture_device {
+            config.device.name = Some(device.name.clone());
+            config.device.index = Some(device.index);
+            config.device.sample_rate = device.default_sample_rate;
+        }
+        config
+    }
+
+    /// Track the driver falling behind the shared memory writer
+    ///
+    /// A new overrun means apps heard a gap: it raises a notice and flags the
+    /// stats line for [`OVERRUN_FLAG`].
+    fn record_shm_overruns(&mut self, overruns: u64, unread: bool, now: Instant) {
+        if overruns > self.shm_overruns {
+            self.last_overrun = Some(now);
+            self.set_notice(
+                "Buffer overrun: the driver fell behind and skipped audio (gap in the virtual mics)"
+                    .to_string(),
+            );
+        }
+        self.shm_overruns = overruns;
+        self.buffer_unread = unread;
+    }
+
+    /// Whether an overrun happened recently enough to flag
+    fn overrun_flagged(&self, now: Instant) -> bool {
+        self.last_overrun
+            .is_some_and(|at| now.saturating_duration_since(at) < OVERRUN_FLAG)
+    }
+
+    fn set_notice(&mut self, message: String) {
+        self.notice = Some((message, Instant::now()));
+    }
+
+    /// Current notice, if it hasn't expired yet
+    fn active_notice(&self) -> Option<&str> {
+        self.notice
+            .as_ref()
+            .filter(|(_, shown)| shown.elapsed() < NOTICE_DURATION)
+            .map(|(message, _)| message.as_str())
+    }
+
+    /// Whether kiosk mode should retry from the error screen now
+    ///
+    /// Waits [`KIOSK_RETRY_MIN`] after the first failure, doubling up to
+    /// [`KIOSK_RETRY_MAX`]; a running dashboard resets the wait.
+    fn kiosk_retry_due(&mut self, now: Instant) -> bool {
+        if !self.kiosk {
+            return false;
+        }
+        if !matches!(self.state, AppState::Error(_)) {
+            if self.state == AppState::Running {
+                self.kiosk_retries = 0;
+            }
+            self.kiosk_retry_at = None;
+            return false;
+        }
+        match self.kiosk_retry_at {
+            None => {
+                let delay = kiosk_backoff(self.kiosk_retries);
+                self.kiosk_retry_at = Some(now + delay);
+                self.set_notice(format!("Retrying in {}s", delay.as_secs()));
+                false
+            }
+            Some(at) if now >= at => {
+                self.kiosk_retry_at = None;
+                self.kiosk_retries += 1;
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    fn set_error(&mut self, context: &str, error: &anyhow::Error) {
+        self.error_kind = DriverError::kind_of(error);
+        self.driver_socket = DriverClient::socket_state();
+        self.driver_ping = None;
+        self.state = AppState::Error(format!("{}: {}", context, error));
+    }
+
+    /// Take the next step of error screen `c` from a worker outcome
+    ///
+    /// Returns whether `op` belonged to the recovery, and the command to queue
+    /// next: the recovery helper when the driver is down and one is set, then a
+    /// second ping.
+    fn recovery_outcome(&mut self, op: &DriverOp, result: &Result<()>) -> (bool, Option<DriverOp>) {
+        let next = match (self.recovery, op) {
+            (Some(step @ (Recovery::Probe | Recovery::Recheck)), DriverOp::Ping) => {
+                self.driver_socket = DriverClient::socket_state();
+                self.driver_ping =
+                    (self.driver_socket == SocketState::Present).then_some(result.is_ok());
+                match (step, result.is_ok(), &self.config.behavior.recovery_helper) {
+                    (Recovery::Probe, true, _) => {
+                        self.set_notice(format!(
+                            "The driver is responding; press {} to retry",
+                            self.keymap.label(KeyAction::Restart)
+                        ));
+                        None
+                    }
+                    (Recovery::Probe, false, None) => {
+                        self.set_notice(format!(
+                            "The driver is down; run `{}` in another terminal",
+                            RESTART_DRIVER_COMMAND
+                        ));
+                        None
+                    }
+                    (Recovery::Probe, false, Some(helper)) => {
+                        let helper = helper.clone();
+                        self.set_notice("Running the recovery helper...".into());
+                        Some(DriverOp::RunHelper(helper))
+                    }
+                    (_, true, _) => {
+                        self.set_notice(format!(
+                            "Recovery helper done, the driver is responding; press {} to retry",
+                            self.keymap.label(KeyAction::Restart)
+                        ));
+                        None
+                    }
+                    (_, false, _) => {
+                        self.set_notice(
+                            "Recovery helper done, but the driver isn't back yet; press c to check again"
+                                .into(),
+                        );
+                        None
+                    }
+                }
+            }
+            (Some(Recovery::Helper), DriverOp::RunHelper(_)) => match result {
+                Ok(()) => Some(DriverOp::Ping),
+                Err(e) => {
+                    self.set_notice(format!("{:#}", e));
+                    None
+                }
+            },
+            _ => return (false, None),
+        };
+        self.recovery = match &next {
+            Some(DriverOp::RunHelper(_)) => Some(Recovery::Helper),
+            Some(_) => Some(Recovery::Recheck),
+            None => None,
+        };
+        (true, next)
+    }
+
+    /// Show why a session didn't start
+    ///
+    /// A missing device goes back to the device list, except in kiosk mode
+    /// where the error screen keeps retrying it.
+    fn set_start_error(&mut self, context: &str, error: DuomicError) {
+        match error {
+            DuomicError::DeviceNotFound(name) if !self.kiosk && !self.devices.is_empty() => {
+                self.set_notice(format!(
+                    "{}: {} is not connected, pick a device",
+                    context, name
+                ));
+                self.state = AppState::SelectDevice;
+            }
+            error => self.set_error(context, &error.into()),
+        }
+    }
+
+    /// What `[metrics] listen` serves, one entry per dashboard row
+    ///
+    /// `peaks` holds the loudest level per device channel since the last tick.
+    fn metrics(
+        &self,
+        capture: Option<&AudioCapture>,
+        peaks: &[f32],
+        driver_connected: bool,
+    ) -> Metrics {
+        let capturing = capture.is_some() && self.state == AppState::Running;
+        let mics = match capture {
+            Some(capture) if capturing => self
+                .dashboard_channels
+                .iter()
+                .zip(&self.dashboard_mics)
+                .enumerate()
+                .filter_map(|(row, (&channel, &mic))| {
+                    Some(MicMetrics {
+                        name: self.config.virtual_mics.get(mic)?.name.clone(),
+                        channel: channel as u32,
+                        peak: peaks.get(channel).copied().unwrap_or(0.0),
+                        rms: capture.rms().get(channel),
+                        clips: self.summary.clips.get(row).copied().unwrap_or(0),
+                    })
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        Metrics {
+            mics,
+            capturing,
+            driver_connected,
+            buffer_fill: if capturing { self.buffer_usage } else { 0.0 },
+            clipped_samples: self.clipped_samples,
+            dropped_peaks: self.dropped_peaks,
+            write_errors: self.write_errors,
+            xruns: self.xruns,
+            shm_overruns: self.shm_overruns,
+        }
+    }
+
+    /// Tick rate for the current screen: `[ui] tick_ms` while meters update, slower otherwise
+    fn tick_rate(&self) -> Duration {
+        match self.state {
+            AppState::SelectChannels | AppState::NameChannel | AppState::Running => {
+                Duration::from_millis(
+                    self.config
+                        .ui
+                        .tick_ms
+                        .clamp(MIN_TICK_MS, IDLE_TICK.as_millis() as u64),
+                )
+            }
+            _ => IDLE_TICK,
+        }
+    }
+
+    /// Buffer fill and latency from the shared memory positions
+    ///
+    /// Falls back to the old write-position estimate when the driver doesn't
+    /// publish a read position (older drivers, or no app reading yet).
+    fn update_buffer(&mut self, write_pos: u32, read_pos: u32, capacity: usize) {
+        match buffer_fill(write_pos, read_pos, capacity) {
+            Some(fill) => {
+                self.buffer_usage = fill as f32 / capacity as f32;
+                let sample_rate = self.sample_rate();
+                self.buffer_latency_ms =
+                    (sample_rate > 0).then(|| fill as f32 * 1000.0 / sample_rate as f32);
+            }
+            None => {
+                self.buffer_usage = (write_pos as usize % capacity) as f32 / capacity as f32;
+                self.buffer_latency_ms = None;
+            }
+        }
+    }
+
+    /// Record the rate the stream actually opened at
+    ///
+    /// The config holds the device's default rate from setup, which the stream
+    /// may not get; the live rate replaces it (and is saved) so the header,
+    /// latency and `duomic status` all agree with what's running.
+    fn set_live_sample_rate(&mut self, rate: u32) {
+        if rate == 0 {
+            return;
+        }
+        self.live_sample_rate = Some(rate);
+        // A followed default input isn't the saved device
+        if self.capture_device.is_none() && self.config.device.sample_rate != rate {
+            tracing::info!(
+                "Device runs at {} Hz (config said {} Hz)",
+                rate,
+                self.config.device.sample_rate
+            );
+            self.config.device.sample_rate = rate;
+            self.config_saver.mark_dirty();
+        }
+    }
+
+    /// Live stream rate, or the configured one before capture starts
+    fn sample_rate(&self) -> u32 {
+        self.live_sample_rate
+            .unwrap_or(self.config.device.sample_rate)
+    }
+
+    fn uptime(&self) -> Duration {
+        self.start_time
+            .map(|t| t.elapsed())
+            .unwrap_or(Duration::ZERO)
+    }
+}
+
+/// Dashboard meters of one `[[extra_devices]]` entry
+#[derive(Debug, Clone)]
+struct ExtraGroup {
+    device: String,
+    /// Mic name and device channel
+    mics: Vec<(String, usize)>,
+    /// Shown level per device channel
+    levels: Vec<f32>,
+    lost: bool,
+}
+
+/// The running device's channel count differs from the one it was set up with
+#[derive(Debug, Clone)]
+struct LayoutChange {
+    device: AudioDevice,
+    /// Channel count at setup
+    was: u16,
+}
+
+/// RMS readings collected for the gain suggestion
+#[derive(Debug)]
+struct Calibration {
+    started: Instant,
+    sums: Vec<f32>,
+    readings: u32,
+}
+
+impl Calibration {
+    fn new(started: Instant) -> Self {
+        Self {
+            started,
+            sums: Vec::new(),
+            readings: 0,
+        }
+    }
+
+    fn add(&mut self, mean_squares: &[f32]) {
+        if self.sums.len() < mean_squares.len() {
+            self.sums.resize(mean_squares.len(), 0.0);
+        }
+        for (sum, ms) in self.sums.iter_mut().zip(mean_squares) {
+            *sum += ms;
+        }
+        self.readings += 1;
+    }
+
+    /// Average mean square per channel
+    fn averages(&self) -> Vec<f32> {
+        let readings = self.readings.max(1) as f32;
+        self.sums.iter().map(|sum| sum / readings).collect()
+    }
+}
+
+/// Dashboard row label for a mic
+fn mic_label(mic: &VirtualMicConfig) -> String {
+    format!("{} [Ch {}]", mic.name, mic.channel)
+}
+
+/// Peak level and clip count per dashboard mic, printed when the TUI exits
+#[derive(Debug, Default)]
+struct SessionSummary {
+    peaks: Vec<f32>,
+    clips: Vec<u64>,
+    // Whether each mic is currently above clip_level (clips count rising edges)
+    clipping: Vec<bool>,
+    clip_level: f32,
+}
+
+impl SessionSummary {
+    fn new(mics: usize, clip_level: f32) -> Self {
+        Self {
+            peaks: vec![0.0; mics],
+            clips: vec![0; mics],
+            clipping: vec![false; mics],
+            clip_level,
+        }
+    }
+
+    fn record(&mut self, levels: &[f32]) {
+        for (i, &level) in levels.iter().enumerate().take(self.peaks.len()) {
+            self.peaks[i] = self.peaks[i].max(level);
+            let clipping = level >= self.clip_level;
+            if clipping && !self.clipping[i] {
+                self.clips[i] += 1;
+            }
+            self.clipping[i] = clipping;
+        }
+    }
+}
+
+/// Error screen `c` in progress, one driver worker command per step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recovery {
+    /// Pinging the driver
+    Probe,
+    /// Running `[behavior] recovery_helper`
+    Helper,
+    /// Pinging again after the helper
+    Recheck,
+}
+
+enum AppAction {
+    RefreshDevices,
+    StartWithConfig,
+    StartPreview,
+    StopPreview,
+    StartMonitor,
+    StopMonitor,
+    SaveAndStart,
+    Restart,
+    Retry,
+    /// Delete the driver socket file nobody listens on anymore
+    RemoveStaleSocket,
+    /// Check the driver again and run `[behavior] recovery_helper` if it's down
+    RecoverDriver,
+    /// Push the config's gain/mute/invert for this channel to the capture
+    ApplyMix(usize),
+    /// Same for every device channel
+    ApplyMixAll,
+    /// A mic was renamed or moved: re-register it with the driver
+    UpdateMic {
+        old: DeviceInfo,
+        new: DeviceInfo,
+    },
+}
+
+/// Options for `duomic run`
+#[derive(Debug, Default)]
+pub struct RunOptions {
+    /// Device name to use (skip device selection)
+    pub device: Option<String>,
+    /// Enumeration index from the device list (instead of `device`)
+    pub device_index: Option<usize>,
+    /// Don't print the session summary on exit
+    pub quiet: bool,
+    /// `--map CHANNEL=NAME` pairs: skip the wizard and start with these mics
+    pub mappings: Vec<(u32, String)>,
+    /// `--default`: every channel of the default input, started without the wizard
+    pub default_device: bool,
+    /// Save the `--map` or `--default` setup to the config
+    pub save: bool,
+    /// Start the saved setup straight into the combined status/meters view
+    pub dashboard: bool,
+    /// Start the saved setup without asking (as if "continue" were picked)
+    pub resume: bool,
+    /// `--log-levels`: CSV file for per-channel peak/RMS every tick
+    pub log_levels: Option<PathBuf>,
+    /// `--names-file`: names for the setup's naming step
+    pub names_file: Option<PathBuf>,
+    /// `--kiosk`: resume in the combined view, no setup or prompts, retry on errors
+    pub kiosk: bool,
+    /// `--simple`: capture the saved setup with one line of meters instead of the TUI
+    pub simple: bool,
+}
+
+/// Parse a `--map` value like `0=Vocals`
+pub fn parse_mapping(value: &str) -> std::result::Result<(u32, String), String> {
+    let (channel, name) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected CHANNEL=NAME (e.g. 0=Vocals), got \"{}\"", value))?;
+    let channel = channel.trim().parse().map_err(|_| {
+        format!(
+            "invalid channel \"{}\" in \"{}\" (expected a number like 0)",
+            channel.trim(),
+            value
+        )
+    })?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!(
+            "missing name in \"{}\" (expected CHANNEL=NAME)",
+            value
+        ));
+    }
+    Ok((channel, name.to_string()))
+}
+
+/// Capture the configured mics from `device` instead of the saved one
+///
+/// Fails if a mic's channel doesn't exist on `device`.
+fn use_device(config: &mut Config, device: &AudioDevice) -> Result<()> {
+    if let Some(mic) = config
+        .virtual_mics
+        .iter()
+        .find(|m| m.channel >= device.channels as u32)
+    {
+        bail!(
+            "{} has channels 0-{}, but \"{}\" uses channel {}",
+            device.name,
+            device.channels.saturating_sub(1),
+            mic.name,
+            mic.channel
+        );
+    }
+    config.device.name = Some(device.name.clone());
+    config.device.index = Some(device.index);
+    config.device.sample_rate = device.default_sample_rate;
+    Ok(())
+}
+
+/// Virtual mics for `--map` pairs on `device`
+///
+/// Every channel must exist on the device, and channels and names must be
+/// unique (the driver keys devices by name).
+fn mics_from_mappings(
+    device: &AudioDevice,
+    mappings: &[(u32, String)],
+) -> Result<Vec<VirtualMicConfig>> {
+    let mut mics: Vec<VirtualMicConfig> = Vec::with_capacity(mappings.len());
+    for (channel, name) in mappings {
+        if *channel >= device.channels as u32 {
+            bail!(
+                "--map {}={}: {} has channels 0-{}",
+                channel,
+                name,
+                device.name,
+                device.channels.saturating_sub(1)
+            );
+        }
+        if let Some(other) = mics.iter().find(|m| m.channel == *channel) {
+            bail!(
+                "--map {}={}: channel {} is already mapped to \"{}\"",
+                channel,
+                name,
+                channel,
+                other.name
+            );
+        }
+        if mics.iter().any(|m| &m.name == name) {
+            bail!(
+                "--map {}={}: name \"{}\" is used twice",
+                channel,
+                name,
+                name
+            );
+        }
+        mics.push(VirtualMicConfig::new(name.clone(), *channel));
+    }
+    Ok(mics)
+}
+
+/// One auto-named virtual mic per channel of `device` (for `--default`)
+///
+/// A mono device gets a single mic that passes its only channel through.
+fn mics_for_all_channels(device: &AudioDevice, template: &str) -> Vec<VirtualMicConfig> {
+    (0..device.channels as usize)
+        .map(|ch| {
+            let name = default_mic_name(template, &device.name, ch, ch + 1);
+            VirtualMicConfig::new(name, ch as u32)
+        })
+        .collect()
+}
+
+/// The default input device among the listed `devices`
+fn find_default_device(devices: &[AudioDevice]) -> Result<&AudioDevice> {
+    let name = default_input_device_name()?;
+    devices
+        .iter()
+        .find(|d| d.name == name)
+        .or_else(|| resolve_device(devices, &name, None))
+        .with_context(|| {
+            format!(
+                "--default: \"{}\" is not available for capture (hidden or unusable)",
+                name
+            )
+        })
+}
+
+pub fn execute(options: RunOptions, config_location: (PathBuf, ConfigSource)) -> Result<()> {
+    let device_name = options.device;
+    let config = Config::load_from(&config_location.0).unwrap_or_default();
+    let imported_names = options
+        .names_file
+        .as_deref()
+        .map(ImportedNames::load)
+        .transpose()?;
+    let devices = load_devices(&config)?;
+
+    // Setup SIGINT/SIGTERM/SIGHUP handler (ctrlc "termination" feature)
+    ctrlc::set_handler(|| {
+        CLEANUP_REQUESTED.store(true, Ordering::SeqCst);
+    })
+    .ok();
+
+    // Driver commands run off the UI thread so a wedged driver can't freeze it
+    let mut driver = DriverWorker::spawn()?;
+
+    // Device and driver changes arrive as events instead of being polled here
+    let watcher = DeviceWatcher::spawn(DEVICE_POLL_INTERVAL)
+        .map_err(|e| tracing::warn!("Failed to watch devices: {}", e))
+        .ok();
+
+    // Initial cleanup: remove orphan devices from driver
+    sync_driver(&mut driver, &expected_mics(&config));
+
+    let (_, key_warnings) = KeyMap::from_config(&config.keybindings);
+    for warning in &key_warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    let mut app = App::new(devices.clone(), config);
+    app.config_location = Some(config_location);
+    app.imported_names = imported_names;
+    if let Some(warning) = key_warnings.first() {
+        app.set_notice(warning.clone());
+    }
+
+    // Device picked on the command line (one-shot `--map` setup needs exactly one)
+    let mut picked: Option<usize> = None;
+
+    // If device specified via CLI, skip to that device
+    if let Some(name) = device_name.as_ref().filter(|_| !devices.is_empty()) {
+        // Same matching as opening the device: an exact name wins over substrings
+        let device_names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+        let matches = match match_device(name, &device_names) {
+            MatchResult::Exact(i) | MatchResult::Unique(i) => vec![i],
+            MatchResult::Ambiguous(all) => all,
+            MatchResult::None => Vec::new(),
+        };
+
+        let names = |indices: &[usize]| {
+            indices
+                .iter()
+                .map(|&i| devices[i].name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        match matches.as_slice() {
+            [] => {
+                let all: Vec<usize> = (0..devices.len()).collect();
+                let message = format!("No device matches \"{}\". Available: {}", name, names(&all));
+                eprintln!("Warning: {}", message);
+                app.set_notice(message);
+            }
+            [idx] => {
+                app.selected_device_idx = *idx;
+                picked = Some(*idx);
+            }
+            [idx, ..] => {
+                if !options.mappings.is_empty() {
+                    bail!(
+                        "\"{}\" matches {} devices ({}), use --device-index with --map",
+                        name,
+                        matches.len(),
+                        names(&matches)
+                    );
+                }
+                let message = format!(
+                    "\"{}\" matches {} devices: {}",
+                    name,
+                    matches.len(),
+                    names(&matches)
+                );
+                eprintln!("Warning: {}", message);
+                app.set_notice(message);
+                app.selected_device_idx = *idx;
+            }
+        }
+        app.state = AppState::SelectDevice;
+    }
+
+    // Index selection bypasses name matching (for identically named devices)
+    if let Some(index) = options.device_index.filter(|_| !devices.is_empty()) {
+        match devices.iter().position(|d| d.index == index) {
+            Some(position) => {
+                app.selected_device_idx = position;
+                picked = Some(position);
+            }
+            None => {
+                let available: Vec<String> = devices
+                    .iter()
+                    .map(|d| format!("{}: {}", d.index, d.name))
+                    .collect();
+                let message = format!(
+                    "No input device has index {}. Available: {}",
+                    index,
+                    available.join(", ")
+                );
+                eprintln!("Warning: {}", message);
+                app.set_notice(message);
+            }
+        }
+        app.state = AppState::SelectDevice;
+    }
+
+    // One-shot setup: mics from --map or --default, started without the wizard
+    let one_shot = !options.mappings.is_empty() || options.default_device;
+    if options.default_device {
+        let device = find_default_device(&devices)?;
+        app.follows_default = true;
+        let mut mics = mics_for_all_channels(device, &app.config.naming.template);
+        mics.truncate(app.config.device.capture_channels(device.channels) as usize);
+        app.use_one_shot(device, mics, options.save)?;
+    } else if one_shot {
+        let device = match (
+            device_name.is_some() || options.device_index.is_some(),
+            picked,
+        ) {
+            (_, Some(idx)) => &devices[idx],
+            (true, None) => bail!("--map: the requested device was not found"),
+            (false, None) => app
+                .config
+                .device
+                .name
+                .as_deref()
+                .and_then(|name| resolve_device(&devices, name, app.config.device.index))
+                .context("--map needs --device or --device-index (no device configured)")?,
+        };
+        let mics = mics_from_mappings(device, &options.mappings)?;
+        app.use_one_shot(device, mics, options.save)?;
+    }
+
+    // A saved `--default` setup keeps following the default input
+    app.follows_default |= !one_shot && app.config.device.system_default;
+
+    // Saved setup started without the "continue or reconfigure?" question
+    let has_saved = app.config.device.name.is_some() && !app.config.virtual_mics.is_empty();
+    if options.kiosk && !one_shot && !has_saved {
+        bail!("Kiosk mode needs a saved setup: run `duomic run` once to create one");
+    }
+    // `[behavior] autostart` waits for a setup to start; until then `duomic run` sets one up
+    let autostart = app.config.behavior.autostart && has_saved;
+    let kiosk = (options.kiosk || autostart) && !one_shot;
+    let resume = (options.resume || options.dashboard || kiosk) && !one_shot;
+    app.kiosk = kiosk;
+    // Kiosk mode starts (and keeps retrying) even with the device missing
+    let start_saved = resume && has_saved && (!devices.is_empty() || kiosk);
+    if start_saved && (device_name.is_some() || options.device_index.is_some()) {
+        let idx = picked.context("--device matches no single device, nothing to resume on")?;
+        use_device(&mut app.config, &devices[idx])?;
+        // This run only: the saved device stays as it is
+        app.persist_edits = false;
+    }
+
+    // Fail before the TUI starts if the log can't be created
+    let mut level_log = options
+        .log_levels
+        .as_deref()
+        .map(LevelLog::create)
+        .transpose()?;
+
+    // Scripts and service managers have no terminal to draw on
+    if options.simple || !std::io::stdout().is_terminal() {
+        if app.config.device.name.is_none() || app.config.virtual_mics.is_empty() {
+            if options.simple {
+                bail!(
+                    "--simple needs a saved setup. Run `duomic run` once to save one, or pass --map or --default"
+                );
+            }
+            bail!(
+                "No interactive terminal (stdout is not a TTY) and no saved setup to run without one. \
+                 Run `duomic run` in a terminal once to save a setup, or pass --map or --default"
+            );
+        }
+        return run_headless(
+            &app.config,
+            driver,
+            watcher.as_ref(),
+            level_log,
+            options.simple,
+        );
+    }
+
+    let mut terminal = Terminal::new().context("Failed to start the terminal UI")?;
+    let events = EventHandler::new(app.tick_rate());
+
+    // Preview capture (channel selection) and the full running session
+    let mut audio_capture: Option<AudioCapture> = None;
+    let mut session: Option<RunningSession> = None;
+
+    // The combined view polls the driver off the UI thread
+    app.flat = options.dashboard || kiosk;
+    let driver_status = app
+        .flat
+        .then(|| spawn_driver_poller(DEVICE_POLL_INTERVAL))
+        .transpose()
+        .map_err(|e| tracing::warn!("Failed to poll driver status: {}", e))
+        .ok()
+        .flatten();
+    let metrics = app.config.metrics.listen.clone().and_then(|addr| {
+        MetricsServer::bind(&addr)
+            .map_err(|e| {
+                tracing::warn!("{:#}", e);
+                app.set_notice(format!("Metrics: {:#}", e));
+            })
+            .ok()
+    });
+    if resume && !has_saved && !devices.is_empty() {
+        app.set_notice("No saved setup to resume yet, set one up first".into());
+    }
+
+    if one_shot || start_saved {
+        session = start_session(&mut app, &mut driver, "Failed to start");
+    }
+    // Cursor channel playback during channel selection
+    let mut monitor: Option<ChannelMonitor> = None;
+    let mut last_stats_publish = Instant::now();
+    let mut redraw = true;
+
+    loop {
+        // Check if cleanup was requested via signal
+        if CLEANUP_REQUESTED.load(Ordering::SeqCst) {
+            tracing::info!("Termination signal received, shutting down");
+            break;
+        }
+        // Draw UI (skipped when we only woke up to check the signal flag)
+        if redraw {
+            if let Err(e) = terminal.draw(|frame| {
+                draw_ui(frame, &app);
+            }) {
+                app.save_prefs(true);
+                return Err(e);
+            }
+        }
+
+        // Wait with a timeout so a signal is noticed even if no events arrive
+        let event = match events.next_timeout(SIGNAL_POLL_INTERVAL) {
+            Ok(Some(event)) => {
+                redraw = true;
+                event
+            }
+            Ok(None) => {
+                redraw = false;
+                continue;
+            }
+            Err(e) => {
+                // Event thread is gone; still fall through to device cleanup
+                tracing::error!("Event handler stopped: {}", e);
+                break;
+            }
+        };
+
+        // Handle events
+        match event {
+            // Ctrl+C is an explicit interrupt: quit without asking
+            AppEvent::Key(key) if is_interrupt(&key) && app.state == AppState::Running => {
+                app.state = AppState::Quit;
+            }
+            AppEvent::Key(key) => {
+                // Use text input mode when entering names (allows all chars like 's', 'n', etc.)
+                let action = if matches!(
+                    app.state,
+                    AppState::NameChannel | AppState::EnterNames | AppState::EditMic
+                ) {
+                    KeyAction::from_text_input(key)
+                } else {
+                    KeyAction::from_navigation_with(key, &app.keymap)
+                };
+                if let Some(app_action) = app.handle_key(action) {
+                    match app_action {
+                        AppAction::RefreshDevices => {
+                            refresh_devices(&mut app);
+                        }
+                        AppAction::StartWithConfig => {
+                            // Start with existing config
+                            session = start_session(&mut app, &mut driver, "Failed to start");
+                        }
+                        AppAction::StartPreview => {
+                            // A session still running on this device (setup from the
+                            // dashboard) feeds the preview meters; anything else is replaced
+                            let same_device = match (&session, &app.current_device) {
+                                (Some(running), Some(device)) => {
+                                    running.device().name == device.name
+                                        && running.device().index == device.index
+                                }
+                                _ => false,
+                            };
+                            if !same_device {
+                                drop(session.take());
+                            }
+
+                            // Start audio preview for channel selection
+                            if let Some(device) =
+                                app.current_device.as_ref().filter(|_| !same_device)
+                            {
+                                if let Ok(cpal_device) = get_cpal_device_for(device) {
+                                    let max_channels = app.config.device.max_channels;
+                                    let options = CaptureOptions {
+                                        max_channels,
+                                        ..CaptureOptions::default()
+                                    };
+                                    if let Ok(buffer) = SharedAudioBuffer::open(
+                                        app.config.device.capture_channels(device.channels) as u32,
+                                        device.default_sample_rate,
+                                    ) {
+                                        if let Ok(capture) =
+                                            AudioCapture::start(&cpal_device, buffer, &options)
+                                        {
+                                            audio_capture = Some(capture);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        AppAction::ApplyMix(channel) => {
+                            if let Some(session) = &session {
+                                session
+                                    .capture()
+                                    .controls()
+                                    .set(channel, channel_mix(&app.config, channel));
+                            }
+                        }
+                        AppAction::ApplyMixAll => {
+                            if let Some(session) = &session {
+                                let capture = session.capture();
+                                for channel in 0..capture.channel_count() as usize {
+                                    capture
+                                        .controls()
+                                        .set(channel, channel_mix(&app.config, channel));
+                                }
+                            }
+                        }
+                        AppAction::UpdateMic { old, new } => {
+                            // The driver has no rename, so replace just this device
+                            driver.submit(DriverOp::Remove(old.name));
+                            driver.submit(DriverOp::Add(new.clone()));
+                            app.driver_busy = true;
+                            if old.channel != new.channel {
+                                if let Some(session) = &session {
+                                    for channel in [old.channel, new.channel] {
+                                        let channel = channel as usize;
+                                        session
+                                            .capture()
+                                            .controls()
+                                            .set(channel, channel_mix(&app.config, channel));
+                                    }
+                                }
+                                let gated = app.config.virtual_mics.iter().any(|m| {
+                                    m.gate_db.is_some()
+                                        && [old.channel, new.channel].contains(&m.channel)
+                                });
+                                if gated {
+                                    let restart = app.keymap.label(KeyAction::Restart);
+                                    app.set_notice(format!(
+                                        "Restart ({restart}) to apply noise gates to the new channel"
+                                    ));
+                                }
+                            }
+                        }
+                        AppAction::StartMonitor => {
+                            monitor = start_monitor(&mut app);
+                        }
+                        AppAction::StopMonitor => {
+                            drop(monitor.take());
+                        }
+                        AppAction::StopPreview => {
+                            drop(audio_capture.take());
+                        }
+                        AppAction::SaveAndStart => {
+                            // Build and save config
+                            let virtual_mics = app.build_virtual_mics();
+                            // Start from the current config so other sections survive
+                            let mut new_config = app.config.clone();
+
+                            new_config.virtual_mics = virtual_mics;
+                            for (old, new) in new_config.dedupe_virtual_mic_names() {
+                                tracing::warn!("Renamed duplicate virtual mic {} to {}", old, new);
+                            }
+                            if let Some(device) = &app.current_device {
+                                new_config.device.name = Some(device.name.clone());
+                                new_config.device.index = Some(device.index);
+                                new_config.device.sample_rate = device.default_sample_rate;
+                                new_config.device.system_default = false;
+                                let mics = new_config.virtual_mics.clone();
+                                new_config.remember_channels(&device.name, device.channels, &mics);
+                            }
+
+                            if let Err(e) = app.write_config(&new_config) {
+                                tracing::warn!("Failed to save config: {}", e);
+                            }
+                            // A finished setup is saved, one-shot or not, and
+                            // captures the device it picked
+                            app.persist_edits = true;
+                            app.follows_default = false;
+                            app.capture_device = None;
+
+                            // Replace the preview with a full session (syncs driver devices),
+                            // or restart the one kept running through setup
+                            drop(audio_capture.take());
+                            app.config = new_config;
+                            restart_session(&mut session, &mut app, &mut driver, "Failed to start");
+                        }
+                        AppAction::Restart | AppAction::Retry => {
+                            drop(audio_capture.take());
+                            restart_session(
+                                &mut session,
+                                &mut app,
+                                &mut driver,
+                                "Failed to restart",
+                            );
+                        }
+                        AppAction::RemoveStaleSocket => {
+                            match DriverClient::remove_stale_socket() {
+                                Ok(true) => app.set_notice(
+                                    "Removed the stale driver socket; restart coreaudiod to load the driver again"
+                                        .into(),
+                                ),
+                                Ok(false) => app.set_notice("The driver socket is no longer stale".into()),
+                                Err(e) => app.set_notice(format!("{:#}", e)),
+                            }
+                            app.driver_socket = DriverClient::socket_state();
+                        }
+                        AppAction::RecoverDriver => recover_driver(&mut app, &mut driver),
+                    }
+                }
+
+                // Monitoring only lives in channel selection and follows the cursor
+                if let Some(active) = &monitor {
+                    if matches!(app.state, AppState::SelectChannels | AppState::NameChannel) {
+                        active.set_channel(app.channel_cursor);
+                    } else {
+                        monitor = None;
+                        app.monitoring = false;
+                    }
+                }
+            }
+            AppEvent::Tick => {
+                app.save_prefs(false);
+
+                while let Some(outcome) = driver.try_recv() {
+                    let (recovery, next) = app.recovery_outcome(&outcome.op, &outcome.result);
+                    if let Some(op) = next {
+                        driver.submit(op);
+                    }
+                    if recovery {
+                        continue;
+                    }
+                    if let Err(e) = outcome.result {
+                        tracing::warn!("Driver command {:?} failed: {}", outcome.op, e);
+                        if DriverError::kind_of(&e).is_some()
+                            || StaleSocket::is_cause_of(&e)
+                            || DriverTimeout::is_cause_of(&e)
+                        {
+                            app.set_notice(format!("Driver: {}", e));
+                        }
+                    }
+                }
+                app.driver_busy = driver.is_busy();
+                app.spinner = app.spinner.wrapping_add(1);
+
+                if app.kiosk_retry_due(Instant::now()) {
+                    drop(audio_capture.take());
+                    restart_session(&mut session, &mut app, &mut driver, "Failed to restart");
+                }
+
+                if let Some(query) = driver_status.as_ref().and_then(|r| r.try_iter().last()) {
+                    app.status = Some(build_report(
+                        &app.config,
+                        app.config_location.as_ref(),
+                        query,
+                    ));
+                }
+
+                let device_events: Vec<DeviceEvent> = watcher
+                    .as_ref()
+                    .map(|w| w.events().try_iter().collect())
+                    .unwrap_or_default();
+                for event in device_events {
+                    match &event {
+                        DeviceEvent::DeviceAdded(_) | DeviceEvent::DeviceRemoved(_) => {
+                            refresh_devices(&mut app);
+                            // Started before any device was plugged in
+                            if resume && app.state == AppState::AskAction {
+                                session = start_session(&mut app, &mut driver, "Failed to start");
+                            }
+                        }
+                        DeviceEvent::DefaultInputChanged(name)
+                            if session.is_some() && app.default_input_changed(name) =>
+                        {
+                            restart_session(
+                                &mut session,
+                                &mut app,
+                                &mut driver,
+                                "Failed to switch to the default input",
+                            );
+                        }
+                        // The driver lost its devices when it went away
+                        DeviceEvent::DriverUp if session.is_some() => {
+                            sync_driver(&mut driver, &expected_mics(&app.config));
+                        }
+                        _ => {}
+                    }
+                    if let Some(message) = device_event_notice(&event) {
+                        app.set_notice(message);
+                    }
+                }
+
+                // Update audio levels and buffer usage from capture
+                let capture = session
+                    .as_ref()
+                    .map(|s| s.capture())
+                    .or(audio_capture.as_ref());
+                let mut tick_peaks = Vec::new();
+                if let Some(capture) = capture {
+                    while let Ok(levels) = capture.peak_receiver().try_recv() {
+                        app.update_levels(&levels);
+                        app.correlation = levels.correlation();
+                        tick_peaks.resize(levels.len(), 0.0f32);
+                        for (peak, &level) in tick_peaks.iter_mut().zip(levels.iter()) {
+                            *peak = peak.max(level);
+                        }
+                    }
+
+                    app.update_buffer(
+                        capture.write_pos(),
+                        capture.read_pos(),
+                        capture.capacity_frames(),
+                    );
+
+                    app.frames_written = capture.frames_written();
+                    app.clock_skew = Some(capture.clock_skew());
+                    app.dropped_peaks = capture.stats().dropped_peaks();
+                    app.write_errors = capture.stats().write_errors();
+                    app.clipped_samples = capture.stats().clipped_samples();
+
+                    // Preview capture during setup doesn't alert
+                    if session.is_some() {
+                        let stats = capture.stats();
+                        app.record_stream_faults(
+                            stats.xruns(),
+                            stats.device_lost(),
+                            stats.last_error(),
+                        );
+                        app.record_shm_overruns(
+                            stats.shm_overruns(),
+                            stats.unread(),
+                            Instant::now(),
+                        );
+                    }
+                    if session.is_some() && app.state == AppState::Running {
+                        if app.calibration.is_some() {
+                            let mean_squares: Vec<f32> = (0..capture.channel_count() as usize)
+                                .map(|ch| capture.rms().mean_square(ch))
+                                .collect();
+                            app.record_rms(&mean_squares, Instant::now());
+                        }
+
+                        let alert = app.alerts.check(
+                            app.clipped_samples,
+                            capture.write_pos(),
+                            Instant::now(),
+                        );
+                        if let Some(alert) = alert {
+                            ring_bell();
+                            app.raise_alert(alert);
+                        }
+                    }
+                }
+                // Even with no capture or no new peaks this tick
+                app.step_meters();
+                app.decay_preview(Instant::now());
+                if let Some(running) = session.as_mut() {
+                    app.update_extra_levels(running.extra_sources_mut());
+                }
+
+                if let Some(server) = &metrics {
+                    let capture = session.as_ref().map(|s| s.capture());
+                    let connected = driver_connected(watcher.as_ref());
+                    server.update(app.metrics(capture, &tick_peaks, connected));
+                }
+
+                // `--log-levels`: a CSV row per tick with new peaks while running
+                let log_capture = session
+                    .as_ref()
+                    .filter(|_| app.state == AppState::Running && !tick_peaks.is_empty())
+                    .map(|s| s.capture());
+                if let (Some(log), Some(capture)) = (level_log.as_mut(), log_capture) {
+                    let rms: Vec<f32> = (0..capture.channel_count() as usize)
+                        .map(|ch| capture.rms().mean_square(ch).sqrt())
+                        .collect();
+                    if let Err(e) = log.record(SystemTime::now(), &tick_peaks, &rms) {
+                        tracing::warn!("Level log stopped: {:#}", e);
+                        app.set_notice(format!("Level log stopped: {:#}", e));
+                        level_log = None;
+                    }
+                }
+
+                // Publish counters for `duomic status` about once a second
+                if session.is_some() && last_stats_publish.elapsed() >= Duration::from_secs(1) {
+                    let mut snapshot =
+                        StatsSnapshot::new(app.dropped_peaks, app.write_errors, app.xruns);
+                    snapshot.shm_overruns = app.shm_overruns;
+                    snapshot.last_error = app.stream_error.clone();
+                    snapshot.skipped_mics = app
+                        .out_of_range
+                        .iter()
+                        .map(|m| format!("{} (channel {})", m.name, m.channel))
+                        .collect();
+                    if let Err(e) = snapshot.write() {
+                        tracing::debug!("Failed to publish stats: {}", e);
+                    }
+                    last_stats_publish = Instant::now();
+                }
+            }
+            AppEvent::Resize(_, _) => {}
+        }
+
+        if app.state == AppState::Quit {
+            break;
+        }
+
+        // Meters need frequent ticks; menus and text entry don't
+        let tick_rate = app.tick_rate();
+        if events.tick_rate() != tick_rate {
+            events.set_tick_rate(tick_rate);
+        }
+    }
+
+    app.save_prefs(true);
+
+    // Restore the terminal before printing anything
+    drop(terminal);
+    drop(monitor);
+
+    if let Some(Err(e)) = level_log.map(LevelLog::finish) {
+        eprintln!("Warning: {:#}", e);
+    }
+
+    // Cleanup: remove all virtual devices from driver on exit (unless configured to keep them)
+    drop(audio_capture);
+    drop(session);
+    StatsSnapshot::remove();
+    // Nothing queued may run after the cleanup
+    driver.shutdown();
+    if app.config.behavior.keep_devices_on_exit {
+        tracing::info!("Keeping virtual devices on exit");
+    } else {
+        cleanup_all_devices();
+    }
+
+    if !options.quiet && app.start_time.is_some() {
+        print_summary(&app);
+    }
+
+    Ok(())
+}
+
+/// Print duration, per-mic peaks/clips and capture errors after the TUI exits
+fn print_summary(app: &App) {
+    let uptime = app.uptime().as_secs();
+    println!(
+        "Session summary ({:02}:{:02}:{:02})",
+        uptime / 3600,
+        (uptime % 3600) / 60,
+        uptime % 60
+    );
+
+    let width = app
+        .dashboard_labels
+        .iter()
+        .map(|l| l.width())
+        .max()
+        .unwrap_or(0);
+    for (i, label) in app.dashboard_labels.iter().enumerate() {
+        let peak = app.summary.peaks.get(i).copied().unwrap_or(0.0);
+        let clips = app.summary.clips.get(i).copied().unwrap_or(0);
+        let peak_text = if peak > 0.0 {
+            format!("{:6.1} dB", amplitude_to_db(peak, DEFAULT_FLOOR_DB))
+        } else {
+            "    -inf".to_string()
+        };
+        let clips_text = format!("{} clips", clips);
+        println!(
+            "  {}{}  peak {}  {}",
+            label,
+            " ".repeat(width - label.width()),
+            peak_text,
+            if clips > 0 {
+                paint("31", clips_text)
+            } else {
+                clips_text
+            }
+        );
+    }
+
+    let dropped = format!(
+        "Dropped peaks: {}  Write errors: {}",
+        app.dropped_peaks, app.write_errors
+    );
+    if app.dropped_peaks + app.write_errors > 0 {
+        println!("  {}", paint("33", dropped));
+    } else {
+        println!("  {}", dropped);
+    }
+}
+
+/// Start playing the cursor channel on `[monitor] output_device` (or the default
+/// output), refusing when output and input are the same device
+///
+/// Playback trails the capture by MONITOR_LATENCY_FRAMES (~43ms at 48kHz) plus
+/// the output device's buffer, so it's for identifying channels, not for live
+/// monitoring.
+fn start_monitor(app: &mut App) -> Option<ChannelMonitor> {
+    let output = match resolve_output_device(app.config.monitor.output_device.as_deref()) {
+        Ok(output) => output,
+        Err(e) => {
+            app.monitoring = false;
+            app.set_notice(format!("Monitoring failed: {}", e));
+            return None;
+        }
+    };
+
+    // Playing back through the capturing device (e.g. an interface's speaker
+    // out next to its mics) feeds the monitor straight back into the input
+    let input = app.current_device.as_ref().map(|d| d.name.clone());
+    if input.is_some() && output.name().ok() == input {
+        app.monitoring = false;
+        app.set_notice(format!(
+            "Not monitoring: {} is also the output (feedback risk, set [monitor] output_device)",
+            input.unwrap_or_default()
+        ));
+        return None;
+    }
+
+    match ChannelMonitor::start_on(&output, app.channel_cursor) {
+        Ok(monitor) => {
+            let latency_ms =
+                MONITOR_LATENCY_FRAMES as u64 * 1000 / monitor.capture_rate().max(1) as u64;
+            app.set_notice(format!(
+                "Monitoring on {} (~{} ms behind) - use headphones to avoid feedback",
+                monitor.device_name(),
+                latency_ms
+            ));
+            Some(monitor)
+        }
+        Err(e) => {
+            app.monitoring = false;
+            app.set_notice(format!("Monitoring failed: {}", e));
+            None
+        }
+    }
+}
+
+/// List input devices with the config's hidden/excluded/pinned filters applied
+fn load_devices(config: &Config) -> Result<Vec<AudioDevice>> {
+    Ok(filter_devices(
+        list_input_devices()?,
+        &config.devices.hidden_patterns(),
+        &config.devices.pinned,
+    ))
+}
+
+/// Re-enumerate input devices into the app
+fn refresh_devices(app: &mut App) {
+    match load_devices(&app.config) {
+        Ok(devices) => app.set_devices(devices),
+        Err(e) => tracing::warn!("Failed to list devices: {}", e),
+    }
+}
+
+/// Banner text for a device or driver change (clips have their own alerts)
+fn device_event_notice(event: &DeviceEvent) -> Option<String> {
+    match event {
+        DeviceEvent::DeviceAdded(device) => Some(format!("Device connected: {}", device.name)),
+        DeviceEvent::DeviceRemoved(device) => Some(format!("Device disconnected: {}", device.name)),
+        DeviceEvent::DriverUp => Some("Driver is back, restoring virtual mics".to_string()),
+        DeviceEvent::DriverDown => Some("Driver stopped responding".to_string()),
+        // The app words its own notice (see `App::default_input_changed`)
+        DeviceEvent::DefaultInputChanged(_) => None,
+        DeviceEvent::Clip { .. } => None,
+    }
+}
+
+/// Queue a sync (removes orphans, adds missing), replacing any queued one
+fn sync_driver(driver: &mut DriverWorker, expected: &[DeviceInfo]) {
+    driver.cancel();
+    driver.submit(DriverOp::Sync(expected.to_vec()));
+}
+
+/// Start capture for `app.config`, sync the driver and show the dashboard
+///
+/// On failure the app shows the error screen (prefixed with `context`).
+fn start_session(
+    app: &mut App,
+    driver: &mut DriverWorker,
+    context: &str,
+) -> Option<RunningSession> {
+    match Session::start_capture(&app.capture_config()) {
+        Ok(started) => {
+            sync_driver(driver, started.virtual_mics());
+            app.set_out_of_range(started.out_of_range_mics());
+            app.set_extra_sources(started.extra_sources());
+            app.check_channel_layout(started.device());
+            app.remember_device(&started.device().name);
+            app.device_channels = started.capture().channel_count() as usize;
+            app.set_live_sample_rate(started.capture().sample_rate());
+            app.start_with_existing_config();
+            Some(started)
+        }
+        Err(e) => {
+            app.set_start_error(context, e);
+            None
+        }
+    }
+}
+
+/// Wait before kiosk retry number `retries` (0-based)
+fn kiosk_backoff(retries: u32) -> Duration {
+    KIOSK_RETRY_MIN
+        .saturating_mul(1 << retries.min(16))
+        .min(KIOSK_RETRY_MAX)
+}
+
+/// Error screen `c`: probe the driver, and if it's down run the recovery helper
+///
+/// Both run on the driver worker; [`App::recovery_outcome`] takes each step.
+/// Without a helper the notice names the command to run by hand; duomic
+/// never runs sudo itself.
+fn recover_driver(app: &mut App, driver: &mut DriverWorker) {
+    app.recovery = Some(Recovery::Probe);
+    app.set_notice("Checking the driver...".into());
+    driver.submit(DriverOp::Ping);
+}
+
+/// Restart `session` for `app.config`, or start a new one
+///
+/// On the same device the shared memory stays mapped and only the stream is
+/// rebuilt; another device or channel count gets a new session.
+fn restart_session(
+    session: &mut Option<RunningSession>,
+    app: &mut App,
+    driver: &mut DriverWorker,
+    context: &str,
+) {
+    let config = app.capture_config();
+    let restarted = match session.as_mut().map(|s| s.restart(&config)) {
+        Some(Ok(())) => true,
+        Some(Err(e)) => {
+            tracing::info!("Restarting with a new session: {}", e);
+            false
+        }
+        None => false,
+    };
+    if let Some(running) = session.as_ref().filter(|_| restarted) {
+        sync_driver(driver, running.virtual_mics());
+        app.set_out_of_range(running.out_of_range_mics());
+        app.set_extra_sources(running.extra_sources());
+        app.set_live_sample_rate(running.capture().sample_rate());
+        app.start_with_existing_config();
+    } else {
+        drop(session.take());
+        *session = start_session(app, driver, context);
+    }
+}
+
+/// Capture `config`'s setup without the TUI until a termination signal
+///
+/// For `duomic run` with stdout redirected: publishes stats for `duomic
+/// status`, serves `[metrics] listen`, prints `[alerts]` to stderr, writes
+/// the level log like the dashboard, and cleans up per `[behavior]
+/// keep_devices_on_exit`. While the device is missing or capture fails it
+/// retries with the kiosk backoff. With `meters` (`--simple`) it also
+/// rewrites one line of level meters on stdout every tick.
+fn run_headless(
+    config: &Config,
+    mut driver: DriverWorker,
+    watcher: Option<&DeviceWatcher>,
+    mut level_log: Option<LevelLog>,
+    meters: bool,
+) -> Result<()> {
+    let tick = Duration::from_millis(config.ui.tick_ms.max(MIN_TICK_MS));
+    let clip_level = clip_threshold(config.meters.clip_threshold_db);
+    let metrics = config.metrics.listen.as_deref().and_then(|addr| {
+        MetricsServer::bind(addr)
+            .map_err(|e| eprintln!("Warning: {:#}", e))
+            .ok()
+    });
+    let mut alerts = App::alert_monitor(config);
+
+    let mut session: Option<RunningSession> = None;
+    // Channels of the primary device that feed a mic, in order
+    let mut meter_channels: Vec<usize> = Vec::new();
+    let mut summary = SessionSummary::new(0, clip_level);
+    let mut retries = 0;
+    let mut retry_at = Instant::now();
+    let mut last_failure: Option<String> = None;
+    let mut last_stats_publish = Instant::now();
+    while !CLEANUP_REQUESTED.load(Ordering::SeqCst) {
+        std::thread::sleep(tick);
+
+        while let Some(outcome) = driver.try_recv() {
+            if let Err(e) = outcome.result {
+                tracing::warn!("Driver command {:?} failed: {}", outcome.op, e);
+            }
+        }
+        for event in watcher.into_iter().flat_map(|w| w.events().try_iter()) {
+            match event {
+                // The device may be back: don't wait out the backoff
+                DeviceEvent::DeviceAdded(_) => retry_at = Instant::now(),
+                // The driver lost its devices when it went away
+                DeviceEvent::DriverUp => {
+                    if let Some(running) = &session {
+                        sync_driver(&mut driver, running.virtual_mics());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if session.is_none() && Instant::now() >= retry_at {
+            match start_headless(config, &mut driver, meters) {
+                Ok(started) => {
+                    meter_channels = started
+                        .virtual_mics()
+                        .iter()
+                        .filter(|mic| mic.source == 0)
+                        .map(|mic| mic.channel as usize)
+                        .collect();
+                    meter_channels.sort_unstable();
+                    meter_channels.dedup();
+                    summary =
+                        SessionSummary::new(started.capture().channel_count() as usize, clip_level);
+                    alerts = App::alert_monitor(config);
+                    retries = 0;
+                    last_failure = None;
+                    session = Some(started);
+                }
+                Err(e) => {
+                    let message = format!("{:#}", e);
+                    let delay = kiosk_backoff(retries);
+                    if last_failure.as_ref() != Some(&message) {
+                        eprintln!("Failed to start: {} (retrying)", message);
+                    }
+                    tracing::info!("Retrying capture in {:?}", delay);
+                    retry_at = Instant::now() + delay;
+                    retries += 1;
+                    last_failure = Some(message);
+                }
+            }
+        }
+
+        let Some(running) = session.as_mut() else {
+            if let Some(server) = &metrics {
+                server.update(Metrics {
+                    driver_connected: driver_connected(watcher),
+                    ..Metrics::default()
+                });
+            }
+            continue;
+        };
+        let peaks = running.levels().to_vec();
+        summary.record(&peaks);
+        let capture = running.capture();
+        let stats = capture.stats();
+
+        if meters {
+            let levels: Vec<(usize, f32)> = meter_channels
+                .iter()
+                .filter_map(|&ch| Some((ch, *peaks.get(ch)?)))
+                .collect();
+            let line =
+                simple_meter_line(&levels, config.meters.floor_db, clip_level, color_enabled());
+            let mut stdout = std::io::stdout();
+            let _ = write!(stdout, "\r{}", line);
+            let _ = stdout.flush();
+        }
+
+        if let Some(log) = &mut level_log {
+            let rms: Vec<f32> = (0..peaks.len()).map(|ch| capture.rms().get(ch)).collect();
+            if let Err(e) = log.record(SystemTime::now(), &peaks, &rms) {
+                eprintln!("Warning: level log stopped: {:#}", e);
+                level_log = None;
+            }
+        }
+
+        if let Some(alert) =
+            alerts.check(stats.clipped_samples(), capture.write_pos(), Instant::now())
+        {
+            if meters {
+                println!();
+            }
+            eprintln!("Alert: {}", alert_message(alert));
+        }
+
+        if let Some(server) = &metrics {
+            let connected = driver_connected(watcher);
+            server.update(headless_metrics(running, &peaks, &summary, connected));
+        }
+
+        if stats.device_lost() {
+            if meters {
+                println!();
+            }
+            eprintln!(
+                "Lost the input device: {} (retrying)",
+                stats.last_error().unwrap_or_else(|| "no audio".to_string())
+            );
+            session = None;
+            retry_at = Instant::now();
+            continue;
+        }
+        if last_stats_publish.elapsed() >= Duration::from_secs(1) {
+            let mut snapshot =
+                StatsSnapshot::new(stats.dropped_peaks(), stats.write_errors(), stats.xruns());
+            snapshot.shm_overruns = stats.shm_overruns();
+            snapshot.last_error = stats.last_error();
+            snapshot.skipped_mics = running
+                .out_of_range_mics()
+                .iter()
+                .map(|m| format!("{} (channel {})", m.name, m.channel))
+                .collect();
+            if let Err(e) = snapshot.write() {
+                tracing::debug!("Failed to publish stats: {}", e);
+            }
+            last_stats_publish = Instant::now();
+        }
+    }
+
+    if meters {
+        println!();
+    }
+    if let Some(Err(e)) = level_log.map(LevelLog::finish) {
+        eprintln!("Warning: {:#}", e);
+    }
+    drop(session);
+    StatsSnapshot::remove();
+    // Nothing queued may run after the cleanup
+    driver.shutdown();
+    if config.behavior.keep_devices_on_exit {
+        tracing::info!("Keeping virtual devices on exit");
+    } else {
+        cleanup_all_devices();
+    }
+    Ok(())
+}
+
+/// Notice text for `alert`
+fn alert_message(alert: Alert) -> &'static str {
+    match alert {
+        Alert::Clip => "Clipping! Lower the input gain",
+        Alert::Disconnect => "No audio from the device, is it still connected?",
+    }
+}
+
+/// Start capture for [`run_headless`] and queue the driver sync behind any
+/// earlier one, so this setup's mics are the ones left in the driver
+fn start_headless(
+    config: &Config,
+    driver: &mut DriverWorker,
+    meters: bool,
+) -> Result<RunningSession, DuomicError> {
+    let session = Session::start_capture(config)?;
+    sync_driver(driver, session.virtual_mics());
+
+    let capturing = format!(
+        "{} mic(s) from {}",
+        session.virtual_mics().len(),
+        session.device().name
+    );
+    if meters {
+        eprintln!("Capturing {} (stop with Ctrl+C)", capturing);
+    } else {
+        eprintln!(
+            "No interactive terminal: capturing {} without the UI (stop with Ctrl+C or SIGTERM)",
+            capturing
+        );
+    }
+    if DriverClient::socket_state() != SocketState::Present {
+        eprintln!("Warning: driver not running, virtual mics are registered once it starts");
+    }
+    for mic in session.out_of_range_mics() {
+        eprintln!(
+            "Warning: skipped {}: channel {} is not on this device",
+            mic.name, mic.channel
+        );
+    }
+    Ok(session)
+}
+
+/// What `[metrics] listen` serves without the TUI: one entry per primary
+/// device mic, clips counted per channel
+fn headless_metrics(
+    session: &RunningSession,
+    peaks: &[f32],
+    summary: &SessionSummary,
+    driver_connected: bool,
+) -> Metrics {
+    let capture = session.capture();
+    let stats = capture.stats();
+    let capacity = capture.capacity_frames();
+    let mics = session
+        .virtual_mics()
+        .iter()
+        .filter(|mic| mic.source == 0)
+        .map(|mic| {
+            let channel = mic.channel as usize;
+            MicMetrics {
+                name: mic.name.clone(),
+                channel: mic.channel,
+                peak: peaks.get(channel).copied().unwrap_or(0.0),
+                rms: capture.rms().get(channel),
+                clips: summary.clips.get(channel).copied().unwrap_or(0),
+            }
+        })
+        .collect();
+    Metrics {
+        mics,
+        capturing: true,
+        driver_connected,
+        buffer_fill: buffer_fill(capture.write_pos(), capture.read_pos(), capacity)
+            .map_or(0.0, |fill| fill as f32 / capacity as f32),
+        clipped_samples: stats.clipped_samples(),
+        dropped_peaks: stats.dropped_peaks(),
+        write_errors: stats.write_errors(),
+        xruns: stats.xruns(),
+        shm_overruns: stats.shm_overruns(),
+    }
+}
+
+/// Whether the driver socket accepts connections and the watcher's last PING
+/// was answered (a socket left by a crashed driver doesn't count)
+fn driver_connected(watcher: Option<&DeviceWatcher>) -> bool {
+    watcher.is_none_or(|w| w.driver_up()) && DriverClient::socket_state() == SocketState::Present
+}
+
+/// Width of each `--simple` meter bar, in characters
+const SIMPLE_METER_WIDTH: usize = 8;
+
+/// One `--simple` line, e.g. `Ch0 |######--|  -8dB  Ch2 |##------| -20dB`
+///
+/// Bars follow the dB scale down to `floor_db`. With `color`, a bar is
+/// yellow from -12 dB and red once it reaches `clip_level`. Every meter has
+/// the same width, so rewriting the line after `\r` leaves nothing behind.
+fn simple_meter_line(
+    levels: &[(usize, f32)],
+    floor_db: f32,
+    clip_level: f32,
+    color: bool,
+) -> String {
+    levels
+        .iter()
+        .map(|&(channel, level)| {
+            let db = amplitude_to_db(level, floor_db);
+            let filled = (((db - floor_db) / -floor_db).clamp(0.0, 1.0) * SIMPLE_METER_WIDTH as f32)
+                .round() as usize;
+            let bar = format!(
+                "{}{}",
+                "#".repeat(filled),
+                "-".repeat(SIMPLE_METER_WIDTH - filled)
+            );
+            let bar = match color {
+                true if level >= clip_level => paint("31", bar),
+                true if db >= -12.0 => paint("33", bar),
+                true => paint("32", bar),
+                false => bar,
+            };
+            format!("Ch{} |{}| {:>4.0}dB", channel, bar, db)
+        })
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+/// Remove all virtual devices from driver (called on exit)
+fn cleanup_all_devices() {
+    if !DriverClient::is_driver_available() {
+        return;
+    }
+
+    let mut client = DriverClient::new();
+    if client.connect().is_err() {
+        return;
+    }
+
+    match client.remove_all_devices() {
+        Ok(count) => {
+            if count > 0 {
+                tracing::info!("Cleaned up {} virtual devices on exit", count);
+            }
+        }
+        Err(e) => {
+            tracing::warn!("Failed to cleanup devices: {}", e);
+        }
+    }
+}
+
+// ============ UI Drawing ============
+
+fn draw_ui(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    frame.render_widget(Clear, area);
+
+    match &app.state {
+        AppState::NoDevices => draw_no_devices(frame, app),
+        AppState::AskAction => draw_ask_action(frame, app),
+        AppState::SelectDevice => draw_select_device(frame, app),
+        AppState::SelectChannels | AppState::NameChannel => draw_select_channels(frame, app),
+        AppState::EnterNames => draw_enter_names(frame, app),
+        AppState::Running => draw_running(frame, app),
+        AppState::EditMic => draw_edit_mic(frame, app),
+        AppState::Error(msg) => draw_error(frame, app, msg),
+        AppState::Quit => {}
+    }
+}
+
+/// Render the transient notice banner (if any) into `area`
+fn draw_notice(frame: &mut Frame, app: &App, area: Rect) {
+    if let Some(notice) = app.active_notice() {
+        frame.render_widget(
+            Paragraph::new(format!("⚠ {}", notice)).style(Style::default().fg(Color::Yellow)),
+            area,
+        );
+    }
+}
+
+fn draw_no_devices(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .split(area);
+
+    let title = Block::default()
+        .title(" duomic ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    frame.render_widget(title, chunks[0]);
+
+    let content = Block::default()
+        .title(" No Input Devices ")
+        .borders(Borders::ALL);
+    let inner = content.inner(chunks[1]);
+    frame.render_widget(content, chunks[1]);
+
+    let lines = vec![
+        Line::from(""),
+        Line::from("  No audio input devices were found.")
+            .style(Style::default().fg(Color::Yellow)),
+        Line::from(""),
+        Line::from("  1. Plug in your USB microphone"),
+        Line::from("  2. Check it appears in System Settings → Sound → Input"),
+        Line::from(""),
+        Line::from("  Waiting for a device... (checks every few seconds)")
+            .style(Style::default().fg(Color::DarkGray)),
+    ];
+    frame.render_widget(Paragraph::new(lines), inner);
+
+    let restart = app.keymap.label(KeyAction::Restart);
+    let quit = app.keymap.label(KeyAction::Quit);
+    let hints = [(restart.as_str(), "Refresh"), (quit.as_str(), "Quit")];
+    frame.render_widget(HelpBar::new(&hints), chunks[2]);
+}
+
+fn draw_ask_action(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(8),
+            Constraint::Length(1),
+        ])
+        .split(area);
+
+    // Title
+    let title = Block::default()
+        .title(" duomic ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    frame.render_widget(title, chunks[0]);
+
+    // Content
+    let content = Block::default()
+        .title(" Current Configuration ")
+        .borders(Borders::ALL);
+    let inner = content.inner(chunks[1]);
+    frame.render_widget(content, chunks[1]);
+
+    let device_name = app.config.device.name.as_deref().unwrap_or("?");
+    let mic_names: Vec<_> = app
+        .config
+        .virtual_mics
+        .iter()
+        .map(|m| m.name.as_str())
+        .collect();
+
+    let mut lines = vec![
+        Line::from(format!("  Device: {}", device_name)),
+        Line::from(format!("  Microphones: {}", mic_names.join(", "))),
+        Line::from(""),
+    ];
+
+    // Options
+    let options = [
+        ("Start with current settings", 0),
+        ("Configure new device", 1),
+    ];
+
+    for (label, idx) in options {
+        let prefix = if app.action_cursor == idx {
+            "→ ●"
+        } else {
+            "  ○"
+        };
+        let style = if app.action_cursor == idx {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        lines.push(Line::styled(format!("  {} {}", prefix, label), style));
+    }
+
+    if let Some((path, source)) = &app.config_location {
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            format!("  Config: {} ({})", path.display(), source.label()),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+
+    frame.render_widget(Paragraph::new(lines), inner);
+
+    // Help
+    let select = app.keymap.label(KeyAction::Select);
+    let quit = app.keymap.label(KeyAction::Quit);
+    let hints = [
+        ("↑/↓", "Select"),
+        (select.as_str(), "Confirm"),
+        (quit.as_str(), "Quit"),
+    ];
+    frame.render_widget(HelpBar::new(&hints), chunks[2]);
+}
+
+fn draw_select_device(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .split(area);
+
+    let title = Block::default()
+        .title(" duomic - Select Device ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let title_inner = title.inner(chunks[0]);
+    frame.render_widget(title, chunks[0]);
+    draw_notice(frame, app, title_inner);
+
+    let content = Block::default()
+        .title(" Input Devices ")
+        .borders(Borders::ALL);
+    let inner = content.inner(chunks[1]);
+    frame.render_widget(content, chunks[1]);
+
+    // The tip takes the bottom of the list area without blocking selection
+    let list_area = match app.aggregate_hint() {
+        Some(hint) => {
+            // Word wrapping may need a line more than the plain width suggests
+            let lines = hint.width() / inner.width.max(1) as usize + 2;
+            let height = lines.min(inner.height as usize / 2) as u16;
+            let tip = Paragraph::new(hint)
+                .wrap(Wrap { trim: true })
+                .style(Style::default().fg(Color::Yellow));
+            let parts = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(1), Constraint::Length(height)])
+                .split(inner);
+            frame.render_widget(tip, parts[1]);
+            parts[0]
+        }
+        None => inner,
+    };
+
+    let device_list = DeviceList::new(&app.devices, app.selected_device_idx);
+    frame.render_widget(device_list, list_area);
+
+    let select = app.keymap.label(KeyAction::Select);
+    let restart = app.keymap.label(KeyAction::Restart);
+    let quit = app.keymap.label(KeyAction::Quit);
+    let hints = [
+        ("↑/↓", "Select"),
+        ("1-9", "Jump"),
+        (select.as_str(), "Confirm"),
+        (restart.as_str(), "Refresh"),
+        (quit.as_str(), "Quit"),
+    ];
+    frame.render_widget(HelpBar::new(&hints), chunks[2]);
+}
+
+fn draw_select_channels(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .split(area);
+
+    let device_name = app
+        .current_device
+        .as_ref()
+        .map(|d| d.name.as_str())
+        .unwrap_or("?");
+
+    let title = Block::default()
+        .title(format!(" {} - Channel Selection ", device_name))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let title_inner = title.inner(chunks[0]);
+    frame.render_widget(title, chunks[0]);
+    draw_notice(frame, app, title_inner);
+
+    // Channel list with multi-select
+    let content = Block::default()
+        .title(format!(
+            " Select Channels ({} to toggle) ",
+            app.keymap.label(KeyAction::Toggle)
+        ))
+        .borders(Borders::ALL);
+    let inner = content.inner(chunks[1]);
+    frame.render_widget(content, chunks[1]);
+
+    for (i, &selected) in app.channel_selected.iter().enumerate() {
+        if i as u16 >= inner.height {
+            break;
+        }
+
+        let is_cursor = i == app.channel_cursor;
+        let checkbox = match (selected, app.config.ui.ascii_only) {
+            (true, false) => "[✓]",
+            (true, true) => "[x]",
+            (false, _) => "[ ]",
+        };
+        let arrow = match (is_cursor, app.config.ui.ascii_only) {
+            (true, false) => "→",
+            (true, true) => ">",
+            (false, _) => " ",
+        };
+        let ch_name = channel_label(i).unwrap_or("Channel");
+        let level = app.channel_levels.get(i).copied().unwrap_or(0.0);
+
+        // Build line
+        let label = format!("{} {} Channel {} ({})", arrow, checkbox, i, ch_name);
+        let style = if is_cursor {
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD)
+        } else if selected {
+            Style::default().fg(Color::Green)
+        } else {
+            Style::default().fg(Color::White)
+        };
+
+        let y = inner.y + i as u16;
+        frame.buffer_mut().set_string(inner.x, y, &label, style);
+
+        // Level meter
+        let meter_x = inner.x + 28;
+        let meter_width = inner.width.saturating_sub(36).min(20);
+        if meter_width > 5 {
+            let fill = (level * meter_width as f32) as u16;
+            let (full, empty) = if app.config.ui.ascii_only {
+                ("#", "-")
+            } else {
+                ("█", "░")
+            };
+            for j in 0..meter_width {
+                let color = if j < meter_width * 3 / 4 {
+                    Color::Green
+                } else if j < meter_width * 7 / 8 {
+                    Color::Yellow
+                } else {
+                    Color::Red
+                };
+
+                let (symbol, style) = if j < fill {
+                    (full, Style::default().fg(color))
+                } else {
+                    (empty, Style::default().fg(Color::DarkGray))
+                };
+                frame.buffer_mut().set_string(meter_x + j, y, symbol, style);
+            }
+        }
+
+        // Names given inline, after the meter
+        let name_x = meter_x + meter_width.max(5) + 2;
+        if let Some(name) = app
+            .inline_name(i)
+            .filter(|_| selected && app.config.ui.inline_naming)
+        {
+            let room = (inner.right().saturating_sub(name_x)) as usize;
+            let ellipsis = if app.config.ui.ascii_only { "~" } else { "…" };
+            frame.buffer_mut().set_string(
+                name_x,
+                y,
+                truncate_to_width(name, room, ellipsis),
+                Style::default().fg(Color::Gray),
+            );
+        }
+    }
+
+    if app.state == AppState::NameChannel {
+        let label = format!("Name for Channel {}:", app.channel_cursor);
+        let input = TextInput::new(&app.name_input, app.name_input.width())
+            .label(&label)
+            .block(Block::default().borders(Borders::ALL));
+        frame.render_widget(input, chunks[2]);
+        let select = app.keymap.label(KeyAction::Select);
+        let cancel = app.keymap.label(KeyAction::Cancel);
+        let hints = [
+            (select.as_str(), "Save name (empty = default)"),
+            (cancel.as_str(), "Don't use this channel"),
+        ];
+        frame.render_widget(HelpBar::new(&hints), chunks[3]);
+        return;
+    }
+
+    // Selection count
+    let count_block = Block::default().borders(Borders::ALL);
+    let count_inner = count_block.inner(chunks[2]);
+    frame.render_widget(count_block, chunks[2]);
+
+    let mut count_text = format!("Selected: {} channels", app.selected_count());
+    if app.monitoring {
+        count_text.push_str(&format!("  ·  Listening to Channel {}", app.channel_cursor));
+    }
+    let count_style = if app.selected_count() > 0 {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default().fg(Color::Yellow)
+    };
+    frame.render_widget(
+        Paragraph::new(count_text).style(count_style).centered(),
+        count_inner,
+    );
+
+    let toggle = app.keymap.label(KeyAction::Toggle);
+    let select = app.keymap.label(KeyAction::Select);
+    let cancel = app.keymap.label(KeyAction::Cancel);
+    let hints = [
+        ("↑/↓", "Navigate"),
+        ("1-9", "Jump"),
+        (toggle.as_str(), "Toggle"),
+        ("a/A", "All/None"),
+        ("i", "Invert"),
+        (
+            "m",
+            if app.monitoring {
+                "Stop listening"
+            } else {
+                "Listen"
+            },
+        ),
+        (select.as_str(), "Confirm"),
+        (cancel.as_str(), "Back"),
+    ];
+    frame.render_widget(HelpBar::new(&hints), chunks[3]);
+}
+
+fn draw_enter_names(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .split(area);
+
+    let selected_channels = app.selected_channels();
+    let current_channel = selected_channels.get(app.name_cursor).copied().unwrap_or(0);
+
+    let title = Block::default()
+        .title(format!(" Name for Channel {} (optional) ", current_channel))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let title_inner = title.inner(chunks[0]);
+    frame.render_widget(title, chunks[0]);
+    draw_notice(frame, app, title_inner);
+
+    let content = Block::default()
+        .title(format!(
+            " {}/{} ",
+            app.name_cursor + 1,
+            app.channel_names.len()
+        ))
+        .borders(Borders::ALL);
+    let inner = content.inner(chunks[1]);
+    frame.render_widget(content, chunks[1]);
+
+    let default_name = app.generate_default_name(app.name_cursor);
+
+    let lines = vec![
+        Line::from(""),
+        Line::from(format!("  > {}█", app.name_input)).style(Style::default().fg(Color::White)),
+        Line::from(""),
+        Line::from(format!("  Leave empty for: \"{}\"", default_name))
+            .style(Style::default().fg(Color::DarkGray)),
+    ];
+
+    frame.render_widget(Paragraph::new(lines), inner);
+
+    let help = HelpBar::new(&[
+        ("Enter", "Confirm"),
+        ("Tab", "Defaults for the rest"),
+        ("Esc", "Back"),
+    ]);
+    frame.render_widget(help, chunks[2]);
+}
+
+fn draw_edit_mic(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ])
+        .split(area);
+
+    let current = app
+        .config
+        .virtual_mics
+        .get(app.edit_mic)
+        .map(|m| m.name.as_str())
+        .unwrap_or("?");
+
+    let title = Block::default()
+        .title(format!(" Edit {} ", current))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+    let title_inner = title.inner(chunks[0]);
+    frame.render_widget(title, chunks[0]);
+    draw_notice(frame, app, title_inner);
+
+    let content = Block::default().borders(Borders::ALL);
+    let inner = content.inner(chunks[1]);
+    frame.render_widget(content, chunks[1]);
+
+    let field = |active: bool| {
+        if active {
+            Style::default().fg(Color::White)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        }
+    };
+    let cursor = if app.edit_on_channel { "" } else { "█" };
+    let lines = vec![
+        Line::from(""),
+        Line::from(format!("  Name:    > {}{}", app.name_input, cursor))
+            .style(field(!app.edit_on_channel)),
+        Line::from(""),
+        Line::from(format!(
+            "  Channel: < {} >  (0-{})",
+            app.edit_channel,
+            app.device_channels.saturating_sub(1)
+        ))
+        .style(field(app.edit_on_channel)),
+    ];
+
+    frame.render_widget(Paragraph::new(lines), inner);
+
+    let help = HelpBar::new(&[
+        ("Enter", "Save"),
+        ("↑/↓", "Field"),
+        ("←/→", "Channel"),
+        ("Esc", "Cancel"),
+    ]);
+    frame.render_widget(help, chunks[2]);
+}
+
+/// Driver state, config file and per-mic sync for the `--dashboard` panel
+fn status_lines(app: &App) -> Vec<Line<'static>> {
+    let Some(report) = &app.status else {
+        return vec![Line::styled(
+            "Checking driver…",
+            Style::default().fg(Color::DarkGray),
+        )];
+    };
+
+    let (symbol, color) = match report.driver {
+        DriverState::Connected => ("●", Color::Green),
+        DriverState::NotResponding | DriverState::TimedOut => ("○", Color::Yellow),
+        DriverState::ConnectFailed | DriverState::StaleSocket | DriverState::NotRunning => {
+            ("○", Color::Red)
+        }
+    };
+    let config = match &report.config_path {
+        Some(path) if report.config_exists => path.clone(),
+        Some(path) => format!("{} (not saved)", path),
+        None => "(no config path)".to_string(),
+    };
+    let mut lines = vec![
+        Line::from(vec![
+            Span::raw("Driver: "),
+            Span::styled(
+                format!("{} {}", symbol, report.driver.label()),
+                Style::default().fg(color),
+            ),
+        ]),
+        Line::from(format!("Config: {}", config)),
+    ];
+
+    if let Some(e) = &report.driver_error {
+        lines.push(Line::styled(
+            format!("Failed to query driver: {}", e),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    for mic in &report.virtual_mics {
+        let color = match mic.state {
+            SyncState::InSync => Color::Green,
+            SyncState::ConfigOnly | SyncState::Moved => Color::Yellow,
+            SyncState::DriverOnly => Color::Red,
+        };
+        let tag = mic.color.as_deref().and_then(tag_color);
+        lines.push(Line::from(vec![
+            Span::styled(
+                if tag.is_some() { "● " } else { "" },
+                Style::default().fg(tag.unwrap_or_default()),
+            ),
+            Span::raw(format!("{} ", mic.name)),
+            Span::styled(
+                format!("(channel {}) ", mic.channel),
+                Style::default().fg(Color::DarkGray),
+            ),
+            Span::styled(mic.state.to_string(), Style::default().fg(color)),
+        ]));
+    }
+    lines
+}
+
+fn draw_running(frame: &mut Frame, app: &App) {
+    let area = frame.area();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .split(area);
+
+    let device_name = app.capture_device_name().unwrap_or("?");
+    let sample_rate = app.sample_rate() / 1000;
+
+    let header = Block::default()
+        .title(format!(
+            " duomic | {} @ {}kHz | ● Running ",
+            device_name, sample_rate
+        ))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(
+            if app.alert_flash.is_some_and(|t| t.elapsed() < ALERT_FLASH) {
+                Color::Red
+            } else {
+                Color::Green
+            },
+        ));
+    let header_inner = header.inner(chunks[0]);
+    frame.render_widget(header, chunks[0]);
+    if app.driver_busy {
+        let frames: &[&str] = if app.config.ui.ascii_only {
+            &["|", "/", "-", "\\"]
+        } else {
+            &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]
+        };
+        frame.render_widget(
+            Paragraph::new(format!(
+                "{} talking to driver…",
+                frames[app.spinner % frames.len()]
+            ))
+            .style(Style::default().fg(Color::Yellow)),
+            header_inner,
+        );
+    } else if let Some(suggestion) = &app.gain_suggestion {
+        let gains: Vec<String> = suggestion
+            .iter()
+            .map(|(channel, gain_db)| {
+                let name = app
+                    .config
+                    .virtual_mics
+                    .iter()
+                    .find(|m| m.channel as usize == *channel)
+                    .map_or("?", |m| m.name.as_str());
+                format!("{} {:+.0} dB", name, gain_db)
+            })
+            .collect();
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Suggested gain: {}. Apply? [y/n]",
+                gains.join(", ")
+            ))
+            .style(Style::default().fg(Color::Yellow)),
+            header_inner,
+        );
+    } else if let Some(change) = app
+        .layout_change
+        .as_ref()
+        .filter(|_| !app.out_of_range_prompt)
+    {
+        frame.render_widget(
+            Paragraph::new(format!(
+                "{} now has {} channels (was {}). Pick channels again, keeping names? [y/n]",
+                change.device.name, change.device.channels, change.was
+            ))
+            .style(Style::default().fg(Color::Yellow)),
+            header_inner,
+        );
+    } else if app.out_of_range_prompt {
+        let mics: Vec<String> = app
+            .out_of_range
+            .iter()
+            .map(|m| format!("{} (ch {})", m.name, m.channel))
+            .collect();
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Not on this {}-channel device, skipped: {}. Remove from config? [y/n] (s remaps)",
+                app.device_channels,
+                mics.join(", ")
+            ))
+            .style(Style::default().fg(Color::Yellow)),
+            header_inner,
+        );
+    } else if app.calibration.is_some() {
+        frame.render_widget(
+            Paragraph::new(format!(
+                "Measuring levels for {}s, speak or play normally…",
+                app.config.meters.calibration_s
+            ))
+            .style(Style::default().fg(Color::Yellow)),
+            header_inner,
+        );
+    } else {
+        draw_notice(frame, app, header_inner);
+    }
+
+    // `--dashboard`: driver and config status between the header and meters
+    let meters_area = if app.flat {
+        let lines = status_lines(app);
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(lines.len() as u16 + 2),
+                Constraint::Min(3),
+            ])
+            .split(chunks[1]);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::default().title(" Status ").borders(Borders::ALL)),
+            parts[0],
+        );
+        parts[1]
+    } else {
+        chunks[1]
+    };
+
+    // `[[extra_devices]]` get a block each under the main device's
+    let meters_area = if app.extra_groups.is_empty() {
+        meters_area
+    } else {
+        let mut constraints = vec![Constraint::Min(3)];
+        constraints.extend(
+            app.extra_groups
+                .iter()
+                .map(|group| Constraint::Length(group.mics.len().max(1) as u16 + 2)),
+        );
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints)
+            .split(meters_area);
+        for (group, area) in app.extra_groups.iter().zip(parts.iter().skip(1)) {
+            draw_extra_group(frame, app, group, *area);
+        }
+        parts[0]
+    };
+
+    // Level meters
+    let title = if app.extra_groups.is_empty() {
+        " Virtual Microphones ".to_string()
+    } else {
+        format!(" {} ", device_name)
+    };
+    let meters = Block::default().title(title).borders(Borders::ALL);
+    let meters_inner = meters.inner(meters_area);
+    frame.render_widget(meters, meters_area);
+
+    // Cursor marker plus any non-default mix, e.g. "> Host +3dB M Ø"
+    let mut labels = Vec::with_capacity(app.dashboard_labels.len());
+    for (i, label) in app.dashboard_labels.iter().enumerate() {
+        let channel = app.dashboard_channels.get(i).copied().unwrap_or(i);
+        let mix = channel_mix(&app.config, channel);
+        let mut label = format!(
+            "{}{}",
+            if i == app.dashboard_cursor {
+                "> "
+            } else {
+                "  "
+            },
+            label
+        );
+        if mix.gain_db != 0.0 {
+            label.push_str(&format!(" {:+.0}dB", mix.gain_db));
+        }
+        if mix.muted {
+            label.push_str(" M");
+        }
+        if mix.highpass_hz.is_some() {
+            label.push_str(" HP");
+        }
+        if mix.invert {
+            label.push_str(if app.config.ui.ascii_only {
+                " INV"
+            } else {
+                " Ø"
+            });
+        }
+        labels.push(label);
+    }
+    // Stereo pair correlation on the row after the mics
+    let correlation = correlation_pair(&app.config, app.device_channels as u16)
+        .map(|(left, right)| format!("  Corr [Ch {}/{}]", left, right));
+
+    // One label column for every row, so meters line up whatever the names
+    let label_width = labels
+        .iter()
+        .chain(&correlation)
+        .map(|l| l.width())
+        .max()
+        .unwrap_or(0) as u16;
+
+    // `color` tags as a dot before each meter, in a column only when some mic has one
+    let tags: Vec<Option<Color>> = app
+        .dashboard_mics
+        .iter()
+        .map(|&idx| {
+            let mic = app.config.virtual_mics.get(idx)?;
+            mic.color.as_deref().and_then(tag_color)
+        })
+        .collect();
+    let tag_width = if tags.iter().any(Option::is_some) {
+        2
+    } else {
+        0
+    };
+
+    // Gain sliders take the right end of every row, correlation included
+    let rows_width = meters_inner.width.saturating_sub(tag_width);
+    let slider_width = if app.config.ui.gain_slider && rows_width >= GAIN_SLIDER_MIN_ROW {
+        GAIN_SLIDER_WIDTH
+    } else {
+        0
+    };
+    let meter_width = rows_width.saturating_sub(slider_width + (slider_width > 0) as u16);
+
+    let params = app.meter_params();
+    for (i, (level, label)) in app.dashboard_levels.iter().zip(&labels).enumerate() {
+        if i as u16 >= meters_inner.height {
+            break;
+        }
+
+        let row = Rect {
+            x: meters_inner.x + tag_width,
+            y: meters_inner.y + i as u16,
+            width: meter_width,
+            height: 1,
+        };
+        if slider_width > 0 {
+            let channel = app.dashboard_channels.get(i).copied().unwrap_or(i);
+            let slider = GainSlider::new(channel_mix(&app.config, channel).gain_db)
+                .range_db(MAX_GAIN_DB)
+                .selected(i == app.dashboard_cursor)
+                .ascii(app.config.ui.ascii_only);
+            let slider_row = Rect {
+                x: meters_inner.right() - slider_width,
+                width: slider_width,
+                ..row
+            };
+            frame.render_widget(slider, slider_row);
+        }
+        if let Some(Some(color)) = tags.get(i) {
+            let dot = if app.config.ui.ascii_only { "*" } else { "●" };
+            frame
+                .buffer_mut()
+                .set_string(meters_inner.x, row.y, dot, Style::default().fg(*color));
+        }
+
+        let hold = (params.hold_ticks > 0)
+            .then(|| app.dashboard_holds.get(i).map(|&(hold, _)| hold))
+            .flatten();
+        let meter = LevelMeter::new(*level)
+            .hold(hold)
+            .clipped(app.dashboard_clips.get(i).is_some_and(|&ticks| ticks > 0))
+            .label(label)
+            .label_width(label_width)
+            .floor_db(app.config.meters.floor_db)
+            .clip_db(app.config.meters.clip_threshold_db)
+            .scale(app.config.meters.scale)
+            .style(app.config.ui.meter_style)
+            .ascii(app.config.ui.ascii_only);
+        frame.render_widget(meter, row);
+    }
+
+    let correlation_y = labels.len() as u16;
+    if let (Some(label), true) = (&correlation, correlation_y < meters_inner.height) {
+        let row = Rect {
+            x: meters_inner.x + tag_width,
+            y: meters_inner.y + correlation_y,
+            width: meter_width,
+            height: 1,
+        };
+        let meter = CorrelationMeter::new(app.correlation)
+            .label(label)
+            .label_width(label_width)
+            .ascii(app.config.ui.ascii_only);
+        frame.render_widget(meter, row);
+    }
+
+    // Stats
+    let uptime = app.uptime();
+    let hours = uptime.as_secs() / 3600;
+    let minutes = (uptime.as_secs() % 3600) / 60;
+    let seconds = uptime.as_secs() % 60;
+
+    // Without a driver read position both numbers are estimates; once the
+    // driver stops reading, the buffer only holds audio nobody will hear
+    let (latency, buffer) = match app.buffer_latency_ms {
+        _ if app.buffer_unread => ("Latency: -".to_string(), "not read".to_string()),
+        Some(ms) => (
+            format!("Latency: {:.0}ms", ms),
+            format!("{:.0}%", app.buffer_usage * 100.0),
+        ),
+        None => (
+            "Latency: ~21ms (est.)".to_string(),
+            format!("{:.0}%", app.buffer_usage * 100.0),
+        ),
+    };
+    let overrun = app.overrun_flagged(Instant::now());
+
+    let stats = Block::default()
+        .title(format!(
+            " {}{} | Buffer: {} | Dropped: {} peaks, {} writes | Xruns: {} | Overruns: {} | Clipped: {} | Duration: {:02}:{:02}:{:02} ",
+            if overrun { "BUFFER OVERRUN | " } else { "" },
+            latency,
+            buffer,
+            app.dropped_peaks,
+            app.write_errors,
+            app.xruns,
+            app.shm_overruns,
+            app.clipped_samples,
+            hours,
+            minutes,
+            seconds
+        ))
+        .title_style(if overrun {
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        })
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(if overrun {
+            Color::Red
+        } else {
+            Color::DarkGray
+        }));
+    frame.render_widget(stats, chunks[2]);
 
-// ============ UI Drawing ============
+    let quit = app.keymap.label(KeyAction::Quit);
+    let restart = app.keymap.label(KeyAction::Restart);
+    let setup = app.keymap.label(KeyAction::Setup);
+    let mut hints = vec![(quit.as_str(), "Quit"), (restart.as_str(), "Restart")];
+    if !app.kiosk {
+        hints.push((setup.as_str(), "Setup"));
+    }
+    hints.extend([
+        ("+/-", "Gain"),
+        ("m", "Mute"),
+        ("i", "Invert"),
+        ("e", "Edit"),
+        ("g", "Suggest gain"),
+        ("v", "Style"),
+        ("a", "ASCII"),
+        ("V", "Vim keys"),
+    ]);
+    frame.render_widget(HelpBar::new(&hints), chunks[3]);
+
+    if app.clock_overlay {
+        draw_clock_overlay(frame, app);
+    }
+    if app.confirm_quit {
+        draw_confirm_quit(frame, app);
+    }
+}
+
+/// Sample clock diagnostics (d), in the dashboard's top right corner
+fn draw_clock_overlay(frame: &mut Frame, app: &App) {
+    let lines: Vec<Line> = clock_lines(app.frames_written, app.sample_rate(), app.clock_skew)
+        .into_iter()
+        .map(Line::from)
+        .collect();
 
-fn draw_ui(frame: &mut Frame, app: &App) {
     let area = frame.area();
-    frame.render_widget(Clear, area);
+    let width = 40.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height.saturating_sub(1));
+    let popup = Rect {
+        x: area.x + area.width - width,
+        y: area.y + 1.min(area.height),
+        width,
+        height,
+    };
 
-    match &app.state {
-        AppState::AskAction => draw_ask_action(frame, app),
-        AppState::SelectDevice => draw_select_device(frame, app),
-        AppState::SelectChannels => draw_select_channels(frame, app),
-        AppState::EnterNames => draw_enter_names(frame, app),
-        AppState::Running => draw_running(frame, app),
-        AppState::Error(msg) => draw_error(frame, msg),
-        AppState::Quit => {}
+    frame.render_widget(Clear, popup);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .title(" Clock ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        ),
+        popup,
+    );
+}
+
+/// Overlay text: frames written, the time they cover, and the skew against the wall clock
+fn clock_lines(frames: u64, sample_rate: u32, skew: Option<f64>) -> Vec<String> {
+    let audio = Duration::from_secs_f64(frames as f64 / sample_rate.max(1) as f64);
+    let secs = audio.as_secs();
+    let skew = match skew {
+        Some(skew) => format!("{:+.1} ms", skew * 1e3),
+        None => "--".to_string(),
+    };
+    vec![
+        format!("Frames:  {}", frames),
+        format!(
+            "Audio:   {:02}:{:02}:{:02}.{:03} @ {} Hz",
+            secs / 3600,
+            (secs % 3600) / 60,
+            secs % 60,
+            audio.subsec_millis(),
+            sample_rate
+        ),
+        format!("Skew:    {} (wall - sample)", skew),
+    ]
+}
+
+/// Meters of an `[[extra_devices]]` entry (the mix keys only act on the main device)
+fn draw_extra_group(frame: &mut Frame, app: &App, group: &ExtraGroup, area: Rect) {
+    let block = Block::default()
+        .title(if group.lost {
+            format!(" {} - device lost, r restarts ", group.device)
+        } else {
+            format!(" {} ", group.device)
+        })
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(if group.lost { Color::Red } else { Color::Reset }));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let labels: Vec<String> = group
+        .mics
+        .iter()
+        .map(|(name, _)| format!("  {}", name))
+        .collect();
+    let label_width = labels.iter().map(|l| l.width()).max().unwrap_or(0) as u16;
+    for (i, ((_, channel), label)) in group.mics.iter().zip(&labels).enumerate() {
+        if i as u16 >= inner.height {
+            break;
+        }
+        let row = Rect {
+            y: inner.y + i as u16,
+            height: 1,
+            ..inner
+        };
+        let meter = LevelMeter::new(group.levels.get(*channel).copied().unwrap_or(0.0))
+            .label(label)
+            .label_width(label_width)
+            .floor_db(app.config.meters.floor_db)
+            .clip_db(app.config.meters.clip_threshold_db)
+            .scale(app.config.meters.scale)
+            .style(app.config.ui.meter_style)
+            .ascii(app.config.ui.ascii_only);
+        frame.render_widget(meter, row);
     }
 }
 
-fn draw_ask_action(frame: &mut Frame, app: &App) {
+/// `[behavior] confirm_quit` overlay, centered over the dashboard
+fn draw_confirm_quit(frame: &mut Frame, app: &App) {
+    let quit = app.keymap.label(KeyAction::Quit);
+    let cancel = app.keymap.label(KeyAction::Cancel);
+    let lines = vec![
+        Line::from("Virtual mics stop receiving audio."),
+        Line::from(""),
+        Line::from(format!("[y/{}] Quit   [{}] Back", quit, cancel)),
+    ];
+
+    let area = frame.area();
+    let width = 40.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(
+        Paragraph::new(lines).centered().block(
+            Block::default()
+                .title(" Quit duomic? ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow)),
+        ),
+        popup,
+    );
+}
+
+/// Plain-language meaning of the driver error codes the user can act on
+fn driver_error_explanation(kind: DriverErrorKind) -> Option<&'static str> {
+    match kind {
+        DriverErrorKind::BadRequest => Some(
+            "The driver didn't understand the request; the CLI and driver versions may not match",
+        ),
+        DriverErrorKind::PermissionDenied => {
+            Some("The driver refused the request; try reinstalling it with sudo ./install.sh")
+        }
+        DriverErrorKind::NotFound => Some("The driver has no virtual mic with that name"),
+        DriverErrorKind::AlreadyExists => {
+            Some("A virtual mic with that name already exists; pick another name in setup")
+        }
+        DriverErrorKind::Other => None,
+    }
+}
+
+/// Error screen suggestions for what's known about the driver
+///
+/// `ping` is `None` until the driver was checked with c; a present socket
+/// alone doesn't say whether the driver behind it still answers.
+fn recovery_steps(socket: SocketState, ping: Option<bool>, helper: Option<&str>) -> Vec<String> {
+    let mut steps = vec!["Make sure the device is connected".to_string()];
+    match (socket, ping) {
+        (SocketState::Missing, _) => steps.push(format!(
+            "Install the driver (sudo ./install.sh), then load it: {}",
+            RESTART_DRIVER_COMMAND
+        )),
+        (SocketState::Stale, _) => steps.push(format!(
+            "Press d to delete the stale socket, then restart the driver: {}",
+            RESTART_DRIVER_COMMAND
+        )),
+        (SocketState::Present, Some(false)) => steps.push(format!(
+            "Restart the unresponsive driver: {}",
+            RESTART_DRIVER_COMMAND
+        )),
+        (SocketState::Present, None) => steps.push(format!(
+            "If the driver is stuck, restart it: {}",
+            RESTART_DRIVER_COMMAND
+        )),
+        (SocketState::Present, Some(true)) => {}
+    }
+    let driver_ok = socket == SocketState::Present && ping == Some(true);
+    match helper {
+        Some(helper) if !driver_ok => {
+            steps.push(format!("Press c to run your recovery helper: {}", helper))
+        }
+        _ => steps.push("Press c to check whether the driver responds".to_string()),
+    }
+    steps.push("Run `duomic doctor` to check the whole setup".to_string());
+    steps
+}
+
+fn draw_error(frame: &mut Frame, app: &App, message: &str) {
     let area = frame.area();
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3),
-            Constraint::Min(8),
+            Constraint::Min(5),
+            Constraint::Length(1),
             Constraint::Length(1),
         ])
         .split(area);
 
-    // Title
     let title = Block::default()
-        .title(" duomic ")
+        .title(" ⚠ Error ")
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
+        .border_style(Style::default().fg(Color::Red));
     frame.render_widget(title, chunks[0]);
 
-    // Content
-    let content = Block::default()
-        .title(" Current Configuration ")
-        .borders(Borders::ALL);
+    let content = Block::default().borders(Borders::ALL);
     let inner = content.inner(chunks[1]);
     frame.render_widget(content, chunks[1]);
 
-    let device_name = app.config.device.name.as_deref().unwrap_or("?");
-    let mic_names: Vec<_> = app
-        .config
-        .virtual_mics
+    let mut lines = vec![Line::from(message).style(Style::default().fg(Color::Red))];
+    if let Some(explanation) = app.error_kind.and_then(driver_error_explanation) {
+        lines.push(Line::from(explanation).style(Style::default().fg(Color::Yellow)));
+    }
+    let socket = runtime_paths().socket.display();
+    let driver_state = match (app.driver_socket, app.driver_ping) {
+        (SocketState::Stale, _) => Some(format!(
+            "Driver socket {} is stale: the file exists but nothing listens (coreaudiod crashed?)",
+            socket
+        )),
+        (SocketState::Missing, _) => Some(format!(
+            "Driver socket {} is missing: the driver isn't loaded",
+            socket
+        )),
+        (SocketState::Present, Some(false)) => Some(format!(
+            "Driver socket {} accepts connections but the driver doesn't answer PING",
+            socket
+        )),
+        (SocketState::Present, Some(true)) => Some("The driver is responding".to_string()),
+        (SocketState::Present, None) => None,
+    };
+    if let Some(state) = driver_state {
+        lines.push(Line::from(state).style(Style::default().fg(Color::Yellow)));
+    }
+    lines.push(Line::from(""));
+    lines.push(Line::from("Suggestions:"));
+    let helper = app.config.behavior.recovery_helper.as_deref();
+    for (i, step) in recovery_steps(app.driver_socket, app.driver_ping, helper)
         .iter()
-        .map(|m| m.name.as_str())
-        .collect();
+        .enumerate()
+    {
+        lines.push(Line::from(format!("  {}. {}", i + 1, step)));
+    }
+    frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), inner);
 
-    let mut lines = vec![
-        Line::from(format!("  Device: {}", device_name)),
-        Line::from(format!("  Microphones: {}", mic_names.join(", "))),
-        Line::from(""),
-    ];
+    let restart = app.keymap.label(KeyAction::Restart);
+    let quit = app.keymap.label(KeyAction::Quit);
+    let mut hints = vec![(restart.as_str(), "Retry")];
+    if app.driver_socket == SocketState::Stale {
+        hints.push(("d", "Delete stale socket"));
+    }
+    hints.push((
+        "c",
+        if helper.is_some() {
+            "Recover driver"
+        } else {
+            "Check driver"
+        },
+    ));
+    if !app.kiosk {
+        hints.push((quit.as_str(), "Quit"));
+    }
+    draw_notice(frame, app, chunks[2]);
+    frame.render_widget(HelpBar::new(&hints), chunks[3]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use duomic::config::MeterStyle;
+    use duomic::ipc::RING_BUFFER_FRAMES;
+
+    fn test_app(channels: u16) -> App {
+        let device = AudioDevice {
+            name: "USB Mic".to_string(),
+            channels,
+            default_sample_rate: 48000,
+            index: 0,
+        };
+        let mut app = App::new(vec![device], Config::default());
+        app.handle_key(KeyAction::Select);
+        app
+    }
+
+    #[test]
+    fn test_picked_device_is_remembered_through_the_saver() {
+        let app = test_app(2);
+        assert_eq!(app.config.device.last_device.as_deref(), Some("USB Mic"));
+        assert!(app.config_saver.is_dirty());
+
+        // Setup run again on the same device: nothing new to write
+        let mut app = App::new(app.devices.clone(), app.config.clone());
+        app.handle_key(KeyAction::Select);
+        assert!(!app.config_saver.is_dirty());
+    }
+
+    #[test]
+    fn test_aggregate_device_hint() {
+        let device = |name: &str, channels, index| AudioDevice {
+            name: name.to_string(),
+            channels,
+            default_sample_rate: 48000,
+            index,
+        };
+        let mut app = App::new(vec![device("Interface", 2, 0)], Config::default());
+        assert_eq!(app.aggregate_hint(), None);
+
+        // Two USB mics, one channel each
+        app.set_devices(vec![
+            device("Interface", 2, 0),
+            device("Mic A", 1, 1),
+            device("Mic B", 1, 2),
+        ]);
+        let hint = app.aggregate_hint().unwrap();
+        assert!(hint.starts_with("Tip: 2 single-channel inputs are connected."));
+        assert!(hint.contains("Aggregate Device"));
+        assert!(hint.ends_with("once created; r refreshes the list."));
+        app.keymap = KeyMap::from_config(&duomic::config::KeybindingsConfig {
+            restart: "x".to_string(),
+            ..Default::default()
+        })
+        .0;
+        assert!(app
+            .aggregate_hint()
+            .unwrap()
+            .ends_with("once created; x refreshes the list."));
+
+        // Saved mics need more channels than the highlighted device has
+        app.config.add_virtual_mic("Host".to_string(), 0);
+        app.config.add_virtual_mic("Guest".to_string(), 3);
+        app.set_devices(vec![device("Interface", 2, 0)]);
+        assert!(app
+            .aggregate_hint()
+            .unwrap()
+            .starts_with("Tip: Interface has 2 channel(s), your saved mics use 4."));
+
+        app.state = AppState::SelectDevice;
+        assert!(matches!(
+            app.handle_key(KeyAction::Restart),
+            Some(AppAction::RefreshDevices)
+        ));
+    }
+
+    #[test]
+    fn test_summary_counts_clip_onsets() {
+        let mut summary = SessionSummary::new(2, clip_threshold(0.0));
+        summary.record(&[0.5, 1.0]);
+        summary.record(&[0.7, 1.0]);
+        summary.record(&[0.2, 0.3]);
+        summary.record(&[0.1, 1.0, 1.0]);
+
+        assert_eq!(summary.peaks, vec![0.7, 1.0]);
+        assert_eq!(summary.clips, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_monitor_toggle() {
+        let mut app = test_app(2);
+        assert!(matches!(
+            app.handle_key(KeyAction::Char('m')),
+            Some(AppAction::StartMonitor)
+        ));
+        assert!(app.monitoring);
+        assert!(matches!(
+            app.handle_key(KeyAction::Char('m')),
+            Some(AppAction::StopMonitor)
+        ));
+        assert!(!app.monitoring);
+    }
+
+    #[test]
+    fn test_inline_naming() {
+        let mut app = test_app(3);
+        app.config.ui.inline_naming = true;
+        let type_name = |app: &mut App, name: &str| {
+            for c in name.chars() {
+                app.handle_key(KeyAction::Char(c));
+            }
+            app.handle_key(KeyAction::Select)
+        };
+
+        // Toggling channel 2 on asks for its name, then moves on
+        app.channel_cursor = 2;
+        app.handle_key(KeyAction::Toggle);
+        assert_eq!(app.state, AppState::NameChannel);
+        assert!(type_name(&mut app, "Guest").is_none());
+        assert_eq!(app.state, AppState::SelectChannels);
+        assert_eq!(app.inline_name(2), Some("Guest"));
+
+        // Esc undoes the toggle; a duplicate name gets a suffix
+        app.channel_cursor = 1;
+        app.handle_key(KeyAction::Toggle);
+        app.handle_key(KeyAction::Cancel);
+        assert!(!app.channel_selected[1]);
+        app.channel_cursor = 0;
+        app.handle_key(KeyAction::Toggle);
+        type_name(&mut app, "Guest");
+        assert_eq!(app.inline_name(0), Some("Guest (2)"));
+
+        // Re-toggling prefills the kept name
+        app.channel_cursor = 2;
+        app.handle_key(KeyAction::Toggle);
+        app.handle_key(KeyAction::Toggle);
+        assert_eq!(app.name_input, "Guest");
+        app.handle_key(KeyAction::Cancel);
+        app.handle_key(KeyAction::Toggle);
+        app.name_input.clear();
+        type_name(&mut app, "Host");
+
+        // Confirming starts right away, mics in channel order
+        assert!(matches!(
+            app.handle_key(KeyAction::Select),
+            Some(AppAction::SaveAndStart)
+        ));
+        let mics: Vec<(u32, String)> = app
+            .build_virtual_mics()
+            .into_iter()
+            .map(|m| (m.channel, m.name))
+            .collect();
+        assert_eq!(
+            mics,
+            [(0, "Guest (2)".to_string()), (2, "Host".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_tick_rate_slows_down_without_meters() {
+        let mut app = test_app(2);
+        app.config.ui.tick_ms = 40;
+        assert_eq!(app.tick_rate(), Duration::from_millis(40));
+
+        app.state = AppState::EnterNames;
+        assert_eq!(app.tick_rate(), IDLE_TICK);
+
+        app.state = AppState::Running;
+        app.config.ui.tick_ms = 0;
+        assert_eq!(app.tick_rate(), Duration::from_millis(MIN_TICK_MS));
+    }
+
+    #[test]
+    fn test_names_follow_selection_changes() {
+        let mut app = test_app(3);
+        app.handle_key(KeyAction::Toggle);
+        app.handle_key(KeyAction::Down);
+        app.handle_key(KeyAction::Toggle);
+        app.handle_key(KeyAction::Select);
+
+        // Name channel 0, then go back to the channel list
+        for c in "Host".chars() {
+            app.handle_key(KeyAction::Char(c));
+        }
+        app.handle_key(KeyAction::Select);
+        app.handle_key(KeyAction::Cancel);
+        app.handle_key(KeyAction::Cancel);
+        assert_eq!(app.state, AppState::SelectChannels);
+
+        // Swap channel 1 for channel 2
+        app.handle_key(KeyAction::Toggle);
+        app.handle_key(KeyAction::Down);
+        app.handle_key(KeyAction::Toggle);
+        app.handle_key(KeyAction::Select);
+        assert_eq!(app.state, AppState::EnterNames);
+        assert_eq!(app.name_input, "Host");
+        assert_eq!(app.channel_names, ["Host", ""]);
+
+        app.handle_key(KeyAction::Select);
+        for c in "Guest".chars() {
+            app.handle_key(KeyAction::Char(c));
+        }
+        assert!(matches!(
+            app.handle_key(KeyAction::Select),
+            Some(AppAction::SaveAndStart)
+        ));
+
+        let mics: Vec<(String, u32)> = app
+            .build_virtual_mics()
+            .into_iter()
+            .map(|m| (m.name, m.channel))
+            .collect();
+        assert_eq!(mics, [("Host".to_string(), 0), ("Guest".to_string(), 2)]);
+    }
+
+    #[test]
+    fn test_accept_default_names_for_the_rest() {
+        let mut app = test_app(3);
+        app.config.naming.template = "Mic".to_string();
+        app.handle_key(KeyAction::Char('a'));
+        app.handle_key(KeyAction::Select);
+        for c in "Mic".chars() {
+            app.handle_key(KeyAction::Char(c));
+        }
+
+        assert!(matches!(
+            app.handle_key(KeyAction::AcceptAll),
+            Some(AppAction::SaveAndStart)
+        ));
+        // Defaults are still made unique
+        assert_eq!(app.channel_names, ["Mic", "Mic (2)", "Mic (3)"]);
+    }
+
+    #[test]
+    fn test_select_all_none_invert() {
+        let mut app = test_app(4);
+        app.handle_key(KeyAction::Toggle);
+
+        app.handle_key(KeyAction::Char('i'));
+        assert_eq!(app.channel_selected, [false, true, true, true]);
+        assert_eq!(app.selected_count(), 3);
+
+        app.handle_key(KeyAction::Char('A'));
+        assert_eq!(app.selected_count(), 0);
+
+        app.handle_key(KeyAction::Char('a'));
+        assert_eq!(app.selected_count(), 4);
+        app.handle_key(KeyAction::Select);
+        assert_eq!(app.state, AppState::EnterNames);
+        assert_eq!(app.channel_names.len(), 4);
+    }
+
+    #[test]
+    fn test_device_recalls_last_channels() {
+        let device = test_app(4).devices.remove(0);
+        let mut config = Config::default();
+        config.remember_channels(
+            &device.name,
+            4,
+            &[
+                VirtualMicConfig::new("Host".to_string(), 1),
+                VirtualMicConfig::new("Guest".to_string(), 3),
+            ],
+        );
+
+        let mut app = App::new(vec![device.clone()], config.clone());
+        app.handle_key(KeyAction::Select);
+        assert_eq!(app.channel_selected, [false, true, false, true]);
+        app.handle_key(KeyAction::Select);
+        assert_eq!(app.state, AppState::EnterNames);
+        assert_eq!(app.channel_names, ["Host", "Guest"]);
+        assert_eq!(app.name_input, "Host");
+
+        // Same name, different channel count: start from scratch
+        let resized = AudioDevice {
+            channels: 2,
+            ..device
+        };
+        let mut app = App::new(vec![resized], config);
+        app.handle_key(KeyAction::Select);
+        assert_eq!(app.channel_selected, [false, false]);
+        assert!(app.channel_names.is_empty());
+    }
+
+    #[test]
+    fn test_no_devices_until_one_appears() {
+        let mut app = App::new(Vec::new(), Config::default());
+        assert_eq!(app.state, AppState::NoDevices);
+        assert!(matches!(
+            app.handle_key(KeyAction::Restart),
+            Some(AppAction::RefreshDevices)
+        ));
+
+        app.set_devices(Vec::new());
+        assert_eq!(app.state, AppState::NoDevices);
+
+        app.set_devices(test_app(2).devices);
+        assert_eq!(app.state, AppState::SelectDevice);
+    }
+
+    #[test]
+    fn test_empty_names_get_distinct_defaults() {
+        let mut app = test_app(2);
+        app.handle_key(KeyAction::Toggle);
+        app.handle_key(KeyAction::Down);
+        app.handle_key(KeyAction::Toggle);
+        app.handle_key(KeyAction::Select);
+        assert_eq!(app.state, AppState::EnterNames);
+
+        app.handle_key(KeyAction::Select);
+        app.handle_key(KeyAction::Select);
+
+        let mics = app.build_virtual_mics();
+        assert_eq!(mics.len(), 2);
+        assert_ne!(mics[0].name, mics[1].name);
+    }
+
+    #[test]
+    fn test_duplicate_typed_name_is_suffixed() {
+        let mut app = test_app(2);
+        app.handle_key(KeyAction::Toggle);
+        app.handle_key(KeyAction::Down);
+        app.handle_key(KeyAction::Toggle);
+        app.handle_key(KeyAction::Select);
+
+        for _ in 0..2 {
+            for c in "Host".chars() {
+                app.handle_key(KeyAction::Char(c));
+            }
+            app.handle_key(KeyAction::Select);
+        }
+
+        assert_eq!(app.channel_names, ["Host", "Host (2)"]);
+        assert!(app.active_notice().is_some());
+    }
+
+    #[test]
+    fn test_ui_prefs_toggle_marks_pending_save() {
+        let mut app = test_app(2);
+        app.state = AppState::Running;
+
+        app.handle_key(KeyAction::Char('v'));
+        app.handle_key(KeyAction::Char('a'));
+        assert_eq!(app.active_notice(), Some("ASCII meters on"));
+        app.handle_key(KeyAction::Char('V'));
+
+        assert_eq!(app.config.ui.meter_style, MeterStyle::Mono);
+        assert!(app.config.ui.ascii_only);
+        assert!(app.config.ui.vim_keys && app.keymap.vim_keys());
+        assert!(app.config_saver.is_dirty());
+
+        // Not settled yet: nothing saved, still pending
+        app.save_prefs(false);
+        assert!(app.config_saver.is_dirty());
+    }
+
+    #[test]
+    fn test_sorted_dashboard_follows_device_channels() {
+        let mut config = Config::default();
+        config.ui.sort_mics = duomic::config::MicSort::Name;
+        config.add_virtual_mic("Zed".to_string(), 0);
+        config.add_virtual_mic("Amy".to_string(), 1);
+        let mut app = App::new(Vec::new(), config);
+
+        app.start_with_existing_config();
+        assert_eq!(app.dashboard_labels, ["Amy [Ch 1]", "Zed [Ch 0]"]);
+
+        app.update_levels(&[0.25, 0.75]);
+        app.step_meters();
+        assert_eq!(app.dashboard_levels, [0.75, 0.25]);
+        assert_eq!(app.summary.peaks, [0.75, 0.25]);
+    }
+
+    #[test]
+    fn test_meter_attack_and_release() {
+        let mut config = Config::default();
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.ui.tick_ms = 50;
+        config.meters.attack_ms = 5.0;
+        config.meters.release_ms = 300.0;
+        let mut app = App::new(Vec::new(), config);
+        app.start_with_existing_config();
+
+        // A step reaches (nearly) full level within one tick
+        app.update_levels(&[0.8]);
+        app.step_meters();
+        assert!(app.dashboard_levels[0] > 0.8 * 0.99);
+
+        // Then falls by exp(-tick/release) per tick once the input stops
+        let top = app.dashboard_levels[0];
+        for _ in 0..6 {
+            app.step_meters();
+        }
+        let expected = top * (-6.0f32 * 50.0 / 300.0).exp();
+        assert!((app.dashboard_levels[0] - expected).abs() < 1e-4);
+
+        // A louder peak mid-release rises again straight away
+        app.update_levels(&[0.5]);
+        app.step_meters();
+        assert!(app.dashboard_levels[0] > 0.49);
+    }
+
+    #[test]
+    fn test_peak_hold_and_clip_latch() {
+        let mut config = Config::default();
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.ui.tick_ms = 50;
+        config.meters.peak_hold_ms = 200.0;
+        config.meters.clip_latch_s = 0.1;
+        let mut app = App::new(Vec::new(), config);
+        app.start_with_existing_config();
+
+        app.update_levels(&[1.0]);
+        app.step_meters();
+        assert_eq!(app.dashboard_holds[0], (1.0, 4));
+        assert_eq!(app.dashboard_clips[0], 2);
+
+        // The hold stays for 4 ticks while the meter falls, then follows it
+        for _ in 0..4 {
+            app.step_meters();
+        }
+        assert_eq!(app.dashboard_holds[0].0, 1.0);
+        assert_eq!(app.dashboard_clips[0], 0);
+        app.step_meters();
+        assert_eq!(app.dashboard_holds[0].0, app.dashboard_levels[0]);
+    }
+
+    #[test]
+    fn test_meters_fade_through_a_data_gap() {
+        let mut config = Config::default();
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.ui.tick_ms = 50;
+        let mut app = App::new(Vec::new(), config);
+        app.start_with_existing_config();
+        app.update_levels(&[0.6]);
+        app.step_meters();
+
+        // No peaks arrive: every tick still releases a little, never a jump to 0
+        let mut previous = app.dashboard_levels[0];
+        for _ in 0..10 {
+            app.step_meters();
+            let level = app.dashboard_levels[0];
+            assert!(level < previous && level > previous * 0.5);
+            previous = level;
+        }
+
+        // Outside the dashboard the meters are left alone
+        app.state = AppState::AskAction;
+        app.step_meters();
+        assert_eq!(app.dashboard_levels[0], previous);
+    }
+
+    #[test]
+    fn test_zero_channel_device_is_not_selectable() {
+        let device = AudioDevice {
+            name: "Aggregate".to_string(),
+            channels: 0,
+            default_sample_rate: 48000,
+            index: 0,
+        };
+        let mut app = App::new(vec![device], Config::default());
+        assert_eq!(app.state, AppState::SelectDevice);
+
+        assert!(app.handle_key(KeyAction::Select).is_none());
+        assert_eq!(app.state, AppState::SelectDevice);
+        assert!(app.active_notice().is_some());
+    }
+
+    #[test]
+    fn test_out_of_range_mics_are_skipped_and_removable() {
+        let mut config = Config::default();
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.add_virtual_mic("Guest".to_string(), 4);
+        let mut app = App::new(Vec::new(), config);
+        app.device_channels = 2;
+
+        let skipped = [DeviceInfo {
+            name: "Guest".to_string(),
+            channel: 4,
+            color: None,
+            source: 0,
+        }];
+        app.set_out_of_range(&skipped);
+        app.start_with_existing_config();
+        assert_eq!(app.dashboard_channels, [0]);
+        assert!(app.out_of_range_prompt);
+
+        // Same skipped mics on restart don't ask again once dismissed
+        app.handle_key(KeyAction::No);
+        app.set_out_of_range(&skipped);
+        assert!(!app.out_of_range_prompt);
+        assert_eq!(app.config.virtual_mics.len(), 2);
+
+        app.out_of_range_prompt = true;
+        app.handle_key(KeyAction::Yes);
+        let names: Vec<&str> = app
+            .config
+            .virtual_mics
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+        assert_eq!(names, ["Host"]);
+        assert!(app.out_of_range.is_empty() && app.config_saver.is_dirty());
+        assert_eq!(app.dashboard_channels, [0]);
+    }
+
+    #[test]
+    fn test_gain_suggestion_targets_rms() {
+        let mut config = Config::default();
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.add_virtual_mic("Guest".to_string(), 1);
+        config.add_virtual_mic("Spare".to_string(), 2);
+        config.virtual_mics[1].gain_db = 2.0;
+        let mut app = App::new(Vec::new(), config);
+        app.start_with_existing_config();
+
+        let start = Instant::now();
+        app.handle_key(KeyAction::Char('g'));
+        app.calibration = Some(Calibration::new(start));
+        // Host at -24 dBFS RMS, Guest at -16 dBFS (with its +2 dB), Spare silent
+        let ms = |db: f32| 10f32.powf(db / 10.0);
+        let readings = [ms(-24.0), ms(-16.0), 0.0];
+        app.record_rms(&readings, start + Duration::from_secs(1));
+        assert!(app.gain_suggestion.is_none());
+        app.record_rms(&readings, start + Duration::from_secs(3));
+
+        assert_eq!(app.gain_suggestion, Some(vec![(0, 6.0), (1, 0.0)]));
+        assert!(matches!(
+            app.handle_key(KeyAction::Yes),
+            Some(AppAction::ApplyMixAll)
+        ));
+        assert_eq!(app.config.virtual_mics[0].gain_db, 6.0);
+        assert_eq!(app.config.virtual_mics[1].gain_db, 0.0);
+        assert_eq!(app.config.virtual_mics[2].gain_db, 0.0);
+        assert!(app.gain_suggestion.is_none() && app.config_saver.is_dirty());
+    }
+
+    #[test]
+    fn test_unsaved_one_shot_keeps_saved_setup() {
+        let dir = std::env::temp_dir().join(format!("duomic-one-shot-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let mut saved = Config::default();
+        saved.device.name = Some("Studio Interface".to_string());
+        saved.add_virtual_mic("Host".to_string(), 0);
+        saved.save_to(&path).unwrap();
+
+        let device = AudioDevice {
+            name: "USB Mic".to_string(),
+            channels: 2,
+            default_sample_rate: 48000,
+            index: 0,
+        };
+        let mut app = App::new(vec![device.clone()], saved);
+        app.config_location = Some((path.clone(), ConfigSource::Flag));
+        let mics = mics_from_mappings(&device, &[(1, "Guest".to_string())]).unwrap();
+        app.use_one_shot(&device, mics, false).unwrap();
+        assert_eq!(app.config.virtual_mics[0].name, "Guest");
+
+        // A live edit marks the config dirty, but nothing reaches the file
+        app.state = AppState::Running;
+        app.handle_key(KeyAction::Char('v'));
+        assert!(app.config_saver.is_dirty());
+        app.save_prefs(true);
+
+        let on_disk = Config::load_from(&path).unwrap();
+        assert_eq!(on_disk.device.name.as_deref(), Some("Studio Interface"));
+        assert_eq!(on_disk.virtual_mics[0].name, "Host");
+        assert_eq!(on_disk.ui.meter_style, Config::default().ui.meter_style);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_map_flags() {
+        assert_eq!(parse_mapping("0=Vocals"), Ok((0, "Vocals".to_string())));
+        assert_eq!(
+            parse_mapping(" 2 = Lead Guitar "),
+            Ok((2, "Lead Guitar".to_string()))
+        );
+        assert!(parse_mapping("Vocals")
+            .unwrap_err()
+            .contains("CHANNEL=NAME"));
+        assert!(parse_mapping("left=Vocals")
+            .unwrap_err()
+            .contains("invalid channel \"left\""));
+        assert!(parse_mapping("1=").unwrap_err().contains("missing name"));
+
+        let device = AudioDevice {
+            name: "USB Mic".to_string(),
+            channels: 4,
+            default_sample_rate: 48000,
+            index: 0,
+        };
+        let map = |pairs: &[(u32, &str)]| {
+            let pairs: Vec<(u32, String)> =
+                pairs.iter().map(|&(c, n)| (c, n.to_string())).collect();
+            mics_from_mappings(&device, &pairs)
+        };
+
+        let mics = map(&[(0, "Vocals"), (2, "Guitar")]).unwrap();
+        assert_eq!((mics[1].name.as_str(), mics[1].channel), ("Guitar", 2));
+
+        let err = |pairs| map(pairs).unwrap_err().to_string();
+        assert!(err(&[(4, "Vocals")]).contains("channels 0-3"));
+        assert!(err(&[(0, "Vocals"), (0, "Guitar")]).contains("already mapped"));
+        assert!(err(&[(0, "Vocals"), (1, "Vocals")]).contains("used twice"));
+    }
+
+    #[test]
+    fn test_mics_for_all_channels() {
+        let mut device = AudioDevice {
+            name: "USB Mic".to_string(),
+            channels: 2,
+            default_sample_rate: 48000,
+            index: 0,
+        };
+        let mics = mics_for_all_channels(&device, "{device} Ch{channel}");
+        let mics: Vec<(&str, u32)> = mics.iter().map(|m| (m.name.as_str(), m.channel)).collect();
+        assert_eq!(mics, [("USB Mic Ch0", 0), ("USB Mic Ch1", 1)]);
+
+        // A mono device is passed through as a single mic
+        device.channels = 1;
+        let mics = mics_for_all_channels(&device, "{device}");
+        assert_eq!(mics.len(), 1);
+        assert_eq!((mics[0].name.as_str(), mics[0].channel), ("USB Mic", 0));
+    }
+
+    #[test]
+    fn test_resume_on_another_device() {
+        let mut config = Config::default();
+        config.device.name = Some("USB Mic".to_string());
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.add_virtual_mic("Guest".to_string(), 3);
+
+        let device = |channels| AudioDevice {
+            name: "Interface".to_string(),
+            channels,
+            default_sample_rate: 44100,
+            index: 2,
+        };
+
+        // Channel 3 doesn't exist on a stereo device: refuse, config untouched
+        let err = use_device(&mut config, &device(2)).unwrap_err();
+        assert!(err.to_string().contains("\"Guest\" uses channel 3"));
+        assert_eq!(config.device.name.as_deref(), Some("USB Mic"));
+
+        use_device(&mut config, &device(4)).unwrap();
+        assert_eq!(config.device.name.as_deref(), Some("Interface"));
+        assert_eq!(config.device.index, Some(2));
+        assert_eq!(config.virtual_mics.len(), 2);
+    }
+
+    #[test]
+    fn test_edit_mic_renames_and_moves_one_mic() {
+        let mut config = Config::default();
+        config.device.name = Some("USB Mic".to_string());
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.add_virtual_mic("Guest".to_string(), 1);
+        let mut app = App::new(Vec::new(), config);
+        app.device_channels = 2;
+        app.start_with_existing_config();
+
+        app.handle_key(KeyAction::Down);
+        app.handle_key(KeyAction::Char('e'));
+        assert_eq!(app.state, AppState::EditMic);
+        assert_eq!(app.name_input, "Guest");
+
+        // Taken name: rejected, still editing
+        app.name_input = "Host".to_string();
+        assert!(app.handle_key(KeyAction::Select).is_none());
+        assert_eq!(app.state, AppState::EditMic);
+
+        app.name_input = "Co-host".to_string();
+        app.handle_key(KeyAction::Down);
+        app.handle_key(KeyAction::Right); // already on the last channel
+        app.handle_key(KeyAction::Left);
+        let Some(AppAction::UpdateMic { old, new }) = app.handle_key(KeyAction::Select) else {
+            panic!("expected a driver update");
+        };
+        assert_eq!((old.name.as_str(), old.channel), ("Guest", 1));
+        assert_eq!((new.name.as_str(), new.channel), ("Co-host", 0));
+        assert_eq!(app.state, AppState::Running);
+        assert_eq!(app.config.virtual_mics[1].name, "Co-host");
+        assert_eq!(app.dashboard_labels[1], "Co-host [Ch 0]");
+        assert_eq!(app.dashboard_channels[1], 0);
+        assert!(app.config_saver.is_dirty());
+
+        // Unchanged edit: nothing to send
+        app.handle_key(KeyAction::Char('e'));
+        assert!(app.handle_key(KeyAction::Select).is_none());
+    }
+
+    #[test]
+    fn test_buffer_latency_from_read_pos() {
+        let mut config = Config::default();
+        config.device.sample_rate = 48000;
+        let mut app = App::new(Vec::new(), config);
+
+        app.update_buffer(10_000, 9_040, RING_BUFFER_FRAMES);
+        assert_eq!(app.buffer_latency_ms, Some(20.0));
+        assert!((app.buffer_usage - 960.0 / 8192.0).abs() < 1e-6);
+
+        // Driver never published readPos: old estimate, no latency
+        app.update_buffer(10_000, 0, RING_BUFFER_FRAMES);
+        assert_eq!(app.buffer_latency_ms, None);
+        assert!((app.buffer_usage - (10_000 % 8192) as f32 / 8192.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_setup_keeps_capture_running() {
+        let mut app = test_app(2);
+        app.config.add_virtual_mic("Host".to_string(), 0);
+        app.start_with_existing_config();
+
+        // No StopPreview/teardown: the session keeps feeding meters through setup
+        assert!(app.handle_key(KeyAction::Setup).is_none());
+        assert_eq!(app.state, AppState::SelectDevice);
+        assert!(matches!(
+            app.handle_key(KeyAction::Select),
+            Some(AppAction::StartPreview)
+        ));
+        assert_eq!(app.state, AppState::SelectChannels);
+        app.update_levels(&[0.5, 0.0]);
+        assert_eq!(app.channel_levels[0], 0.5);
+    }
+
+    #[test]
+    fn test_preview_decay_ignores_message_rate() {
+        // Same peak, then a second of silence: 1 or 20 messages per tick,
+        // and 50 or 100 ms ticks, all end up at the same level
+        let run = |messages: usize, tick_ms: u64| {
+            let mut app = test_app(1);
+            assert_eq!(app.state, AppState::SelectChannels);
+            let start = Instant::now();
+            app.decay_preview(start);
+            app.update_levels(&[0.8]);
+            for tick in 1..=1000 / tick_ms {
+                for _ in 0..messages {
+                    app.update_levels(&[0.0]);
+                }
+                app.decay_preview(start + Duration::from_millis(tick * tick_ms));
+            }
+            app.channel_levels[0]
+        };
+
+        let expected = 0.8 * PREVIEW_DECAY.powi(20);
+        for level in [run(1, 50), run(20, 50), run(1, 100), run(20, 100)] {
+            assert!((level - expected).abs() < 1e-4, "{} vs {}", level, expected);
+        }
+    }
 
-    // Options
-    let options = [
-        ("Start with current settings", 0),
-        ("Configure new device", 1),
-    ];
+    #[test]
+    fn test_stream_faults() {
+        let mut config = Config::default();
+        config.add_virtual_mic("Host".to_string(), 0);
+        let mut app = App::new(Vec::new(), config);
+        app.start_with_existing_config();
 
-    for (label, idx) in options {
-        let prefix = if app.action_cursor == idx {
-            "→ ●"
-        } else {
-            "  ○"
-        };
-        let style = if app.action_cursor == idx {
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD)
-        } else {
-            Style::default().fg(Color::White)
-        };
-        lines.push(Line::styled(format!("  {} {}", prefix, label), style));
+        // Xruns are counted and shown, capture carries on
+        app.record_stream_faults(2, false, Some("input overflow".to_string()));
+        assert_eq!(app.xruns, 2);
+        assert_eq!(app.state, AppState::Running);
+        assert_eq!(app.active_notice(), Some("Audio glitch: input overflow"));
+
+        // A lost device ends up on the error screen, where r retries
+        app.record_stream_faults(2, true, Some("unplugged".to_string()));
+        assert_eq!(
+            app.state,
+            AppState::Error("Audio device lost: unplugged".to_string())
+        );
+        assert!(matches!(
+            app.handle_key(KeyAction::Restart),
+            Some(AppAction::Retry)
+        ));
     }
 
-    frame.render_widget(Paragraph::new(lines), inner);
+    #[test]
+    fn test_simple_meter_line() {
+        let line = simple_meter_line(&[(0, 0.4), (2, 0.0)], -60.0, 1.0, false);
+        assert_eq!(line, "Ch0 |#######-|   -8dB  Ch2 |--------|  -60dB");
+        // Same width at any level, so `\r` overwrites the previous line
+        let loud = simple_meter_line(&[(0, 1.0), (2, 0.1)], -60.0, 1.0, false);
+        assert_eq!(loud, "Ch0 |########|    0dB  Ch2 |#####---|  -20dB");
+        assert_eq!(simple_meter_line(&[], -60.0, 1.0, false), "");
+    }
 
-    // Help
-    let help = HelpBar::new(&[("↑/↓", "Select"), ("Enter", "Confirm"), ("q", "Quit")]);
-    frame.render_widget(help, chunks[2]);
-}
+    #[test]
+    fn test_shm_overruns_flag_stats_line() {
+        let mut app = test_app(2);
+        let start = Instant::now();
 
-fn draw_select_device(frame: &mut Frame, app: &App) {
-    let area = frame.area();
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(5),
-            Constraint::Length(1),
-        ])
-        .split(area);
+        // Nobody reading: discarded, but not an overrun
+        app.record_shm_overruns(0, true, start);
+        assert!(app.buffer_unread);
+        assert!(!app.overrun_flagged(start));
+        assert_eq!(app.active_notice(), None);
 
-    let title = Block::default()
-        .title(" duomic - Select Device ")
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
-    frame.render_widget(title, chunks[0]);
+        // The driver came back a buffer behind: flagged for a while
+        app.record_shm_overruns(1, false, start);
+        assert!(app.overrun_flagged(start + Duration::from_secs(1)));
+        assert!(!app.overrun_flagged(start + OVERRUN_FLAG));
+        assert!(app.active_notice().unwrap().starts_with("Buffer overrun"));
+        assert_eq!(app.metrics(None, &[], true).shm_overruns, 1);
+    }
 
-    let content = Block::default()
-        .title(" Input Devices ")
-        .borders(Borders::ALL);
-    let inner = content.inner(chunks[1]);
-    frame.render_widget(content, chunks[1]);
+    #[test]
+    fn test_stale_socket_can_be_deleted_from_error_screen() {
+        let mut app = test_app(2);
+        app.state = AppState::Error("Failed to start: driver".to_string());
+        app.driver_socket = SocketState::Present;
+        assert!(app.handle_key(KeyAction::Char('d')).is_none());
 
-    let device_list = DeviceList::new(&app.devices, app.selected_device_idx);
-    frame.render_widget(device_list, inner);
+        app.driver_socket = SocketState::Stale;
+        assert!(matches!(
+            app.handle_key(KeyAction::Char('d')),
+            Some(AppAction::RemoveStaleSocket)
+        ));
+    }
 
-    let help = HelpBar::new(&[("↑/↓", "Select"), ("Enter", "Confirm"), ("q", "Quit")]);
-    frame.render_widget(help, chunks[2]);
-}
+    #[test]
+    fn test_recovery_steps_follow_driver_state() {
+        let helper = Some("~/bin/restart-audio");
+        let steps = |socket, ping, helper| recovery_steps(socket, ping, helper).join("\n");
 
-fn draw_select_channels(frame: &mut Frame, app: &App) {
-    let area = frame.area();
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(5),
-            Constraint::Length(3),
-            Constraint::Length(1),
-        ])
-        .split(area);
+        assert!(steps(SocketState::Missing, None, None).contains("Install the driver"));
+        let stale = steps(SocketState::Stale, None, None);
+        assert!(stale.contains("Press d") && stale.contains(RESTART_DRIVER_COMMAND));
+        assert!(steps(SocketState::Present, Some(false), None).contains("unresponsive"));
+        assert!(steps(SocketState::Present, None, helper).contains("recovery helper"));
 
-    let device_name = app
-        .current_device
-        .as_ref()
-        .map(|d| d.name.as_str())
-        .unwrap_or("?");
+        // A responding driver needs no restart, so the helper isn't offered
+        let alive = steps(SocketState::Present, Some(true), helper);
+        assert!(!alive.contains(RESTART_DRIVER_COMMAND) && !alive.contains("helper"));
 
-    let title = Block::default()
-        .title(format!(" {} - Channel Selection ", device_name))
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
-    frame.render_widget(title, chunks[0]);
+        let mut app = test_app(2);
+        app.state = AppState::Error("Failed to start: driver".to_string());
+        assert!(matches!(
+            app.handle_key(KeyAction::Char('c')),
+            Some(AppAction::RecoverDriver)
+        ));
+    }
 
-    // Channel list with multi-select
-    let content = Block::default()
-        .title(" Select Channels (Space to toggle) ")
-        .borders(Borders::ALL);
-    let inner = content.inner(chunks[1]);
-    frame.render_widget(content, chunks[1]);
+    #[test]
+    fn test_recovery_runs_on_worker_outcomes() {
+        let down = || Err(anyhow::anyhow!("Driver not running"));
+        let mut app = test_app(2);
+        app.config.behavior.recovery_helper = Some("restart-audio".to_string());
 
-    let channel_names = [
-        "Left",
-        "Right",
-        "Center",
-        "LFE",
-        "Rear Left",
-        "Rear Right",
-        "Side Left",
-        "Side Right",
-    ];
+        // Other commands' outcomes aren't part of it
+        app.recovery = Some(Recovery::Probe);
+        assert_eq!(
+            app.recovery_outcome(&DriverOp::Remove("Host".into()), &Ok(())),
+            (false, None)
+        );
 
-    for (i, &selected) in app.channel_selected.iter().enumerate() {
-        if i as u16 >= inner.height {
-            break;
-        }
+        // Down: run the helper, then ping again
+        assert_eq!(
+            app.recovery_outcome(&DriverOp::Ping, &down()),
+            (true, Some(DriverOp::RunHelper("restart-audio".into())))
+        );
+        assert_eq!(
+            app.recovery_outcome(&DriverOp::RunHelper("restart-audio".into()), &Ok(())),
+            (true, Some(DriverOp::Ping))
+        );
+        assert_eq!(app.recovery_outcome(&DriverOp::Ping, &Ok(())), (true, None));
+        assert!(app.active_notice().unwrap().contains("helper done"));
+        assert_eq!(app.recovery, None);
 
-        let is_cursor = i == app.channel_cursor;
-        let checkbox = if selected { "[✓]" } else { "[ ]" };
-        let arrow = if is_cursor { "→" } else { " " };
-        let ch_name = channel_names.get(i).unwrap_or(&"Channel");
-        let level = app.channel_levels.get(i).copied().unwrap_or(0.0);
+        // Without a helper only the notice says what to run
+        app.config.behavior.recovery_helper = None;
+        app.recovery = Some(Recovery::Probe);
+        assert_eq!(app.recovery_outcome(&DriverOp::Ping, &down()), (true, None));
+        assert!(app
+            .active_notice()
+            .unwrap()
+            .contains(RESTART_DRIVER_COMMAND));
+    }
 
-        // Build line
-        let label = format!("{} {} Channel {} ({})", arrow, checkbox, i, ch_name);
-        let style = if is_cursor {
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD)
-        } else if selected {
-            Style::default().fg(Color::Green)
-        } else {
-            Style::default().fg(Color::White)
+    #[test]
+    fn test_missing_device_returns_to_device_list() {
+        let mut app = test_app(2);
+        app.set_start_error(
+            "Failed to start",
+            DuomicError::DeviceNotFound("Old Mic".to_string()),
+        );
+        assert_eq!(app.state, AppState::SelectDevice);
+        assert!(app.active_notice().unwrap().contains("Old Mic"));
+
+        // Anything else is still an error
+        app.set_start_error(
+            "Failed to start",
+            DuomicError::Unsupported(cpal::SampleFormat::I8),
+        );
+        assert!(matches!(app.state, AppState::Error(_)));
+    }
+
+    #[test]
+    fn test_default_input_changed() {
+        let device = |name: &str, channels, index| AudioDevice {
+            name: name.to_string(),
+            channels,
+            default_sample_rate: 48000,
+            index,
         };
+        let mut config = Config::default();
+        config.device.name = Some("USB Mic".to_string());
+        config.add_virtual_mic("Host".to_string(), 0);
+        let mut app = App::new(
+            vec![
+                device("USB Mic", 2, 0),
+                device("MacBook Pro Microphone", 1, 1),
+            ],
+            config,
+        );
+        app.start_with_existing_config();
 
-        let y = inner.y + i as u16;
-        frame.buffer_mut().set_string(inner.x, y, &label, style);
+        // Picked explicitly, not as the default: left alone
+        assert!(!app.default_input_changed("MacBook Pro Microphone"));
+        assert_eq!(app.active_notice(), None);
 
-        // Level meter
-        let meter_x = inner.x + 28;
-        let meter_width = inner.width.saturating_sub(36).min(20);
-        if meter_width > 5 {
-            let fill = (level * meter_width as f32) as u16;
-            for j in 0..meter_width {
-                let color = if j < meter_width * 3 / 4 {
-                    Color::Green
-                } else if j < meter_width * 7 / 8 {
-                    Color::Yellow
-                } else {
-                    Color::Red
-                };
+        // Set up for the default without follow_default: only a notice
+        app.follows_default = true;
+        assert!(!app.default_input_changed("MacBook Pro Microphone"));
+        assert!(app
+            .active_notice()
+            .unwrap()
+            .contains("still capturing USB Mic"));
+        // Not in the device list (e.g. a duomic virtual mic made the default)
+        assert!(!app.default_input_changed("duomic Host"));
 
-                let (symbol, style) = if j < fill {
-                    ("█", Style::default().fg(color))
-                } else {
-                    ("░", Style::default().fg(Color::DarkGray))
-                };
-                frame.buffer_mut().set_string(meter_x + j, y, symbol, style);
-            }
-        }
+        app.config.behavior.follow_default = true;
+        assert!(app.default_input_changed("MacBook Pro Microphone"));
+        let capture = app.capture_config();
+        assert_eq!(
+            capture.device.name.as_deref(),
+            Some("MacBook Pro Microphone")
+        );
+        assert_eq!(capture.device.index, Some(1));
+        // The saved device isn't touched, even by the new device's rate
+        app.set_live_sample_rate(44100);
+        assert_eq!(app.config.device.name.as_deref(), Some("USB Mic"));
+        assert!(!app.config_saver.is_dirty());
+
+        // Back on the saved device: nothing left to override
+        assert!(app.default_input_changed("USB Mic"));
+        assert!(app.capture_device.is_none());
     }
 
-    // Selection count
-    let count_block = Block::default().borders(Borders::ALL);
-    let count_inner = count_block.inner(chunks[2]);
-    frame.render_widget(count_block, chunks[2]);
+    #[test]
+    fn test_kiosk_mode() {
+        let mut config = Config::default();
+        config.device.name = Some("USB Mic".to_string());
+        config.add_virtual_mic("Host".to_string(), 0);
+        let mut app = App::new(Vec::new(), config);
+        app.kiosk = true;
+        app.start_with_existing_config();
 
-    let count_text = format!("Selected: {} channels", app.selected_count());
-    let count_style = if app.selected_count() > 0 {
-        Style::default().fg(Color::Green)
-    } else {
-        Style::default().fg(Color::Yellow)
-    };
-    frame.render_widget(
-        Paragraph::new(count_text).style(count_style).centered(),
-        count_inner,
-    );
+        // No setup, and q always asks
+        assert!(app.handle_key(KeyAction::Setup).is_none());
+        assert_eq!(app.state, AppState::Running);
+        app.handle_key(KeyAction::Quit);
+        assert!(app.confirm_quit);
+        app.handle_key(KeyAction::Cancel);
 
-    let help = HelpBar::new(&[
-        ("↑/↓", "Navigate"),
-        ("Space", "Toggle"),
-        ("Enter", "Confirm"),
-        ("Esc", "Back"),
-    ]);
-    frame.render_widget(help, chunks[3]);
-}
+        // The error screen can't be left with q and retries with backoff
+        let start = Instant::now();
+        app.set_start_error(
+            "Failed to start",
+            DuomicError::DeviceNotFound("USB Mic".to_string()),
+        );
+        app.handle_key(KeyAction::Quit);
+        assert!(matches!(app.state, AppState::Error(_)));
+        assert!(!app.kiosk_retry_due(start));
+        assert!(!app.kiosk_retry_due(start + Duration::from_millis(500)));
+        assert!(app.kiosk_retry_due(start + Duration::from_secs(1)));
+        // Still failing: the next wait is twice as long
+        let later = start + Duration::from_secs(1);
+        assert!(!app.kiosk_retry_due(later));
+        assert!(!app.kiosk_retry_due(later + Duration::from_millis(1500)));
+        assert!(app.kiosk_retry_due(later + Duration::from_secs(2)));
 
-fn draw_enter_names(frame: &mut Frame, app: &App) {
-    let area = frame.area();
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(5),
-            Constraint::Length(1),
-        ])
-        .split(area);
+        assert_eq!(kiosk_backoff(0), KIOSK_RETRY_MIN);
+        assert_eq!(kiosk_backoff(3), Duration::from_secs(8));
+        assert_eq!(kiosk_backoff(40), KIOSK_RETRY_MAX);
 
-    let selected_channels = app.selected_channels();
-    let current_channel = selected_channels.get(app.name_cursor).copied().unwrap_or(0);
+        // Back on the dashboard the backoff starts over
+        app.start_with_existing_config();
+        assert!(!app.kiosk_retry_due(later));
+        assert_eq!(app.kiosk_retries, 0);
+    }
 
-    let title = Block::default()
-        .title(format!(" Name for Channel {} (optional) ", current_channel))
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Cyan));
-    frame.render_widget(title, chunks[0]);
+    #[test]
+    fn test_clock_overlay() {
+        let mut config = Config::default();
+        config.device.name = Some("USB Mic".to_string());
+        config.add_virtual_mic("Host".to_string(), 0);
+        let mut app = App::new(Vec::new(), config);
+        app.start_with_existing_config();
+        app.handle_key(KeyAction::Char('d'));
+        assert!(app.clock_overlay);
+        app.handle_key(KeyAction::Char('d'));
+        assert!(!app.clock_overlay);
 
-    let content = Block::default()
-        .title(format!(
-            " {}/{} ",
-            app.name_cursor + 1,
-            app.channel_names.len()
-        ))
-        .borders(Borders::ALL);
-    let inner = content.inner(chunks[1]);
-    frame.render_widget(content, chunks[1]);
+        // 90.5 s at 48 kHz, with the wall clock 2.5 ms ahead; beyond u32 frames too
+        assert_eq!(
+            clock_lines(4_344_000, 48000, Some(0.0025)),
+            [
+                "Frames:  4344000",
+                "Audio:   00:01:30.500 @ 48000 Hz",
+                "Skew:    +2.5 ms (wall - sample)",
+            ]
+        );
+        assert_eq!(
+            clock_lines(5_000_000_000, 48000, None)[1],
+            "Audio:   28:56:06.666 @ 48000 Hz"
+        );
+        assert_eq!(
+            clock_lines(0, 48000, None)[2],
+            "Skew:    -- (wall - sample)"
+        );
+    }
 
-    let default_name = app.generate_default_name(app.name_cursor);
+    #[test]
+    fn test_confirm_quit() {
+        let mut config = Config::default();
+        config.device.name = Some("USB Mic".to_string());
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.behavior.confirm_quit = true;
+        let mut app = App::new(Vec::new(), config);
+        app.start_with_existing_config();
 
-    let lines = vec![
-        Line::from(""),
-        Line::from(format!("  > {}█", app.name_input)).style(Style::default().fg(Color::White)),
-        Line::from(""),
-        Line::from(format!("  Leave empty for: \"{}\"", default_name))
-            .style(Style::default().fg(Color::DarkGray)),
-    ];
+        // First q asks, Esc goes back to the dashboard
+        app.handle_key(KeyAction::Quit);
+        assert!(app.confirm_quit);
+        assert_eq!(app.state, AppState::Running);
+        app.handle_key(KeyAction::Up);
+        assert!(app.confirm_quit);
+        app.handle_key(KeyAction::Cancel);
+        assert!(!app.confirm_quit);
+        assert_eq!(app.state, AppState::Running);
 
-    frame.render_widget(Paragraph::new(lines), inner);
+        // Second q confirms
+        app.handle_key(KeyAction::Quit);
+        app.handle_key(KeyAction::Quit);
+        assert_eq!(app.state, AppState::Quit);
 
-    let help = HelpBar::new(&[("Enter", "Confirm"), ("Esc", "Back")]);
-    frame.render_widget(help, chunks[2]);
-}
+        // Off by default
+        let mut app = App::new(Vec::new(), Config::default());
+        app.start_with_existing_config();
+        app.handle_key(KeyAction::Quit);
+        assert_eq!(app.state, AppState::Quit);
+    }
 
-fn draw_running(frame: &mut Frame, app: &App) {
-    let area = frame.area();
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(5),
-            Constraint::Length(3),
-            Constraint::Length(1),
-        ])
-        .split(area);
+    #[test]
+    fn test_live_sample_rate_replaces_default() {
+        // Setup saved the device default, the stream negotiated 44.1 kHz
+        let mut config = Config::default();
+        config.device.sample_rate = 48000;
+        let mut app = App::new(Vec::new(), config);
+        assert_eq!(app.sample_rate(), 48000);
 
-    let device_name = app.config.device.name.as_deref().unwrap_or("?");
-    let sample_rate = app.config.device.sample_rate / 1000;
+        app.set_live_sample_rate(44100);
+        assert_eq!(app.sample_rate(), 44100);
+        assert_eq!(app.config.device.sample_rate, 44100);
+        assert!(app.config_saver.is_dirty());
 
-    let header = Block::default()
-        .title(format!(
-            " duomic | {} @ {}kHz | ● Running ",
-            device_name, sample_rate
-        ))
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Green));
-    frame.render_widget(header, chunks[0]);
+        // Latency follows the live rate
+        app.update_buffer(10_000, 9_118, RING_BUFFER_FRAMES);
+        assert_eq!(app.buffer_latency_ms, Some(20.0));
 
-    // Level meters
-    let meters = Block::default()
-        .title(" Virtual Microphones ")
-        .borders(Borders::ALL);
-    let meters_inner = meters.inner(chunks[1]);
-    frame.render_widget(meters, chunks[1]);
+        // Matching rates (or a backend reporting none) change nothing
+        app.config_saver = ConfigSaver::default();
+        app.set_live_sample_rate(44100);
+        app.set_live_sample_rate(0);
+        assert_eq!(app.sample_rate(), 44100);
+        assert!(!app.config_saver.is_dirty());
+    }
 
-    for (i, (level, label)) in app
-        .dashboard_levels
-        .iter()
-        .zip(app.dashboard_labels.iter())
-        .enumerate()
-    {
-        if i as u16 >= meters_inner.height {
-            break;
-        }
+    #[test]
+    fn test_names_file_fills_the_names_step_once() {
+        let mut app = App::new(Vec::new(), Config::default());
+        app.imported_names = Some(ImportedNames::parse("Host\nGuest\nMusic\n").unwrap());
+        app.channel_selected = vec![true, false, true, false];
 
-        let row = Rect {
-            x: meters_inner.x,
-            y: meters_inner.y + i as u16,
-            width: meters_inner.width,
-            height: 1,
-        };
+        app.enter_names();
+        assert_eq!(app.channel_names, ["Host", "Guest"]);
+        assert_eq!(app.name_input, "Host");
+        assert!(app
+            .active_notice()
+            .is_some_and(|n| n.contains("3 names for 2 selected channels")));
 
-        let meter = LevelMeter::new(*level).label(label);
-        frame.render_widget(meter, row);
+        // Typed names win on the next pass; the file isn't applied again
+        app.name_input = "Anchor".to_string();
+        app.handle_key(KeyAction::Select);
+        app.enter_names();
+        assert_eq!(app.channel_names, ["Anchor", "Guest"]);
     }
 
-    // Stats
-    let uptime = app.uptime();
-    let hours = uptime.as_secs() / 3600;
-    let minutes = (uptime.as_secs() % 3600) / 60;
-    let seconds = uptime.as_secs() % 60;
+    #[test]
+    fn test_channel_count_change_offers_remap() {
+        let mut config = Config::default();
+        config.device.name = Some("Aggregate".to_string());
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.add_virtual_mic("Guest".to_string(), 2);
+        let mics = config.virtual_mics.clone();
+        config.remember_channels("Aggregate", 3, &mics);
+        let device = AudioDevice {
+            name: "Aggregate".to_string(),
+            channels: 4,
+            default_sample_rate: 48000,
+            index: 0,
+        };
 
-    let stats = Block::default()
-        .title(format!(
-            " Latency: 21ms | Buffer: {:.0}% | Duration: {:02}:{:02}:{:02} ",
-            app.buffer_usage * 100.0,
-            hours,
-            minutes,
-            seconds
-        ))
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::DarkGray));
-    frame.render_widget(stats, chunks[2]);
+        let mut app = App::new(Vec::new(), config.clone());
+        app.start_with_existing_config();
+        app.check_channel_layout(&device);
+        assert_eq!(app.layout_change.as_ref().map(|c| c.was), Some(3));
 
-    let help = HelpBar::new(&[("q", "Quit"), ("r", "Restart"), ("s", "Setup")]);
-    frame.render_widget(help, chunks[3]);
-}
+        // y: channel selection on the grown device, names kept
+        assert!(matches!(
+            app.handle_key(KeyAction::Yes),
+            Some(AppAction::StartPreview)
+        ));
+        assert_eq!(app.state, AppState::SelectChannels);
+        assert_eq!(app.channel_selected, [true, false, true, false]);
+        assert_eq!(app.named_channels, [0, 2]);
+        assert_eq!(app.channel_names, ["Host", "Guest"]);
 
-fn draw_error(frame: &mut Frame, message: &str) {
-    let area = frame.area();
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(5),
-            Constraint::Length(1),
-        ])
-        .split(area);
+        // n: the new layout is remembered and not asked about again
+        let mut app = App::new(Vec::new(), config);
+        app.start_with_existing_config();
+        app.check_channel_layout(&device);
+        app.handle_key(KeyAction::No);
+        assert!(app.layout_change.is_none());
+        assert_eq!(app.state, AppState::Running);
+        app.check_channel_layout(&device);
+        assert!(app.layout_change.is_none());
+    }
 
-    let title = Block::default()
-        .title(" ⚠ Error ")
-        .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Red));
-    frame.render_widget(title, chunks[0]);
+    #[test]
+    fn test_arrows_move_the_gain_slider_only_when_shown() {
+        let mut config = Config::default();
+        config.device.name = Some("USB Mic".to_string());
+        config.add_virtual_mic("Host".to_string(), 0);
+        let mut app = App::new(Vec::new(), config);
+        app.start_with_existing_config();
 
-    let content = Block::default().borders(Borders::ALL);
-    let inner = content.inner(chunks[1]);
-    frame.render_widget(content, chunks[1]);
+        assert!(app.handle_key(KeyAction::Right).is_none());
+        assert_eq!(app.config.virtual_mics[0].gain_db, 0.0);
 
-    let lines = vec![
-        Line::from(message).style(Style::default().fg(Color::Red)),
-        Line::from(""),
-        Line::from("Suggestions:"),
-        Line::from("  1. Make sure the device is connected"),
-        Line::from("  2. Restart the driver: sudo killall coreaudiod"),
-    ];
-    frame.render_widget(Paragraph::new(lines), inner);
+        app.config.ui.gain_slider = true;
+        assert!(matches!(
+            app.handle_key(KeyAction::Right),
+            Some(AppAction::ApplyMix(0))
+        ));
+        app.handle_key(KeyAction::Right);
+        app.handle_key(KeyAction::Left);
+        assert_eq!(app.config.virtual_mics[0].gain_db, 1.0);
+    }
 
-    let help = HelpBar::new(&[("r", "Retry"), ("q", "Quit")]);
-    frame.render_widget(help, chunks[2]);
+    #[test]
+    fn test_mix_keys_update_config_for_the_cursor_channel() {
+        let mut config = Config::default();
+        config.device.name = Some("USB Mic".to_string());
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.add_virtual_mic("Guest".to_string(), 1);
+        let mut app = App::new(Vec::new(), config);
+        app.start_with_existing_config();
+
+        app.handle_key(KeyAction::Down);
+        assert!(matches!(
+            app.handle_key(KeyAction::Char('+')),
+            Some(AppAction::ApplyMix(1))
+        ));
+        app.handle_key(KeyAction::Char('+'));
+        app.handle_key(KeyAction::Char('m'));
+        app.handle_key(KeyAction::Char('i'));
+        app.handle_key(KeyAction::Char('f'));
+
+        let guest = &app.config.virtual_mics[1];
+        assert_eq!(guest.gain_db, 2.0);
+        assert!(guest.muted && guest.invert);
+        assert_eq!(guest.highpass_hz, Some(DEFAULT_HIGHPASS_HZ));
+        assert_eq!(app.config.virtual_mics[0].highpass_hz, None);
+        assert_eq!(app.config.virtual_mics[0].gain_db, 0.0);
+        assert!(app.config_saver.is_dirty());
+
+        // Re-running setup on the same device keeps the mix
+        app.current_device = Some(AudioDevice {
+            name: "USB Mic".to_string(),
+            channels: 2,
+            default_sample_rate: 48000,
+            index: 0,
+        });
+        app.channel_selected = vec![true, true];
+        app.enter_names();
+        let mics = app.build_virtual_mics();
+        assert_eq!(mics[1].gain_db, 2.0);
+        assert!(mics[1].muted);
+    }
 }
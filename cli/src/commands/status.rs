@@ -1,12 +1,289 @@
 use anyhow::Result;
-use std::io::Write;
+use crossbeam_channel::{bounded, Receiver};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::config::Config;
-use crate::ipc::DriverClient;
+use super::{paint, tag_sgr};
+use duomic::config::{Config, ConfigSource};
+use duomic::ipc::{
+    reconcile, runtime_paths, DeviceInfo, DriverClient, MicSync, SocketState, StatsSnapshot,
+    SyncState, RESTART_DRIVER_COMMAND,
+};
+use duomic::{expected_mics, DuomicError};
 
-pub fn execute() -> Result<()> {
-    let config = Config::load().unwrap_or_default();
+/// Driver connection state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DriverState {
+    Connected,
+    NotResponding,
+    /// Accepted the connection but never answered (hung rather than crashed)
+    TimedOut,
+    ConnectFailed,
+    /// Socket file left behind with nothing listening (e.g. coreaudiod crashed)
+    StaleSocket,
+    NotRunning,
+}
+
+impl DriverState {
+    /// Human-readable state, e.g. "Not running"
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            DriverState::Connected => "Connected",
+            DriverState::NotResponding => "Socket exists but not responding",
+            DriverState::TimedOut => "Connected but timed out (driver may be hung)",
+            DriverState::ConnectFailed => "Failed to connect",
+            DriverState::StaleSocket => "Not running (stale socket left behind)",
+            DriverState::NotRunning => "Not running",
+        }
+    }
+}
+
+/// What the driver said, independent of any config
+#[derive(Debug, Clone)]
+pub(crate) struct DriverQuery {
+    state: DriverState,
+    /// Live device list (only asked for when connected)
+    devices: Option<std::result::Result<Vec<DeviceInfo>, String>>,
+}
+
+/// Everything `status` reports, gathered once for text or JSON output
+#[derive(Debug, Serialize)]
+pub(crate) struct StatusReport {
+    pub(crate) driver: DriverState,
+    pub(crate) config_path: Option<String>,
+    /// Which setting chose `config_path`: flag, env, xdg or default
+    pub(crate) config_source: Option<ConfigSource>,
+    pub(crate) config_exists: bool,
+    pub(crate) device: Option<String>,
+    pub(crate) sample_rate: u32,
+    /// Config vs driver reconciliation (config-only rows when the driver is down)
+    pub(crate) virtual_mics: Vec<MicSync>,
+    /// Error from listing driver devices, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) driver_error: Option<String>,
+    capture: Option<StatsSnapshot>,
+    #[serde(skip)]
+    has_configured_mics: bool,
+}
+
+pub fn execute(json: bool, config_location: &(PathBuf, ConfigSource)) -> Result<()> {
+    let report = gather(config_location);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    print_report(&report, true);
+    Ok(())
+}
+
+/// Socket timeout for `--oneline`, which runs on every prompt render
+const ONELINE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Print `duomic: ●3 mics @48k` (driver up), `duomic: ○ driver not responding`
+/// or `duomic: ○ driver down`, for shell prompts and status bars
+///
+/// Only asks the driver for its device list, with a short timeout.
+pub fn oneline(config_path: &Path) -> Result<()> {
+    let sample_rate = Config::load_from(config_path)
+        .unwrap_or_default()
+        .device
+        .sample_rate;
+    let mics = match DriverClient::socket_state() {
+        SocketState::Present => Some(
+            DriverClient::with_timeout(ONELINE_TIMEOUT)
+                .list_devices()
+                .map(|devices| devices.len())
+                .ok(),
+        ),
+        SocketState::Stale | SocketState::Missing => None,
+    };
+    let (code, text) = oneline_text(mics, sample_rate);
+    println!("duomic: {}", paint(code, text));
+    Ok(())
+}
+
+/// `--oneline` text after "duomic: " with its color
+///
+/// `mics` is `None` without a driver socket, `Some(None)` if it didn't answer.
+fn oneline_text(mics: Option<Option<usize>>, sample_rate: u32) -> (&'static str, String) {
+    match mics {
+        Some(Some(count)) => {
+            let khz = if sample_rate % 1000 == 0 {
+                format!("{}", sample_rate / 1000)
+            } else {
+                format!("{:.1}", sample_rate as f64 / 1000.0)
+            };
+            let noun = if count == 1 { "mic" } else { "mics" };
+            ("32", format!("●{} {} @{}k", count, noun, khz))
+        }
+        Some(None) => ("33", "○ driver not responding".to_string()),
+        None => ("31", "○ driver down".to_string()),
+    }
+}
+
+/// Re-query and redraw the status every `interval_secs` until Ctrl+C
+pub fn watch(interval_secs: u64, config_location: &(PathBuf, ConfigSource)) -> Result<()> {
+    let interval = Duration::from_secs(interval_secs.max(1));
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_handler = stop.clone();
+    ctrlc::set_handler(move || {
+        stop_handler.store(true, Ordering::SeqCst);
+    })
+    .ok();
+
+    let mut first = true;
+    while !stop.load(Ordering::SeqCst) {
+        let report = gather(config_location);
+
+        // Clear screen and home the cursor, then redraw
+        print!("\x1b[2J\x1b[H");
+        // Setup hints only on the first render, so the block doesn't jump around
+        print_report(&report, first);
+        println!(
+            "{}",
+            paint(
+                "90",
+                format!(
+                    "Refreshing every {}s - press Ctrl+C to stop",
+                    interval.as_secs()
+                )
+            )
+        );
+        first = false;
+
+        let next = Instant::now() + interval;
+        while !stop.load(Ordering::SeqCst) && Instant::now() < next {
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    Ok(())
+}
+
+/// Query the driver every `interval` on a background thread
+///
+/// Each query can block for the socket timeout, so the dashboard never runs
+/// them on the UI thread. The thread exits once the receiver is dropped.
+pub(crate) fn spawn_driver_poller(interval: Duration) -> Result<Receiver<DriverQuery>> {
+    let (sender, receiver) = bounded(1);
+    thread::Builder::new()
+        .name("duomic-status".into())
+        .spawn(move || {
+            while sender.send(query_driver()).is_ok() {
+                thread::sleep(interval);
+            }
+        })?;
+    Ok(receiver)
+}
+
+fn gather(config_location: &(PathBuf, ConfigSource)) -> StatusReport {
+    let config = Config::load_from(&config_location.0).unwrap_or_default();
+    build_report(&config, Some(config_location), query_driver())
+}
+
+pub(crate) fn query_driver() -> DriverQuery {
+    let state = match DriverClient::socket_state() {
+        SocketState::Present => {
+            let mut client = DriverClient::new();
+            if client.connect().is_ok() {
+                match client.ping() {
+                    Ok(true) => DriverState::Connected,
+                    Err(DuomicError::DriverTimeout(_)) => DriverState::TimedOut,
+                    _ => DriverState::NotResponding,
+                }
+            } else {
+                DriverState::ConnectFailed
+            }
+        }
+        SocketState::Stale => DriverState::StaleSocket,
+        SocketState::Missing => DriverState::NotRunning,
+    };
+
+    let devices = (state == DriverState::Connected).then(|| {
+        DriverClient::new()
+            .list_devices()
+            .map_err(|e| e.to_string())
+    });
+
+    DriverQuery { state, devices }
+}
+
+/// Reconcile a driver query against `config`
+pub(crate) fn build_report(
+    config: &Config,
+    config_location: Option<&(PathBuf, ConfigSource)>,
+    query: DriverQuery,
+) -> StatusReport {
+    let expected = expected_mics(config);
+
+    // Reconcile against the live list when the driver is up
+    let mut driver_error = None;
+    let mut virtual_mics = if let Some(devices) = query.devices {
+        match devices {
+            Ok(current) => reconcile(&expected, &current),
+            Err(e) => {
+                driver_error = Some(e);
+                Vec::new()
+            }
+        }
+    } else {
+        expected
+            .into_iter()
+            .map(|m| MicSync {
+                name: m.name,
+                channel: m.channel,
+                state: SyncState::ConfigOnly,
+                color: m.color,
+            })
+            .collect()
+    };
 
+    config
+        .ui
+        .sort_mics
+        .apply(&mut virtual_mics, |m| (&m.name, m.channel));
+
+    let (config_path, config_source) = config_location.cloned().unzip();
+
+    StatusReport {
+        driver: query.state,
+        config_exists: config_path.as_ref().is_some_and(|p| p.exists()),
+        config_path: config_path.map(|p| p.display().to_string()),
+        config_source,
+        device: config.device.name.clone(),
+        sample_rate: config.device.sample_rate,
+        virtual_mics,
+        driver_error,
+        capture: StatsSnapshot::read().ok().flatten(),
+        has_configured_mics: !config.virtual_mics.is_empty(),
+    }
+}
+
+/// The driver's channel, or "?" if it didn't report one
+fn channel_text(channel: u32) -> String {
+    match channel {
+        DeviceInfo::UNKNOWN_CHANNEL => "?".to_string(),
+        channel => channel.to_string(),
+    }
+}
+
+/// "● " in the mic's color tag, or nothing without one
+fn tag_dot(mic: &MicSync) -> String {
+    match mic.color.as_deref().and_then(tag_sgr) {
+        Some(code) if super::color_enabled() => paint(&code, "● "),
+        _ => String::new(),
+    }
+}
+
+fn print_report(report: &StatusReport, show_help: bool) {
     println!();
     println!("╭─────────────────────────────────────────╮");
     println!("│           duomic status                 │");
@@ -15,53 +292,64 @@ pub fn execute() -> Result<()> {
 
     // Check driver status
     print!("Driver status: ");
-    std::io::stdout().flush()?;
-
-    let driver_ok = if DriverClient::is_driver_available() {
-        let mut client = DriverClient::new();
-        if client.connect().is_ok() {
-            match client.ping() {
-                Ok(true) => {
-                    println!("\x1b[32m● Connected\x1b[0m");
-                    true
-                }
-                _ => {
-                    println!("\x1b[33m○ Socket exists but not responding\x1b[0m");
-                    false
-                }
-            }
-        } else {
-            println!("\x1b[31m○ Failed to connect\x1b[0m");
-            false
+    match report.driver {
+        DriverState::Connected => {
+            println!("{}", paint("32", format!("● {}", report.driver.label())))
         }
-    } else {
-        println!("\x1b[31m○ Not running\x1b[0m");
-        println!("         (socket not found at /tmp/duomic.sock)");
-        false
-    };
+        DriverState::NotResponding => {
+            println!("{}", paint("33", format!("○ {}", report.driver.label())))
+        }
+        DriverState::TimedOut => {
+            println!("{}", paint("33", format!("○ {}", report.driver.label())));
+            println!("         (restart it: {})", RESTART_DRIVER_COMMAND);
+        }
+        DriverState::ConnectFailed => {
+            println!("{}", paint("31", format!("○ {}", report.driver.label())))
+        }
+        DriverState::StaleSocket => {
+            println!("{}", paint("31", format!("○ {}", report.driver.label())));
+            println!(
+                "         (nothing listens on {}; restart coreaudiod, or remove the file)",
+                runtime_paths().socket.display()
+            );
+        }
+        DriverState::NotRunning => {
+            println!("{}", paint("31", format!("○ {}", report.driver.label())));
+            println!(
+                "         (socket not found at {})",
+                runtime_paths().socket.display()
+            );
+        }
+    }
+    let driver_ok = report.driver == DriverState::Connected;
 
     println!();
 
     // Show config info
     println!("Configuration:");
-    match Config::path() {
-        Ok(path) => {
-            if path.exists() {
-                println!("  Path: \x1b[36m{}\x1b[0m", path.display());
-            } else {
-                println!("  Path: \x1b[33m{} (not created)\x1b[0m", path.display());
-            }
+    match &report.config_path {
+        Some(path) if report.config_exists => {
+            println!("  Path: {}", paint("36", path));
         }
-        Err(_) => {
-            println!("  Path: \x1b[31mCould not determine\x1b[0m");
+        Some(path) => {
+            println!("  Path: {}", paint("33", format!("{} (not created)", path)));
         }
+        None => {
+            println!(
+                "  Path: {}",
+                paint("31", "Could not determine (set DUOMIC_CONFIG or HOME)")
+            );
+        }
+    }
+    if let Some(source) = report.config_source {
+        println!("  Source: {}", source.label());
     }
 
-    if let Some(ref device) = config.device.name {
+    if let Some(ref device) = report.device {
         println!("  Device: {}", device);
-        println!("  Sample rate: {} Hz", config.device.sample_rate);
+        println!("  Sample rate: {} Hz", report.sample_rate);
     } else {
-        println!("  Device: \x1b[33m(not configured)\x1b[0m");
+        println!("  Device: {}", paint("33", "(not configured)"));
     }
 
     println!();
@@ -69,55 +357,129 @@ pub fn execute() -> Result<()> {
     // Show virtual microphones
     println!("Virtual Microphones:");
 
-    if driver_ok {
-        // Get live list from driver
-        let mut client = DriverClient::new();
-        if client.connect().is_ok() {
-            match client.list_devices() {
-                Ok(devices) if !devices.is_empty() => {
-                    for device in devices {
-                        println!(
-                            "  \x1b[32m●\x1b[0m {} \x1b[90m(channel {})\x1b[0m",
-                            device.name, device.channel
-                        );
-                    }
-                }
-                Ok(_) => {
-                    println!("  \x1b[33m(no active devices)\x1b[0m");
-                }
-                Err(e) => {
-                    println!("  \x1b[31mFailed to query: {}\x1b[0m", e);
-                }
-            }
+    if let Some(ref e) = report.driver_error {
+        println!("  {}", paint("31", format!("Failed to query: {}", e)));
+    } else if driver_ok && !report.virtual_mics.is_empty() {
+        // Config vs driver reconciliation
+        for mic in &report.virtual_mics {
+            let (symbol, color) = match mic.state {
+                SyncState::InSync => ("●", "32"),
+                SyncState::ConfigOnly => ("○", "33"),
+                SyncState::DriverOnly => ("◌", "31"),
+                SyncState::Moved => ("◐", "33"),
+            };
+            println!(
+                "  {} {}{} {} {}",
+                paint(color, symbol),
+                tag_dot(mic),
+                mic.name,
+                paint("90", format!("(channel {})", channel_text(mic.channel))),
+                paint(color, mic.state)
+            );
         }
-    } else if !config.virtual_mics.is_empty() {
+    } else if driver_ok {
+        println!("  {}", paint("33", "(no active devices)"));
+    } else if !report.virtual_mics.is_empty() {
         // Show from config (offline mode)
-        println!("  \x1b[33m(from config, driver not running)\x1b[0m");
-        for mic in &config.virtual_mics {
+        println!("  {}", paint("33", "(from config, driver not running)"));
+        for mic in &report.virtual_mics {
             println!(
-                "  \x1b[90m○\x1b[0m {} \x1b[90m(channel {})\x1b[0m",
-                mic.name, mic.channel
+                "  {} {}{} {}",
+                paint("90", "○"),
+                tag_dot(mic),
+                mic.name,
+                paint("90", format!("(channel {})", mic.channel))
             );
         }
     } else {
-        println!("  \x1b[33m(none configured)\x1b[0m");
+        println!("  {}", paint("33", "(none configured)"));
     }
 
     println!();
 
+    // Capture counters published by a running `duomic run`
+    println!("Capture:");
+    match &report.capture {
+        Some(stats) => {
+            let color = if stats.dropped_peaks
+                + stats.write_errors
+                + stats.xruns
+                + stats.shm_overruns
+                > 0
+            {
+                "33"
+            } else {
+                "32"
+            };
+            println!(
+                "  {}",
+                paint(
+                    color,
+                    format!(
+                        "Dropped peaks: {}  Write errors: {}  Xruns: {}  Overruns: {}",
+                        stats.dropped_peaks, stats.write_errors, stats.xruns, stats.shm_overruns
+                    )
+                )
+            );
+            if let Some(error) = &stats.last_error {
+                println!("  {}", paint("90", format!("Last stream error: {}", error)));
+            }
+            if !stats.skipped_mics.is_empty() {
+                println!(
+                    "  {}",
+                    paint(
+                        "33",
+                        format!(
+                            "Skipped, channel not on the device: {}",
+                            stats.skipped_mics.join(", ")
+                        )
+                    )
+                );
+            }
+        }
+        None => {
+            println!("  {}", paint("90", "(not capturing)"));
+        }
+    }
+
+    println!();
+
+    if !show_help {
+        return;
+    }
+
     // Quick help
     if !driver_ok {
         println!("To start the driver:");
-        println!("  1. Ensure driver is installed: \x1b[36msudo ./install.sh\x1b[0m");
-        println!("  2. Restart CoreAudio: \x1b[36msudo killall coreaudiod\x1b[0m");
+        println!(
+            "  1. Ensure driver is installed: {}",
+            paint("36", "sudo ./install.sh")
+        );
+        println!(
+            "  2. Restart CoreAudio: {}",
+            paint("36", "sudo killall coreaudiod")
+        );
         println!();
     }
 
-    if config.virtual_mics.is_empty() {
+    if !report.has_configured_mics {
         println!("To configure virtual microphones:");
-        println!("  Run: \x1b[36mduomic setup\x1b[0m");
+        println!("  Run: {}", paint("36", "duomic setup"));
         println!();
     }
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oneline_text() {
+        let text = |mics, rate| oneline_text(mics, rate).1;
+        assert_eq!(text(Some(Some(3)), 48000), "●3 mics @48k");
+        assert_eq!(text(Some(Some(1)), 44100), "●1 mic @44.1k");
+        assert_eq!(text(Some(Some(0)), 96000), "●0 mics @96k");
+        assert_eq!(text(Some(None), 48000), "○ driver not responding");
+        assert_eq!(text(None, 48000), "○ driver down");
+    }
 }
@@ -0,0 +1,104 @@
+use anyhow::{bail, Result};
+use std::path::Path;
+
+use super::paint;
+use duomic::config::Config;
+use duomic::expected_mics;
+use duomic::ipc::{reconcile, DriverClient, SocketState, SyncReport};
+
+/// Push the configured virtual mics into the driver and exit
+///
+/// No audio is captured. With `dry_run` the driver's list is only read and
+/// the changes a sync would make are printed. Fails if the driver can't be
+/// reached or any mic could not be added or removed.
+pub fn execute(dry_run: bool, config_path: &Path) -> Result<()> {
+    let mut config = Config::load_from(config_path)?;
+    for (old, new) in config.dedupe_virtual_mic_names() {
+        tracing::warn!("Renamed duplicate virtual mic {} to {}", old, new);
+    }
+    let expected = expected_mics(&config);
+
+    match DriverClient::socket_state() {
+        SocketState::Missing => bail!("Driver not running"),
+        SocketState::Stale => bail!("Driver not running (stale socket left behind)"),
+        SocketState::Present => {}
+    }
+
+    let mut client = DriverClient::new();
+    let report = if dry_run {
+        SyncReport::planned(&reconcile(&expected, &client.list_devices()?))
+    } else {
+        client.sync_devices(&expected)?
+    };
+
+    for (color, line) in summary_lines(&report, dry_run) {
+        match color {
+            Some(code) => println!("{}", paint(code, line)),
+            None => println!("{}", line),
+        }
+    }
+    if !report.failed.is_empty() {
+        bail!("{} virtual mic(s) could not be synced", report.failed.len());
+    }
+    Ok(())
+}
+
+/// One line per kind of change, e.g. "Added: Host, Guest", with its color
+fn summary_lines(report: &SyncReport, dry_run: bool) -> Vec<(Option<&'static str>, String)> {
+    let (added, removed) = if dry_run {
+        ("Would add", "Would remove")
+    } else {
+        ("Added", "Removed")
+    };
+    let list = |names: &[String]| {
+        if names.is_empty() {
+            "none".to_string()
+        } else {
+            names.join(", ")
+        }
+    };
+
+    let mut lines = vec![
+        (Some("32"), format!("{}: {}", added, list(&report.added))),
+        (
+            Some("33"),
+            format!("{}: {}", removed, list(&report.removed)),
+        ),
+        (None, format!("Unchanged: {}", list(&report.unchanged))),
+    ];
+    for (name, error) in &report.failed {
+        lines.push((Some("31"), format!("Failed: {} ({})", name, error)));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_lines() {
+        let report = SyncReport {
+            added: vec!["Host".to_string(), "Guest".to_string()],
+            unchanged: vec!["Music".to_string()],
+            failed: vec![("Old".to_string(), "busy".to_string())],
+            ..SyncReport::default()
+        };
+        let text = |dry_run| -> Vec<String> {
+            summary_lines(&report, dry_run)
+                .into_iter()
+                .map(|(_, line)| line)
+                .collect()
+        };
+        assert_eq!(
+            text(false),
+            [
+                "Added: Host, Guest",
+                "Removed: none",
+                "Unchanged: Music",
+                "Failed: Old (busy)"
+            ]
+        );
+        assert_eq!(text(true)[0], "Would add: Host, Guest");
+    }
+}
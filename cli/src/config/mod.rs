@@ -1,6 +1,10 @@
 // Config library - some methods are prepared for future use
 #![allow(dead_code)]
 
+mod names_file;
+mod saver;
 mod store;
 
+pub use names_file::*;
+pub use saver::*;
 pub use store::*;
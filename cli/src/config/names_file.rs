@@ -0,0 +1,267 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Mic names read from a `duomic run --names-file`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportedNames {
+    /// Names for the selected channels, in order (plain lines, a JSON array
+    /// or the audio input sources of an OBS scene collection)
+    InOrder(Vec<String>),
+    /// Names for specific device channels (`channel=name` lines or a JSON
+    /// object like `{"0": "Host"}`)
+    ByChannel(Vec<(u32, String)>),
+}
+
+impl ImportedNames {
+    /// Read and parse `path` (see [`ImportedNames::parse`])
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read names file {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid names file {}", path.display()))
+    }
+
+    /// Parse JSON (an array, a channel object or an OBS scene collection) or
+    /// text with one `name` or `channel=name` per line
+    ///
+    /// Blank lines and lines starting with `#` are skipped.
+    pub fn parse(text: &str) -> Result<Self> {
+        let trimmed = text.trim_start();
+        let names = if trimmed.starts_with('{') || trimmed.starts_with('[') {
+            Self::parse_json(&serde_json::from_str(trimmed).context("Invalid JSON")?)?
+        } else {
+            Self::parse_lines(text)?
+        };
+        if names.is_empty() {
+            bail!("No names found");
+        }
+        Ok(names)
+    }
+
+    /// How many names there are
+    pub fn len(&self) -> usize {
+        match self {
+            ImportedNames::InOrder(names) => names.len(),
+            ImportedNames::ByChannel(pairs) => pairs.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Name for device `channel`, the `position`-th selected channel
+    pub fn name_for(&self, channel: u32, position: usize) -> Option<&str> {
+        match self {
+            ImportedNames::InOrder(names) => names.get(position),
+            ImportedNames::ByChannel(pairs) => pairs
+                .iter()
+                .find(|(ch, _)| *ch == channel)
+                .map(|(_, name)| name),
+        }
+        .map(String::as_str)
+    }
+
+    /// What doesn't fit `selected` channels, e.g. "4 names for 3 selected
+    /// channels, extra names ignored"; `None` if everything matched
+    pub fn mismatch(&self, selected: &[u32]) -> Option<String> {
+        match self {
+            ImportedNames::InOrder(names) if names.len() > selected.len() => Some(format!(
+                "{} names for {} selected channels, extra names ignored",
+                names.len(),
+                selected.len()
+            )),
+            ImportedNames::InOrder(names) if names.len() < selected.len() => Some(format!(
+                "{} names for {} selected channels, the rest get default names",
+                names.len(),
+                selected.len()
+            )),
+            ImportedNames::InOrder(_) => None,
+            ImportedNames::ByChannel(pairs) => {
+                let unselected: Vec<String> = pairs
+                    .iter()
+                    .filter(|(ch, _)| !selected.contains(ch))
+                    .map(|(ch, _)| ch.to_string())
+                    .collect();
+                (!unselected.is_empty()).then(|| {
+                    format!(
+                        "names for unselected channel(s) {} ignored",
+                        unselected.join(", ")
+                    )
+                })
+            }
+        }
+    }
+
+    fn parse_json(value: &Value) -> Result<Self> {
+        match value {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| match item.as_str().map(str::trim) {
+                    Some(name) if !name.is_empty() => Ok(name.to_string()),
+                    _ => bail!("Expected an array of names, found {}", item),
+                })
+                .collect::<Result<_>>()
+                .map(ImportedNames::InOrder),
+            Value::Object(object) => match object.get("sources") {
+                Some(Value::Array(sources)) => Ok(ImportedNames::InOrder(obs_input_names(sources))),
+                _ => {
+                    let mut pairs = Vec::with_capacity(object.len());
+                    for (key, name) in object {
+                        let name = name.as_str().map(str::trim).unwrap_or_default();
+                        if name.is_empty() {
+                            bail!("Channel {} has no name", key);
+                        }
+                        pairs.push((parse_channel(key)?, name.to_string()));
+                    }
+                    by_channel(pairs)
+                }
+            },
+            _ => bail!("Expected a JSON array or object"),
+        }
+    }
+
+    fn parse_lines(text: &str) -> Result<Self> {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+
+        let mapped = |line: &&str| {
+            line.split_once('=')
+                .is_some_and(|(channel, _)| channel.trim().parse::<u32>().is_ok())
+        };
+        if !lines.iter().any(mapped) {
+            return Ok(ImportedNames::InOrder(
+                lines.into_iter().map(str::to_string).collect(),
+            ));
+        }
+
+        let mut pairs = Vec::with_capacity(lines.len());
+        for line in lines {
+            let Some((channel, name)) = line.split_once('=').filter(|_| mapped(&line)) else {
+                bail!(
+                    "\"{}\": use channel=name on every line, or plain names on every line",
+                    line
+                );
+            };
+            let name = name.trim();
+            if name.is_empty() {
+                bail!("\"{}\": missing name", line);
+            }
+            pairs.push((parse_channel(channel)?, name.to_string()));
+        }
+        by_channel(pairs)
+    }
+}
+
+fn parse_channel(text: &str) -> Result<u32> {
+    text.trim()
+        .parse()
+        .with_context(|| format!("Invalid channel \"{}\" (expected a number like 0)", text))
+}
+
+fn by_channel(pairs: Vec<(u32, String)>) -> Result<ImportedNames> {
+    for (i, (channel, _)) in pairs.iter().enumerate() {
+        if pairs[..i].iter().any(|(other, _)| other == channel) {
+            bail!("Channel {} is named twice", channel);
+        }
+    }
+    Ok(ImportedNames::ByChannel(pairs))
+}
+
+/// Names of the audio input sources (mics, interfaces) in an OBS scene collection
+///
+/// Input capture source ids end in `_input_capture` on every platform
+/// (`coreaudio_input_capture`, `wasapi_input_capture`, ...).
+fn obs_input_names(sources: &[Value]) -> Vec<String> {
+    sources
+        .iter()
+        .filter(|source| {
+            source
+                .get("id")
+                .and_then(Value::as_str)
+                .is_some_and(|id| id.ends_with("_input_capture"))
+        })
+        .filter_map(|source| source.get("name")?.as_str())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_names_file() {
+        let in_order =
+            |names: &[&str]| ImportedNames::InOrder(names.iter().map(|n| n.to_string()).collect());
+
+        assert_eq!(
+            ImportedNames::parse("# podcast\nHost\n\nGuest\n").unwrap(),
+            in_order(&["Host", "Guest"])
+        );
+        assert_eq!(
+            ImportedNames::parse("0 = Host\n3=Guest = Remote\n").unwrap(),
+            ImportedNames::ByChannel(vec![
+                (0, "Host".to_string()),
+                (3, "Guest = Remote".to_string())
+            ])
+        );
+        assert_eq!(
+            ImportedNames::parse(r#"["Host", "Guest"]"#).unwrap(),
+            in_order(&["Host", "Guest"])
+        );
+        assert_eq!(
+            ImportedNames::parse(r#"{"1": "Guest"}"#).unwrap(),
+            ImportedNames::ByChannel(vec![(1, "Guest".to_string())])
+        );
+
+        // OBS scene collection: only audio inputs, in file order
+        let obs = r#"{"name": "Podcast", "sources": [
+            {"id": "coreaudio_input_capture", "name": "Host Mic"},
+            {"id": "display_capture", "name": "Screen"},
+            {"id": "wasapi_input_capture", "name": "Guest Mic"},
+            {"id": "coreaudio_output_capture", "name": "Desktop Audio"}
+        ]}"#;
+        assert_eq!(
+            ImportedNames::parse(obs).unwrap(),
+            in_order(&["Host Mic", "Guest Mic"])
+        );
+
+        // Mixed styles, duplicates and empty files are errors
+        assert!(ImportedNames::parse("0=Host\nGuest\n").is_err());
+        assert!(ImportedNames::parse("0=Host\n0=Guest\n").is_err());
+        assert!(ImportedNames::parse("# nothing\n").is_err());
+        assert!(ImportedNames::parse(r#"{"sources": []}"#).is_err());
+        assert!(ImportedNames::parse(r#"{"x": "Host"}"#).is_err());
+    }
+
+    #[test]
+    fn test_imported_names_fit_the_selection() {
+        let in_order = ImportedNames::parse("Host\nGuest\n").unwrap();
+        assert_eq!(in_order.name_for(5, 1), Some("Guest"));
+        assert_eq!(in_order.name_for(6, 2), None);
+        assert_eq!(in_order.mismatch(&[0, 1]), None);
+        assert!(in_order
+            .mismatch(&[0])
+            .unwrap()
+            .contains("extra names ignored"));
+        assert!(in_order
+            .mismatch(&[0, 1, 2])
+            .unwrap()
+            .contains("the rest get default names"));
+
+        let by_channel = ImportedNames::parse("1=Guest\n4=Music\n").unwrap();
+        assert_eq!(by_channel.name_for(1, 0), Some("Guest"));
+        assert_eq!(by_channel.name_for(0, 0), None);
+        assert_eq!(
+            by_channel.mismatch(&[0, 1]).unwrap(),
+            "names for unselected channel(s) 4 ignored"
+        );
+    }
+}
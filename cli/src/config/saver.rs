@@ -0,0 +1,125 @@
+use anyhow::Result;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::Config;
+
+/// Pending live changes are written at most this often
+pub const CONFIG_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Debounced config writes for live edits (mix keys, UI toggles, renames)
+///
+/// Edits only mark the config dirty; [`ConfigSaver::flush`] on each tick
+/// writes it once the oldest unsaved edit is [`CONFIG_SAVE_INTERVAL`] old, so
+/// a burst of keystrokes costs one write per interval. Flush with `force` on
+/// exit to keep the last edits. A failed write keeps the edits pending and
+/// tries again an interval later.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConfigSaver {
+    /// First edit since the last write
+    dirty_since: Option<Instant>,
+    last_save: Option<Instant>,
+}
+
+impl ConfigSaver {
+    pub fn mark_dirty(&mut self) {
+        self.mark_dirty_at(Instant::now());
+    }
+
+    pub fn mark_dirty_at(&mut self, now: Instant) {
+        self.dirty_since.get_or_insert(now);
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_since.is_some()
+    }
+
+    /// When the config was last written by [`ConfigSaver::flush`]
+    pub fn last_save(&self) -> Option<Instant> {
+        self.last_save
+    }
+
+    /// Whether a write is due at `now` (any pending edit, if `force`)
+    pub fn is_due(&self, now: Instant, force: bool) -> bool {
+        match self.dirty_since {
+            Some(since) => force || now.saturating_duration_since(since) >= CONFIG_SAVE_INTERVAL,
+            None => false,
+        }
+    }
+
+    /// Record a successful write at `now`, clearing the pending edits
+    pub fn mark_saved(&mut self, now: Instant) {
+        self.dirty_since = None;
+        self.last_save = Some(now);
+    }
+
+    /// Save `config` to `path` if a write is due; returns whether it was written
+    pub fn flush(&mut self, config: &Config, path: &Path, force: bool) -> Result<bool> {
+        let now = Instant::now();
+        if !self.is_due(now, force) {
+            return Ok(false);
+        }
+        if let Err(e) = config.save_to(path) {
+            // Still dirty; wait an interval before the next attempt
+            self.dirty_since = Some(now);
+            return Err(e);
+        }
+        self.mark_saved(now);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rapid_changes_coalesce() {
+        let start = Instant::now();
+        let mut saver = ConfigSaver::default();
+        let mut writes = 0;
+
+        // A held key: an edit and a tick every 50ms for 3 seconds
+        for step in 0..60 {
+            let now = start + Duration::from_millis(step * 50);
+            saver.mark_dirty_at(now);
+            if saver.is_due(now, false) {
+                saver.mark_saved(now);
+                writes += 1;
+            }
+        }
+        assert_eq!(writes, 2);
+        assert!(saver.is_dirty());
+
+        // Exit keeps the tail, then there's nothing left to write
+        let end = start + Duration::from_secs(3);
+        assert!(saver.is_due(end, true));
+        saver.mark_saved(end);
+        assert_eq!(saver.last_save(), Some(end));
+        assert!(!saver.is_due(end, true) && !saver.is_dirty());
+    }
+
+    #[test]
+    fn test_failed_write_keeps_edits() {
+        let dir = std::env::temp_dir().join(format!("duomic-saver-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        // A file where the config's directory should be: the write fails
+        let blocker = dir.join("blocker");
+        std::fs::write(&blocker, "").unwrap();
+
+        let mut saver = ConfigSaver::default();
+        let config = Config::default();
+        saver.mark_dirty();
+        assert!(saver
+            .flush(&config, &blocker.join("config.toml"), true)
+            .is_err());
+        assert!(saver.is_dirty() && saver.last_save().is_none());
+
+        let path = dir.join("config.toml");
+        assert!(saver.flush(&config, &path, true).unwrap());
+        assert!(!saver.is_dirty() && path.exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
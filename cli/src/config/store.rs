@@ -1,7 +1,11 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::DuomicError;
 
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -17,6 +21,69 @@ pub struct Config {
 
     #[serde(default)]
     pub logging: LoggingConfig,
+
+    #[serde(default)]
+    pub gate: GateConfig,
+
+    #[serde(default)]
+    pub behavior: BehaviorConfig,
+
+    #[serde(default)]
+    pub meters: MetersConfig,
+
+    #[serde(default)]
+    pub devices: DevicesConfig,
+
+    #[serde(default)]
+    pub keybindings: KeybindingsConfig,
+
+    #[serde(default)]
+    pub monitor: MonitorConfig,
+
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    #[serde(default)]
+    pub naming: NamingConfig,
+
+    /// Last channel selection per device name, offered again on the next setup
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub channel_memory: BTreeMap<String, ChannelMemory>,
+
+    /// More devices captured alongside `[device]`, each with its own mics
+    /// and shared memory (capture source 1, 2, ... in list order)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_devices: Vec<ExtraDeviceConfig>,
+}
+
+/// Most `[[extra_devices]]` the driver can read (it has 8 sources, 0 is `[device]`)
+pub const MAX_EXTRA_DEVICES: usize = 7;
+
+/// One `[[extra_devices]]` entry: device settings like `[device]`, plus its mics
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ExtraDeviceConfig {
+    #[serde(flatten)]
+    pub device: DeviceConfig,
+    /// Mics on this device's channels (names share one namespace with `[[virtual_mics]]`)
+    #[serde(default)]
+    pub virtual_mics: Vec<VirtualMicConfig>,
+}
+
+/// Channels picked (and their names) the last time a device was set up
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChannelMemory {
+    /// Device channel count at the time; a different count makes this stale
+    pub channels: u16,
+    pub mics: Vec<RememberedMic>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RememberedMic {
+    pub channel: u32,
+    pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -24,6 +91,29 @@ pub struct DeviceConfig {
     pub name: Option<String>,
     #[serde(default = "default_sample_rate")]
     pub sample_rate: u32,
+    /// Last device picked in the selector (pre-selected on the next setup run)
+    #[serde(default)]
+    pub last_device: Option<String>,
+    /// Enumeration index of `name`, to tell apart devices with the same name
+    #[serde(default)]
+    pub index: Option<usize>,
+    /// Capture only the first N channels of the device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_channels: Option<u16>,
+    /// Set up for the system default input (`run --default --save`), so
+    /// `[behavior] follow_default` applies
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub system_default: bool,
+}
+
+impl DeviceConfig {
+    /// Channels captured from a device with `device_channels` inputs
+    pub fn capture_channels(&self, device_channels: u16) -> u16 {
+        match self.max_channels {
+            Some(max) if max > 0 => device_channels.min(max),
+            _ => device_channels,
+        }
+    }
 }
 
 fn default_sample_rate() -> u32 {
@@ -34,6 +124,48 @@ fn default_sample_rate() -> u32 {
 pub struct VirtualMicConfig {
     pub name: String,
     pub channel: u32,
+    /// Noise gate threshold in dBFS (no gate when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gate_db: Option<f32>,
+    /// Gain applied to the channel in dB (0 = unchanged)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub gain_db: f32,
+    /// Send silence instead of the channel
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub muted: bool,
+    /// Flip the channel's polarity
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub invert: bool,
+    /// High-pass cutoff in Hz to remove rumble (no filter when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highpass_hz: Option<f32>,
+    /// Color tag, e.g. "red" or "#ff8800" (passed to the driver as metadata)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+impl VirtualMicConfig {
+    /// Mic with no gate, unity gain, unmuted and normal polarity
+    pub fn new(name: String, channel: u32) -> Self {
+        Self {
+            name,
+            channel,
+            gate_db: None,
+            gain_db: 0.0,
+            muted: false,
+            invert: false,
+            highpass_hz: None,
+            color: None,
+        }
+    }
+}
+
+fn is_zero(value: &f32) -> bool {
+    *value == 0.0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -42,6 +174,24 @@ pub struct UiConfig {
     pub color: bool,
     #[serde(default = "default_meter_style")]
     pub meter_style: MeterStyle,
+    /// UI refresh interval in ms while meters are shown (idle screens tick slower)
+    #[serde(default = "default_tick_ms")]
+    pub tick_ms: u64,
+    /// h/j/k/l navigate like the arrow keys
+    #[serde(default)]
+    pub vim_keys: bool,
+    /// Draw meters with plain ASCII characters (for limited terminal fonts)
+    #[serde(default)]
+    pub ascii_only: bool,
+    /// Display order of virtual mics in the dashboard and `status`
+    #[serde(default)]
+    pub sort_mics: MicSort,
+    /// Ask for each channel's name as it's toggled on instead of in a separate step
+    #[serde(default)]
+    pub inline_naming: bool,
+    /// Show a gain slider after each dashboard meter
+    #[serde(default)]
+    pub gain_slider: bool,
 }
 
 impl Default for UiConfig {
@@ -49,6 +199,12 @@ impl Default for UiConfig {
         Self {
             color: true,
             meter_style: MeterStyle::Gradient,
+            tick_ms: default_tick_ms(),
+            vim_keys: false,
+            ascii_only: false,
+            sort_mics: MicSort::None,
+            inline_naming: false,
+            gain_slider: false,
         }
     }
 }
@@ -61,7 +217,11 @@ fn default_meter_style() -> MeterStyle {
     MeterStyle::Gradient
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+fn default_tick_ms() -> u64 {
+    50
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum MeterStyle {
     #[default]
@@ -70,6 +230,58 @@ pub enum MeterStyle {
     Minimal,
 }
 
+impl MeterStyle {
+    /// Next style when cycling through them in the UI
+    pub fn next(self) -> Self {
+        match self {
+            MeterStyle::Gradient => MeterStyle::Mono,
+            MeterStyle::Mono => MeterStyle::Minimal,
+            MeterStyle::Minimal => MeterStyle::Gradient,
+        }
+    }
+}
+
+/// How levels map onto the meter bar and its readout
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MeterScale {
+    /// Bar proportional to amplitude, readout in percent of full scale
+    Linear,
+    /// dBFS from the floor to 0 dB
+    #[default]
+    Db,
+    /// K-system: 0 on the readout is -20 dBFS, bands colored around it
+    K20,
+    /// K-system with 0 at -14 dBFS
+    K14,
+}
+
+/// How virtual mics are ordered for display (the config order stays authoritative)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MicSort {
+    /// Config order
+    #[default]
+    None,
+    /// By source channel
+    Channel,
+    /// Alphabetically, ignoring case
+    Name,
+}
+
+impl MicSort {
+    /// Sort `items` for display by the (name, channel) from `key`
+    ///
+    /// The sort is stable, so ties keep their config order.
+    pub fn apply<T>(self, items: &mut [T], key: impl Fn(&T) -> (&str, u32)) {
+        match self {
+            MicSort::None => {}
+            MicSort::Channel => items.sort_by_key(|item| key(item).1),
+            MicSort::Name => items.sort_by_cached_key(|item| key(item).0.to_lowercase()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggingConfig {
     #[serde(default = "default_log_level")]
@@ -88,31 +300,628 @@ fn default_log_level() -> String {
     "info".to_string()
 }
 
+/// Noise gate timing shared by all gated channels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GateConfig {
+    #[serde(default = "default_gate_attack_ms")]
+    pub attack_ms: f32,
+    #[serde(default = "default_gate_release_ms")]
+    pub release_ms: f32,
+}
+
+impl Default for GateConfig {
+    fn default() -> Self {
+        Self {
+            attack_ms: default_gate_attack_ms(),
+            release_ms: default_gate_release_ms(),
+        }
+    }
+}
+
+fn default_gate_attack_ms() -> f32 {
+    5.0
+}
+
+fn default_gate_release_ms() -> f32 {
+    150.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BehaviorConfig {
+    /// Leave virtual mics in the driver on exit (orphans are synced on next start)
+    #[serde(default)]
+    pub keep_devices_on_exit: bool,
+    /// Ask before quitting the dashboard while mics are live (Ctrl+C never asks)
+    #[serde(default)]
+    pub confirm_quit: bool,
+    /// Shell command the error screen runs (c) when the driver is down,
+    /// e.g. a script allowed to restart coreaudiod without a password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_helper: Option<String>,
+    /// Kiosk mode on every launch (like `duomic run --kiosk`)
+    #[serde(default)]
+    pub autostart: bool,
+    /// Move capture to the new system default input when it changes (only
+    /// for a `--default` setup, and for that run only; otherwise just a notice)
+    #[serde(default)]
+    pub follow_default: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetersConfig {
+    /// Bottom of the meter scale in dBFS (e.g. -80 for more detail in quiet signals)
+    #[serde(default = "default_floor_db")]
+    pub floor_db: f32,
+    /// Level in dBFS that counts as clipping (e.g. -0.1 for headroom warnings, max 0)
+    #[serde(default)]
+    pub clip_threshold_db: f32,
+    /// Average (RMS) level the dashboard's gain suggestion aims for
+    #[serde(default = "default_target_rms_db")]
+    pub target_rms_db: f32,
+    /// Linear, dB or K-system meters
+    #[serde(default)]
+    pub scale: MeterScale,
+    /// Time constant for dashboard meters rising to a louder level (0 = instant)
+    #[serde(default)]
+    pub attack_ms: f32,
+    /// Time constant for dashboard meters falling back after a peak
+    #[serde(default = "default_release_ms")]
+    pub release_ms: f32,
+    /// How long the dashboard's peak-hold marker stays before following the meter (0 = off)
+    #[serde(default = "default_peak_hold_ms")]
+    pub peak_hold_ms: f32,
+    /// How long a clipped channel's readout stays red (0 = only while clipping)
+    #[serde(default = "default_clip_latch_s")]
+    pub clip_latch_s: f32,
+    /// No new audio for this long counts as a disconnect (`[alerts] on_disconnect`)
+    #[serde(default = "default_silence_timeout_s")]
+    pub silence_timeout_s: f32,
+    /// How long the dashboard's gain suggestion (`g`) measures
+    #[serde(default = "default_calibration_s")]
+    pub calibration_s: f32,
+    /// Show a stereo correlation meter on the dashboard (needs a channel pair)
+    #[serde(default = "default_true")]
+    pub correlation: bool,
+    /// Device channels to correlate, e.g. `[0, 1]` (default: the two channels
+    /// when the mics use exactly two)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_pair: Option<[u32; 2]>,
+}
+
+impl Default for MetersConfig {
+    fn default() -> Self {
+        Self {
+            floor_db: default_floor_db(),
+            clip_threshold_db: 0.0,
+            target_rms_db: default_target_rms_db(),
+            scale: MeterScale::Db,
+            attack_ms: 0.0,
+            release_ms: default_release_ms(),
+            peak_hold_ms: default_peak_hold_ms(),
+            clip_latch_s: default_clip_latch_s(),
+            silence_timeout_s: default_silence_timeout_s(),
+            calibration_s: default_calibration_s(),
+            correlation: true,
+            correlation_pair: None,
+        }
+    }
+}
+
+fn default_release_ms() -> f32 {
+    300.0
+}
+
+fn default_peak_hold_ms() -> f32 {
+    1000.0
+}
+
+fn default_clip_latch_s() -> f32 {
+    2.0
+}
+
+fn default_silence_timeout_s() -> f32 {
+    2.0
+}
+
+fn default_calibration_s() -> f32 {
+    3.0
+}
+
+impl MetersConfig {
+    /// Pull out-of-range values back into range; returns a warning per fix
+    ///
+    /// Non-finite values fall back to the default.
+    pub fn validate(&mut self) -> Vec<String> {
+        let defaults = Self::default();
+        let mut warnings = Vec::new();
+        let mut check = |name: &str, value: &mut f32, min: f32, max: f32, default: f32| {
+            let fixed = if !value.is_finite() {
+                default
+            } else {
+                value.clamp(min, max)
+            };
+            if fixed != *value {
+                warnings.push(format!(
+                    "meters.{} must be between {} and {} (got {}), using {}",
+                    name, min, max, value, fixed
+                ));
+                *value = fixed;
+            }
+        };
+        check(
+            "floor_db",
+            &mut self.floor_db,
+            -120.0,
+            -1.0,
+            defaults.floor_db,
+        );
+        check(
+            "clip_threshold_db",
+            &mut self.clip_threshold_db,
+            -120.0,
+            0.0,
+            defaults.clip_threshold_db,
+        );
+        check(
+            "attack_ms",
+            &mut self.attack_ms,
+            0.0,
+            5_000.0,
+            defaults.attack_ms,
+        );
+        check(
+            "release_ms",
+            &mut self.release_ms,
+            0.0,
+            10_000.0,
+            defaults.release_ms,
+        );
+        check(
+            "peak_hold_ms",
+            &mut self.peak_hold_ms,
+            0.0,
+            10_000.0,
+            defaults.peak_hold_ms,
+        );
+        check(
+            "clip_latch_s",
+            &mut self.clip_latch_s,
+            0.0,
+            60.0,
+            defaults.clip_latch_s,
+        );
+        check(
+            "silence_timeout_s",
+            &mut self.silence_timeout_s,
+            0.5,
+            60.0,
+            defaults.silence_timeout_s,
+        );
+        check(
+            "calibration_s",
+            &mut self.calibration_s,
+            1.0,
+            60.0,
+            defaults.calibration_s,
+        );
+        warnings
+    }
+}
+
+/// `[meters]` timing converted for a UI tick rate (see [`MeterParams::resolve`])
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeterParams {
+    /// Share of the distance to a louder level left after one tick (0 = instant)
+    pub attack: f32,
+    /// Share of the distance to a quieter level left after one tick
+    pub release: f32,
+    /// Ticks the peak-hold marker stays put (0 = no marker)
+    pub hold_ticks: u32,
+    /// Ticks a clip stays latched after the last clipping tick
+    pub clip_latch_ticks: u32,
+    pub silence_timeout: Duration,
+    pub calibration: Duration,
+}
+
+impl MeterParams {
+    /// Per-tick coefficients and tick counts for ticks of length `tick`
+    ///
+    /// Attack and release are time constants: the meter covers ~63% of a
+    /// step in that time, whatever the tick rate.
+    pub fn resolve(meters: &MetersConfig, tick: Duration) -> Self {
+        let tick_ms = (tick.as_secs_f32() * 1000.0).max(1.0);
+        let coefficient = |time_ms: f32| {
+            if time_ms > 0.0 {
+                (-tick_ms / time_ms).exp()
+            } else {
+                0.0
+            }
+        };
+        let ticks = |time_ms: f32| (time_ms.max(0.0) / tick_ms).ceil() as u32;
+        let seconds = |s: f32| Duration::from_secs_f32(s.max(0.0));
+
+        Self {
+            attack: coefficient(meters.attack_ms),
+            release: coefficient(meters.release_ms),
+            hold_ticks: ticks(meters.peak_hold_ms),
+            clip_latch_ticks: ticks(meters.clip_latch_s * 1000.0),
+            silence_timeout: seconds(meters.silence_timeout_s),
+            calibration: seconds(meters.calibration_s),
+        }
+    }
+
+    /// A meter at `current` one tick later, moving towards `peak`
+    pub fn step(&self, current: f32, peak: f32) -> f32 {
+        let keep = if peak > current {
+            self.attack
+        } else {
+            self.release
+        };
+        peak + (current - peak) * keep
+    }
+}
+
+fn default_target_rms_db() -> f32 {
+    -18.0
+}
+
+fn default_floor_db() -> f32 {
+    -60.0
+}
+
+/// Device selector filtering (case-insensitive substring match)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevicesConfig {
+    /// Devices never shown in the selector
+    #[serde(default)]
+    pub hidden: Vec<String>,
+    /// Families of devices never shown, e.g. app loopbacks (defaults to
+    /// [`DEFAULT_EXCLUDE_PATTERNS`]; `[]` shows them all)
+    #[serde(default = "default_exclude_patterns")]
+    pub exclude_patterns: Vec<String>,
+    /// Devices listed first, in this order
+    #[serde(default)]
+    pub pinned: Vec<String>,
+}
+
+/// Inputs that conferencing apps and macOS add, which are never a microphone
+pub const DEFAULT_EXCLUDE_PATTERNS: &[&str] = &[
+    "ZoomAudioDevice",
+    "Microsoft Teams Audio",
+    "CADefaultDeviceAggregate",
+];
+
+fn default_exclude_patterns() -> Vec<String> {
+    DEFAULT_EXCLUDE_PATTERNS
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+}
+
+impl Default for DevicesConfig {
+    fn default() -> Self {
+        Self {
+            hidden: Vec::new(),
+            exclude_patterns: default_exclude_patterns(),
+            pinned: Vec::new(),
+        }
+    }
+}
+
+impl DevicesConfig {
+    /// Everything kept out of the selector: `hidden` and `exclude_patterns`
+    pub fn hidden_patterns(&self) -> Vec<String> {
+        self.hidden
+            .iter()
+            .chain(&self.exclude_patterns)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Channel monitoring (the `m` key in channel selection and `duomic monitor`)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MonitorConfig {
+    /// Output device to listen on (partial match); default output when unset or missing
+    #[serde(default)]
+    pub output_device: Option<String>,
+}
+
+/// Names given to mics left blank in setup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamingConfig {
+    /// Template with `{device}`, `{channel}`, `{index}` and `{channel_name}` placeholders
+    #[serde(default = "default_name_template")]
+    pub template: String,
+}
+
+impl Default for NamingConfig {
+    fn default() -> Self {
+        Self {
+            template: default_name_template(),
+        }
+    }
+}
+
+fn default_name_template() -> String {
+    "{device} Ch{channel}".to_string()
+}
+
+/// Placeholders a name template can use
+pub const NAME_PLACEHOLDERS: [&str; 4] = ["device", "channel", "index", "channel_name"];
+
+/// Check that `template` has matched braces and at least one known placeholder
+pub fn validate_name_template(template: &str) -> Result<()> {
+    let mut rest = template;
+    let mut known = false;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .with_context(|| format!("Unclosed '{{' in name template {:?}", template))?;
+        let placeholder = &rest[start + 1..start + end];
+        known |= NAME_PLACEHOLDERS.contains(&placeholder);
+        rest = &rest[start + end + 1..];
+    }
+    if !known {
+        anyhow::bail!(
+            "Name template {:?} needs at least one of {{{}}}",
+            template,
+            NAME_PLACEHOLDERS.join("}, {")
+        );
+    }
+    Ok(())
+}
+
+/// Whether `color` can be sent to the driver as `ADD` metadata
+fn valid_color(color: &str) -> bool {
+    !color.trim().is_empty() && !color.contains([':', ',', '=', '\n'])
+}
+
+/// Conventional name of a device channel ("Left", "Right", ...), if it has one
+pub fn channel_label(channel: usize) -> Option<&'static str> {
+    [
+        "Left",
+        "Right",
+        "Center",
+        "LFE",
+        "Rear Left",
+        "Rear Right",
+        "Side Left",
+        "Side Right",
+    ]
+    .get(channel)
+    .copied()
+}
+
+/// Prometheus metrics served while capturing
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MetricsConfig {
+    /// Address for `GET /metrics`, e.g. "127.0.0.1:9184"; off when unset
+    #[serde(default)]
+    pub listen: Option<String>,
+}
+
+/// Terminal bell (and header flash) when something goes wrong while running
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertsConfig {
+    /// Alert when any channel clips
+    #[serde(default)]
+    pub on_clip: bool,
+    /// Alert when the device stops delivering audio (e.g. unplugged)
+    #[serde(default)]
+    pub on_disconnect: bool,
+    /// Flash the dashboard header red along with the bell
+    #[serde(default = "default_true")]
+    pub flash: bool,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            on_clip: false,
+            on_disconnect: false,
+            flash: true,
+        }
+    }
+}
+
+/// Where the config file path came from (see [`Config::locate`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    /// `--config`
+    Flag,
+    /// `DUOMIC_CONFIG`
+    Env,
+    /// `$XDG_CONFIG_HOME/duomic/config.toml`
+    Xdg,
+    /// `~/.config/duomic/config.toml`
+    Default,
+}
+
+impl ConfigSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            ConfigSource::Flag => "--config",
+            ConfigSource::Env => "DUOMIC_CONFIG",
+            ConfigSource::Xdg => "XDG_CONFIG_HOME",
+            ConfigSource::Default => "default",
+        }
+    }
+}
+
+/// Config file location from the override, XDG and home directories, in that order
+///
+/// Empty values are ignored, and XDG_CONFIG_HOME must be absolute (per the spec).
+fn resolve_path(
+    override_file: Option<PathBuf>,
+    xdg_config_home: Option<PathBuf>,
+    home: Option<PathBuf>,
+) -> Option<(PathBuf, ConfigSource)> {
+    let non_empty = |p: &PathBuf| !p.as_os_str().is_empty();
+
+    if let Some(file) = override_file.filter(non_empty) {
+        return Some((file, ConfigSource::Env));
+    }
+
+    let (config_dir, source) = match xdg_config_home.filter(|p| non_empty(p) && p.is_absolute()) {
+        Some(xdg) => (xdg, ConfigSource::Xdg),
+        None => (
+            home.filter(non_empty)?.join(".config"),
+            ConfigSource::Default,
+        ),
+    };
+
+    Some((config_dir.join("duomic").join("config.toml"), source))
+}
+
+/// [`Config::locate`] with environment variables read through `var`
+fn locate_in(var: impl Fn(&str) -> Option<PathBuf>) -> Result<(PathBuf, ConfigSource)> {
+    let home = var("HOME")
+        .filter(|p| !p.as_os_str().is_empty())
+        .or_else(dirs::home_dir);
+
+    resolve_path(var("DUOMIC_CONFIG"), var("XDG_CONFIG_HOME"), home)
+        .context("Could not determine config path (set DUOMIC_CONFIG, XDG_CONFIG_HOME or HOME)")
+}
+
+/// Keys for TUI actions: a single character or a name like "space", "enter", "esc", "tab"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeybindingsConfig {
+    pub quit: String,
+    pub restart: String,
+    pub setup: String,
+    /// Toggle a channel in channel selection
+    pub toggle: String,
+    pub select: String,
+    pub cancel: String,
+}
+
+impl Default for KeybindingsConfig {
+    fn default() -> Self {
+        Self {
+            quit: "q".to_string(),
+            restart: "r".to_string(),
+            setup: "s".to_string(),
+            toggle: "space".to_string(),
+            select: "enter".to_string(),
+            cancel: "esc".to_string(),
+        }
+    }
+}
+
 impl Config {
     /// Get the config file path (~/.config/duomic/config.toml)
-    /// Uses XDG standard on all platforms
+    ///
+    /// `DUOMIC_CONFIG` (a file path) overrides everything; otherwise uses
+    /// `$XDG_CONFIG_HOME/duomic/config.toml`, then `$HOME/.config/duomic/config.toml`
+    /// (falling back to the OS home lookup when `HOME` is unset).
     pub fn path() -> Result<PathBuf> {
-        // Use XDG standard: ~/.config/duomic/config.toml
-        let home = dirs::home_dir().context("Could not determine home directory")?;
+        Self::locate().map(|(path, _)| path)
+    }
 
-        let config_dir = home.join(".config").join("duomic");
-        Ok(config_dir.join("config.toml"))
+    /// The config file path and which setting chose it (see [`Config::path`])
+    pub fn locate() -> Result<(PathBuf, ConfigSource)> {
+        locate_in(|name| std::env::var_os(name).map(PathBuf::from))
+    }
+
+    /// [`Config::locate`], unless `flag` (the `--config` path) is given
+    pub fn locate_with(flag: Option<PathBuf>) -> Result<(PathBuf, ConfigSource)> {
+        match flag {
+            Some(path) => Ok((path, ConfigSource::Flag)),
+            None => Self::locate(),
+        }
     }
 
     /// Load config from file, or return default if not exists
-    pub fn load() -> Result<Self> {
-        let path = Self::path()?;
+    pub fn load() -> Result<Self, DuomicError> {
+        Self::load_from(&Self::path()?)
+    }
 
+    /// Load config from `path`, or return default if it doesn't exist
+    pub fn load_from(path: &Path) -> Result<Self, DuomicError> {
         if !path.exists() {
             tracing::debug!("Config file not found, using defaults");
             return Ok(Self::default());
         }
 
-        let content = fs::read_to_string(&path)
-            .with_context(|| format!("Failed to read config from {:?}", path))?;
+        let content = fs::read_to_string(path).map_err(|source| DuomicError::ConfigRead {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let mut config: Config =
+            toml::from_str(&content).map_err(|source| DuomicError::ConfigParse {
+                path: path.to_path_buf(),
+                source: Box::new(source),
+            })?;
 
-        let config: Config = toml::from_str(&content)
-            .with_context(|| format!("Failed to parse config from {:?}", path))?;
+        if let Err(e) = validate_name_template(&config.naming.template) {
+            tracing::warn!("naming.template: {:#}, using the default", e);
+            config.naming = NamingConfig::default();
+        }
+
+        for warning in config.meters.validate() {
+            tracing::warn!("{}", warning);
+        }
+        if config.extra_devices.len() > MAX_EXTRA_DEVICES {
+            tracing::warn!(
+                "Only {} extra_devices are supported, ignoring the rest",
+                MAX_EXTRA_DEVICES
+            );
+            config.extra_devices.truncate(MAX_EXTRA_DEVICES);
+        }
+        let extra_mics = config
+            .extra_devices
+            .iter_mut()
+            .flat_map(|d| d.virtual_mics.iter_mut());
+        for mic in config.virtual_mics.iter_mut().chain(extra_mics) {
+            if mic
+                .highpass_hz
+                .is_some_and(|hz| !(hz.is_finite() && hz > 0.0))
+            {
+                tracing::warn!("{}: highpass_hz must be above 0, filter off", mic.name);
+                mic.highpass_hz = None;
+            }
+            if mic.gate_db.is_some_and(|db| !(db.is_finite() && db <= 0.0)) {
+                tracing::warn!("{}: gate_db must be at most 0 dBFS, gate off", mic.name);
+                mic.gate_db = None;
+            }
+            if mic.color.as_deref().is_some_and(|c| !valid_color(c)) {
+                tracing::warn!(
+                    "{}: color can't be empty or contain ':', ',' or '='",
+                    mic.name
+                );
+                mic.color = None;
+            }
+        }
+        let gate = &mut config.gate;
+        for (name, value, default) in [
+            ("attack_ms", &mut gate.attack_ms, default_gate_attack_ms()),
+            (
+                "release_ms",
+                &mut gate.release_ms,
+                default_gate_release_ms(),
+            ),
+        ] {
+            if !(value.is_finite() && *value >= 0.0) {
+                let fixed = if value.is_finite() {
+                    value.max(0.0)
+                } else {
+                    default
+                };
+                tracing::warn!("gate.{} must be at least 0, using {}", name, fixed);
+                *value = fixed;
+            }
+        }
+        let extra_devices = config.extra_devices.iter_mut().map(|d| &mut d.device);
+        for device in std::iter::once(&mut config.device).chain(extra_devices) {
+            if device.max_channels == Some(0) {
+                tracing::warn!("max_channels must be at least 1, capturing all channels");
+                device.max_channels = None;
+            }
+        }
 
         tracing::info!("Loaded config from {:?}", path);
         Ok(config)
@@ -120,8 +929,11 @@ impl Config {
 
     /// Save config to file
     pub fn save(&self) -> Result<()> {
-        let path = Self::path()?;
+        self.save_to(&Self::path()?)
+    }
 
+    /// Save config to `path`, creating its directory if needed
+    pub fn save_to(&self, path: &Path) -> Result<()> {
         // Create config directory if it doesn't exist
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)
@@ -130,7 +942,7 @@ impl Config {
 
         let content = toml::to_string_pretty(self).context("Failed to serialize config")?;
 
-        fs::write(&path, content)
+        fs::write(path, content)
             .with_context(|| format!("Failed to write config to {:?}", path))?;
 
         tracing::info!("Saved config to {:?}", path);
@@ -140,8 +952,89 @@ impl Config {
     /// Add a virtual microphone configuration
     pub fn add_virtual_mic(&mut self, name: String, channel: u32) {
         // Remove existing with same name
+        if self.virtual_mics.iter().any(|m| m.name == name) {
+            tracing::warn!("Replacing existing virtual mic: {}", name);
+        }
         self.virtual_mics.retain(|m| m.name != name);
-        self.virtual_mics.push(VirtualMicConfig { name, channel });
+        self.virtual_mics.push(VirtualMicConfig::new(name, channel));
+    }
+
+    /// Rename virtual mics whose names collide (the driver keys devices by name)
+    ///
+    /// The first mic keeps its name; later duplicates get a " (2)", " (3)", ...
+    /// suffix. Returns the `(old, new)` names of renamed mics.
+    pub fn dedupe_virtual_mic_names(&mut self) -> Vec<(String, String)> {
+        let mut renamed = Vec::new();
+        let mut taken: Vec<String> = Vec::new();
+
+        // `[[extra_devices]]` mics come after `[[virtual_mics]]`, in list order
+        let extra_mics = self
+            .extra_devices
+            .iter_mut()
+            .flat_map(|d| d.virtual_mics.iter_mut());
+        for mic in self.virtual_mics.iter_mut().chain(extra_mics) {
+            if taken.contains(&mic.name) {
+                let new_name = unique_name(&mic.name, |candidate| {
+                    taken.iter().any(|name| name == candidate)
+                });
+                renamed.push((std::mem::replace(&mut mic.name, new_name.clone()), new_name));
+            }
+            taken.push(mic.name.clone());
+        }
+
+        renamed
+    }
+
+    /// This config as seen from capture `source`
+    ///
+    /// Source 0 is `[device]` and `[[virtual_mics]]` unchanged; source N puts
+    /// the Nth `[[extra_devices]]` entry in their place, so everything that
+    /// works on one device (capture options, mix, expected mics) applies.
+    /// `None` past the last extra device.
+    pub fn for_source(&self, source: u32) -> Option<Config> {
+        let mut config = self.clone();
+        if source > 0 {
+            let extra = config.extra_devices.get(source as usize - 1)?.clone();
+            config.device = extra.device;
+            config.virtual_mics = extra.virtual_mics;
+        }
+        config.extra_devices.clear();
+        Some(config)
+    }
+
+    /// Remember `mics` as the setup of `device` (a device with `channels` channels)
+    pub fn remember_channels(&mut self, device: &str, channels: u16, mics: &[VirtualMicConfig]) {
+        let mics = mics
+            .iter()
+            .map(|m| RememberedMic {
+                channel: m.channel,
+                name: m.name.clone(),
+            })
+            .collect();
+        self.channel_memory
+            .insert(device.to_string(), ChannelMemory { channels, mics });
+    }
+
+    /// The remembered setup of `device`, unless its channel count has changed since
+    pub fn recall_channels(&self, device: &str, channels: u16) -> Option<&ChannelMemory> {
+        self.channel_memory
+            .get(device)
+            .filter(|memory| memory.channels == channels)
+    }
+
+    /// The channel count `device` had when it was set up, if it now has `channels` instead
+    ///
+    /// Taken from the channel memory; without one, the count implied by the
+    /// configured mics (highest channel + 1) is used, which only shows a shrink.
+    pub fn channel_count_change(&self, device: &str, channels: u16) -> Option<u16> {
+        let previous = match self.channel_memory.get(device) {
+            Some(memory) => memory.channels,
+            None => {
+                let implied = self.virtual_mics.iter().map(|m| m.channel + 1).max()?;
+                u16::try_from(implied).ok().filter(|&n| n > channels)?
+            }
+        };
+        (previous != channels).then_some(previous)
     }
 
     /// Remove a virtual microphone configuration
@@ -152,16 +1045,259 @@ impl Config {
     }
 }
 
+/// Default virtual mic name for a device channel from a `[naming] template`
+///
+/// `channel` is the 0-based device channel and `index` the mic's 1-based
+/// position in the selection. Unknown placeholders are kept as written.
+pub fn default_mic_name(template: &str, device_name: &str, channel: usize, index: usize) -> String {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        name.push_str(&rest[..start]);
+        match &rest[start + 1..start + end] {
+            "device" => name.push_str(device_name),
+            "channel" => name.push_str(&channel.to_string()),
+            "index" => name.push_str(&index.to_string()),
+            "channel_name" => match channel_label(channel) {
+                Some(label) => name.push_str(label),
+                None => name.push_str(&format!("Ch{}", channel)),
+            },
+            _ => name.push_str(&rest[start..=start + end]),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+    name
+}
+
+/// Append " (2)", " (3)", ... to `name` until `is_taken` returns false
+pub fn unique_name(name: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    if !is_taken(name) {
+        return name.to_string();
+    }
+
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find(|candidate| !is_taken(candidate))
+        .expect("unbounded suffix search")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_name_template() {
+        let default = NamingConfig::default().template;
+        assert_eq!(default_mic_name(&default, "BOYALINK", 1, 2), "BOYALINK Ch1");
+        assert_eq!(
+            default_mic_name("Studio-Mic-{index} ({channel_name})", "USB", 1, 2),
+            "Studio-Mic-2 (Right)"
+        );
+        assert_eq!(default_mic_name("{channel_name}", "USB", 9, 1), "Ch9");
+        // Unknown placeholders and stray braces are kept as written
+        assert_eq!(
+            default_mic_name("{device} {colour} {channel", "USB", 0, 1),
+            "USB {colour} {channel"
+        );
+
+        assert!(validate_name_template(&default).is_ok());
+        assert!(validate_name_template("Mic {index}").is_ok());
+        assert!(validate_name_template("Mic").is_err());
+        assert!(validate_name_template("Mic {colour}").is_err());
+        assert!(validate_name_template("Mic {index").is_err());
+    }
+
+    #[test]
+    fn test_mic_sort_modes() {
+        let mics = [("guest", 1), ("Host", 0), ("Aux", 1), ("band", 3)];
+        let sorted = |mode: MicSort| {
+            let mut items = mics.to_vec();
+            mode.apply(&mut items, |&(name, channel)| (name, channel));
+            items.into_iter().map(|(name, _)| name).collect::<Vec<_>>()
+        };
+
+        assert_eq!(sorted(MicSort::None), ["guest", "Host", "Aux", "band"]);
+        // Same channel keeps config order (guest before Aux)
+        assert_eq!(sorted(MicSort::Channel), ["Host", "guest", "Aux", "band"]);
+        assert_eq!(sorted(MicSort::Name), ["Aux", "band", "guest", "Host"]);
+
+        let parsed: UiConfig = toml::from_str("sort_mics = \"channel\"").unwrap();
+        assert_eq!(parsed.sort_mics, MicSort::Channel);
+    }
+
+    #[test]
+    fn test_ui_prefs_round_trip() {
+        let mut config = Config::default();
+        config.ui.color = false;
+        config.ui.meter_style = MeterStyle::Minimal;
+        config.ui.tick_ms = 100;
+        config.ui.vim_keys = true;
+        config.ui.ascii_only = true;
+
+        let toml = toml::to_string_pretty(&config).unwrap();
+        let parsed: Config = toml::from_str(&toml).unwrap();
+
+        assert!(!parsed.ui.color);
+        assert_eq!(parsed.ui.meter_style, MeterStyle::Minimal);
+        assert_eq!(parsed.ui.tick_ms, 100);
+        assert!(parsed.ui.vim_keys);
+        assert!(parsed.ui.ascii_only);
+    }
+
+    #[test]
+    fn test_meters_section_round_trip_and_validation() {
+        // A partial section keeps the other defaults
+        let parsed: Config = toml::from_str("[meters]\npeak_hold_ms = 500\n").unwrap();
+        assert_eq!(parsed.meters.peak_hold_ms, 500.0);
+        assert_eq!(parsed.meters.release_ms, 300.0);
+        assert_eq!(parsed.meters.clip_latch_s, 2.0);
+
+        let mut config = Config::default();
+        config.meters.attack_ms = 10.0;
+        config.meters.calibration_s = 5.0;
+        let toml = toml::to_string_pretty(&config).unwrap();
+        let parsed: Config = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.meters.attack_ms, 10.0);
+        assert_eq!(parsed.meters.calibration_s, 5.0);
+
+        let mut meters = parsed.meters;
+        assert!(meters.validate().is_empty());
+        meters.clip_threshold_db = 3.0;
+        meters.silence_timeout_s = 0.0;
+        meters.release_ms = f32::NAN;
+        meters.peak_hold_ms = -5.0;
+        meters.clip_latch_s = 120.0;
+        let warnings = meters.validate();
+        assert_eq!(warnings.len(), 5);
+        assert_eq!(
+            warnings[0],
+            "meters.clip_threshold_db must be between -120 and 0 (got 3), using 0"
+        );
+        assert_eq!(meters.silence_timeout_s, 0.5);
+        assert_eq!(meters.release_ms, 300.0);
+        assert_eq!(meters.peak_hold_ms, 0.0);
+        assert_eq!(meters.clip_latch_s, 60.0);
+
+        // 50 ms ticks
+        let params = MeterParams::resolve(&meters, Duration::from_millis(50));
+        assert!((params.attack - (-5.0f32).exp()).abs() < 1e-6);
+        assert!((params.release - (-50.0f32 / 300.0).exp()).abs() < 1e-6);
+        assert!((params.step(1.0, 0.0) - params.release).abs() < 1e-6);
+        assert!((params.step(0.0, 1.0) - (1.0 - params.attack)).abs() < 1e-6);
+        assert_eq!(params.hold_ticks, 0);
+        assert_eq!(params.clip_latch_ticks, 1200);
+        let defaults = MeterParams::resolve(&MetersConfig::default(), Duration::from_millis(50));
+        assert_eq!(defaults.hold_ticks, 20);
+        assert_eq!(defaults.clip_latch_ticks, 40);
+        assert_eq!(params.calibration, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_resolve_path_fallbacks() {
+        let p = |s: &str| Some(PathBuf::from(s));
+
+        let at = |s: &str, source| Some((PathBuf::from(s), source));
+
+        assert_eq!(
+            resolve_path(p("/etc/duomic.toml"), p("/xdg"), p("/home/me")),
+            at("/etc/duomic.toml", ConfigSource::Env)
+        );
+        assert_eq!(
+            resolve_path(None, p("/xdg"), p("/home/me")),
+            at("/xdg/duomic/config.toml", ConfigSource::Xdg)
+        );
+        // Relative or empty XDG_CONFIG_HOME is ignored
+        assert_eq!(
+            resolve_path(p(""), p("rel"), p("/home/me")),
+            at("/home/me/.config/duomic/config.toml", ConfigSource::Default)
+        );
+        assert_eq!(resolve_path(None, None, p("")), None);
+    }
+
+    #[test]
+    fn test_flag_path_beats_env() {
+        let path = PathBuf::from("/tmp/duomic-flag/studio.toml");
+        assert_eq!(
+            Config::locate_with(Some(path.clone())).unwrap(),
+            (path, ConfigSource::Flag)
+        );
+    }
+
+    #[test]
+    fn test_path_from_env() {
+        let env = |vars: &'static [(&str, &str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| PathBuf::from(value))
+            }
+        };
+
+        assert_eq!(
+            locate_in(env(&[
+                ("DUOMIC_CONFIG", "/tmp/duomic-test/config.toml"),
+                ("XDG_CONFIG_HOME", "/tmp/duomic-xdg"),
+            ]))
+            .unwrap(),
+            (
+                PathBuf::from("/tmp/duomic-test/config.toml"),
+                ConfigSource::Env
+            )
+        );
+        assert_eq!(
+            locate_in(env(&[("XDG_CONFIG_HOME", "/tmp/duomic-xdg")])).unwrap(),
+            (
+                PathBuf::from("/tmp/duomic-xdg/duomic/config.toml"),
+                ConfigSource::Xdg
+            )
+        );
+        assert_eq!(
+            locate_in(env(&[("HOME", "/home/me")])).unwrap().0,
+            PathBuf::from("/home/me/.config/duomic/config.toml")
+        );
+    }
+
+    #[test]
+    fn test_load_and_save_explicit_path() {
+        let dir = std::env::temp_dir().join(format!("duomic-config-{}", std::process::id()));
+        let path = dir.join("nested").join("studio.toml");
+        let _ = fs::remove_dir_all(&dir);
+
+        // Missing file: defaults, nothing created
+        assert!(Config::load_from(&path).unwrap().virtual_mics.is_empty());
+        assert!(!path.exists());
+
+        let mut config = Config::default();
+        config.add_virtual_mic("Host".to_string(), 1);
+        config.save_to(&path).unwrap();
+        let loaded = Config::load_from(&path).unwrap();
+        assert_eq!(loaded.virtual_mics[0].name, "Host");
+        assert_eq!(loaded.virtual_mics[0].channel, 1);
+
+        // A color the driver can't take as metadata is dropped, not the mic
+        let mut config = loaded;
+        config.virtual_mics[0].color = Some("red:blue".to_string());
+        config.add_virtual_mic("Guest".to_string(), 2);
+        config.virtual_mics[1].color = Some("#ff8800".to_string());
+        config.save_to(&path).unwrap();
+        let loaded = Config::load_from(&path).unwrap();
+        assert_eq!(loaded.virtual_mics[0].color, None);
+        assert_eq!(loaded.virtual_mics[1].color.as_deref(), Some("#ff8800"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_default_config() {
         let config = Config::default();
         assert!(config.virtual_mics.is_empty());
         assert!(config.ui.color);
         assert_eq!(config.ui.meter_style, MeterStyle::Gradient);
+        assert!(!config.behavior.keep_devices_on_exit);
     }
 
     #[test]
@@ -177,4 +1313,231 @@ mod tests {
         assert_eq!(deserialized.virtual_mics.len(), 1);
         assert_eq!(deserialized.virtual_mics[0].name, "Test Mic");
     }
+
+    #[test]
+    fn test_mic_mix_settings_round_trip() {
+        let mut config = Config::default();
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.add_virtual_mic("Guest".to_string(), 1);
+        config.virtual_mics[1].gain_db = -4.5;
+        config.virtual_mics[1].muted = true;
+        config.virtual_mics[1].invert = true;
+
+        let serialized = toml::to_string(&config).unwrap();
+        let deserialized: Config = toml::from_str(&serialized).unwrap();
+
+        let guest = &deserialized.virtual_mics[1];
+        assert_eq!(guest.gain_db, -4.5);
+        assert!(guest.muted && guest.invert);
+        // Defaults stay out of the file
+        assert!(!serialized.contains("gain_db = 0"));
+
+        // Configs written before these fields existed still load
+        let old: Config =
+            toml::from_str("[[virtual_mics]]\nname = \"Host\"\nchannel = 0\n").unwrap();
+        let host = &old.virtual_mics[0];
+        assert_eq!(host.gain_db, 0.0);
+        assert!(!host.muted && !host.invert);
+    }
+
+    #[test]
+    fn test_invalid_gate_settings_are_rejected_on_load() {
+        let dir = std::env::temp_dir().join(format!("duomic-gate-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            &path,
+            r#"
+            [gate]
+            attack_ms = -5
+            release_ms = nan
+
+            [[virtual_mics]]
+            name = "Host"
+            channel = 0
+            gate_db = 40
+
+            [[virtual_mics]]
+            name = "Guest"
+            channel = 1
+            gate_db = nan
+
+            [[virtual_mics]]
+            name = "Third"
+            channel = 2
+            gate_db = -45
+            "#,
+        )
+        .unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.virtual_mics[0].gate_db, None);
+        assert_eq!(config.virtual_mics[1].gate_db, None);
+        assert_eq!(config.virtual_mics[2].gate_db, Some(-45.0));
+        assert_eq!(config.gate.attack_ms, 0.0);
+        assert_eq!(config.gate.release_ms, 150.0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dedupe_virtual_mic_names() {
+        let mut config = Config::default();
+        for (name, channel) in [("Mic", 0), ("Mic", 1), ("Mic (2)", 2), ("Mic", 3)] {
+            config
+                .virtual_mics
+                .push(VirtualMicConfig::new(name.to_string(), channel));
+        }
+
+        let renamed = config.dedupe_virtual_mic_names();
+        let names: Vec<&str> = config
+            .virtual_mics
+            .iter()
+            .map(|m| m.name.as_str())
+            .collect();
+
+        assert_eq!(names, ["Mic", "Mic (2)", "Mic (2) (2)", "Mic (3)"]);
+        assert_eq!(renamed.len(), 3);
+    }
+
+    #[test]
+    fn test_device_exclude_patterns() {
+        use crate::audio::{filter_devices, AudioDevice};
+
+        let devices = || {
+            ["MacBook Pro Microphone", "ZoomAudioDevice", "BlackHole 2ch"]
+                .iter()
+                .enumerate()
+                .map(|(index, name)| AudioDevice {
+                    name: name.to_string(),
+                    channels: 2,
+                    default_sample_rate: 48000,
+                    index,
+                })
+                .collect::<Vec<_>>()
+        };
+        let shown = |config: &Config| -> Vec<String> {
+            filter_devices(devices(), &config.devices.hidden_patterns(), &[])
+                .into_iter()
+                .map(|d| d.name)
+                .collect()
+        };
+
+        // Built-in patterns apply without a [devices] table, next to `hidden`
+        let config: Config = toml::from_str("[devices]\nhidden = [\"blackhole\"]").unwrap();
+        assert_eq!(shown(&config), ["MacBook Pro Microphone"]);
+        assert_eq!(shown(&Config::default()).len(), 2);
+
+        // Replacing the list drops the defaults; `[]` shows everything
+        let config: Config = toml::from_str("[devices]\nexclude_patterns = [\"MACBOOK\"]").unwrap();
+        assert_eq!(shown(&config), ["ZoomAudioDevice", "BlackHole 2ch"]);
+        let config: Config = toml::from_str("[devices]\nexclude_patterns = []").unwrap();
+        assert_eq!(shown(&config).len(), 3);
+    }
+
+    #[test]
+    fn test_extra_devices() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [device]
+            name = "USB Mic"
+
+            [[virtual_mics]]
+            name = "Host"
+            channel = 0
+
+            [[extra_devices]]
+            name = "Second Mic"
+            max_channels = 1
+
+            [[extra_devices.virtual_mics]]
+            name = "Host"
+            channel = 0
+            gain_db = 3.0
+            "#,
+        )
+        .unwrap();
+
+        // Names are unique across devices, the driver keys mics by name
+        assert_eq!(
+            config.dedupe_virtual_mic_names(),
+            [("Host".to_string(), "Host (2)".to_string())]
+        );
+
+        let primary = config.for_source(0).unwrap();
+        assert_eq!(primary.device.name.as_deref(), Some("USB Mic"));
+        assert_eq!(primary.virtual_mics[0].name, "Host");
+        assert!(primary.extra_devices.is_empty());
+
+        let extra = config.for_source(1).unwrap();
+        assert_eq!(extra.device.name.as_deref(), Some("Second Mic"));
+        assert_eq!(extra.device.sample_rate, 48000);
+        assert_eq!(extra.device.capture_channels(2), 1);
+        assert_eq!(extra.virtual_mics[0].name, "Host (2)");
+        assert_eq!(extra.virtual_mics[0].gain_db, 3.0);
+        assert!(config.for_source(2).is_none());
+
+        let serialized = toml::to_string_pretty(&config).unwrap();
+        let deserialized: Config = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.extra_devices.len(), 1);
+        assert_eq!(deserialized.extra_devices[0].virtual_mics.len(), 1);
+    }
+
+    #[test]
+    fn test_channel_memory() {
+        let mut config = Config::default();
+        let mics = [
+            VirtualMicConfig::new("Host".to_string(), 0),
+            VirtualMicConfig::new("Guest".to_string(), 3),
+        ];
+        config.remember_channels("Interface", 4, &mics);
+        config.remember_channels("USB Mic", 2, &mics[..1]);
+
+        let serialized = toml::to_string_pretty(&config).unwrap();
+        let deserialized: Config = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.channel_memory, config.channel_memory);
+
+        let memory = deserialized.recall_channels("Interface", 4).unwrap();
+        assert_eq!(memory.mics[1].name, "Guest");
+        assert_eq!(memory.mics[1].channel, 3);
+        // Unknown device, or the device changed its channel count
+        assert!(deserialized.recall_channels("Other", 4).is_none());
+        assert!(deserialized.recall_channels("Interface", 2).is_none());
+
+        // Nothing remembered yet: no table in the file
+        assert!(!toml::to_string(&Config::default())
+            .unwrap()
+            .contains("channel_memory"));
+    }
+
+    #[test]
+    fn test_channel_count_change() {
+        let mut config = Config::default();
+        config.add_virtual_mic("Host".to_string(), 0);
+        config.add_virtual_mic("Guest".to_string(), 3);
+
+        // No memory: only a device too small for the mics counts
+        assert_eq!(config.channel_count_change("Aggregate", 4), None);
+        assert_eq!(config.channel_count_change("Aggregate", 6), None);
+        assert_eq!(config.channel_count_change("Aggregate", 2), Some(4));
+
+        // With memory, growing is noticed too
+        let mics = config.virtual_mics.clone();
+        config.remember_channels("Aggregate", 4, &mics);
+        assert_eq!(config.channel_count_change("Aggregate", 4), None);
+        assert_eq!(config.channel_count_change("Aggregate", 6), Some(4));
+        assert_eq!(config.channel_count_change("Aggregate", 3), Some(4));
+    }
+
+    #[test]
+    fn test_last_device_is_optional() {
+        let config: Config = toml::from_str("[device]\nname = \"Mic\"\n").unwrap();
+        assert_eq!(config.device.last_device, None);
+
+        let mut config = Config::default();
+        config.device.last_device = Some("USB Mic".to_string());
+        let serialized = toml::to_string(&config).unwrap();
+        let deserialized: Config = toml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.device.last_device, Some("USB Mic".to_string()));
+    }
 }
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+
+use crate::ipc::{DriverError, DriverTimeout, StaleSocket};
+
+/// Error returned by the core APIs
+///
+/// Match on it to branch on what went wrong; everything without a variant of
+/// its own ends up in [`DuomicError::Other`]. Converts into `anyhow::Error`
+/// with `?`, and back with `From` (the driver errors in its chain are kept).
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum DuomicError {
+    /// No input device with this name (unplugged or renamed)
+    #[error("Device not found: {0}")]
+    DeviceNotFound(String),
+    /// The device delivers samples in a format duomic can't convert
+    #[error("Unsupported sample format: {0:?}")]
+    Unsupported(cpal::SampleFormat),
+    /// The driver socket is missing or couldn't be connected to
+    #[error("Driver unavailable: {0}")]
+    DriverUnavailable(String),
+    /// The driver socket is left behind by a driver that isn't running
+    #[error(transparent)]
+    StaleSocket(#[from] StaleSocket),
+    /// The driver accepted the connection but never answered
+    #[error(transparent)]
+    DriverTimeout(#[from] DriverTimeout),
+    /// The driver answered with `ERROR:...`
+    #[error(transparent)]
+    Driver(#[from] DriverError),
+    /// The shared memory file couldn't be created or mapped
+    #[error("Shared memory {}: {message}", path.display())]
+    Shm { path: PathBuf, message: String },
+    /// The config file exists but couldn't be read
+    #[error("Failed to read config from {}", path.display())]
+    ConfigRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The config file isn't valid TOML or doesn't match the schema
+    #[error("Failed to parse config from {}", path.display())]
+    ConfigParse {
+        path: PathBuf,
+        source: Box<toml::de::Error>,
+    },
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl DuomicError {
+    /// The driver error this is, if any
+    pub fn driver_error(&self) -> Option<&DriverError> {
+        match self {
+            Self::Driver(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// Whether the driver can't be reached (missing, stale or hung)
+    pub fn is_driver_down(&self) -> bool {
+        matches!(
+            self,
+            Self::DriverUnavailable(_) | Self::StaleSocket(_) | Self::DriverTimeout(_)
+        )
+    }
+
+    /// A [`DuomicError::Shm`] for `path` from the error that stopped it
+    pub(crate) fn shm(path: impl Into<PathBuf>, error: anyhow::Error) -> Self {
+        match Self::from(error) {
+            Self::Other(e) => Self::Shm {
+                path: path.into(),
+                message: format!("{:#}", e),
+            },
+            classified => classified,
+        }
+    }
+
+    /// The `DuomicError` anywhere in `error`'s chain
+    pub(crate) fn find(error: &anyhow::Error) -> Option<&DuomicError> {
+        error.chain().find_map(|e| e.downcast_ref::<DuomicError>())
+    }
+}
+
+impl From<anyhow::Error> for DuomicError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<DuomicError>() {
+            Ok(e) => return e,
+            Err(error) => error,
+        };
+        for cause in error.chain() {
+            if let Some(e) = cause.downcast_ref::<DriverError>() {
+                return Self::Driver(e.clone());
+            }
+            if let Some(e) = cause.downcast_ref::<StaleSocket>() {
+                return Self::StaleSocket(e.clone());
+            }
+            if cause.is::<DriverTimeout>() {
+                return Self::DriverTimeout(DriverTimeout);
+            }
+        }
+        Self::Other(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_from_anyhow_keeps_kind() {
+        let error = anyhow::Error::from(DuomicError::DeviceNotFound("Mic".into()))
+            .context("Failed to start");
+        assert!(matches!(
+            DuomicError::from(error),
+            DuomicError::DeviceNotFound(name) if name == "Mic"
+        ));
+
+        let error = Err::<(), _>(DriverError::parse("404:Device not found"))
+            .context("Failed to remove Mic")
+            .unwrap_err();
+        let error = DuomicError::from(error);
+        assert_eq!(error.driver_error().map(|e| e.code), Some(Some(404)));
+
+        let error = DuomicError::from(anyhow::Error::from(DriverTimeout).context("ping"));
+        assert!(error.is_driver_down());
+
+        let error = DuomicError::shm("/tmp/duomic_audio", anyhow::anyhow!("Permission denied"));
+        assert_eq!(
+            error.to_string(),
+            "Shared memory /tmp/duomic_audio: Permission denied"
+        );
+    }
+}
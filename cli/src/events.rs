@@ -0,0 +1,182 @@
+use anyhow::Result;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::audio::{default_input_device_name, list_input_devices, AudioDevice};
+use crate::ipc::DriverClient;
+
+/// Something changed around a session (shared by the TUI and library users)
+#[derive(Debug, Clone)]
+pub enum DeviceEvent {
+    /// An input device appeared
+    DeviceAdded(AudioDevice),
+    /// An input device disappeared
+    DeviceRemoved(AudioDevice),
+    /// The system default input is now the device with this name
+    DefaultInputChanged(String),
+    /// The driver started answering
+    DriverUp,
+    /// The driver stopped answering (socket gone or PING failed)
+    DriverDown,
+    /// A device channel started clipping (see [`crate::RunningSession::send_events_to`])
+    Clip { channel: usize },
+}
+
+/// Watches input devices and the driver on a background thread
+///
+/// CoreAudio hotplug notifications aren't exposed by cpal, so this polls
+/// every `interval` and reports differences. The first poll only records the
+/// current state; events describe changes after that.
+///
+/// ```no_run
+/// let watcher = duomic::DeviceWatcher::spawn(std::time::Duration::from_secs(2))?;
+/// for event in watcher.events() {
+///     println!("{:?}", event);
+/// }
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct DeviceWatcher {
+    events: Receiver<DeviceEvent>,
+    sender: Sender<DeviceEvent>,
+    running: Arc<AtomicBool>,
+    driver_up: Arc<AtomicBool>,
+}
+
+impl DeviceWatcher {
+    /// Start polling (the thread stops when the watcher is dropped)
+    pub fn spawn(interval: Duration) -> Result<Self> {
+        let (sender, events) = unbounded();
+        let running = Arc::new(AtomicBool::new(true));
+        let driver_up = Arc::new(AtomicBool::new(false));
+
+        let tx = sender.clone();
+        let keep_running = running.clone();
+        let answered = driver_up.clone();
+        thread::Builder::new()
+            .name("duomic-watch".into())
+            .spawn(move || {
+                let mut devices = list_input_devices().unwrap_or_default();
+                let mut default_input = default_input_device_name().ok();
+                let mut driver_up = driver_alive();
+                answered.store(driver_up, Ordering::Relaxed);
+
+                while keep_running.load(Ordering::Relaxed) {
+                    thread::sleep(interval);
+
+                    // A failed enumeration (e.g. coreaudiod restarting) isn't "all removed"
+                    if let Ok(current) = list_input_devices() {
+                        for event in device_changes(&devices, &current) {
+                            if tx.send(event).is_err() {
+                                return;
+                            }
+                        }
+                        devices = current;
+                    }
+
+                    // Reported after the device list, so a new default is already listed
+                    if let Ok(name) = default_input_device_name() {
+                        if default_input.as_ref() != Some(&name) {
+                            let changed = default_input.replace(name.clone()).is_some();
+                            if changed && tx.send(DeviceEvent::DefaultInputChanged(name)).is_err() {
+                                return;
+                            }
+                        }
+                    }
+
+                    let up = driver_alive();
+                    answered.store(up, Ordering::Relaxed);
+                    if up != driver_up {
+                        let event = if up {
+                            DeviceEvent::DriverUp
+                        } else {
+                            DeviceEvent::DriverDown
+                        };
+                        if tx.send(event).is_err() {
+                            return;
+                        }
+                        driver_up = up;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            events,
+            sender,
+            running,
+            driver_up,
+        })
+    }
+
+    /// Whether the driver answered the last PING (checked every `interval`)
+    pub fn driver_up(&self) -> bool {
+        self.driver_up.load(Ordering::Relaxed)
+    }
+
+    /// Events in the order they were detected
+    pub fn events(&self) -> &Receiver<DeviceEvent> {
+        &self.events
+    }
+
+    /// Sender into the same channel, e.g. for a session's clip events
+    pub fn sender(&self) -> Sender<DeviceEvent> {
+        self.sender.clone()
+    }
+}
+
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+fn driver_alive() -> bool {
+    DriverClient::is_driver_available() && DriverClient::new().ping().unwrap_or(false)
+}
+
+/// Added and removed devices between two listings (matched by name and index)
+fn device_changes(before: &[AudioDevice], after: &[AudioDevice]) -> Vec<DeviceEvent> {
+    let same = |a: &AudioDevice, b: &AudioDevice| a.name == b.name && a.index == b.index;
+    let removed = before
+        .iter()
+        .filter(|old| !after.iter().any(|new| same(old, new)))
+        .map(|d| DeviceEvent::DeviceRemoved(d.clone()));
+    let added = after
+        .iter()
+        .filter(|new| !before.iter().any(|old| same(old, new)))
+        .map(|d| DeviceEvent::DeviceAdded(d.clone()));
+    removed.chain(added).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(name: &str, index: usize) -> AudioDevice {
+        AudioDevice {
+            name: name.to_string(),
+            channels: 2,
+            default_sample_rate: 48000,
+            index,
+        }
+    }
+
+    #[test]
+    fn test_device_changes() {
+        let before = [device("Built-in", 0), device("USB Mic", 1)];
+        let after = [device("Built-in", 0), device("Rode", 1)];
+
+        let names: Vec<String> = device_changes(&before, &after)
+            .iter()
+            .map(|e| match e {
+                DeviceEvent::DeviceAdded(d) => format!("+{}", d.name),
+                DeviceEvent::DeviceRemoved(d) => format!("-{}", d.name),
+                other => format!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(names, ["-USB Mic", "+Rode"]);
+        assert!(device_changes(&after, &after).is_empty());
+    }
+}
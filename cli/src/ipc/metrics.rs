@@ -0,0 +1,290 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long a scraper gets to send its request line
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Levels of one virtual mic for `/metrics`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MicMetrics {
+    pub name: String,
+    pub channel: u32,
+    /// Loudest peak (linear) since the last scrape
+    pub peak: f32,
+    /// Current RMS (linear)
+    pub rms: f32,
+    /// Times the mic started clipping
+    pub clips: u64,
+}
+
+/// Everything `/metrics` exposes, refreshed by the capture loop
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metrics {
+    pub mics: Vec<MicMetrics>,
+    pub capturing: bool,
+    pub driver_connected: bool,
+    /// Ring buffer fill, 0.0 to 1.0
+    pub buffer_fill: f32,
+    pub clipped_samples: u64,
+    pub dropped_peaks: u64,
+    pub write_errors: u64,
+    pub xruns: u64,
+    pub shm_overruns: u64,
+}
+
+impl Metrics {
+    /// Prometheus text exposition format (version 0.0.4)
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        let mut family = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for (labels, value) in samples {
+                let _ = writeln!(out, "{}{} {}", name, labels, value);
+            }
+        };
+
+        let per_mic = |value: fn(&MicMetrics) -> String| {
+            self.mics
+                .iter()
+                .map(|m| {
+                    let labels = format!(
+                        "{{mic=\"{}\",channel=\"{}\"}}",
+                        escape_label(&m.name),
+                        m.channel
+                    );
+                    (labels, value(m))
+                })
+                .collect()
+        };
+        let single = |value: String| vec![(String::new(), value)];
+
+        family(
+            "duomic_mic_peak",
+            "gauge",
+            "Peak level per mic since the last scrape (linear, 0 to 1)",
+            per_mic(|m| m.peak.to_string()),
+        );
+        family(
+            "duomic_mic_rms",
+            "gauge",
+            "RMS level per mic (linear, 0 to 1)",
+            per_mic(|m| m.rms.to_string()),
+        );
+        family(
+            "duomic_mic_clips_total",
+            "counter",
+            "Times each mic started clipping",
+            per_mic(|m| m.clips.to_string()),
+        );
+        family(
+            "duomic_capturing",
+            "gauge",
+            "Whether audio is being captured (1) or not (0)",
+            single(u8::from(self.capturing).to_string()),
+        );
+        family(
+            "duomic_driver_connected",
+            "gauge",
+            "Whether the driver socket accepts connections and answered the last PING (1) or not (0)",
+            single(u8::from(self.driver_connected).to_string()),
+        );
+        family(
+            "duomic_buffer_fill",
+            "gauge",
+            "Shared ring buffer fill (0 to 1)",
+            single(self.buffer_fill.to_string()),
+        );
+        family(
+            "duomic_clipped_samples_total",
+            "counter",
+            "Samples at or above the clip threshold",
+            single(self.clipped_samples.to_string()),
+        );
+        family(
+            "duomic_dropped_peaks_total",
+            "counter",
+            "Peak updates dropped because the UI fell behind",
+            single(self.dropped_peaks.to_string()),
+        );
+        family(
+            "duomic_write_errors_total",
+            "counter",
+            "Failed writes to shared memory",
+            single(self.write_errors.to_string()),
+        );
+        family(
+            "duomic_xruns_total",
+            "counter",
+            "Transient audio stream errors (over/underruns)",
+            single(self.xruns.to_string()),
+        );
+        family(
+            "duomic_shm_overruns_total",
+            "counter",
+            "Times the driver fell a full buffer behind and skipped audio",
+            single(self.shm_overruns.to_string()),
+        );
+
+        out
+    }
+}
+
+/// Escape a label value (backslash, double quote and newline)
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Tiny HTTP server answering `GET /metrics` for `[metrics] listen`
+///
+/// Requests are answered one at a time on a background thread, which runs
+/// until the process exits.
+pub struct MetricsServer {
+    metrics: Arc<Mutex<Metrics>>,
+    addr: SocketAddr,
+}
+
+impl MetricsServer {
+    /// Listen on `addr`, e.g. "127.0.0.1:9184"
+    pub fn bind(addr: &str) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .with_context(|| format!("Failed to listen for metrics on {}", addr))?;
+        let addr = listener.local_addr()?;
+        let metrics = Arc::new(Mutex::new(Metrics::default()));
+
+        let shared = metrics.clone();
+        thread::Builder::new()
+            .name("duomic-metrics".into())
+            .spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    if let Err(e) = serve(stream, &shared) {
+                        tracing::debug!("Metrics request failed: {}", e);
+                    }
+                }
+            })?;
+
+        tracing::info!("Serving metrics on http://{}/metrics", addr);
+        Ok(Self { metrics, addr })
+    }
+
+    /// Address actually bound (useful with port 0)
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Replace the metrics, keeping the loudest peaks not yet scraped
+    pub fn update(&self, mut metrics: Metrics) {
+        let Ok(mut current) = self.metrics.lock() else {
+            return;
+        };
+        for mic in &mut metrics.mics {
+            if let Some(previous) = current
+                .mics
+                .iter()
+                .find(|m| m.name == mic.name && m.channel == mic.channel)
+            {
+                mic.peak = mic.peak.max(previous.peak);
+            }
+        }
+        *current = metrics;
+    }
+}
+
+/// Answer one request; peaks restart from zero once scraped
+fn serve(mut stream: TcpStream, metrics: &Mutex<Metrics>) -> Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers so closing doesn't reset the connection
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    let (status, body) = if path == "/metrics" {
+        let mut metrics = metrics
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Metrics lock poisoned"))?;
+        let body = metrics.render();
+        metrics.mics.iter_mut().for_each(|m| m.peak = 0.0);
+        ("200 OK", body)
+    } else {
+        ("404 Not Found", "Not found, try /metrics\n".to_string())
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn scrape(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_serves_metrics_and_resets_peaks() {
+        let server = MetricsServer::bind("127.0.0.1:0").unwrap();
+        let mic = |peak| MicMetrics {
+            name: "Host \"A\"".to_string(),
+            channel: 1,
+            peak,
+            rms: 0.25,
+            clips: 2,
+        };
+        server.update(Metrics {
+            mics: vec![mic(0.5)],
+            capturing: true,
+            driver_connected: true,
+            buffer_fill: 0.125,
+            ..Metrics::default()
+        });
+        // A quieter update before the scrape keeps the louder peak
+        server.update(Metrics {
+            mics: vec![mic(0.1)],
+            capturing: true,
+            driver_connected: true,
+            buffer_fill: 0.125,
+            ..Metrics::default()
+        });
+
+        let response = scrape(server.local_addr(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("# TYPE duomic_mic_peak gauge"));
+        assert!(response.contains("duomic_mic_peak{mic=\"Host \\\"A\\\"\",channel=\"1\"} 0.5\n"));
+        assert!(
+            response.contains("duomic_mic_clips_total{mic=\"Host \\\"A\\\"\",channel=\"1\"} 2\n")
+        );
+        assert!(response.contains("duomic_driver_connected 1\n"));
+        assert!(response.contains("duomic_buffer_fill 0.125\n"));
+
+        let response = scrape(server.local_addr(), "/metrics");
+        assert!(response.contains("duomic_mic_peak{mic=\"Host \\\"A\\\"\",channel=\"1\"} 0\n"));
+
+        assert!(scrape(server.local_addr(), "/").starts_with("HTTP/1.1 404"));
+    }
+}
@@ -1,8 +1,18 @@
 // IPC library - some methods are prepared for future use
 #![allow(dead_code)]
 
+mod metrics;
+mod paths;
+mod rpc;
 mod shm;
 mod socket;
+mod stats;
+mod worker;
 
+pub use metrics::*;
+pub use paths::*;
+pub use rpc::*;
 pub use shm::*;
 pub use socket::*;
+pub use stats::*;
+pub use worker::*;
@@ -0,0 +1,96 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Directory holding the socket, shared memory and stats files
+///
+/// The driver reads the same variable, so both sides must see the same value.
+/// The system `coreaudiod` doesn't inherit it from a user's shell, so this is
+/// only useful with a driver host started by hand (driver development).
+pub const RUNTIME_DIR_ENV: &str = "DUOMIC_RUNTIME_DIR";
+
+/// Default runtime directory (what drivers without the variable use)
+const DEFAULT_RUNTIME_DIR: &str = "/tmp";
+
+/// Where the CLI and driver meet
+///
+/// Files live directly in the runtime directory under fixed names
+/// (`duomic.sock`, `duomic_audio`, `duomic_stats`). The installed driver runs
+/// once for the whole machine and always uses `/tmp`, so several users can't
+/// run duomic side by side; [`RUNTIME_DIR_ENV`] only moves the files for a
+/// driver host that was started with the same value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimePaths {
+    pub dir: PathBuf,
+    pub socket: PathBuf,
+    pub shm: PathBuf,
+    pub stats: PathBuf,
+}
+
+impl RuntimePaths {
+    /// Paths inside `dir`
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        Self {
+            socket: dir.join("duomic.sock"),
+            shm: dir.join("duomic_audio"),
+            stats: dir.join("duomic_stats"),
+            dir,
+        }
+    }
+
+    /// Paths from the `DUOMIC_RUNTIME_DIR` value (empty or relative values are ignored)
+    fn resolve(runtime_dir: Option<OsString>) -> Self {
+        let dir = runtime_dir
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_RUNTIME_DIR));
+        Self::in_dir(dir)
+    }
+
+    /// Shared memory of capture `source`: `shm` for 0, `duomic_audio_N` for extra devices
+    pub fn shm_for(&self, source: u32) -> PathBuf {
+        match source {
+            0 => self.shm.clone(),
+            n => self.dir.join(format!("duomic_audio_{}", n)),
+        }
+    }
+
+    /// Whether the runtime directory is the shared default
+    pub fn is_default(&self) -> bool {
+        self.dir == Path::new(DEFAULT_RUNTIME_DIR)
+    }
+}
+
+/// Runtime paths for this process, resolved from the environment on first use
+pub fn runtime_paths() -> &'static RuntimePaths {
+    static PATHS: OnceLock<RuntimePaths> = OnceLock::new();
+    PATHS.get_or_init(|| RuntimePaths::resolve(std::env::var_os(RUNTIME_DIR_ENV)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_paths_resolve() {
+        let default = RuntimePaths::resolve(None);
+        assert!(default.is_default());
+        assert_eq!(default.socket, PathBuf::from("/tmp/duomic.sock"));
+        assert_eq!(default.shm, PathBuf::from("/tmp/duomic_audio"));
+
+        let custom = RuntimePaths::resolve(Some("/tmp/duomic-501".into()));
+        assert!(!custom.is_default());
+        assert_eq!(custom.socket, PathBuf::from("/tmp/duomic-501/duomic.sock"));
+        assert_eq!(custom.stats, PathBuf::from("/tmp/duomic-501/duomic_stats"));
+        assert_eq!(custom.shm_for(0), custom.shm);
+        assert_eq!(
+            custom.shm_for(2),
+            PathBuf::from("/tmp/duomic-501/duomic_audio_2")
+        );
+
+        // Relative or empty values fall back to the default
+        assert!(RuntimePaths::resolve(Some("run".into())).is_default());
+        assert!(RuntimePaths::resolve(Some("".into())).is_default());
+    }
+}
@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::{DeviceInfo, DriverError};
+
+/// `CAPS` feature for the JSON protocol
+pub const JSON_FEATURE: &str = "json";
+
+/// One JSON request line, for drivers advertising `json` in `CAPS`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+pub enum Request {
+    Add {
+        name: String,
+        channel: u32,
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        metadata: BTreeMap<String, String>,
+    },
+    Remove {
+        name: String,
+    },
+    List,
+    Ping,
+}
+
+impl Request {
+    /// `add` request for `device`, with its color and capture source as metadata
+    pub fn add(device: &DeviceInfo) -> Self {
+        let mut metadata = BTreeMap::new();
+        if let Some(color) = &device.color {
+            metadata.insert("color".to_string(), color.clone());
+        }
+        if device.source > 0 {
+            metadata.insert("source".to_string(), device.source.to_string());
+        }
+        Request::Add {
+            name: device.name.clone(),
+            channel: device.channel,
+            metadata,
+        }
+    }
+
+    /// The request as one line (without the newline)
+    pub fn to_line(&self) -> Result<String> {
+        serde_json::to_string(self).context("Failed to encode driver request")
+    }
+}
+
+/// One JSON response line
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub code: Option<u32>,
+    /// Only in replies to `list`
+    #[serde(default)]
+    pub devices: Option<Vec<RpcDevice>>,
+}
+
+/// A virtual device in a `list` reply
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RpcDevice {
+    pub name: String,
+    pub channel: u32,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl From<RpcDevice> for DeviceInfo {
+    fn from(device: RpcDevice) -> Self {
+        let mut metadata = device.metadata;
+        DeviceInfo {
+            name: device.name,
+            channel: device.channel,
+            color: metadata.remove("color"),
+            source: metadata
+                .remove("source")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+        }
+    }
+}
+
+impl Response {
+    /// Parse a response line; `ok: false` becomes a [`DriverError`]
+    pub fn parse(line: &str) -> Result<Self> {
+        let response: Response = serde_json::from_str(line.trim())
+            .with_context(|| format!("Invalid JSON from driver: {}", line.trim()))?;
+        if response.ok {
+            Ok(response)
+        } else {
+            Err(DriverError {
+                code: response.code,
+                message: response
+                    .error
+                    .unwrap_or_else(|| "Unknown error".to_string()),
+            }
+            .into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::DriverErrorKind;
+
+    #[test]
+    fn test_requests_and_responses() {
+        let device = DeviceInfo {
+            name: "Host \"1\"".to_string(),
+            channel: 1,
+            color: Some("red".to_string()),
+            source: 2,
+        };
+        assert_eq!(
+            Request::add(&device).to_line().unwrap(),
+            r#"{"method":"add","name":"Host \"1\"","channel":1,"metadata":{"color":"red","source":"2"}}"#
+        );
+        assert_eq!(Request::List.to_line().unwrap(), r#"{"method":"list"}"#);
+
+        let response = Response::parse(
+            r#"{"ok":true,"devices":[{"name":"Host","channel":0,"metadata":{"color":"red","x":"y"}},{"name":"Guest","channel":1}]}"#,
+        )
+        .unwrap();
+        let devices: Vec<DeviceInfo> = response
+            .devices
+            .unwrap()
+            .into_iter()
+            .map(DeviceInfo::from)
+            .collect();
+        assert_eq!(devices[0].color.as_deref(), Some("red"));
+        assert_eq!(
+            (devices[1].name.as_str(), devices[1].color.as_ref()),
+            ("Guest", None)
+        );
+
+        let error = Response::parse(r#"{"ok":false,"error":"Device already exists","code":409}"#)
+            .unwrap_err();
+        assert_eq!(
+            DriverError::kind_of(&error),
+            Some(DriverErrorKind::AlreadyExists)
+        );
+        assert!(Response::parse("OK").is_err());
+    }
+}
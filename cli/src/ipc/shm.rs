@@ -1,11 +1,79 @@
-use anyhow::{Context, Result};
-use memmap2::MmapMut;
-use std::fs::OpenOptions;
+use anyhow::{bail, Context, Result};
+use memmap2::{Mmap, MmapMut};
+use std::fs::{self, OpenOptions};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
 use std::sync::atomic::{fence, Ordering};
 
-const SHM_PATH: &str = "/tmp/duomic_audio";
-const RING_BUFFER_FRAMES: usize = 8192;
-const HEADER_SIZE: usize = 16;
+use super::runtime_paths;
+use crate::DuomicError;
+
+/// Ring buffer size in frames (the driver's `RING_BUFFER_FRAMES` must match)
+///
+/// Code holding a buffer should ask [`SharedAudioBuffer::capacity_frames`].
+pub const RING_BUFFER_FRAMES: usize = 8192;
+const HEADER_SIZE: usize = 20;
+
+/// Memory mapping of the shared buffer file
+enum Mapping {
+    /// Owned by the capturing CLI
+    ReadWrite(MmapMut),
+    /// Observer (e.g. `duomic monitor`), never modifies the buffer
+    ReadOnly(Mmap),
+}
+
+impl Mapping {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Mapping::ReadWrite(mmap) => mmap.as_ref(),
+            Mapping::ReadOnly(mmap) => mmap.as_ref(),
+        }
+    }
+
+    fn bytes_mut(&mut self) -> Option<&mut [u8]> {
+        match self {
+            Mapping::ReadWrite(mmap) => Some(mmap.as_mut()),
+            Mapping::ReadOnly(_) => None,
+        }
+    }
+}
+
+/// User coreaudiod, and so the driver, runs as
+const DRIVER_USER: &str = "_coreaudiod";
+
+/// Let the driver write `readPos` without making the file world-writable
+///
+/// Mode 0644 (readable so the driver can still map it read-only if the ACL
+/// fails), plus a macOS ACL entry giving [`DRIVER_USER`] write access.
+fn grant_driver_access(path: &Path) {
+    if let Err(e) = fs::set_permissions(path, fs::Permissions::from_mode(0o644)) {
+        tracing::debug!("Failed to set shared memory permissions: {}", e);
+    }
+    if !cfg!(target_os = "macos") {
+        return;
+    }
+    let entry = format!("user:{} allow write", DRIVER_USER);
+    // Drop the entry a previous run added so they don't pile up
+    let _ = Command::new("/bin/chmod")
+        .arg("-a")
+        .arg(&entry)
+        .arg(path)
+        .output();
+    match Command::new("/bin/chmod")
+        .arg("+a")
+        .arg(&entry)
+        .arg(path)
+        .output()
+    {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => tracing::debug!(
+            "Failed to let the driver write shared memory: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => tracing::debug!("Failed to run chmod: {}", e),
+    }
+}
 
 /// Shared memory audio buffer for IPC with the driver
 ///
@@ -14,16 +82,44 @@ const HEADER_SIZE: usize = 16;
 /// - Bytes 4-7:   channelCount (uint32) - Number of channels
 /// - Bytes 8-11:  sampleRate (uint32) - Sample rate in Hz
 /// - Bytes 12-15: active (uint32) - CLI active flag (0/1)
-/// - Bytes 16+:   Audio data (interleaved float samples)
+/// - Bytes 16-19: readPos (uint32) - Driver read position (0 until it reads)
+/// - Bytes 20+:   Audio data (interleaved float samples)
 pub struct SharedAudioBuffer {
-    mmap: MmapMut,
+    mmap: Mapping,
     channel_count: u32,
     sample_rate: u32,
 }
 
 impl SharedAudioBuffer {
     /// Create or open the shared memory buffer
-    pub fn open(channel_count: u32, sample_rate: u32) -> Result<Self> {
+    pub fn open(channel_count: u32, sample_rate: u32) -> Result<Self, DuomicError> {
+        Self::open_source(0, channel_count, sample_rate)
+    }
+
+    /// Create or open the buffer of capture `source` (0 is the one `open` uses)
+    pub fn open_source(
+        source: u32,
+        channel_count: u32,
+        sample_rate: u32,
+    ) -> Result<Self, DuomicError> {
+        let paths = runtime_paths();
+        let path = paths.shm_for(source);
+        if !paths.is_default() {
+            // Readable (not writable) by others so the driver can reach it
+            fs::create_dir_all(&paths.dir)
+                .with_context(|| {
+                    format!("Failed to create runtime directory {}", paths.dir.display())
+                })
+                .map_err(|e| DuomicError::shm(&path, e))?;
+            let _ = fs::set_permissions(&paths.dir, fs::Permissions::from_mode(0o755));
+        }
+        Self::open_path(&path, channel_count, sample_rate).map_err(|e| DuomicError::shm(&path, e))
+    }
+
+    pub(crate) fn open_path(path: &Path, channel_count: u32, sample_rate: u32) -> Result<Self> {
+        if channel_count == 0 {
+            bail!("Shared memory needs at least one channel");
+        }
         let data_size = RING_BUFFER_FRAMES * channel_count as usize * std::mem::size_of::<f32>();
         let total_size = HEADER_SIZE + data_size;
 
@@ -33,17 +129,28 @@ impl SharedAudioBuffer {
             .write(true)
             .create(true)
             .truncate(false)
-            .open(SHM_PATH)
+            .open(path)
             .context("Failed to open shared memory file")?;
 
         // Set file size
         file.set_len(total_size as u64)
             .context("Failed to set shared memory size")?;
 
+        grant_driver_access(path);
+
         // Memory map the file
         let mut mmap =
             unsafe { MmapMut::map_mut(&file).context("Failed to memory map shared memory")? };
 
+        // Someone may have truncated the file between set_len and map
+        if mmap.len() < total_size {
+            bail!(
+                "Shared memory is too small ({} bytes, need {})",
+                mmap.len(),
+                total_size
+            );
+        }
+
         // Initialize header
         let header = mmap.as_mut();
 
@@ -56,6 +163,9 @@ impl SharedAudioBuffer {
         // Set active flag (bytes 12-15)
         header[12..16].copy_from_slice(&1u32.to_ne_bytes());
 
+        // Clear readPos left over from a previous session (bytes 16-19)
+        header[16..20].copy_from_slice(&0u32.to_ne_bytes());
+
         tracing::debug!(
             "Opened shared memory: {} channels, {} Hz, {} frames",
             channel_count,
@@ -64,27 +174,91 @@ impl SharedAudioBuffer {
         );
 
         Ok(Self {
-            mmap,
+            mmap: Mapping::ReadWrite(mmap),
+            channel_count,
+            sample_rate,
+        })
+    }
+
+    /// Open an existing shared memory buffer for reading only
+    ///
+    /// Channel count and sample rate come from the header written by the capturing CLI.
+    pub fn open_readonly() -> Result<Self, DuomicError> {
+        let path = &runtime_paths().shm;
+        Self::open_readonly_path(path).map_err(|e| DuomicError::shm(path, e))
+    }
+
+    pub(crate) fn open_readonly_path(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .open(path)
+            .with_context(|| format!("Failed to open shared memory file {:?}", path))?;
+
+        let mmap = unsafe { Mmap::map(&file).context("Failed to memory map shared memory")? };
+
+        if mmap.len() < HEADER_SIZE {
+            bail!("Shared memory is too small ({} bytes)", mmap.len());
+        }
+
+        let header = mmap.as_ref();
+        let channel_count = u32::from_ne_bytes([header[4], header[5], header[6], header[7]]);
+        let sample_rate = u32::from_ne_bytes([header[8], header[9], header[10], header[11]]);
+
+        let expected =
+            HEADER_SIZE + RING_BUFFER_FRAMES * channel_count as usize * std::mem::size_of::<f32>();
+        if channel_count == 0 || mmap.len() < expected {
+            bail!(
+                "Shared memory header doesn't match its size ({} channels, {} bytes)",
+                channel_count,
+                mmap.len()
+            );
+        }
+
+        Ok(Self {
+            mmap: Mapping::ReadOnly(mmap),
             channel_count,
             sample_rate,
         })
     }
 
+    /// Header field at `offset` (0 if the mapping is too short to hold it)
+    fn header_u32(&self, offset: usize) -> u32 {
+        self.mmap
+            .bytes()
+            .get(offset..offset + 4)
+            .map_or(0, |b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Write a header field (no-op when read-only or too short)
+    fn set_header_u32(&mut self, offset: usize, value: u32) {
+        if let Some(field) = self
+            .mmap
+            .bytes_mut()
+            .and_then(|header| header.get_mut(offset..offset + 4))
+        {
+            field.copy_from_slice(&value.to_ne_bytes());
+        }
+    }
+
     /// Get the write position
     pub fn write_pos(&self) -> u32 {
-        let header = self.mmap.as_ref();
-        u32::from_ne_bytes([header[0], header[1], header[2], header[3]])
+        self.header_u32(0)
+    }
+
+    /// Get the driver's read position (0 if the driver hasn't read yet)
+    pub fn read_pos(&self) -> u32 {
+        self.header_u32(16)
     }
 
     /// Set the write position
     fn set_write_pos(&mut self, pos: u32) {
-        let header = self.mmap.as_mut();
-        header[0..4].copy_from_slice(&pos.to_ne_bytes());
+        self.set_header_u32(0, pos);
     }
 
     /// Write audio samples to the ring buffer
     ///
-    /// `samples` should be interleaved: [ch0, ch1, ch0, ch1, ...]
+    /// `samples` should be interleaved: [ch0, ch1, ch0, ch1, ...] and hold whole
+    /// frames only; a trailing partial frame is ignored (callers carry it over)
     ///
     /// IMPORTANT: write_pos is monotonically increasing (wraps at u32::MAX, not at RING_BUFFER_FRAMES)
     /// The driver calculates available samples as: writePos - readPos (unsigned arithmetic)
@@ -102,16 +276,27 @@ impl SharedAudioBuffer {
         let data_offset = HEADER_SIZE;
         let sample_size = std::mem::size_of::<f32>();
         let frame_size = self.channel_count as usize * sample_size;
+        let channel_count = self.channel_count as usize;
 
-        let buffer = self.mmap.as_mut();
+        let buffer = self
+            .mmap
+            .bytes_mut()
+            .context("Shared memory is opened read-only")?;
 
         for frame_idx in 0..frames {
-            let src_offset = frame_idx * self.channel_count as usize;
+            let src_offset = frame_idx * channel_count;
             // Use modulo ONLY for buffer indexing, not for position tracking
             let buffer_idx = (write_pos as usize) % buffer_frames;
             let dst_offset = data_offset + buffer_idx * frame_size;
+            if dst_offset + frame_size > buffer.len() {
+                bail!(
+                    "Shared memory is too small for {} channels ({} bytes)",
+                    channel_count,
+                    buffer.len()
+                );
+            }
 
-            for ch in 0..self.channel_count as usize {
+            for ch in 0..channel_count {
                 let sample = samples[src_offset + ch];
                 let bytes = sample.to_ne_bytes();
                 let byte_offset = dst_offset + ch * sample_size;
@@ -131,11 +316,57 @@ impl SharedAudioBuffer {
         Ok(())
     }
 
-    /// Set the active flag
+    /// Read interleaved frames starting at `read_pos` into `out`
+    ///
+    /// Mirrors the driver: `read_pos` is monotonic like `write_pos`, and available
+    /// frames are `write_pos - read_pos` in wrapping arithmetic. Reads at most
+    /// `out.len() / channel_count` frames and returns how many were copied; the
+    /// caller advances with `read_pos.wrapping_add(frames)`. If the reader falls
+    /// more than `capacity_frames()` behind, the oldest frames have already been
+    /// overwritten and the caller should resync.
+    pub fn read_samples(&self, read_pos: u32, out: &mut [f32]) -> usize {
+        let write_pos = self.write_pos();
+
+        // Pairs with the Release fence in write_samples: data written before
+        // write_pos was published is visible once we've observed write_pos
+        fence(Ordering::Acquire);
+
+        let channel_count = self.channel_count as usize;
+        let available = write_pos.wrapping_sub(read_pos) as usize;
+        let frames = available
+            .min(out.len() / channel_count)
+            .min(RING_BUFFER_FRAMES);
+
+        let sample_size = std::mem::size_of::<f32>();
+        let frame_size = channel_count * sample_size;
+        let buffer = self.mmap.bytes();
+
+        for frame_idx in 0..frames {
+            let pos = read_pos.wrapping_add(frame_idx as u32);
+            let src_offset = HEADER_SIZE + (pos as usize % RING_BUFFER_FRAMES) * frame_size;
+            if src_offset + frame_size > buffer.len() {
+                return frame_idx;
+            }
+
+            for ch in 0..channel_count {
+                let byte_offset = src_offset + ch * sample_size;
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(&buffer[byte_offset..byte_offset + sample_size]);
+                out[frame_idx * channel_count + ch] = f32::from_ne_bytes(bytes);
+            }
+        }
+
+        frames
+    }
+
+    /// Set the active flag (no-op when opened read-only)
     pub fn set_active(&mut self, active: bool) {
-        let header = self.mmap.as_mut();
-        let value: u32 = if active { 1 } else { 0 };
-        header[12..16].copy_from_slice(&value.to_ne_bytes());
+        self.set_header_u32(12, active as u32);
+    }
+
+    /// Whether the capturing CLI has marked the buffer active
+    pub fn is_active(&self) -> bool {
+        self.header_u32(12) != 0
     }
 
     /// Get channel count
@@ -148,17 +379,81 @@ impl SharedAudioBuffer {
         self.sample_rate
     }
 
+    /// Publish a new sample rate (e.g. the device changed rate on restart)
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.set_header_u32(8, sample_rate);
+        self.sample_rate = sample_rate;
+    }
+
     /// Get ring buffer capacity in frames
     pub fn capacity_frames(&self) -> usize {
         RING_BUFFER_FRAMES
     }
 }
 
+/// Frames written but not yet read by the driver, at most `capacity`
+///
+/// `None` when `read_pos` is 0, i.e. the driver never published a read
+/// position (older drivers, or no app has opened a virtual mic yet).
+pub fn buffer_fill(write_pos: u32, read_pos: u32, capacity: usize) -> Option<usize> {
+    if read_pos == 0 {
+        return None;
+    }
+    Some((write_pos.wrapping_sub(read_pos) as usize).min(capacity))
+}
+
+/// Lag at which the driver drops unread frames and resyncs
+/// (`RING_BUFFER_FRAMES - 512` in Driver.cpp)
+pub(crate) const DRIVER_RESYNC_LAG: usize = RING_BUFFER_FRAMES - 512;
+
+/// Watches the driver's read position for audio lost to the writer
+///
+/// A driver still reading but more than [`DRIVER_RESYNC_LAG`] frames behind
+/// skips ahead on its next read, so an app hears a gap: that's an overrun.
+/// A driver that stopped reading (no app has the mic open) lags as well, but
+/// its audio is simply discarded, and catching up when an app opens the mic
+/// again isn't a gap anyone hears.
+#[derive(Debug, Default)]
+pub(crate) struct ReaderWatch {
+    last_read: u32,
+    /// Lagging while it was still reading: the next move is a skip
+    skipping: bool,
+    /// Lagging without reading
+    stalled: bool,
+}
+
+impl ReaderWatch {
+    /// Check the positions after a write; true when the driver just skipped
+    pub(crate) fn observe(&mut self, write_pos: u32, read_pos: u32) -> bool {
+        // No read position published (older driver, or nothing read yet)
+        if read_pos == 0 {
+            return false;
+        }
+        let moved = self.last_read != 0 && read_pos != self.last_read;
+        let overrun = moved && self.skipping;
+        self.last_read = read_pos;
+
+        let lagging = write_pos.wrapping_sub(read_pos) as usize > DRIVER_RESYNC_LAG;
+        if moved || !lagging {
+            self.skipping = lagging && moved;
+        }
+        self.stalled = lagging && !moved;
+        overrun
+    }
+
+    /// Whether the driver stopped reading and unread frames are being overwritten
+    pub(crate) fn lapped(&self) -> bool {
+        self.stalled && !self.skipping
+    }
+}
+
 impl Drop for SharedAudioBuffer {
     fn drop(&mut self) {
-        // Mark as inactive when dropped
-        self.set_active(false);
-        tracing::debug!("Shared audio buffer closed");
+        // Mark as inactive when dropped (only the writer owns the flag)
+        if let Mapping::ReadWrite(_) = self.mmap {
+            self.set_active(false);
+            tracing::debug!("Shared audio buffer closed");
+        }
     }
 }
 
@@ -166,6 +461,25 @@ impl Drop for SharedAudioBuffer {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_ring_buffer_size_matches_driver() {
+        let driver = include_str!("../../../Driver/duomicDriver/Driver.cpp");
+        let expected = format!(
+            "constexpr size_t RING_BUFFER_FRAMES = {};",
+            RING_BUFFER_FRAMES
+        );
+        assert!(
+            driver.contains(&expected),
+            "Driver.cpp must declare `{}`",
+            expected
+        );
+        assert_eq!(DRIVER_RESYNC_LAG, RING_BUFFER_FRAMES - 512);
+        assert!(
+            driver.contains("available > RING_BUFFER_FRAMES - 512"),
+            "DRIVER_RESYNC_LAG must match the driver's resync check"
+        );
+    }
+
     #[test]
     fn test_shared_buffer_creation() {
         // Skip if we can't create temp files
@@ -180,4 +494,111 @@ mod tests {
             assert_eq!(buffer.sample_rate(), 48000);
         }
     }
+
+    #[test]
+    fn test_buffer_fill() {
+        assert_eq!(buffer_fill(5000, 0, 8192), None);
+        assert_eq!(buffer_fill(5000, 3976, 8192), Some(1024));
+        // Across the u32 wrap
+        assert_eq!(buffer_fill(100, u32::MAX - 99, 8192), Some(200));
+        // Reader stalled for longer than the ring holds
+        assert_eq!(buffer_fill(100_000, 1, 8192), Some(8192));
+    }
+
+    #[test]
+    fn test_reader_watch() {
+        let mut watch = ReaderWatch::default();
+        // Never read: nothing to report
+        assert!(!watch.observe(100_000, 0));
+        assert!(!watch.lapped());
+
+        // Keeping up
+        assert!(!watch.observe(5000, 4000));
+        assert!(!watch.observe(6000, 5000));
+
+        // No app reading: the writer runs a ring ahead, nothing is heard
+        assert!(!watch.observe(14_000, 5000));
+        assert!(watch.lapped());
+        assert!(!watch.observe(20_000, 5000));
+        // An app opens the mic again and the driver catches up: not a gap
+        assert!(!watch.observe(21_000, 20_000));
+        assert!(!watch.lapped());
+
+        // Still reading, but falling past the driver's resync lag
+        assert!(!watch.observe(26_000, 22_000));
+        assert!(!watch.observe(30_700, 23_000));
+        assert!(!watch.lapped());
+        // Its next read skips ahead: one overrun
+        assert!(watch.observe(31_000, 30_500));
+        assert!(!watch.observe(32_000, 31_500));
+    }
+
+    #[test]
+    fn test_read_back_across_position_wrap() {
+        let path = std::env::temp_dir().join(format!("duomic_shm_test_{}", std::process::id()));
+        let mut writer = SharedAudioBuffer::open_path(&path, 2, 48000)
+            .expect("temp dir should hold a shared memory file");
+
+        // Start just before u32::MAX so the write crosses the wrap
+        let start = u32::MAX - 2;
+        writer.set_write_pos(start);
+        let samples: Vec<f32> = (0..12).map(|i| i as f32).collect();
+        writer.write_samples(&samples).unwrap();
+        assert_eq!(writer.write_pos(), start.wrapping_add(6));
+
+        let reader = SharedAudioBuffer::open_readonly_path(&path).unwrap();
+        assert_eq!(reader.channel_count(), 2);
+        assert_eq!(reader.sample_rate(), 48000);
+
+        // Partial read, then the rest from the advanced position
+        let mut out = [0.0f32; 8];
+        let frames = reader.read_samples(start, &mut out);
+        assert_eq!(frames, 4);
+        assert_eq!(&out[..], &samples[..8]);
+
+        let mut rest = [0.0f32; 8];
+        let frames = reader.read_samples(start.wrapping_add(4), &mut rest);
+        assert_eq!(frames, 2);
+        assert_eq!(&rest[..4], &samples[8..]);
+
+        // Caught up: nothing left
+        assert_eq!(reader.read_samples(writer.write_pos(), &mut out), 0);
+
+        drop(reader);
+        drop(writer);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_undersized_buffer_is_an_error() {
+        let path =
+            std::env::temp_dir().join(format!("duomic_shm_small_test_{}", std::process::id()));
+
+        // A stale file whose header claims more channels than it holds
+        let mut stale = vec![0u8; HEADER_SIZE + 64];
+        stale[4..8].copy_from_slice(&2u32.to_ne_bytes());
+        std::fs::write(&path, &stale).unwrap();
+        let err = SharedAudioBuffer::open_readonly_path(&path).err().unwrap();
+        assert!(err.to_string().contains("doesn't match its size"));
+
+        // Header-only file
+        std::fs::write(&path, [0u8; 8]).unwrap();
+        assert!(SharedAudioBuffer::open_readonly_path(&path).is_err());
+
+        assert!(SharedAudioBuffer::open_path(&path, 0, 48000).is_err());
+
+        // A mapping shorter than the channel layout: writes fail instead of panicking
+        let mut writer = SharedAudioBuffer::open_path(&path, 1, 48000).unwrap();
+        writer.channel_count = 4;
+        writer.set_write_pos(RING_BUFFER_FRAMES as u32 - 1);
+        assert!(writer.write_samples(&[0.5; 8]).is_err());
+        let mut out = [0.0f32; 8];
+        assert_eq!(
+            writer.read_samples(RING_BUFFER_FRAMES as u32 - 2, &mut out),
+            0
+        );
+
+        drop(writer);
+        let _ = std::fs::remove_file(&path);
+    }
 }
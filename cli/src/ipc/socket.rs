@@ -1,42 +1,95 @@
-use anyhow::{bail, Context, Result};
-use std::io::{Read, Write};
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::io::{ErrorKind, Read, Write};
 use std::os::unix::net::UnixStream;
-use std::path::Path;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::{runtime_paths, Request, Response, JSON_FEATURE};
+use crate::DuomicError;
 
-const SOCKET_PATH: &str = "/tmp/duomic.sock";
 const TIMEOUT: Duration = Duration::from_secs(5);
 
+/// `CAPS` feature for `source=N` metadata (mics on extra capture devices)
+pub const SOURCES_FEATURE: &str = "sources";
+
 /// Driver IPC client for sending commands via Unix socket
 pub struct DriverClient {
     stream: Option<UnixStream>,
+    // Optional features from `CAPS`, asked once per client
+    caps: Option<Vec<String>>,
+    // Read/write timeout of each connection
+    timeout: Duration,
 }
 
 impl DriverClient {
     /// Create a new driver client (not connected yet)
     pub fn new() -> Self {
-        Self { stream: None }
+        Self::with_timeout(TIMEOUT)
+    }
+
+    /// A client giving up on each read or write after `timeout` (default 5 s)
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            stream: None,
+            caps: None,
+            timeout,
+        }
     }
 
     /// Check if driver socket exists
     pub fn is_driver_available() -> bool {
-        Path::new(SOCKET_PATH).exists()
+        runtime_paths().socket.exists()
+    }
+
+    /// Whether the driver socket is missing, left behind or accepting connections
+    pub fn socket_state() -> SocketState {
+        SocketState::of(&runtime_paths().socket)
+    }
+
+    /// Delete the driver socket if nothing is listening on it anymore
+    ///
+    /// Returns whether a file was removed; a live socket is never touched.
+    pub fn remove_stale_socket() -> Result<bool, DuomicError> {
+        Ok(remove_stale_socket_at(&runtime_paths().socket)?)
     }
 
     /// Connect to the driver socket
-    pub fn connect(&mut self) -> Result<()> {
-        let stream =
-            UnixStream::connect(SOCKET_PATH).context("Failed to connect to driver socket")?;
+    ///
+    /// A socket file nobody listens on fails with [`DuomicError::StaleSocket`],
+    /// a missing one with [`DuomicError::DriverUnavailable`].
+    pub fn connect(&mut self) -> Result<(), DuomicError> {
+        self.connect_at(&runtime_paths().socket, self.timeout)
+    }
+
+    /// Connect to `path`, giving up on reads and writes after `timeout`
+    fn connect_at(&mut self, path: &Path, timeout: Duration) -> Result<(), DuomicError> {
+        let stream = match UnixStream::connect(path) {
+            Ok(stream) => stream,
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => {
+                return Err(StaleSocket {
+                    path: path.to_path_buf(),
+                }
+                .into());
+            }
+            Err(e) => {
+                return Err(DuomicError::DriverUnavailable(format!(
+                    "can't connect to {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
 
         stream
-            .set_read_timeout(Some(TIMEOUT))
+            .set_read_timeout(Some(timeout))
             .context("Failed to set read timeout")?;
         stream
-            .set_write_timeout(Some(TIMEOUT))
+            .set_write_timeout(Some(timeout))
             .context("Failed to set write timeout")?;
 
         self.stream = Some(stream);
-        tracing::debug!("Connected to driver socket at {}", SOCKET_PATH);
+        tracing::debug!("Connected to driver socket at {}", path.display());
         Ok(())
     }
 
@@ -47,6 +100,9 @@ impl DriverClient {
     }
 
     /// Send a command and receive response
+    ///
+    /// Single-line responses end at the first newline; `LIST` spans several
+    /// lines, so it's read until the driver closes the connection.
     fn send_command(&mut self, command: &str) -> Result<String> {
         let stream = self.stream.as_mut().context("Not connected to driver")?;
 
@@ -59,13 +115,9 @@ impl DriverClient {
 
         tracing::debug!("Sent command: {}", command);
 
-        // Read response
-        let mut buffer = [0u8; 1024];
-        let n = stream
-            .read(&mut buffer)
-            .context("Failed to read response from driver")?;
-
-        let response = String::from_utf8_lossy(&buffer[..n]).to_string();
+        // Read response (may arrive in several chunks)
+        let multiline = command == "LIST";
+        let response = read_response(stream, multiline, self.timeout)?;
         tracing::debug!("Received response: {}", response);
 
         Ok(response)
@@ -82,9 +134,8 @@ impl DriverClient {
                 .trim()
                 .to_string();
             Ok(message)
-        } else if response.starts_with("ERROR:") {
-            let error = response.strip_prefix("ERROR:").unwrap_or("Unknown error");
-            bail!("{}", error.trim())
+        } else if let Some(error) = response.strip_prefix("ERROR:") {
+            Err(DriverError::parse(error).into())
         } else if response == "PONG" {
             Ok("PONG".to_string())
         } else {
@@ -94,37 +145,146 @@ impl DriverClient {
     }
 
     /// Ping the driver to check if it's responsive
-    pub fn ping(&mut self) -> Result<bool> {
+    pub fn ping(&mut self) -> Result<bool, DuomicError> {
         // Driver closes connection after each command, so reconnect
         self.connect()?;
         let response = self.send_command("PING")?;
         Ok(response.trim() == "PONG")
     }
 
+    /// Whether the driver advertises `feature` in `CAPS`
+    ///
+    /// Drivers older than `CAPS` answer with an error, meaning no optional features.
+    pub fn supports(&mut self, feature: &str) -> Result<bool, DuomicError> {
+        if self.caps.is_none() {
+            self.connect()?;
+            let response = self.send_command("CAPS")?;
+            let caps = match Self::parse_response(&response) {
+                Ok(message) => message
+                    .split(',')
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            self.caps = Some(caps);
+        }
+        Ok(self
+            .caps
+            .as_ref()
+            .is_some_and(|c| c.iter().any(|c| c == feature)))
+    }
+
+    /// Send a JSON request (drivers with `json` in `CAPS`) and parse the reply
+    fn call(&mut self, request: &Request) -> Result<Response> {
+        // Driver closes connection after each command, so reconnect
+        self.connect()?;
+        let response = self.send_command(&request.to_line()?)?;
+        Response::parse(&response)
+    }
+
     /// Add a virtual device (reconnects for each command)
-    pub fn add_device(&mut self, name: &str, channel: u32) -> Result<()> {
+    pub fn add_device(&mut self, name: &str, channel: u32) -> Result<(), DuomicError> {
+        self.add_device_with(name, channel, &[])
+    }
+
+    /// Add a virtual device with `key=value` metadata, e.g. `("color", "red")`
+    ///
+    /// Metadata is dropped for drivers that don't support it, and entries the
+    /// protocol can't carry are skipped with a warning, so the device is
+    /// still created.
+    pub fn add_device_with(
+        &mut self,
+        name: &str,
+        channel: u32,
+        metadata: &[(&str, &str)],
+    ) -> Result<(), DuomicError> {
+        if self.supports(JSON_FEATURE)? {
+            self.call(&Request::Add {
+                name: name.to_string(),
+                channel,
+                metadata: metadata
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            })?;
+            tracing::info!("Added virtual device: {} (channel {})", name, channel);
+            return Ok(());
+        }
+
+        let mut command = format!("ADD {}:{}", name, channel);
+        if !metadata.is_empty() && self.supports("meta")? {
+            for (key, value) in metadata {
+                if [key, value]
+                    .iter()
+                    .any(|s| s.is_empty() || s.contains([':', ',', '=', '\n']))
+                {
+                    tracing::warn!("Skipping invalid metadata {}={} for {}", key, value, name);
+                    continue;
+                }
+                command.push_str(&format!(":{}={}", key, value));
+            }
+        }
+
         // Driver closes connection after each command, so reconnect
         self.connect()?;
-        let command = format!("ADD {}:{}", name, channel);
         let response = self.send_command(&command)?;
         Self::parse_response(&response)?;
         tracing::info!("Added virtual device: {} (channel {})", name, channel);
         Ok(())
     }
 
+    /// Add `device`, passing its color and capture source when it has them
+    ///
+    /// Fails for an extra source on drivers without `sources` in `CAPS`,
+    /// which would read the channel from the primary device instead.
+    pub fn add_device_info(&mut self, device: &DeviceInfo) -> Result<(), DuomicError> {
+        let source = device.source.to_string();
+        let mut metadata = Vec::new();
+        if let Some(color) = &device.color {
+            metadata.push(("color", color.as_str()));
+        }
+        if device.source > 0 {
+            if !self.supports(SOURCES_FEATURE)? {
+                return Err(anyhow!(
+                    "The driver can't capture from more than one device, update it (sudo ./install.sh)"
+                )
+                .into());
+            }
+            metadata.push(("source", source.as_str()));
+        }
+        self.add_device_with(&device.name, device.channel, &metadata)
+    }
+
     /// Remove a virtual device (reconnects for each command)
-    pub fn remove_device(&mut self, name: &str) -> Result<()> {
-        // Driver closes connection after each command, so reconnect
-        self.connect()?;
-        let command = format!("REMOVE {}", name);
-        let response = self.send_command(&command)?;
-        Self::parse_response(&response)?;
+    pub fn remove_device(&mut self, name: &str) -> Result<(), DuomicError> {
+        if self.supports(JSON_FEATURE)? {
+            self.call(&Request::Remove {
+                name: name.to_string(),
+            })?;
+        } else {
+            // Driver closes connection after each command, so reconnect
+            self.connect()?;
+            let command = format!("REMOVE {}", name);
+            let response = self.send_command(&command)?;
+            Self::parse_response(&response)?;
+        }
         tracing::info!("Removed virtual device: {}", name);
         Ok(())
     }
 
     /// List active virtual devices (reconnects for each command)
-    pub fn list_devices(&mut self) -> Result<Vec<DeviceInfo>> {
+    pub fn list_devices(&mut self) -> Result<Vec<DeviceInfo>, DuomicError> {
+        if self.supports(JSON_FEATURE)? {
+            let response = self.call(&Request::List)?;
+            return Ok(response
+                .devices
+                .unwrap_or_default()
+                .into_iter()
+                .map(DeviceInfo::from)
+                .collect());
+        }
+
         // Driver closes connection after each command, so reconnect
         self.connect()?;
         let response = self.send_command("LIST")?;
@@ -134,33 +294,58 @@ impl DriverClient {
             return Ok(Vec::new());
         }
 
-        // Parse device list - supports both formats:
-        // - Newline separated: "name1:channel1\nname2:channel2"
-        // - Comma separated: "name1:channel1,name2:channel2"
-        let devices = message
+        Ok(Self::parse_device_list(&message))
+    }
+
+    /// Parse a `LIST` reply, in either format:
+    /// - Newline separated: "name1:channel1\nname2:channel2"
+    /// - Comma separated: "name1:channel1,name2:channel2"
+    ///
+    /// Fields after the channel are `key=value` metadata; unknown ones are ignored.
+    fn parse_device_list(message: &str) -> Vec<DeviceInfo> {
+        message
             .split([',', '\n'])
-            .filter_map(|entry| {
-                let entry = entry.trim();
-                if entry.is_empty() {
-                    return None;
-                }
-                let parts: Vec<&str> = entry.split(':').collect();
-                if parts.len() >= 2 {
-                    Some(DeviceInfo {
-                        name: parts[0].to_string(),
-                        channel: parts[1].parse().unwrap_or(0),
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect();
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(Self::parse_device_entry)
+            .collect()
+    }
 
-        Ok(devices)
+    /// Parse one `name:channel[:key=value...]` entry
+    ///
+    /// Names may contain colons ("Zoom: Guest:1"), so the channel is the last
+    /// field that is a number. Without one, the whole entry is the name and
+    /// the channel is [`DeviceInfo::UNKNOWN_CHANNEL`].
+    fn parse_device_entry(entry: &str) -> DeviceInfo {
+        let fields: Vec<&str> = entry.split(':').collect();
+        let channel_field = fields
+            .iter()
+            .rposition(|field| field.parse::<u32>().is_ok())
+            .filter(|&i| i > 0);
+        let Some(i) = channel_field else {
+            tracing::warn!("No channel in LIST entry {:?}", entry);
+            return DeviceInfo {
+                name: entry.to_string(),
+                channel: DeviceInfo::UNKNOWN_CHANNEL,
+                color: None,
+                source: 0,
+            };
+        };
+        let field = |key: &str| {
+            fields[i + 1..]
+                .iter()
+                .find_map(|field| field.strip_prefix(key)?.strip_prefix('='))
+        };
+        DeviceInfo {
+            name: fields[..i].join(":"),
+            channel: fields[i].parse().unwrap_or(DeviceInfo::UNKNOWN_CHANNEL),
+            color: field("color").map(str::to_string),
+            source: field("source").and_then(|s| s.parse().ok()).unwrap_or(0),
+        }
     }
 
     /// Remove all virtual devices from driver
-    pub fn remove_all_devices(&mut self) -> Result<usize> {
+    pub fn remove_all_devices(&mut self) -> Result<usize, DuomicError> {
         let devices = self.list_devices()?;
         let count = devices.len();
 
@@ -176,35 +361,282 @@ impl DriverClient {
 
     /// Sync driver devices with expected list
     /// Removes devices not in expected list, adds missing ones
-    pub fn sync_devices(&mut self, expected: &[DeviceInfo]) -> Result<()> {
+    ///
+    /// Failures on single mics are logged and listed in the report rather
+    /// than stopping the sync.
+    pub fn sync_devices(&mut self, expected: &[DeviceInfo]) -> Result<SyncReport, DuomicError> {
         let current = self.list_devices()?;
+        let mut report = SyncReport::default();
 
-        // Find devices to remove (in driver but not in expected)
-        for device in &current {
-            let should_exist = expected.iter().any(|e| e.name == device.name);
-            if !should_exist {
-                tracing::info!("Removing orphan device: {}", device.name);
-                if let Err(e) = self.remove_device(&device.name) {
-                    tracing::warn!("Failed to remove orphan {}: {}", device.name, e);
+        for mic in reconcile(expected, &current) {
+            match mic.state {
+                SyncState::InSync => report.unchanged.push(mic.name),
+                // In driver but not in expected
+                SyncState::DriverOnly => {
+                    tracing::info!("Removing orphan device: {}", mic.name);
+                    match self.remove_device(&mic.name) {
+                        Ok(()) => report.removed.push(mic.name),
+                        Err(e) => {
+                            tracing::warn!("Failed to remove orphan {}: {}", mic.name, e);
+                            report.failed.push((mic.name, format!("{:#}", e)));
+                        }
+                    }
+                }
+                // Reading the wrong channel or source: add it again
+                SyncState::Moved => {
+                    tracing::info!("Re-adding moved device: {}", mic.name);
+                    let Some(device) = expected.iter().find(|e| e.name == mic.name) else {
+                        continue;
+                    };
+                    match self
+                        .remove_device(&mic.name)
+                        .and_then(|()| self.add_device_info(device))
+                    {
+                        Ok(()) => report.added.push(mic.name),
+                        Err(e) => {
+                            tracing::warn!("Failed to re-add {}: {}", mic.name, e);
+                            report.failed.push((mic.name, format!("{:#}", e)));
+                        }
+                    }
+                }
+                // In expected but not in driver
+                SyncState::ConfigOnly => {
+                    tracing::info!("Adding missing device: {}", mic.name);
+                    let Some(device) = expected.iter().find(|e| e.name == mic.name) else {
+                        continue;
+                    };
+                    match self.add_device_info(device) {
+                        Ok(()) => report.added.push(mic.name),
+                        // Raced with another client adding it; that's what we wanted
+                        Err(DuomicError::Driver(e))
+                            if e.kind() == DriverErrorKind::AlreadyExists =>
+                        {
+                            tracing::debug!("{} already exists", mic.name);
+                            report.unchanged.push(mic.name);
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to add {}: {}", mic.name, e);
+                            report.failed.push((mic.name, format!("{:#}", e)));
+                        }
+                    }
                 }
             }
         }
 
-        // Find devices to add (in expected but not in driver)
-        for device in expected {
-            let exists = current.iter().any(|c| c.name == device.name);
-            if !exists {
-                tracing::info!("Adding missing device: {}", device.name);
-                if let Err(e) = self.add_device(&device.name, device.channel) {
-                    tracing::warn!("Failed to add {}: {}", device.name, e);
-                }
+        Ok(report)
+    }
+}
+
+/// Read a response until its terminating newline (or EOF when `multiline`)
+///
+/// A single `read` can return part of a line over a slow socket, so keep
+/// accumulating; the whole read is bounded by `timeout`. Bytes are decoded only
+/// once complete, since a chunk can end inside a multibyte character. Running
+/// out of time fails with [`DriverTimeout`].
+fn read_response<R: Read>(reader: &mut R, multiline: bool, timeout: Duration) -> Result<String> {
+    let deadline = Instant::now() + timeout;
+    let mut bytes = Vec::new();
+    let mut buffer = [0u8; 1024];
+
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(n) => n,
+            // The socket's read timeout (WouldBlock on macOS, TimedOut elsewhere)
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Err(DriverTimeout.into());
             }
+            Err(e) => return Err(e).context("Failed to read response from driver"),
+        };
+        if n == 0 {
+            break;
         }
+        bytes.extend_from_slice(&buffer[..n]);
 
-        Ok(())
+        if !multiline && buffer[..n].contains(&b'\n') {
+            break;
+        }
+        if Instant::now() >= deadline {
+            return Err(DriverTimeout.into());
+        }
     }
+
+    Ok(String::from_utf8_lossy(&bytes).to_string())
 }
 
+/// Command that restarts CoreAudio and with it the driver (needs sudo)
+pub const RESTART_DRIVER_COMMAND: &str = "sudo killall coreaudiod";
+
+/// What's at the driver socket path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketState {
+    /// No socket file: the driver isn't loaded (or coreaudiod isn't running)
+    Missing,
+    /// The file exists but connecting is refused, e.g. after coreaudiod crashed
+    Stale,
+    /// Connectable, or failing for another reason that `connect` reports
+    Present,
+}
+
+impl SocketState {
+    /// Probe `path` by connecting (the connection is closed right away)
+    pub fn of(path: &Path) -> Self {
+        match UnixStream::connect(path) {
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => SocketState::Stale,
+            Err(_) if !path.exists() => SocketState::Missing,
+            _ => SocketState::Present,
+        }
+    }
+}
+
+/// Remove `path` if it's a stale socket
+fn remove_stale_socket_at(path: &Path) -> Result<bool> {
+    if SocketState::of(path) != SocketState::Stale {
+        return Ok(false);
+    }
+    std::fs::remove_file(path)
+        .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+    tracing::info!("Removed stale driver socket {}", path.display());
+    Ok(true)
+}
+
+/// The driver socket exists but nothing is listening on it
+///
+/// Usually left behind when coreaudiod crashed; returned as
+/// [`DuomicError::StaleSocket`], or find it in an `anyhow::Error` with
+/// [`StaleSocket::is_cause_of`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleSocket {
+    pub path: PathBuf,
+}
+
+impl StaleSocket {
+    /// Whether `error` (or anything in its chain) is a stale socket
+    pub fn is_cause_of(error: &anyhow::Error) -> bool {
+        error.chain().any(|e| e.is::<StaleSocket>())
+            || matches!(DuomicError::find(error), Some(DuomicError::StaleSocket(_)))
+    }
+}
+
+impl std::fmt::Display for StaleSocket {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Driver socket {} is stale (connection refused), the driver isn't running",
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for StaleSocket {}
+
+/// The driver accepted the connection but never answered
+///
+/// Unlike a refused connection ([`StaleSocket`]) the driver is loaded, but
+/// likely hung (e.g. deadlocked) rather than crashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriverTimeout;
+
+impl DriverTimeout {
+    /// Whether `error` (or anything in its chain) is a driver timeout
+    pub fn is_cause_of(error: &anyhow::Error) -> bool {
+        error.chain().any(|e| e.is::<DriverTimeout>())
+            || matches!(
+                DuomicError::find(error),
+                Some(DuomicError::DriverTimeout(_))
+            )
+    }
+}
+
+impl std::fmt::Display for DriverTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Driver connected but timed out without answering (it may be hung)"
+        )
+    }
+}
+
+impl std::error::Error for DriverTimeout {}
+
+/// Broad class of a driver error, from its numeric code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverErrorKind {
+    /// 400: malformed or unknown command (CLI/driver version mismatch)
+    BadRequest,
+    /// 403: the driver refused the operation
+    PermissionDenied,
+    /// 404: no virtual device with that name
+    NotFound,
+    /// 409: a virtual device with that name already exists
+    AlreadyExists,
+    /// Any other code, or none (plain `ERROR:message`)
+    Other,
+}
+
+/// Error reported by the driver as `ERROR:message` or `ERROR:code:message`
+///
+/// Returned as [`DuomicError::Driver`] from the command methods; inside an
+/// `anyhow::Error` use [`DriverError::kind_of`] to branch on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DriverError {
+    pub code: Option<u32>,
+    pub message: String,
+}
+
+impl DriverError {
+    /// Parse the part after `ERROR:`
+    pub fn parse(body: &str) -> Self {
+        let body = body.trim();
+        if let Some((code, message)) = body.split_once(':') {
+            if let Ok(code) = code.trim().parse() {
+                return Self {
+                    code: Some(code),
+                    message: message.trim().to_string(),
+                };
+            }
+        }
+
+        Self {
+            code: None,
+            message: if body.is_empty() {
+                "Unknown error".to_string()
+            } else {
+                body.to_string()
+            },
+        }
+    }
+
+    pub fn kind(&self) -> DriverErrorKind {
+        match self.code {
+            Some(400) => DriverErrorKind::BadRequest,
+            Some(403) => DriverErrorKind::PermissionDenied,
+            Some(404) => DriverErrorKind::NotFound,
+            Some(409) => DriverErrorKind::AlreadyExists,
+            _ => DriverErrorKind::Other,
+        }
+    }
+
+    /// Kind of the driver error in `error`'s chain, if there is one
+    pub fn kind_of(error: &anyhow::Error) -> Option<DriverErrorKind> {
+        error
+            .chain()
+            .find_map(|e| e.downcast_ref::<DriverError>())
+            .or_else(|| DuomicError::find(error)?.driver_error())
+            .map(DriverError::kind)
+    }
+}
+
+impl std::fmt::Display for DriverError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "{} (driver error {})", self.message, code),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for DriverError {}
+
 impl Default for DriverClient {
     fn default() -> Self {
         Self::new()
@@ -212,16 +644,185 @@ impl Default for DriverClient {
 }
 
 /// Information about a virtual device
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DeviceInfo {
     pub name: String,
     pub channel: u32,
+    /// Color tag (metadata; only kept by drivers that support it)
+    pub color: Option<String>,
+    /// Capture source the channel is read from (0 = `[device]`, N = the Nth `[[extra_devices]]`)
+    pub source: u32,
+}
+
+impl DeviceInfo {
+    /// Channel of a driver device whose `LIST` entry had none
+    pub const UNKNOWN_CHANNEL: u32 = u32::MAX;
+}
+
+/// How a virtual mic in the config relates to the driver's live list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncState {
+    /// Present in both
+    InSync,
+    /// Configured but not created in the driver
+    ConfigOnly,
+    /// Created in the driver but not configured (orphan)
+    DriverOnly,
+    /// In the driver, but reading another channel or capture source
+    Moved,
+}
+
+impl std::fmt::Display for SyncState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncState::InSync => write!(f, "in sync"),
+            SyncState::ConfigOnly => write!(f, "config only (not in driver)"),
+            SyncState::DriverOnly => write!(f, "driver only (orphan)"),
+            SyncState::Moved => write!(f, "other channel or source in driver"),
+        }
+    }
+}
+
+/// One row of a config/driver reconciliation
+#[derive(Debug, Clone, Serialize)]
+pub struct MicSync {
+    pub name: String,
+    /// [`DeviceInfo::UNKNOWN_CHANNEL`] (`null` in JSON) if the driver didn't say
+    #[serde(serialize_with = "serialize_channel")]
+    pub channel: u32,
+    pub state: SyncState,
+    /// Color tag from the config (or the driver, for orphans)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+}
+
+/// What [`DriverClient::sync_devices`] did (or would do), by mic name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Mics that could not be added or removed, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+impl SyncReport {
+    /// The changes a sync would make for reconciled `rows` (for dry runs)
+    pub fn planned(rows: &[MicSync]) -> Self {
+        let mut report = Self::default();
+        for row in rows {
+            let list = match row.state {
+                SyncState::InSync => &mut report.unchanged,
+                SyncState::ConfigOnly | SyncState::Moved => &mut report.added,
+                SyncState::DriverOnly => &mut report.removed,
+            };
+            list.push(row.name.clone());
+        }
+        report
+    }
+}
+
+fn serialize_channel<S: serde::Serializer>(
+    channel: &u32,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match *channel {
+        DeviceInfo::UNKNOWN_CHANNEL => serializer.serialize_none(),
+        channel => serializer.serialize_u32(channel),
+    }
+}
+
+/// Compare expected (config) mics against the driver's list by name
+///
+/// Returns config entries first (in config order), then driver orphans.
+/// The channel reported for in-sync mics is the driver's (if it gave one).
+/// A mic the driver has on another channel or source (e.g. after reordering
+/// `[[extra_devices]]`) is [`SyncState::Moved`], with the expected channel.
+pub fn reconcile(expected: &[DeviceInfo], current: &[DeviceInfo]) -> Vec<MicSync> {
+    let moved = |e: &DeviceInfo, c: &DeviceInfo| {
+        c.source != e.source || (c.channel != DeviceInfo::UNKNOWN_CHANNEL && c.channel != e.channel)
+    };
+    let mut result: Vec<MicSync> = expected
+        .iter()
+        .map(|e| match current.iter().find(|c| c.name == e.name) {
+            Some(c) if moved(e, c) => MicSync {
+                name: e.name.clone(),
+                channel: e.channel,
+                state: SyncState::Moved,
+                color: e.color.clone(),
+            },
+            Some(c) => MicSync {
+                name: c.name.clone(),
+                channel: match c.channel {
+                    DeviceInfo::UNKNOWN_CHANNEL => e.channel,
+                    channel => channel,
+                },
+                state: SyncState::InSync,
+                color: e.color.clone(),
+            },
+            None => MicSync {
+                name: e.name.clone(),
+                channel: e.channel,
+                state: SyncState::ConfigOnly,
+                color: e.color.clone(),
+            },
+        })
+        .collect();
+
+    result.extend(
+        current
+            .iter()
+            .filter(|c| !expected.iter().any(|e| e.name == c.name))
+            .map(|c| MicSync {
+                name: c.name.clone(),
+                channel: c.channel,
+                state: SyncState::DriverOnly,
+                color: c.color.clone(),
+            }),
+    );
+
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_socket_state_and_stale_cleanup() {
+        let path = std::env::temp_dir().join(format!("duomic-sock-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(SocketState::of(&path), SocketState::Missing);
+
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert_eq!(SocketState::of(&path), SocketState::Present);
+        assert!(!remove_stale_socket_at(&path).unwrap());
+
+        // Closing the listener leaves the file behind, like a crashed driver
+        drop(listener);
+        assert_eq!(SocketState::of(&path), SocketState::Stale);
+        assert!(remove_stale_socket_at(&path).unwrap());
+        assert_eq!(SocketState::of(&path), SocketState::Missing);
+        assert!(!remove_stale_socket_at(&path).unwrap());
+    }
+
+    #[test]
+    fn test_silent_driver_times_out() {
+        let path = std::env::temp_dir().join(format!("duomic-hung-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        // Connections queue in the backlog but nothing ever reads or answers
+        let _listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+
+        let mut client = DriverClient::new();
+        client.connect_at(&path, Duration::from_millis(50)).unwrap();
+        let err = client.send_command("PING").unwrap_err();
+        assert!(DriverTimeout::is_cause_of(&err), "{:#}", err);
+        assert!(!StaleSocket::is_cause_of(&err));
+
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[test]
     fn test_parse_response_ok() {
         assert!(DriverClient::parse_response("OK").is_ok());
@@ -232,6 +833,243 @@ mod tests {
     #[test]
     fn test_parse_response_error() {
         let result = DriverClient::parse_response("ERROR:Device not found");
-        assert!(result.is_err());
+        let error = result.unwrap_err();
+        let driver_error = error.downcast_ref::<DriverError>().unwrap();
+        assert_eq!(driver_error.code, None);
+        assert_eq!(driver_error.message, "Device not found");
+        assert_eq!(DriverError::kind_of(&error), Some(DriverErrorKind::Other));
+    }
+
+    #[test]
+    fn test_parse_response_error_code() {
+        let error = DriverClient::parse_response("ERROR:409:device exists\n").unwrap_err();
+        let driver_error = error.downcast_ref::<DriverError>().unwrap();
+        assert_eq!(driver_error.code, Some(409));
+        assert_eq!(driver_error.message, "device exists");
+        assert_eq!(
+            DriverError::kind_of(&error),
+            Some(DriverErrorKind::AlreadyExists)
+        );
+
+        // A colon in a plain message isn't a code
+        let plain = DriverError::parse("bad name: Host:1");
+        assert_eq!(plain.code, None);
+        assert_eq!(plain.message, "bad name: Host:1");
+    }
+
+    #[test]
+    fn test_response_split_across_reads() {
+        let (mut reader, mut writer) = UnixStream::pair().unwrap();
+        reader.set_read_timeout(Some(TIMEOUT)).unwrap();
+
+        let sender = std::thread::spawn(move || {
+            writer.write_all(b"OK").unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            writer.write_all(b":Device added\n").unwrap();
+            // Keep the connection open: the newline alone must end the read
+            std::thread::sleep(Duration::from_millis(200));
+        });
+
+        let response = read_response(&mut reader, false, TIMEOUT).unwrap();
+        assert_eq!(response, "OK:Device added\n");
+        assert_eq!(
+            DriverClient::parse_response(&response).unwrap(),
+            "Device added"
+        );
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_multibyte_name_split_across_reads() {
+        let (mut reader, mut writer) = UnixStream::pair().unwrap();
+        reader.set_read_timeout(Some(TIMEOUT)).unwrap();
+
+        let reply = "OK\nCafé 🎤:0\nHost:1\n".as_bytes();
+        // Split inside the emoji's four bytes
+        let split = reply.iter().position(|&b| b == 0xF0).unwrap() + 2;
+        let sender = std::thread::spawn(move || {
+            writer.write_all(&reply[..split]).unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            writer.write_all(&reply[split..]).unwrap();
+        });
+
+        let response = read_response(&mut reader, true, TIMEOUT).unwrap();
+        sender.join().unwrap();
+        let message = DriverClient::parse_response(&response).unwrap();
+        let names: Vec<String> = DriverClient::parse_device_list(&message)
+            .into_iter()
+            .map(|d| d.name)
+            .collect();
+        assert_eq!(names, ["Café 🎤", "Host"]);
+    }
+
+    #[test]
+    fn test_list_metadata_fields() {
+        let devices =
+            DriverClient::parse_device_list("Host:0:color=red:tag=x\nGuest:1:future\nOld:2");
+        let parsed: Vec<(&str, u32, Option<&str>)> = devices
+            .iter()
+            .map(|d| (d.name.as_str(), d.channel, d.color.as_deref()))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                ("Host", 0, Some("red")),
+                ("Guest", 1, None),
+                ("Old", 2, None)
+            ]
+        );
+        let extra = DriverClient::parse_device_list("Guest:1:source=2\nHost:0:sourced=x");
+        assert_eq!((extra[0].source, extra[1].source), (2, 0));
+    }
+
+    #[test]
+    fn test_list_names_with_colons() {
+        let devices = DriverClient::parse_device_list(
+            "Zoom: Guest:1\nA:B:C:2:color=blue:future\n12:3\nNo channel: here",
+        );
+        let parsed: Vec<(&str, u32, Option<&str>)> = devices
+            .iter()
+            .map(|d| (d.name.as_str(), d.channel, d.color.as_deref()))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                ("Zoom: Guest", 1, None),
+                ("A:B:C", 2, Some("blue")),
+                ("12", 3, None),
+                ("No channel: here", DeviceInfo::UNKNOWN_CHANNEL, None),
+            ]
+        );
+
+        // Unknown channels report as null and defer to the config's
+        let rows = reconcile(
+            &[DeviceInfo {
+                name: "No channel: here".to_string(),
+                channel: 5,
+                color: None,
+                source: 0,
+            }],
+            &devices,
+        );
+        assert_eq!(rows[0].channel, 5);
+        let orphan = rows.iter().find(|r| r.name == "Zoom: Guest").unwrap();
+        assert_eq!(serde_json::to_value(orphan).unwrap()["channel"], 1);
+        let unknown = MicSync {
+            channel: DeviceInfo::UNKNOWN_CHANNEL,
+            ..orphan.clone()
+        };
+        assert!(serde_json::to_value(&unknown).unwrap()["channel"].is_null());
+    }
+
+    #[test]
+    fn test_trickling_response_is_bounded_by_the_timeout() {
+        let (mut reader, mut writer) = UnixStream::pair().unwrap();
+        reader.set_read_timeout(Some(TIMEOUT)).unwrap();
+
+        // A byte every 20ms never times out a single read, only the deadline
+        let sender = std::thread::spawn(move || {
+            for _ in 0..25 {
+                if writer.write_all(b"x").is_err() {
+                    return;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        let error = read_response(&mut reader, true, Duration::from_millis(100)).unwrap_err();
+        assert!(error.is::<DriverTimeout>());
+        drop(reader);
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_multiline_response_reads_to_eof() {
+        let (mut reader, mut writer) = UnixStream::pair().unwrap();
+        reader.set_read_timeout(Some(TIMEOUT)).unwrap();
+
+        let sender = std::thread::spawn(move || {
+            writer.write_all(b"OK\nHost:0\n").unwrap();
+            std::thread::sleep(Duration::from_millis(50));
+            writer.write_all(b"Guest:1\n").unwrap();
+        });
+
+        let response = read_response(&mut reader, true, TIMEOUT).unwrap();
+        assert_eq!(response, "OK\nHost:0\nGuest:1\n");
+        sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let info = |name: &str, channel| DeviceInfo {
+            name: name.to_string(),
+            channel,
+            color: None,
+            source: 0,
+        };
+        let expected = [info("Host", 0), info("Guest", 1)];
+        let current = [info("Old", 0), info("Host", 0)];
+
+        let rows: Vec<(String, SyncState)> = reconcile(&expected, &current)
+            .into_iter()
+            .map(|m| (m.name, m.state))
+            .collect();
+
+        assert_eq!(
+            rows,
+            [
+                ("Host".to_string(), SyncState::InSync),
+                ("Guest".to_string(), SyncState::ConfigOnly),
+                ("Old".to_string(), SyncState::DriverOnly),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_planned_sync_matches_reconcile() {
+        let info = |name: &str, channel| DeviceInfo {
+            name: name.to_string(),
+            channel,
+            color: None,
+            source: 0,
+        };
+        let expected = [info("Host", 0), info("Guest", 1)];
+        let current = [info("Old", 0), info("Host", 0)];
+
+        // A dry run reports the same changes a sync makes
+        let planned = SyncReport::planned(&reconcile(&expected, &current));
+        assert_eq!(planned.added, ["Guest"]);
+        assert_eq!(planned.removed, ["Old"]);
+        assert_eq!(planned.unchanged, ["Host"]);
+        assert!(planned.failed.is_empty());
+    }
+
+    #[test]
+    fn test_reconcile_moved_source() {
+        let info = |name: &str, channel, source| DeviceInfo {
+            name: name.to_string(),
+            channel,
+            color: None,
+            source,
+        };
+        // `[[extra_devices]]` reordered: Guest now reads source 2, Host channel 1
+        let expected = [info("Host", 1, 0), info("Guest", 0, 2), info("Cam", 0, 1)];
+        let current = [
+            info("Host", 0, 0),
+            info("Guest", 0, 1),
+            info("Cam", DeviceInfo::UNKNOWN_CHANNEL, 1),
+        ];
+
+        let reconciled = reconcile(&expected, &current);
+        let states: Vec<SyncState> = reconciled.iter().map(|m| m.state).collect();
+        assert_eq!(
+            states,
+            [SyncState::Moved, SyncState::Moved, SyncState::InSync]
+        );
+        assert_eq!(reconciled[0].channel, 1);
+
+        let planned = SyncReport::planned(&reconciled);
+        assert_eq!(planned.added, ["Host", "Guest"]);
+        assert!(planned.removed.is_empty());
     }
 }
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::runtime_paths;
+
+/// Snapshots older than this are from a capture that is no longer running
+const STALE_AFTER: Duration = Duration::from_secs(5);
+
+/// Capture counters published by a running `duomic run` for `duomic status`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatsSnapshot {
+    /// Unix time (seconds) the snapshot was written
+    pub updated: u64,
+    pub dropped_peaks: u64,
+    pub write_errors: u64,
+    /// Transient stream errors (over/underruns)
+    #[serde(default)]
+    pub xruns: u64,
+    /// Times the driver fell a whole buffer behind (gaps in the virtual mics)
+    #[serde(default)]
+    pub shm_overruns: u64,
+    /// Latest stream error message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Configured mics left out because their channel isn't on the device
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_mics: Vec<String>,
+}
+
+impl StatsSnapshot {
+    pub fn new(dropped_peaks: u64, write_errors: u64, xruns: u64) -> Self {
+        Self {
+            updated: unix_now(),
+            dropped_peaks,
+            write_errors,
+            xruns,
+            shm_overruns: 0,
+            last_error: None,
+            skipped_mics: Vec::new(),
+        }
+    }
+
+    /// Publish the snapshot
+    pub fn write(&self) -> Result<()> {
+        let content = toml::to_string(self).context("Failed to serialize stats")?;
+        fs::write(&runtime_paths().stats, content).context("Failed to write stats file")?;
+        Ok(())
+    }
+
+    /// Read the latest snapshot, ignoring stale ones
+    pub fn read() -> Result<Option<Self>> {
+        let path = &runtime_paths().stats;
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(path).context("Failed to read stats file")?;
+        let snapshot: Self = toml::from_str(&content).context("Failed to parse stats file")?;
+
+        Ok(if snapshot.is_stale() {
+            None
+        } else {
+            Some(snapshot)
+        })
+    }
+
+    /// Remove the published snapshot (called when capture stops)
+    pub fn remove() {
+        let _ = fs::remove_file(&runtime_paths().stats);
+    }
+
+    fn is_stale(&self) -> bool {
+        unix_now().saturating_sub(self.updated) > STALE_AFTER.as_secs()
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_staleness() {
+        let fresh = StatsSnapshot::new(3, 1, 0);
+        assert!(!fresh.is_stale());
+
+        let old = StatsSnapshot {
+            updated: fresh.updated - 60,
+            ..fresh
+        };
+        assert!(old.is_stale());
+    }
+}
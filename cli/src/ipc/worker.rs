@@ -0,0 +1,228 @@
+use anyhow::{bail, Context, Result};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use super::{runtime_paths, DeviceInfo, DriverClient, SocketState, StaleSocket};
+
+/// Driver command run on the worker thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriverOp {
+    /// Make the driver's virtual mics match this list
+    Sync(Vec<DeviceInfo>),
+    Add(DeviceInfo),
+    Remove(String),
+    Ping,
+    /// Run a `[behavior] recovery_helper` command through `sh -c`
+    RunHelper(String),
+}
+
+/// Result of a submitted `DriverOp`
+#[derive(Debug)]
+pub struct DriverOutcome {
+    pub op: DriverOp,
+    pub result: Result<()>,
+}
+
+/// Runs driver commands on a background thread
+///
+/// Each command can block for the socket timeout (5s) when the driver is
+/// wedged; the TUI submits them here and polls `try_recv` on its tick instead
+/// of freezing. The non-TUI commands keep using `DriverClient` directly.
+pub struct DriverWorker {
+    requests: Sender<(u64, DriverOp)>,
+    outcomes: Receiver<DriverOutcome>,
+    // Ops submitted before the current generation are skipped
+    generation: Arc<AtomicU64>,
+    pending: usize,
+    thread: JoinHandle<()>,
+}
+
+impl DriverWorker {
+    /// Start the worker thread (it exits once the worker is dropped)
+    pub fn spawn() -> Result<Self> {
+        Self::spawn_with(execute)
+    }
+
+    fn spawn_with(run: impl Fn(&DriverOp) -> Result<()> + Send + 'static) -> Result<Self> {
+        let (requests, request_rx) = unbounded::<(u64, DriverOp)>();
+        let (outcome_tx, outcomes) = unbounded();
+        let generation = Arc::new(AtomicU64::new(0));
+        let current = generation.clone();
+
+        let thread = thread::Builder::new()
+            .name("duomic-driver".into())
+            .spawn(move || {
+                for (submitted, op) in request_rx {
+                    let result = if submitted < current.load(Ordering::SeqCst) {
+                        Err(anyhow::anyhow!("Cancelled"))
+                    } else {
+                        run(&op)
+                    };
+                    if outcome_tx.send(DriverOutcome { op, result }).is_err() {
+                        break;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            requests,
+            outcomes,
+            generation,
+            pending: 0,
+            thread,
+        })
+    }
+
+    /// Queue a command; its outcome arrives through `try_recv`
+    pub fn submit(&mut self, op: DriverOp) {
+        let generation = self.generation.load(Ordering::SeqCst);
+        if self.requests.send((generation, op)).is_ok() {
+            self.pending += 1;
+        }
+    }
+
+    /// Skip every queued command that hasn't started yet
+    ///
+    /// A command already talking to the driver still finishes (bounded by
+    /// the socket timeout). Skipped commands report a "Cancelled" error.
+    pub fn cancel(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Cancel everything queued and wait for the thread to exit
+    ///
+    /// Call before cleaning up the driver on exit, so a queued sync can't add
+    /// mics back afterwards. Waits for a command already running (bounded by
+    /// the socket timeout).
+    pub fn shutdown(self) {
+        self.cancel();
+        drop(self.requests);
+        if self.thread.join().is_err() {
+            tracing::warn!("Driver worker panicked");
+        }
+    }
+
+    /// Next finished command, if any
+    pub fn try_recv(&mut self) -> Option<DriverOutcome> {
+        let outcome = self.outcomes.try_recv().ok()?;
+        self.pending = self.pending.saturating_sub(1);
+        Some(outcome)
+    }
+
+    /// Whether submitted commands are still queued or running
+    pub fn is_busy(&self) -> bool {
+        self.pending > 0
+    }
+}
+
+/// Run one command with a fresh connection (the driver closes it after each)
+fn execute(op: &DriverOp) -> Result<()> {
+    match op {
+        DriverOp::Sync(expected) => Ok(client()?.sync_devices(expected).map(drop)?),
+        DriverOp::Add(device) => Ok(client()?.add_device_info(device)?),
+        DriverOp::Remove(name) => Ok(client()?.remove_device(name)?),
+        DriverOp::Ping => {
+            if !client()?.ping()? {
+                bail!("Driver did not answer PING");
+            }
+            Ok(())
+        }
+        DriverOp::RunHelper(command) => run_helper(command),
+    }
+}
+
+/// A client for the running driver; fails right away if its socket is missing or stale
+fn client() -> Result<DriverClient> {
+    match DriverClient::socket_state() {
+        SocketState::Missing => bail!("Driver not running"),
+        SocketState::Stale => {
+            return Err(StaleSocket {
+                path: runtime_paths().socket.clone(),
+            }
+            .into())
+        }
+        SocketState::Present => {}
+    }
+    Ok(DriverClient::new())
+}
+
+/// Run `command` through `sh -c`, failing with the last line of its stderr
+fn run_helper(command: &str) -> Result<()> {
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run recovery helper `{}`", command))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "Recovery helper failed ({}): {}",
+            output.status,
+            stderr.lines().last().unwrap_or("no output")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn wait(worker: &mut DriverWorker) -> DriverOutcome {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(outcome) = worker.try_recv() {
+                return outcome;
+            }
+            assert!(Instant::now() < deadline, "worker never answered");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_worker_runs_off_thread_and_cancels_queued_ops() {
+        // Each command reports that it started, then blocks until released
+        let (started_tx, started) = crossbeam_channel::unbounded();
+        let (release, released) = crossbeam_channel::unbounded::<()>();
+        let mut worker = DriverWorker::spawn_with(move |op: &DriverOp| {
+            started_tx.send(op.clone()).unwrap();
+            released.recv_timeout(Duration::from_secs(5))?;
+            match op {
+                DriverOp::Ping => Ok(()),
+                _ => bail!("unsupported"),
+            }
+        })
+        .unwrap();
+
+        // Submitting never waits for the driver
+        worker.submit(DriverOp::Ping);
+        worker.submit(DriverOp::Remove("Host".into()));
+        worker.submit(DriverOp::Ping);
+        assert!(worker.is_busy());
+
+        // The first is running; cancel the two that haven't started
+        let first = started.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(first, DriverOp::Ping);
+        worker.cancel();
+        release.send(()).unwrap();
+
+        let first = wait(&mut worker);
+        assert_eq!(first.op, DriverOp::Ping);
+        assert!(first.result.is_ok());
+        let second = wait(&mut worker);
+        let third = wait(&mut worker);
+        assert_eq!(second.op, DriverOp::Remove("Host".into()));
+        assert_eq!(second.result.unwrap_err().to_string(), "Cancelled");
+        assert_eq!(third.result.unwrap_err().to_string(), "Cancelled");
+        assert!(!worker.is_busy());
+
+        // Shutdown skips what's queued and returns once the thread is gone
+        worker.submit(DriverOp::Sync(Vec::new()));
+        worker.shutdown();
+        assert!(started.try_recv().is_err());
+    }
+}
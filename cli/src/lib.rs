@@ -0,0 +1,17 @@
+//! duomic core library
+//!
+//! Reusable pieces of duomic without the TUI: configuration, audio capture,
+//! and driver IPC. [`Session`] ties them together for embedding duomic in
+//! another application, and [`DeviceWatcher`] reports device and driver
+//! changes as [`DeviceEvent`]s. The core APIs fail with a [`DuomicError`].
+
+pub mod audio;
+pub mod config;
+mod error;
+mod events;
+pub mod ipc;
+mod session;
+
+pub use error::*;
+pub use events::*;
+pub use session::*;
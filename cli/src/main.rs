@@ -1,10 +1,10 @@
-mod audio;
 mod commands;
-mod config;
-mod ipc;
 mod tui;
 
 use clap::{Parser, Subcommand};
+use duomic::config::Config;
+use std::ffi::OsStr;
+use std::path::PathBuf;
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
@@ -26,18 +26,141 @@ struct Cli {
     /// Disable colored output
     #[arg(long, global = true)]
     no_color: bool,
+
+    /// Config file to load and save instead of the default (overrides DUOMIC_CONFIG)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Start interactive TUI (device selection, channel config, dashboard)
+    #[command(group = clap::ArgGroup::new("quick_setup").args(["mappings", "default_device"]))]
     Run {
         /// Device name to use (skip device selection)
         #[arg(short, long)]
         device: Option<String>,
+
+        /// Device by enumeration index, as listed by `duomic doctor` (may shift when devices change)
+        #[arg(long, value_name = "N", conflicts_with = "device")]
+        device_index: Option<usize>,
+
+        /// Don't print the session summary on exit
+        #[arg(short, long)]
+        quiet: bool,
+
+        /// Map a device channel to a virtual mic and skip setup (repeatable), e.g. --map 0=Vocals
+        #[arg(long = "map", value_name = "CHANNEL=NAME", value_parser = commands::run::parse_mapping)]
+        mappings: Vec<(u32, String)>,
+
+        /// Split every channel of the default input device and start right away
+        #[arg(long = "default", conflicts_with_all = ["device", "device_index", "mappings"])]
+        default_device: bool,
+
+        /// Save the --map or --default setup to the config file
+        #[arg(long, requires = "quick_setup")]
+        save: bool,
+
+        /// Start the saved setup in one screen with driver status above the meters
+        #[arg(long)]
+        dashboard: bool,
+
+        /// Start the saved setup without asking first (for scripts and launchd)
+        #[arg(short = 'y', long, visible_alias = "yes")]
+        resume: bool,
+
+        /// Append per-channel peak and RMS to a CSV file on every UI tick
+        #[arg(long, value_name = "FILE")]
+        log_levels: Option<PathBuf>,
+
+        /// Pre-fill setup's mic names from a file: one name or CHANNEL=NAME per line, JSON, or an OBS scene collection
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["mappings", "default_device"])]
+        names_file: Option<PathBuf>,
+
+        /// Unattended install: resume the saved setup on one screen, no setup or prompts, retry failures
+        #[arg(long, conflicts_with_all = ["mappings", "default_device", "names_file"])]
+        kiosk: bool,
+
+        /// Print one refreshing line of ASCII level meters instead of the full-screen UI
+        #[arg(long, conflicts_with_all = ["dashboard", "kiosk", "names_file"])]
+        simple: bool,
     },
     /// Show driver status and active devices
-    Status,
+    Status {
+        /// Print machine-readable JSON instead of text
+        #[arg(long)]
+        json: bool,
+
+        /// Refresh in place every SECONDS (default 1) until Ctrl+C
+        #[arg(
+            short,
+            long,
+            value_name = "SECONDS",
+            num_args = 0..=1,
+            default_missing_value = "1",
+            conflicts_with = "json"
+        )]
+        watch: Option<u64>,
+
+        /// Print one short line for shell prompts, e.g. "duomic: ●3 mics @48k"
+        #[arg(long, conflicts_with_all = ["json", "watch"])]
+        oneline: bool,
+    },
+    /// Check the audio host, devices, driver, shared memory and config
+    Doctor {
+        /// Print machine-readable JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Play a captured channel from shared memory on an output device (for debugging)
+    Monitor {
+        /// Device channel to play (0-based)
+        #[arg(short, long, default_value_t = 0)]
+        channel: u32,
+
+        /// Output device name (partial match, overrides [monitor] output_device)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+    /// Remove virtual mics, runtime files and/or the config
+    Reset {
+        /// Remove every virtual mic from the driver
+        #[arg(long)]
+        devices: bool,
+
+        /// Delete the config file (the one --config points at, if given)
+        #[arg(long = "config-file")]
+        config_file: bool,
+
+        /// Delete shared memory, the stats file and a stale driver socket
+        #[arg(long)]
+        shm: bool,
+
+        /// All of the above
+        #[arg(long)]
+        all: bool,
+
+        /// Don't ask for confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Create the configured virtual mics in the driver and exit (no capture)
+    Sync {
+        /// Print what would change without touching the driver
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Capture for a while and report callback timing (for tuning buffer sizes)
+    #[command(hide = true)]
+    Bench {
+        /// How long to capture
+        #[arg(short, long, value_name = "SECONDS", default_value_t = 10)]
+        seconds: u64,
+
+        /// Device name (partial match; default: the configured device, then the default input)
+        #[arg(short, long)]
+        device: Option<String>,
+    },
 }
 
 fn setup_logging(verbosity: u8) {
@@ -60,22 +183,133 @@ fn setup_logging(verbosity: u8) {
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 }
 
+/// Whether output may be colored: `--no-color`, then a non-empty `NO_COLOR`, then `[ui] color`
+fn use_color(no_color_flag: bool, no_color_env: Option<&OsStr>, config_color: bool) -> bool {
+    if no_color_flag {
+        return false;
+    }
+    if no_color_env.is_some_and(|v| !v.is_empty()) {
+        return false;
+    }
+    config_color
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     setup_logging(cli.verbose);
 
-    // Set color preference
-    if cli.no_color {
+    // Resolved once; every command loads and saves this file
+    let config_location = Config::locate_with(cli.config)?;
+    let config_path = config_location.0.as_path();
+
+    // Set color preference; everything downstream only looks at NO_COLOR
+    let config_color = Config::load_from(config_path)
+        .map(|c| c.ui.color)
+        .unwrap_or(true);
+    let no_color_env = std::env::var_os("NO_COLOR");
+    if !use_color(cli.no_color, no_color_env.as_deref(), config_color) {
         std::env::set_var("NO_COLOR", "1");
     }
 
     match cli.command {
-        Some(Commands::Run { device }) => commands::run::execute(device),
-        Some(Commands::Status) => commands::status::execute(),
+        Some(Commands::Run {
+            device,
+            device_index,
+            quiet,
+            mappings,
+            default_device,
+            save,
+            dashboard,
+            resume,
+            log_levels,
+            names_file,
+            kiosk,
+            simple,
+        }) => commands::run::execute(
+            commands::run::RunOptions {
+                device,
+                device_index,
+                quiet,
+                mappings,
+                default_device,
+                save,
+                dashboard,
+                resume,
+                log_levels,
+                names_file,
+                kiosk,
+                simple,
+            },
+            config_location.clone(),
+        ),
+        Some(Commands::Status {
+            json,
+            watch,
+            oneline,
+        }) => match watch {
+            Some(interval) => commands::status::watch(interval, &config_location),
+            None if oneline => commands::status::oneline(config_path),
+            None => commands::status::execute(json, &config_location),
+        },
+        Some(Commands::Doctor { json }) => commands::doctor::execute(json, config_path),
+        Some(Commands::Monitor { channel, output }) => {
+            commands::monitor::execute(channel, output, config_path)
+        }
+        Some(Commands::Reset {
+            devices,
+            config_file,
+            shm,
+            all,
+            force,
+        }) => commands::reset::execute(
+            commands::reset::ResetOptions {
+                devices,
+                config: config_file,
+                shm,
+                all,
+                force,
+            },
+            config_path,
+        ),
+        Some(Commands::Sync { dry_run }) => commands::sync::execute(dry_run, config_path),
+        Some(Commands::Bench { seconds, device }) => {
+            commands::bench::execute(seconds, device, config_path)
+        }
         None => {
             // Default to run command (includes setup flow)
-            commands::run::execute(None)
+            commands::run::execute(
+                commands::run::RunOptions::default(),
+                config_location.clone(),
+            )
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_color_precedence() {
+        let set = Some(OsStr::new("1"));
+        let empty = Some(OsStr::new(""));
+
+        assert!(use_color(false, None, true));
+        // Config alone can turn colors off
+        assert!(!use_color(false, None, false));
+        // NO_COLOR overrides config; an empty value doesn't count
+        assert!(!use_color(false, set, true));
+        assert!(use_color(false, empty, true));
+        assert!(!use_color(false, empty, false));
+        // The flag wins over everything
+        assert!(!use_color(true, None, true));
+        assert!(!use_color(true, empty, true));
+    }
+}
@@ -0,0 +1,540 @@
+use anyhow::{anyhow, Context, Result};
+use crossbeam_channel::Sender;
+
+use crate::audio::{
+    clip_threshold, find_device, get_cpal_device_for, AudioCapture, AudioDevice, CaptureOptions,
+    GateSettings, MixSettings,
+};
+use crate::config::{Config, VirtualMicConfig};
+use crate::ipc::{DeviceInfo, DriverClient, DriverErrorKind, SharedAudioBuffer};
+use crate::{DeviceEvent, DuomicError};
+
+/// Entry point for running duomic without the TUI
+///
+/// ```no_run
+/// let config = duomic::config::Config::load()?;
+/// let mut session = duomic::Session::start(&config)?;
+/// let levels = session.levels();
+/// session.stop();
+/// # Ok::<(), duomic::DuomicError>(())
+/// ```
+pub struct Session;
+
+impl Session {
+    /// Open the configured device and shared memory, start capturing,
+    /// and sync the driver's virtual mics with the config
+    ///
+    /// A missing device fails with [`DuomicError::DeviceNotFound`].
+    pub fn start(config: &Config) -> Result<RunningSession, DuomicError> {
+        let mut session = Self::start_capture(config)?;
+
+        if DriverClient::is_driver_available() {
+            let mut client = DriverClient::new();
+            if client.connect().is_ok() {
+                // Sync: removes orphans, adds missing
+                if let Err(e) = client.sync_devices(&session.virtual_mics) {
+                    tracing::warn!("Failed to sync devices: {}", e);
+                }
+                session.driver_client = Some(client);
+            }
+        }
+
+        Ok(session)
+    }
+
+    /// Like [`Session::start`] but without talking to the driver
+    ///
+    /// For callers that sync [`RunningSession::virtual_mics`] themselves,
+    /// e.g. on a background thread.
+    pub fn start_capture(config: &Config) -> Result<RunningSession, DuomicError> {
+        // Duplicate names would collide in the driver
+        let mut config = config.clone();
+        for (old, new) in config.dedupe_virtual_mic_names() {
+            tracing::warn!("Renamed duplicate virtual mic {} to {}", old, new);
+        }
+        let config = &config;
+
+        let (device, capture) = open_capture(config, 0)?;
+        let channels = capture.channel_count() as usize;
+        let (mut virtual_mics, out_of_range) =
+            split_by_channel(source_mics(&config.virtual_mics, 0).collect(), channels);
+        warn_out_of_range(&out_of_range, &device, channels);
+
+        // A missing extra device leaves the others running
+        let mut extra_sources = Vec::new();
+        for source in 1..=config.extra_devices.len() as u32 {
+            match ExtraSource::start(config, source) {
+                Ok(extra) => {
+                    virtual_mics.extend_from_slice(&extra.virtual_mics);
+                    extra_sources.push(extra);
+                }
+                Err(e) => tracing::warn!("Not capturing extra device {}: {:#}", source, e),
+            }
+        }
+
+        Ok(RunningSession {
+            device,
+            capture,
+            extra_sources,
+            extra_devices: config.extra_devices.len(),
+            driver_client: None,
+            virtual_mics,
+            out_of_range,
+            levels: vec![0.0; channels],
+            events: None,
+            clip_level: clip_threshold(config.meters.clip_threshold_db),
+            clipping: vec![false; channels],
+        })
+    }
+}
+
+/// Open capture `source` of `config` into that source's shared memory
+fn open_capture(config: &Config, source: u32) -> Result<(AudioDevice, AudioCapture)> {
+    let view = config
+        .for_source(source)
+        .with_context(|| format!("No extra device {}", source))?;
+    let device_name = view.device.name.as_ref().context("No device configured")?;
+
+    let device = find_device(device_name, view.device.index)?
+        .ok_or_else(|| DuomicError::DeviceNotFound(device_name.clone()))?;
+
+    let channels = view.device.capture_channels(device.channels);
+    let buffer =
+        SharedAudioBuffer::open_source(source, channels as u32, device.default_sample_rate)?;
+    let cpal_device = get_cpal_device_for(&device)?;
+    let options = capture_options(&view, channels);
+    let capture = AudioCapture::start(&cpal_device, buffer, &options)?;
+    Ok((device, capture))
+}
+
+/// Virtual mics the driver should have for `config`, extra devices' included
+pub fn expected_mics(config: &Config) -> Vec<DeviceInfo> {
+    let extra = config
+        .extra_devices
+        .iter()
+        .zip(1..)
+        .flat_map(|(extra, source)| source_mics(&extra.virtual_mics, source));
+    source_mics(&config.virtual_mics, 0).chain(extra).collect()
+}
+
+fn source_mics(mics: &[VirtualMicConfig], source: u32) -> impl Iterator<Item = DeviceInfo> + '_ {
+    mics.iter().map(move |m| DeviceInfo {
+        name: m.name.clone(),
+        channel: m.channel,
+        color: m.color.clone(),
+        source,
+    })
+}
+
+/// Split `mics` into those on a channel the device has and those beyond its `channels`
+///
+/// Registering the others would give the driver mics that never get audio,
+/// e.g. when a different device with the same name has fewer channels.
+pub fn split_by_channel(
+    mics: Vec<DeviceInfo>,
+    channels: usize,
+) -> (Vec<DeviceInfo>, Vec<DeviceInfo>) {
+    mics.into_iter()
+        .partition(|mic| (mic.channel as usize) < channels)
+}
+
+fn warn_out_of_range(mics: &[DeviceInfo], device: &AudioDevice, channels: usize) {
+    for mic in mics {
+        tracing::warn!(
+            "Skipping {}: channel {} is not captured from {} ({} channels)",
+            mic.name,
+            mic.channel,
+            device.name,
+            channels
+        );
+    }
+}
+
+/// Build per-channel capture processing from the virtual mic config
+///
+/// Gates apply to the device channel, so if several mics share a channel
+/// the last one with a `gate_db` wins.
+pub fn capture_options(config: &Config, channels: u16) -> CaptureOptions {
+    let mut gates = vec![None; channels as usize];
+    for mic in &config.virtual_mics {
+        if let (Some(threshold_db), Some(gate)) = (mic.gate_db, gates.get_mut(mic.channel as usize))
+        {
+            *gate = Some(GateSettings {
+                threshold_db,
+                attack_ms: config.gate.attack_ms,
+                release_ms: config.gate.release_ms,
+            });
+        }
+    }
+    CaptureOptions {
+        gates,
+        mix: (0..channels as usize)
+            .map(|ch| channel_mix(config, ch))
+            .collect(),
+        clip_threshold: clip_threshold(config.meters.clip_threshold_db),
+        correlation: correlation_pair(config, channels),
+        measure_timing: false,
+        max_channels: config.device.max_channels,
+    }
+}
+
+/// Device channels for the correlation meter, if it's shown
+///
+/// `[meters] correlation_pair` when set, otherwise the mics' channels when
+/// they use exactly two. `None` with `[meters] correlation = false` or a
+/// channel the device doesn't have.
+pub fn correlation_pair(config: &Config, channels: u16) -> Option<(usize, usize)> {
+    if !config.meters.correlation {
+        return None;
+    }
+    let (left, right) = match config.meters.correlation_pair {
+        Some([left, right]) => (left, right),
+        None => {
+            let mut used: Vec<u32> = config.virtual_mics.iter().map(|m| m.channel).collect();
+            used.sort_unstable();
+            used.dedup();
+            match used[..] {
+                [left, right] => (left, right),
+                _ => return None,
+            }
+        }
+    };
+    let in_range = |ch: u32| ch < channels as u32;
+    (left != right && in_range(left) && in_range(right)).then_some((left as usize, right as usize))
+}
+
+/// Gain/mute/invert/high-pass for a device channel from the mics on it (last one wins, like gates)
+pub fn channel_mix(config: &Config, channel: usize) -> MixSettings {
+    config
+        .virtual_mics
+        .iter()
+        .rfind(|m| m.channel as usize == channel)
+        .map(mix_settings)
+        .unwrap_or_default()
+}
+
+fn mix_settings(mic: &VirtualMicConfig) -> MixSettings {
+    MixSettings {
+        gain_db: mic.gain_db,
+        muted: mic.muted,
+        invert: mic.invert,
+        highpass_hz: mic.highpass_hz,
+    }
+}
+
+/// Capture from one `[[extra_devices]]` entry, into its own shared memory
+pub struct ExtraSource {
+    source: u32,
+    device: AudioDevice,
+    capture: AudioCapture,
+    virtual_mics: Vec<DeviceInfo>,
+    levels: Vec<f32>,
+}
+
+impl ExtraSource {
+    fn start(config: &Config, source: u32) -> Result<Self> {
+        let (device, capture) = open_capture(config, source)?;
+        let view = config
+            .for_source(source)
+            .context("Extra device disappeared")?;
+        let channels = capture.channel_count() as usize;
+        let (virtual_mics, out_of_range) =
+            split_by_channel(source_mics(&view.virtual_mics, source).collect(), channels);
+        warn_out_of_range(&out_of_range, &device, channels);
+        Ok(Self {
+            source,
+            device,
+            capture,
+            virtual_mics,
+            levels: vec![0.0; channels],
+        })
+    }
+
+    /// Capture source number (the Nth `[[extra_devices]]` entry, from 1)
+    pub fn source(&self) -> u32 {
+        self.source
+    }
+
+    /// Device being captured
+    pub fn device(&self) -> &AudioDevice {
+        &self.device
+    }
+
+    /// Underlying audio capture
+    pub fn capture(&self) -> &AudioCapture {
+        &self.capture
+    }
+
+    /// Virtual mics on this device's channels
+    pub fn virtual_mics(&self) -> &[DeviceInfo] {
+        &self.virtual_mics
+    }
+
+    /// Peak level per device channel, like [`RunningSession::levels`]
+    pub fn levels(&mut self) -> &[f32] {
+        drain_peaks(&self.capture, &mut self.levels);
+        &self.levels
+    }
+
+    /// [`RunningSession::restart`] for this device
+    fn restart(&mut self, config: &Config) -> Result<()> {
+        let view = config
+            .for_source(self.source)
+            .context("Extra device was removed")?;
+        let device = find_device(&self.device.name, Some(self.device.index))?
+            .ok_or_else(|| DuomicError::DeviceNotFound(self.device.name.clone()))?;
+        self.capture.restart(&capture_options(
+            &view,
+            view.device.capture_channels(device.channels),
+        ))?;
+
+        let channels = self.capture.channel_count() as usize;
+        let (virtual_mics, out_of_range) = split_by_channel(
+            source_mics(&view.virtual_mics, self.source).collect(),
+            channels,
+        );
+        warn_out_of_range(&out_of_range, &device, channels);
+        self.device = device;
+        self.virtual_mics = virtual_mics;
+        self.levels.fill(0.0);
+        Ok(())
+    }
+}
+
+/// Loudest peak per channel since the last call, kept when nothing arrived
+fn drain_peaks(capture: &AudioCapture, levels: &mut [f32]) {
+    let mut received = false;
+    while let Ok(peaks) = capture.peak_receiver().try_recv() {
+        if !received {
+            levels.iter_mut().for_each(|l| *l = 0.0);
+            received = true;
+        }
+        for (level, peak) in levels.iter_mut().zip(peaks.iter()) {
+            *level = level.max(*peak);
+        }
+    }
+}
+
+/// A capture session started by [`Session::start`]
+///
+/// Dropping the session stops capture but leaves the virtual mics in the
+/// driver; call [`RunningSession::stop`] to remove them as well.
+pub struct RunningSession {
+    device: AudioDevice,
+    capture: AudioCapture,
+    // `[[extra_devices]]` that opened, and how many the config had
+    extra_sources: Vec<ExtraSource>,
+    extra_devices: usize,
+    driver_client: Option<DriverClient>,
+    virtual_mics: Vec<DeviceInfo>,
+    // Configured mics on channels the device doesn't have (not registered)
+    out_of_range: Vec<DeviceInfo>,
+    levels: Vec<f32>,
+    events: Option<Sender<DeviceEvent>>,
+    clip_level: f32,
+    // Whether each channel was clipping at the last `levels` call
+    clipping: Vec<bool>,
+}
+
+impl RunningSession {
+    /// Device being captured
+    pub fn device(&self) -> &AudioDevice {
+        &self.device
+    }
+
+    /// Underlying audio capture
+    pub fn capture(&self) -> &AudioCapture {
+        &self.capture
+    }
+
+    /// `[[extra_devices]]` being captured (ones that failed to open are left out)
+    pub fn extra_sources(&self) -> &[ExtraSource] {
+        &self.extra_sources
+    }
+
+    /// Like [`RunningSession::extra_sources`], for reading their levels
+    pub fn extra_sources_mut(&mut self) -> &mut [ExtraSource] {
+        &mut self.extra_sources
+    }
+
+    /// Whether the driver was reachable when the session started
+    pub fn driver_connected(&self) -> bool {
+        self.driver_client.is_some()
+    }
+
+    /// Virtual mics this session expects in the driver (names deduplicated),
+    /// extra devices' included
+    pub fn virtual_mics(&self) -> &[DeviceInfo] {
+        &self.virtual_mics
+    }
+
+    /// Configured mics skipped because their channel isn't on the device
+    pub fn out_of_range_mics(&self) -> &[DeviceInfo] {
+        &self.out_of_range
+    }
+
+    /// Peak level per device channel (linear 0.0 to 1.0)
+    ///
+    /// Drains pending peak updates from the capture callback and returns the
+    /// loudest value seen since the previous call.
+    pub fn levels(&mut self) -> &[f32] {
+        drain_peaks(&self.capture, &mut self.levels);
+
+        for (channel, (&level, clipping)) in self.levels.iter().zip(&mut self.clipping).enumerate()
+        {
+            let now_clipping = level >= self.clip_level;
+            if now_clipping && !*clipping {
+                if let Some(events) = &self.events {
+                    let _ = events.send(DeviceEvent::Clip { channel });
+                }
+            }
+            *clipping = now_clipping;
+        }
+        &self.levels
+    }
+
+    /// Restart capture with a (possibly edited) config on the same device
+    ///
+    /// Keeps the shared memory mapped (see [`AudioCapture::restart`]). Fails
+    /// if the config now names another device, lists other extra devices or
+    /// the channel count changed; start a new session then. An extra device
+    /// that fails to restart is dropped with a warning. The driver is not
+    /// synced here.
+    pub fn restart(&mut self, config: &Config) -> Result<(), DuomicError> {
+        let mut config = config.clone();
+        for (old, new) in config.dedupe_virtual_mic_names() {
+            tracing::warn!("Renamed duplicate virtual mic {} to {}", old, new);
+        }
+
+        let same_device = config.device.name.as_ref() == Some(&self.device.name)
+            && config.device.index.is_none_or(|i| i == self.device.index);
+        if !same_device {
+            return Err(anyhow!("Configured device changed").into());
+        }
+        let same_extras = config.extra_devices.len() == self.extra_devices
+            && self.extra_sources.iter().all(|extra| {
+                let configured = &config.extra_devices[extra.source as usize - 1].device;
+                configured.name.as_ref() == Some(&extra.device.name)
+            });
+        if !same_extras {
+            return Err(anyhow!("Configured extra devices changed").into());
+        }
+        let device = find_device(&self.device.name, Some(self.device.index))?
+            .ok_or_else(|| DuomicError::DeviceNotFound(self.device.name.clone()))?;
+
+        self.capture.restart(&capture_options(
+            &config,
+            config.device.capture_channels(device.channels),
+        ))?;
+
+        let channels = self.capture.channel_count() as usize;
+        let (mut virtual_mics, out_of_range) =
+            split_by_channel(source_mics(&config.virtual_mics, 0).collect(), channels);
+        warn_out_of_range(&out_of_range, &device, channels);
+
+        self.extra_sources
+            .retain_mut(|extra| match extra.restart(&config) {
+                Ok(()) => {
+                    virtual_mics.extend_from_slice(&extra.virtual_mics);
+                    true
+                }
+                Err(e) => {
+                    tracing::warn!("Stopped capturing {}: {:#}", extra.device.name, e);
+                    false
+                }
+            });
+        self.device = device;
+        self.virtual_mics = virtual_mics;
+        self.out_of_range = out_of_range;
+        self.clip_level = clip_threshold(config.meters.clip_threshold_db);
+        self.levels.fill(0.0);
+        self.clipping.fill(false);
+        Ok(())
+    }
+
+    /// Send a [`DeviceEvent::Clip`] whenever a channel starts clipping
+    ///
+    /// Clipping is checked in [`RunningSession::levels`], so keep calling it.
+    /// Pass [`crate::DeviceWatcher::sender`] to get clips alongside device events.
+    pub fn send_events_to(&mut self, sender: Sender<DeviceEvent>) {
+        self.events = Some(sender);
+    }
+
+    /// Stop capturing and remove this session's virtual mics from the driver
+    pub fn stop(mut self) {
+        self.capture.stop();
+        for extra in &mut self.extra_sources {
+            extra.capture.stop();
+        }
+
+        if let Some(mut client) = self.driver_client.take() {
+            for mic in &self.virtual_mics {
+                match client.remove_device(&mic.name) {
+                    Ok(()) => {}
+                    // Already gone (e.g. removed by another client)
+                    Err(DuomicError::Driver(e)) if e.kind() == DriverErrorKind::NotFound => {}
+                    Err(e) => tracing::warn!("Failed to remove device {}: {}", mic.name, e),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ExtraDeviceConfig;
+
+    #[test]
+    fn test_split_by_channel() {
+        let mic = |name: &str, channel| DeviceInfo {
+            name: name.to_string(),
+            channel,
+            color: None,
+            source: 0,
+        };
+        let (kept, skipped) =
+            split_by_channel(vec![mic("Host", 0), mic("Guest", 4), mic("Room", 1)], 2);
+        let names = |mics: &[DeviceInfo]| mics.iter().map(|m| m.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&kept), ["Host", "Room"]);
+        assert_eq!(names(&skipped), ["Guest"]);
+    }
+
+    #[test]
+    fn test_expected_mics_cover_extra_devices() {
+        let mut config = Config::default();
+        config.add_virtual_mic("Host".to_string(), 0);
+        let mut extra = ExtraDeviceConfig::default();
+        extra
+            .virtual_mics
+            .push(VirtualMicConfig::new("Guest".to_string(), 1));
+        config.extra_devices = vec![ExtraDeviceConfig::default(), extra];
+
+        let mics: Vec<(String, u32, u32)> = expected_mics(&config)
+            .into_iter()
+            .map(|m| (m.name, m.channel, m.source))
+            .collect();
+        assert_eq!(
+            mics,
+            [("Host".to_string(), 0, 0), ("Guest".to_string(), 1, 2)]
+        );
+    }
+
+    #[test]
+    fn test_correlation_pair() {
+        let mut config = Config::default();
+        config.add_virtual_mic("Left".to_string(), 1);
+        config.add_virtual_mic("Right".to_string(), 0);
+        config.add_virtual_mic("Both".to_string(), 1);
+        assert_eq!(correlation_pair(&config, 2), Some((0, 1)));
+        assert_eq!(correlation_pair(&config, 1), None);
+
+        config.add_virtual_mic("Room".to_string(), 2);
+        assert_eq!(correlation_pair(&config, 4), None);
+        config.meters.correlation_pair = Some([2, 3]);
+        assert_eq!(correlation_pair(&config, 4), Some((2, 3)));
+
+        config.meters.correlation = false;
+        assert_eq!(correlation_pair(&config, 4), None);
+    }
+}
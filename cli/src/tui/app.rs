@@ -1,35 +1,63 @@
 use anyhow::Result;
 use crossterm::{
+    cursor::Show,
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::prelude::*;
-use std::io::{self, Stdout};
+use std::io::{self, Stdout, Write};
+use std::panic;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+use crate::commands::color_enabled;
+
+/// Whether a `Terminal` has the screen in raw mode and the alternate screen
+static TUI_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+static PANIC_HOOK: Once = Once::new();
 
 /// Terminal wrapper for TUI applications
 pub struct Terminal {
     terminal: ratatui::Terminal<CrosstermBackend<Stdout>>,
+    /// False when NO_COLOR is set (by `--no-color`, the env or `[ui] color`)
+    color: bool,
 }
 
 impl Terminal {
     /// Create a new terminal and enter alternate screen mode
+    ///
+    /// The first call also installs a panic hook that restores the terminal
+    /// before the panic message is printed (then runs the previous hook), so
+    /// a panic never leaves the shell in raw mode.
     pub fn new() -> Result<Self> {
+        install_panic_hook();
         enable_raw_mode()?;
+        TUI_ACTIVE.store(true, Ordering::SeqCst);
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen)?;
 
         let backend = CrosstermBackend::new(stdout);
         let terminal = ratatui::Terminal::new(backend)?;
 
-        Ok(Self { terminal })
+        Ok(Self {
+            terminal,
+            color: color_enabled(),
+        })
     }
 
-    /// Draw a frame
+    /// Draw a frame (colors stripped when disabled)
     pub fn draw<F>(&mut self, f: F) -> Result<()>
     where
         F: FnOnce(&mut Frame),
     {
-        self.terminal.draw(f)?;
+        let color = self.color;
+        self.terminal.draw(|frame| {
+            f(frame);
+            if !color {
+                strip_colors(frame.buffer_mut());
+            }
+        })?;
         Ok(())
     }
 
@@ -46,14 +74,47 @@ impl Terminal {
     }
 }
 
+/// Reset every cell to the terminal's default colors
+///
+/// Cells that relied on a background color (highlights) are reversed instead,
+/// so the selection stays visible.
+pub fn strip_colors(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        if cell.bg != Color::Reset {
+            cell.modifier.insert(Modifier::REVERSED);
+        }
+        cell.fg = Color::Reset;
+        cell.bg = Color::Reset;
+    }
+}
+
 impl Drop for Terminal {
     fn drop(&mut self) {
-        // Restore terminal state
+        restore_terminal(self.terminal.backend_mut());
+    }
+}
+
+/// Leave raw mode and the alternate screen and show the cursor
+///
+/// Only the first call after `Terminal::new` does anything, so the panic
+/// hook and `Drop` can both call it.
+fn restore_terminal(out: &mut impl Write) {
+    if TUI_ACTIVE.swap(false, Ordering::SeqCst) {
         let _ = disable_raw_mode();
-        let _ = execute!(self.terminal.backend_mut(), LeaveAlternateScreen);
+        let _ = execute!(out, LeaveAlternateScreen, Show);
     }
 }
 
+fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal(&mut io::stdout());
+            previous(info);
+        }));
+    });
+}
+
 /// Application state enum for state machine pattern
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AppState {
@@ -136,3 +197,27 @@ impl InputState {
         self.cursor = 0;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_restores_terminal_once() {
+        install_panic_hook();
+        // As if a Terminal had entered the TUI (raw mode isn't needed for the flag)
+        TUI_ACTIVE.store(true, Ordering::SeqCst);
+        let result = panic::catch_unwind(|| panic!("draw failed"));
+        assert!(result.is_err());
+        assert!(!TUI_ACTIVE.load(Ordering::SeqCst));
+
+        // Drop after the hook writes nothing more
+        let mut out = Vec::new();
+        restore_terminal(&mut out);
+        assert!(out.is_empty());
+
+        TUI_ACTIVE.store(true, Ordering::SeqCst);
+        restore_terminal(&mut out);
+        assert_eq!(String::from_utf8_lossy(&out), "\x1b[?1049l\x1b[?25h");
+    }
+}
@@ -1,8 +1,12 @@
 use anyhow::Result;
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+use super::KeyMap;
 
 /// Terminal events that can be handled by the TUI
 #[derive(Debug, Clone)]
@@ -18,6 +22,8 @@ pub enum AppEvent {
 /// Event handler for terminal input
 pub struct EventHandler {
     receiver: Receiver<AppEvent>,
+    /// Tick interval in milliseconds (read by the event thread each cycle)
+    tick_ms: Arc<AtomicU64>,
     _handle: thread::JoinHandle<()>,
 }
 
@@ -25,39 +31,60 @@ impl EventHandler {
     /// Create a new event handler with the specified tick rate
     pub fn new(tick_rate: Duration) -> Self {
         let (sender, receiver) = bounded(100);
+        let tick_ms = Arc::new(AtomicU64::new(tick_rate.as_millis().max(1) as u64));
+        let tick_ms_thread = tick_ms.clone();
 
         let handle = thread::spawn(move || {
-            Self::event_loop(sender, tick_rate);
+            Self::event_loop(sender, tick_ms_thread);
         });
 
         Self {
             receiver,
+            tick_ms,
             _handle: handle,
         }
     }
 
-    fn event_loop(sender: Sender<AppEvent>, tick_rate: Duration) {
+    /// Change the tick rate (takes effect from the next tick)
+    pub fn set_tick_rate(&self, tick_rate: Duration) {
+        self.tick_ms
+            .store(tick_rate.as_millis().max(1) as u64, Ordering::Relaxed);
+    }
+
+    /// Current tick rate
+    pub fn tick_rate(&self) -> Duration {
+        Duration::from_millis(self.tick_ms.load(Ordering::Relaxed))
+    }
+
+    fn event_loop(sender: Sender<AppEvent>, tick_ms: Arc<AtomicU64>) {
+        let tick_rate = || Duration::from_millis(tick_ms.load(Ordering::Relaxed));
+        let mut next_tick = Instant::now() + tick_rate();
+
         loop {
-            // Poll for events with timeout
-            if event::poll(tick_rate).unwrap_or(false) {
-                match event::read() {
-                    Ok(Event::Key(key)) => {
-                        if sender.send(AppEvent::Key(key)).is_err() {
-                            break;
-                        }
+            // Wait for input until the next tick is due (input doesn't reset the timer)
+            let timeout = next_tick.saturating_duration_since(Instant::now());
+            match event::poll(timeout) {
+                Ok(true) => match event::read() {
+                    Ok(Event::Key(key)) if sender.send(AppEvent::Key(key)).is_err() => {
+                        break;
                     }
-                    Ok(Event::Resize(w, h)) => {
-                        if sender.send(AppEvent::Resize(w, h)).is_err() {
-                            break;
-                        }
+                    Ok(Event::Resize(w, h)) if sender.send(AppEvent::Resize(w, h)).is_err() => {
+                        break;
                     }
                     _ => {}
-                }
+                },
+                Ok(false) => {}
+                // No terminal to poll: don't spin, just keep ticking
+                Err(_) => thread::sleep(timeout),
             }
 
-            // Send tick event
-            if sender.send(AppEvent::Tick).is_err() {
-                break;
+            // Send tick event on its own schedule
+            let now = Instant::now();
+            if now >= next_tick {
+                if sender.send(AppEvent::Tick).is_err() {
+                    break;
+                }
+                next_tick = now + tick_rate();
             }
         }
     }
@@ -67,6 +94,17 @@ impl EventHandler {
         Ok(self.receiver.recv()?)
     }
 
+    /// Get the next event, giving up after `timeout`
+    ///
+    /// Lets the caller poll shutdown flags even if the event thread stalls.
+    pub fn next_timeout(&self, timeout: Duration) -> Result<Option<AppEvent>> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(event) => Ok(Some(event)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Try to get the next event without blocking
     pub fn try_next(&self) -> Option<AppEvent> {
         self.receiver.try_recv().ok()
@@ -87,8 +125,11 @@ pub enum KeyAction {
     No,
     Restart,
     Setup,
+    Toggle,
     Retry,
     Backspace,
+    /// Tab while typing: accept the defaults for the remaining fields
+    AcceptAll,
     Char(char),
     None,
 }
@@ -97,23 +138,32 @@ impl KeyAction {
     /// Convert KeyEvent to KeyAction for navigation/menu contexts
     /// Use this when NOT in text input mode
     pub fn from_navigation(key: KeyEvent) -> Self {
+        Self::from_navigation_with(key, &KeyMap::default())
+    }
+
+    /// Like `from_navigation`, with quit/restart/setup/toggle/select/cancel
+    /// looked up in `keymap` instead of the default keys
+    pub fn from_navigation_with(key: KeyEvent, keymap: &KeyMap) -> Self {
         // Ctrl+C always quits
-        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        if is_interrupt(&key) {
             return KeyAction::Quit;
         }
 
+        if let Some(action) = keymap.action(key.code) {
+            return action;
+        }
+
         match key.code {
-            KeyCode::Char('q') => KeyAction::Quit,
+            KeyCode::Char('h') if keymap.vim_keys() => KeyAction::Left,
+            KeyCode::Char('j') if keymap.vim_keys() => KeyAction::Down,
+            KeyCode::Char('k') if keymap.vim_keys() => KeyAction::Up,
+            KeyCode::Char('l') if keymap.vim_keys() => KeyAction::Right,
             KeyCode::Char('y') => KeyAction::Yes,
             KeyCode::Char('n') => KeyAction::No,
-            KeyCode::Char('r') => KeyAction::Restart,
-            KeyCode::Char('s') => KeyAction::Setup,
             KeyCode::Up => KeyAction::Up,
             KeyCode::Down => KeyAction::Down,
             KeyCode::Left => KeyAction::Left,
             KeyCode::Right => KeyAction::Right,
-            KeyCode::Enter => KeyAction::Select,
-            KeyCode::Esc => KeyAction::Cancel,
             KeyCode::Backspace => KeyAction::Backspace,
             KeyCode::Char(c) => KeyAction::Char(c),
             _ => KeyAction::None,
@@ -124,7 +174,7 @@ impl KeyAction {
     /// All character keys pass through as Char(c)
     pub fn from_text_input(key: KeyEvent) -> Self {
         // Ctrl+C always quits
-        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        if is_interrupt(&key) {
             return KeyAction::Quit;
         }
 
@@ -132,12 +182,22 @@ impl KeyAction {
             KeyCode::Enter => KeyAction::Select,
             KeyCode::Esc => KeyAction::Cancel,
             KeyCode::Backspace => KeyAction::Backspace,
+            KeyCode::Tab => KeyAction::AcceptAll,
+            KeyCode::Up => KeyAction::Up,
+            KeyCode::Down => KeyAction::Down,
+            KeyCode::Left => KeyAction::Left,
+            KeyCode::Right => KeyAction::Right,
             KeyCode::Char(c) => KeyAction::Char(c), // All chars pass through!
             _ => KeyAction::None,
         }
     }
 }
 
+/// Whether `key` is Ctrl+C (an explicit interrupt, never remapped or confirmed)
+pub fn is_interrupt(key: &KeyEvent) -> bool {
+    key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c')
+}
+
 impl From<KeyEvent> for KeyAction {
     fn from(key: KeyEvent) -> Self {
         // Default behavior: navigation mode (for backwards compatibility)
@@ -0,0 +1,236 @@
+use crossterm::event::KeyCode;
+
+use duomic::config::KeybindingsConfig;
+
+use super::KeyAction;
+
+/// Letters the Running, Setup and Error screens handle themselves
+const SCREEN_KEYS: &str = "mievVgaAdfc+-=";
+
+/// Keys with a fixed meaning that can't be rebound (y/n prompts, navigation,
+/// digit jump and the screen keys)
+fn is_reserved(code: KeyCode) -> bool {
+    match code {
+        KeyCode::Up | KeyCode::Down | KeyCode::Left | KeyCode::Right | KeyCode::Backspace => true,
+        KeyCode::Char(c) => matches!(c, 'y' | 'n') || c.is_ascii_digit() || SCREEN_KEYS.contains(c),
+        _ => false,
+    }
+}
+
+/// Parse a key string from the config ("q", "space", "enter", "esc", "tab", ...)
+pub fn parse_key(key: &str) -> Option<KeyCode> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+
+    match key.to_lowercase().as_str() {
+        "space" => Some(KeyCode::Char(' ')),
+        "enter" | "return" => Some(KeyCode::Enter),
+        "esc" | "escape" => Some(KeyCode::Esc),
+        "tab" => Some(KeyCode::Tab),
+        "delete" | "del" => Some(KeyCode::Delete),
+        "home" => Some(KeyCode::Home),
+        "end" => Some(KeyCode::End),
+        _ => None,
+    }
+}
+
+/// Short label for help bars
+fn key_label(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Rebindable keys, built from the `[keybindings]` config section
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    bindings: Vec<(KeyCode, KeyAction)>,
+    vim_keys: bool,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::from_config(&KeybindingsConfig::default()).0
+    }
+}
+
+impl KeyMap {
+    /// Build the key map, returning warnings for unparseable or conflicting bindings
+    ///
+    /// A binding that can't be used falls back to the action's default key if
+    /// that is still free; otherwise the action is left unbound.
+    pub fn from_config(config: &KeybindingsConfig) -> (Self, Vec<String>) {
+        let defaults = KeybindingsConfig::default();
+        let entries = [
+            ("quit", &config.quit, &defaults.quit, KeyAction::Quit),
+            (
+                "restart",
+                &config.restart,
+                &defaults.restart,
+                KeyAction::Restart,
+            ),
+            ("setup", &config.setup, &defaults.setup, KeyAction::Setup),
+            (
+                "toggle",
+                &config.toggle,
+                &defaults.toggle,
+                KeyAction::Toggle,
+            ),
+            (
+                "select",
+                &config.select,
+                &defaults.select,
+                KeyAction::Select,
+            ),
+            (
+                "cancel",
+                &config.cancel,
+                &defaults.cancel,
+                KeyAction::Cancel,
+            ),
+        ];
+
+        let mut bindings: Vec<(KeyCode, KeyAction)> = Vec::new();
+        let mut warnings = Vec::new();
+
+        for (name, key, default, action) in entries {
+            let bound_to = |code: KeyCode, bindings: &[(KeyCode, KeyAction)]| {
+                bindings
+                    .iter()
+                    .find(|(c, _)| *c == code)
+                    .map(|(_, a)| format!("{:?}", a).to_lowercase())
+            };
+
+            let checked = match parse_key(key) {
+                None => Err(format!("unknown key \"{}\"", key)),
+                Some(code) if is_reserved(code) => Err(format!("\"{}\" is reserved", key)),
+                Some(code) => match bound_to(code, &bindings) {
+                    Some(other) => Err(format!("\"{}\" is already bound to {}", key, other)),
+                    None => Ok(code),
+                },
+            };
+
+            let problem = match checked {
+                Ok(code) => {
+                    bindings.push((code, action));
+                    continue;
+                }
+                Err(problem) => problem,
+            };
+
+            match parse_key(default).filter(|&code| bound_to(code, &bindings).is_none()) {
+                Some(code) if key != default => {
+                    warnings.push(format!(
+                        "Keybinding {}: {}, using \"{}\"",
+                        name, problem, default
+                    ));
+                    bindings.push((code, action));
+                }
+                _ => {
+                    warnings.push(format!(
+                        "Keybinding {}: {}, leaving it unbound",
+                        name, problem
+                    ));
+                }
+            }
+        }
+
+        (
+            Self {
+                bindings,
+                vim_keys: false,
+            },
+            warnings,
+        )
+    }
+
+    /// Let h/j/k/l navigate (`[ui] vim_keys`); bindings above still win
+    pub fn set_vim_keys(&mut self, enabled: bool) {
+        self.vim_keys = enabled;
+    }
+
+    /// Whether h/j/k/l navigate
+    pub fn vim_keys(&self) -> bool {
+        self.vim_keys
+    }
+
+    /// Action bound to a key, if any
+    pub fn action(&self, code: KeyCode) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, action)| *action)
+    }
+
+    /// Help bar label for an action's key ("-" when unbound)
+    pub fn label(&self, action: KeyAction) -> String {
+        self.bindings
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map(|(code, _)| key_label(*code))
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_match_builtin_keys() {
+        let (keymap, warnings) = KeyMap::from_config(&KeybindingsConfig::default());
+        assert!(warnings.is_empty());
+        assert_eq!(keymap.action(KeyCode::Char('q')), Some(KeyAction::Quit));
+        assert_eq!(keymap.action(KeyCode::Char('r')), Some(KeyAction::Restart));
+        assert_eq!(keymap.action(KeyCode::Char('s')), Some(KeyAction::Setup));
+        assert_eq!(keymap.action(KeyCode::Char(' ')), Some(KeyAction::Toggle));
+        assert_eq!(keymap.action(KeyCode::Enter), Some(KeyAction::Select));
+        assert_eq!(keymap.action(KeyCode::Esc), Some(KeyAction::Cancel));
+        assert_eq!(keymap.label(KeyAction::Toggle), "Space");
+    }
+
+    #[test]
+    fn test_conflicts_fall_back_with_warning() {
+        let config = KeybindingsConfig {
+            quit: "x".to_string(),
+            restart: "x".to_string(),
+            setup: "y".to_string(),
+            toggle: "bogus".to_string(),
+            ..KeybindingsConfig::default()
+        };
+        let (keymap, warnings) = KeyMap::from_config(&config);
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[1].contains("is reserved"));
+
+        assert_eq!(keymap.action(KeyCode::Char('x')), Some(KeyAction::Quit));
+        assert_eq!(keymap.action(KeyCode::Char('q')), None);
+        assert_eq!(keymap.action(KeyCode::Char('r')), Some(KeyAction::Restart));
+        assert_eq!(keymap.action(KeyCode::Char('s')), Some(KeyAction::Setup));
+        assert_eq!(keymap.action(KeyCode::Char(' ')), Some(KeyAction::Toggle));
+    }
+
+    #[test]
+    fn test_screen_keys_are_reserved() {
+        let config = KeybindingsConfig {
+            quit: "m".to_string(),
+            restart: "+".to_string(),
+            setup: "A".to_string(),
+            ..KeybindingsConfig::default()
+        };
+        let (keymap, warnings) = KeyMap::from_config(&config);
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings.iter().all(|w| w.contains("is reserved")));
+        assert_eq!(keymap.action(KeyCode::Char('m')), None);
+        assert_eq!(keymap.action(KeyCode::Char('q')), Some(KeyAction::Quit));
+    }
+}
@@ -3,7 +3,9 @@
 
 mod app;
 mod events;
+mod keymap;
 pub mod widgets;
 
 pub use app::*;
 pub use events::*;
+pub use keymap::*;
@@ -2,6 +2,9 @@ use ratatui::{
     prelude::*,
     widgets::{Block, Widget},
 };
+use unicode_width::UnicodeWidthStr;
+
+use super::truncate_to_width;
 
 /// Channel picker with real-time level preview
 pub struct ChannelPicker<'a> {
@@ -75,6 +78,7 @@ impl Widget for ChannelPicker<'_> {
 
             // Channel label
             let label = format!("Channel {} ({}):", i, channel_name);
+            let label = truncate_to_width(&label, inner.width.saturating_sub(2) as usize, "…");
             let label_style = if is_selected {
                 Style::default()
                     .fg(Color::White)
@@ -85,7 +89,7 @@ impl Widget for ChannelPicker<'_> {
             buf.set_string(inner.x + 2, y, &label, label_style);
 
             // Level meter (inline, compact)
-            let meter_start = inner.x + 2 + label.len() as u16 + 1;
+            let meter_start = inner.x + 2 + label.width() as u16 + 1;
             let meter_width = 16u16;
 
             if meter_start + meter_width < inner.x + inner.width {
@@ -191,18 +195,21 @@ impl Widget for TextInput<'_> {
         );
 
         // Render input field
-        let input_x = inner.x + self.label.len() as u16 + 1;
-        let input_width = inner.width.saturating_sub(self.label.len() as u16 + 2);
+        let label_width = self.label.width() as u16;
+        let input_x = inner.x + label_width + 1;
+        let input_width = inner.width.saturating_sub(label_width + 2);
 
         // Background for input field
         buf.set_string(input_x, inner.y, "> ", Style::default().fg(Color::Yellow));
 
-        // Render value with cursor
-        let display_value = if self.value.len() > input_width as usize - 3 {
-            &self.value[self.value.len() - (input_width as usize - 3)..]
-        } else {
-            self.value
-        };
+        // Render value with cursor, keeping the end of a long value in view
+        let room = (input_width as usize).saturating_sub(3);
+        let mut display_value = self.value;
+        while display_value.width() > room {
+            let mut chars = display_value.chars();
+            chars.next();
+            display_value = chars.as_str();
+        }
 
         buf.set_string(
             input_x + 2,
@@ -212,7 +219,7 @@ impl Widget for TextInput<'_> {
         );
 
         // Render cursor
-        let cursor_x = input_x + 2 + self.cursor.min(display_value.len()) as u16;
+        let cursor_x = input_x + 2 + self.cursor.min(display_value.width()) as u16;
         buf.set_string(
             cursor_x,
             inner.y,
@@ -0,0 +1,159 @@
+use ratatui::{prelude::*, widgets::Widget};
+use unicode_width::UnicodeWidthStr;
+
+use super::truncate_to_width;
+
+/// Stereo correlation meter: a bar growing left (out of phase) or right (in
+/// phase) from a center line
+///
+/// - Green: +0.5 to +1 (mono-compatible)
+/// - Yellow: 0 to +0.5 (wide stereo)
+/// - Red: below 0 (phase problems, cancels in mono)
+pub struct CorrelationMeter<'a> {
+    /// Correlation coefficient (-1.0 to 1.0), `None` while there's no signal
+    value: Option<f32>,
+    label: Option<&'a str>,
+    /// Columns reserved for the label (defaults to the label's own width)
+    label_width: Option<u16>,
+    /// Plain ASCII glyphs instead of block characters
+    ascii: bool,
+}
+
+impl<'a> CorrelationMeter<'a> {
+    pub fn new(value: Option<f32>) -> Self {
+        Self {
+            value: value.map(|v| v.clamp(-1.0, 1.0)),
+            label: None,
+            label_width: None,
+            ascii: false,
+        }
+    }
+
+    pub fn label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Reserve `width` columns for the label to line up with level meters
+    pub fn label_width(mut self, width: u16) -> Self {
+        self.label_width = Some(width);
+        self
+    }
+
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    fn color_for(value: f32) -> Color {
+        if value < 0.0 {
+            Color::Red
+        } else if value < 0.5 {
+            Color::Yellow
+        } else {
+            Color::Green
+        }
+    }
+
+    /// Columns filled on each side of `center` for `value` (left, right)
+    fn fill(value: f32, center: u16) -> (u16, u16) {
+        let cells = (value.abs() * center as f32).round() as u16;
+        if value < 0.0 {
+            (cells, 0)
+        } else {
+            (0, cells)
+        }
+    }
+}
+
+impl Widget for CorrelationMeter<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width < 10 || area.height < 1 {
+            return;
+        }
+
+        // " +0.85" after the bar, like the level meters' dB readout
+        let readout_width = 8;
+        let label_width = self
+            .label
+            .map(|l| {
+                let wanted = self.label_width.unwrap_or(l.width() as u16);
+                wanted.min(area.width.saturating_sub(readout_width) / 2) + 1
+            })
+            .unwrap_or(0);
+        let meter_width = area.width.saturating_sub(label_width + readout_width);
+        if meter_width < 5 {
+            return;
+        }
+
+        let y = area.y;
+        let mut x = area.x;
+        if let Some(label) = self.label {
+            let ellipsis = if self.ascii { "~" } else { "…" };
+            let shown = truncate_to_width(label, label_width as usize - 1, ellipsis);
+            buf.set_string(x, y, shown, Style::default().fg(Color::White));
+            x += label_width;
+        }
+
+        let (fill, empty, center_mark) = if self.ascii {
+            ("#", "-", "|")
+        } else {
+            ("█", "░", "│")
+        };
+        // An odd width keeps the center line in the middle
+        let meter_width = meter_width - (1 - meter_width % 2);
+        let center = meter_width / 2;
+        let (left, right) = self.value.map_or((0, 0), |v| Self::fill(v, center));
+        let color = self.value.map_or(Color::DarkGray, Self::color_for);
+
+        for i in 0..meter_width {
+            let filled = (i < center && i >= center - left) || (i > center && i - center <= right);
+            let (symbol, style) = if i == center {
+                (center_mark, Style::default().fg(Color::Gray))
+            } else if filled {
+                (fill, Style::default().fg(color))
+            } else {
+                (empty, Style::default().fg(Color::DarkGray))
+            };
+            buf.set_string(x + i, y, symbol, style);
+        }
+
+        let readout = match self.value {
+            Some(v) => format!(" {:>+5.2}", v),
+            None => "    --".to_string(),
+        };
+        buf.set_string(
+            x + meter_width,
+            y,
+            readout,
+            Style::default().fg(Color::Gray),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(value: Option<f32>) -> String {
+        let area = Rect::new(0, 0, 19, 1);
+        let mut buf = Buffer::empty(area);
+        CorrelationMeter::new(value)
+            .ascii(true)
+            .render(area, &mut buf);
+        let row: String = (0..area.width).map(|x| buf[(x, 0)].symbol()).collect();
+        row.trim_end().to_string()
+    }
+
+    #[test]
+    fn test_bar_grows_from_the_center() {
+        // 11 meter columns (center at 5), then the readout
+        assert_eq!(render(Some(1.0)), "-----|##### +1.00");
+        assert_eq!(render(Some(-0.4)), "---##|----- -0.40");
+        assert_eq!(render(Some(0.0)), "-----|----- +0.00");
+        assert_eq!(render(None), "-----|-----    --");
+        assert_eq!(CorrelationMeter::color_for(-0.1), Color::Red);
+        assert_eq!(CorrelationMeter::color_for(0.3), Color::Yellow);
+        assert_eq!(CorrelationMeter::color_for(0.9), Color::Green);
+    }
+}
@@ -2,8 +2,11 @@ use ratatui::{
     prelude::*,
     widgets::{Block, List, ListItem, ListState as RatatuiListState, Widget},
 };
+use unicode_width::UnicodeWidthStr;
 
-use crate::audio::AudioDevice;
+use super::truncate_to_width;
+
+use duomic::audio::AudioDevice;
 
 /// A selectable device list widget with arrow key navigation
 pub struct DeviceList<'a> {
@@ -29,13 +32,22 @@ impl<'a> DeviceList<'a> {
 
 impl Widget for DeviceList<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        let inner_width = match &self.block {
+            Some(block) => block.inner(area).width,
+            None => area.width,
+        } as usize;
+
         let items: Vec<ListItem> = self
             .devices
             .iter()
             .enumerate()
             .map(|(i, device)| {
                 let prefix = if i == self.selected { "● " } else { "○ " };
-                let content = format!("{}{} ({} channels)", prefix, device.name, device.channels);
+                // Shorten the name, not the channel count, when the row is too narrow
+                let suffix = format!(" ({} channels)", device.channels);
+                let room = inner_width.saturating_sub(prefix.width() + suffix.width());
+                let name = truncate_to_width(&device.name, room, "…");
+                let content = format!("{}{}{}", prefix, name, suffix);
 
                 let style = if i == self.selected {
                     Style::default()
@@ -98,11 +110,11 @@ impl Widget for HelpBar<'_> {
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::BOLD),
             );
-            x += key_str.len() as u16 + 1;
+            x += key_str.width() as u16 + 1;
 
             // Render action
             buf.set_string(x, area.y, *action, Style::default().fg(Color::Gray));
-            x += action.len() as u16 + 2;
+            x += action.width() as u16 + 2;
         }
     }
 }
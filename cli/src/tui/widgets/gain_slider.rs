@@ -0,0 +1,112 @@
+use ratatui::{prelude::*, widgets::Widget};
+
+/// Horizontal slider showing a channel's software gain
+///
+/// A track over `-range_db..=+range_db` with a tick at 0 dB and a handle
+/// at the current gain. Boosts draw the handle yellow, cuts cyan.
+pub struct GainSlider {
+    gain_db: f32,
+    range_db: f32,
+    /// Highlight the handle (the dashboard cursor is on this row)
+    selected: bool,
+    /// Plain ASCII glyphs instead of box-drawing characters
+    ascii: bool,
+}
+
+impl GainSlider {
+    pub fn new(gain_db: f32) -> Self {
+        Self {
+            gain_db,
+            range_db: 24.0,
+            selected: false,
+            ascii: false,
+        }
+    }
+
+    /// Gain at either end of the track (default ±24 dB)
+    pub fn range_db(mut self, range_db: f32) -> Self {
+        self.range_db = range_db.max(f32::EPSILON);
+        self
+    }
+
+    pub fn selected(mut self, selected: bool) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    /// Column of `gain_db` on a track `width` columns wide
+    fn position(gain_db: f32, range_db: f32, width: u16) -> u16 {
+        let fraction = (gain_db.clamp(-range_db, range_db) + range_db) / (2.0 * range_db);
+        (fraction * width.saturating_sub(1) as f32).round() as u16
+    }
+}
+
+impl Widget for GainSlider {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.width < 3 || area.height < 1 {
+            return;
+        }
+
+        let (track, center_mark, handle) = if self.ascii {
+            ("-", "+", "O")
+        } else {
+            ("─", "┼", "●")
+        };
+        let center = Self::position(0.0, self.range_db, area.width);
+        let at = Self::position(self.gain_db, self.range_db, area.width);
+        let handle_color = if self.gain_db > 0.0 {
+            Color::Yellow
+        } else if self.gain_db < 0.0 {
+            Color::Cyan
+        } else {
+            Color::Gray
+        };
+        let handle_style = if self.selected {
+            Style::default()
+                .fg(handle_color)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(handle_color)
+        };
+
+        for i in 0..area.width {
+            let (symbol, style) = if i == at {
+                (handle, handle_style)
+            } else if i == center {
+                (center_mark, Style::default().fg(Color::Gray))
+            } else {
+                (track, Style::default().fg(Color::DarkGray))
+            };
+            buf.set_string(area.x + i, area.y, symbol, style);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(gain_db: f32) -> String {
+        let area = Rect::new(0, 0, 13, 1);
+        let mut buf = Buffer::empty(area);
+        GainSlider::new(gain_db).ascii(true).render(area, &mut buf);
+        (0..area.width).map(|x| buf[(x, 0)].symbol()).collect()
+    }
+
+    #[test]
+    fn test_handle_follows_gain() {
+        // 13 columns: -24 dB at 0, 0 dB at 6, +24 dB at 12 (4 dB per column)
+        assert_eq!(render(0.0), "------O------");
+        assert_eq!(render(-24.0), "O-----+------");
+        assert_eq!(render(24.0), "------+-----O");
+        assert_eq!(render(8.0), "------+-O----");
+        assert_eq!(render(-8.0), "----O-+------");
+        // Out-of-range gains stop at the ends
+        assert_eq!(render(40.0), "------+-----O");
+    }
+}
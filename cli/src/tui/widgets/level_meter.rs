@@ -2,22 +2,45 @@ use ratatui::{
     prelude::*,
     widgets::{Block, Widget},
 };
+use unicode_width::UnicodeWidthStr;
 
-use crate::audio::amplitude_to_db;
+use super::truncate_to_width;
+
+use duomic::audio::{amplitude_to_db, db_to_amplitude, DEFAULT_FLOOR_DB};
+use duomic::config::{MeterScale, MeterStyle};
 
 /// A gradient audio level meter widget
 ///
-/// Displays audio level with gradient colors:
-/// - Green: -60dB to -12dB (safe)
+/// Displays audio level with gradient colors (on the default dB scale):
+/// - Green: floor (default -60dB) to -12dB (safe)
 /// - Yellow: -12dB to -6dB (caution)
 /// - Red: -6dB to 0dB (peak)
+///
+/// K-system scales color around their reference instead: green below it,
+/// yellow up to 4 dB above, red beyond.
 pub struct LevelMeter<'a> {
     /// Current level (0.0 to 1.0 linear amplitude)
     level: f32,
+    /// Held peak, marked past the bar (linear amplitude)
+    hold: Option<f32>,
+    /// Recently clipped: readout in red
+    clipped: bool,
     /// Label to display
     label: Option<&'a str>,
+    /// Columns reserved for the label (defaults to the label's own width)
+    label_width: Option<u16>,
     /// Show dB value
     show_db: bool,
+    /// Bottom of the meter scale (dBFS)
+    floor_db: f32,
+    /// Clip threshold (dBFS), marked on the meter when below 0 dB
+    clip_db: f32,
+    /// Gradient, single-color or minimal (no dB readout)
+    style: MeterStyle,
+    /// Linear, dB or K-system bar and readout
+    scale: MeterScale,
+    /// Plain ASCII glyphs instead of block characters
+    ascii: bool,
     /// Block for borders
     block: Option<Block<'a>>,
 }
@@ -26,17 +49,70 @@ impl<'a> LevelMeter<'a> {
     pub fn new(level: f32) -> Self {
         Self {
             level: level.clamp(0.0, 1.0),
+            hold: None,
+            clipped: false,
             label: None,
+            label_width: None,
             show_db: true,
+            floor_db: DEFAULT_FLOOR_DB,
+            clip_db: 0.0,
+            style: MeterStyle::Gradient,
+            scale: MeterScale::Db,
+            ascii: false,
             block: None,
         }
     }
 
+    /// Mark a held peak level (see `[meters] peak_hold_ms`)
+    pub fn hold(mut self, hold: Option<f32>) -> Self {
+        self.hold = hold.map(|h| h.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Show the readout in red (see `[meters] clip_latch_s`)
+    pub fn clipped(mut self, clipped: bool) -> Self {
+        self.clipped = clipped;
+        self
+    }
+
+    pub fn style(mut self, style: MeterStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn scale(mut self, scale: MeterScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn ascii(mut self, ascii: bool) -> Self {
+        self.ascii = ascii;
+        self
+    }
+
+    pub fn floor_db(mut self, floor_db: f32) -> Self {
+        // A floor at or above 0 dB would leave nothing to draw
+        self.floor_db = floor_db.min(-1.0);
+        self
+    }
+
+    /// Draw a headroom line at the clip threshold (no line at 0 dB)
+    pub fn clip_db(mut self, clip_db: f32) -> Self {
+        self.clip_db = clip_db.min(0.0);
+        self
+    }
+
     pub fn label(mut self, label: &'a str) -> Self {
         self.label = Some(label);
         self
     }
 
+    /// Reserve `width` columns for the label so meters line up across rows
+    pub fn label_width(mut self, width: u16) -> Self {
+        self.label_width = Some(width);
+        self
+    }
+
     pub fn show_db(mut self, show: bool) -> Self {
         self.show_db = show;
         self
@@ -47,16 +123,59 @@ impl<'a> LevelMeter<'a> {
         self
     }
 
+    /// Map a dB value onto 0.0..=1.0 of the meter width
+    fn normalize_db(db: f32, floor_db: f32) -> f32 {
+        ((db - floor_db) / -floor_db).clamp(0.0, 1.0)
+    }
+
+    /// Position of `db` on a `scale` meter, 0.0..=1.0 of its width
+    fn position(scale: MeterScale, db: f32, floor_db: f32) -> f32 {
+        match scale {
+            MeterScale::Linear if db <= floor_db => 0.0,
+            MeterScale::Linear => db_to_amplitude(db).clamp(0.0, 1.0),
+            MeterScale::Db | MeterScale::K20 | MeterScale::K14 => Self::normalize_db(db, floor_db),
+        }
+    }
+
+    /// dB at `position` on a `scale` meter (inverse of `position`)
+    fn db_at(scale: MeterScale, position: f32, floor_db: f32) -> f32 {
+        match scale {
+            MeterScale::Linear => amplitude_to_db(position, floor_db),
+            MeterScale::Db | MeterScale::K20 | MeterScale::K14 => floor_db - position * floor_db,
+        }
+    }
+
     /// Get color for a given dB level
-    fn color_for_db(db: f32) -> Color {
-        if db >= -6.0 {
+    fn color_for_db(scale: MeterScale, db: f32) -> Color {
+        // (yellow from, red from) in dBFS
+        let (caution, peak) = match scale {
+            MeterScale::Linear | MeterScale::Db => (-12.0, -6.0),
+            MeterScale::K20 => (-20.0, -16.0),
+            MeterScale::K14 => (-14.0, -10.0),
+        };
+        if db >= peak {
             Color::Red
-        } else if db >= -12.0 {
+        } else if db >= caution {
             Color::Yellow
         } else {
             Color::Green
         }
     }
+
+    /// Readout after the bar, e.g. " -12dB", "  25%" or "  +3K20"
+    fn readout(scale: MeterScale, db: f32, floor_db: f32) -> String {
+        let silent = db <= floor_db;
+        match scale {
+            MeterScale::Linear if silent => "   0%".to_string(),
+            MeterScale::Linear => format!(" {:>3.0}%", db_to_amplitude(db) * 100.0),
+            MeterScale::Db if silent => " -∞dB".to_string(),
+            MeterScale::Db => format!(" {:>3.0}dB", db),
+            MeterScale::K20 if silent => "  -∞K20".to_string(),
+            MeterScale::K20 => format!(" {:>+3.0}K20", db + 20.0),
+            MeterScale::K14 if silent => "  -∞K14".to_string(),
+            MeterScale::K14 => format!(" {:>+3.0}K14", db + 14.0),
+        }
+    }
 }
 
 impl Widget for LevelMeter<'_> {
@@ -75,8 +194,17 @@ impl Widget for LevelMeter<'_> {
         }
 
         // Calculate layout
-        let label_width = self.label.map(|l| l.len() as u16 + 1).unwrap_or(0);
-        let db_width = if self.show_db { 8 } else { 0 }; // " -12dB "
+        let show_db = self.show_db && self.style != MeterStyle::Minimal;
+        let db_width = if show_db { 8 } else { 0 }; // " -12dB "
+
+        // Long labels never take more than half the row from the meter
+        let label_width = self
+            .label
+            .map(|l| {
+                let wanted = self.label_width.unwrap_or(l.width() as u16);
+                wanted.min(inner.width.saturating_sub(db_width) / 2) + 1
+            })
+            .unwrap_or(0);
         let meter_width = inner.width.saturating_sub(label_width + db_width);
 
         if meter_width < 5 {
@@ -88,42 +216,72 @@ impl Widget for LevelMeter<'_> {
 
         // Render label
         if let Some(label) = self.label {
-            buf.set_string(x, y, label, Style::default().fg(Color::White));
+            let ellipsis = if self.ascii { "~" } else { "…" };
+            let shown = truncate_to_width(label, label_width as usize - 1, ellipsis);
+            buf.set_string(x, y, shown, Style::default().fg(Color::White));
             x += label_width;
         }
 
         // Calculate meter fill
-        let db = amplitude_to_db(self.level);
-        let db_normalized = ((db + 60.0) / 60.0).clamp(0.0, 1.0);
-        let fill_width = (meter_width as f32 * db_normalized) as u16;
+        let db = amplitude_to_db(self.level, self.floor_db);
+        let position = Self::position(self.scale, db, self.floor_db);
+        let fill_width = (meter_width as f32 * position) as u16;
+
+        // Headroom line column (only when the threshold is inside the scale)
+        let clip_x = (self.clip_db < 0.0 && self.clip_db > self.floor_db).then(|| {
+            (meter_width as f32 * Self::position(self.scale, self.clip_db, self.floor_db)) as u16
+        });
 
-        // Render meter bar with gradient
+        // Held peak column, when it's past the end of the bar
+        let hold_x = self
+            .hold
+            .map(|hold| {
+                let db = amplitude_to_db(hold, self.floor_db);
+                let position = Self::position(self.scale, db, self.floor_db);
+                ((meter_width as f32 * position) as u16).min(meter_width - 1)
+            })
+            .filter(|&x| x >= fill_width && self.hold.is_some_and(|h| h > 0.0));
+
+        let (fill, empty, marker) = if self.ascii {
+            ("#", "-", "|")
+        } else {
+            ("█", "░", "│")
+        };
+
+        // Render meter bar (gradient, one color, or colored by the current level)
         for i in 0..meter_width {
-            let char_db = -60.0 + (i as f32 / meter_width as f32) * 60.0;
-            let color = Self::color_for_db(char_db);
+            let color = match self.style {
+                MeterStyle::Gradient => {
+                    let char_db =
+                        Self::db_at(self.scale, i as f32 / meter_width as f32, self.floor_db);
+                    Self::color_for_db(self.scale, char_db)
+                }
+                MeterStyle::Mono => Color::Cyan,
+                MeterStyle::Minimal => Self::color_for_db(self.scale, db),
+            };
 
             let (symbol, style) = if i < fill_width {
-                ("█", Style::default().fg(color))
+                (fill, Style::default().fg(color))
+            } else if Some(i) == hold_x {
+                (marker, Style::default().fg(Color::White))
+            } else if Some(i) == clip_x {
+                (marker, Style::default().fg(Color::Red))
             } else {
-                ("░", Style::default().fg(Color::DarkGray))
+                (empty, Style::default().fg(Color::DarkGray))
             };
 
             buf.set_string(x + i, y, symbol, style);
         }
 
         // Render dB value
-        if self.show_db {
-            let db_str = if db <= -60.0 {
-                " -∞dB".to_string()
+        if show_db {
+            let db_str = Self::readout(self.scale, db, self.floor_db);
+            let style = if self.clipped {
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
             } else {
-                format!(" {:>3.0}dB", db)
+                Style::default().fg(Color::Gray)
             };
-            buf.set_string(
-                x + meter_width,
-                y,
-                &db_str,
-                Style::default().fg(Color::Gray),
-            );
+            buf.set_string(x + meter_width, y, &db_str, style);
         }
     }
 }
@@ -134,17 +292,37 @@ pub struct MultiLevelMeter<'a> {
     levels: &'a [f32],
     /// Channel labels
     labels: &'a [String],
+    /// Bottom of the meter scale (dBFS)
+    floor_db: f32,
+    /// Clip threshold (dBFS)
+    clip_db: f32,
 }
 
 impl<'a> MultiLevelMeter<'a> {
     pub fn new(levels: &'a [f32], labels: &'a [String]) -> Self {
-        Self { levels, labels }
+        Self {
+            levels,
+            labels,
+            floor_db: DEFAULT_FLOOR_DB,
+            clip_db: 0.0,
+        }
+    }
+
+    pub fn floor_db(mut self, floor_db: f32) -> Self {
+        self.floor_db = floor_db;
+        self
+    }
+
+    pub fn clip_db(mut self, clip_db: f32) -> Self {
+        self.clip_db = clip_db;
+        self
     }
 }
 
 impl Widget for MultiLevelMeter<'_> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let max_channels = area.height as usize;
+        let label_width = self.labels.iter().map(|l| l.width()).max().unwrap_or(0) as u16;
 
         for (i, (level, label)) in self
             .levels
@@ -162,7 +340,10 @@ impl Widget for MultiLevelMeter<'_> {
 
             LevelMeter::new(*level)
                 .label(label)
+                .label_width(label_width)
                 .show_db(true)
+                .floor_db(self.floor_db)
+                .clip_db(self.clip_db)
                 .render(row, buf);
         }
     }
@@ -174,8 +355,89 @@ mod tests {
 
     #[test]
     fn test_color_for_db() {
-        assert_eq!(LevelMeter::color_for_db(-30.0), Color::Green);
-        assert_eq!(LevelMeter::color_for_db(-10.0), Color::Yellow);
-        assert_eq!(LevelMeter::color_for_db(-3.0), Color::Red);
+        assert_eq!(
+            LevelMeter::color_for_db(MeterScale::Db, -30.0),
+            Color::Green
+        );
+        assert_eq!(
+            LevelMeter::color_for_db(MeterScale::Db, -10.0),
+            Color::Yellow
+        );
+        assert_eq!(LevelMeter::color_for_db(MeterScale::Db, -3.0), Color::Red);
+    }
+
+    #[test]
+    fn test_scales() {
+        use MeterScale::*;
+
+        // dB and K-system share the dBFS bar; linear follows amplitude
+        assert!((LevelMeter::position(Db, -30.0, -60.0) - 0.5).abs() < 0.001);
+        assert!((LevelMeter::position(K20, -30.0, -60.0) - 0.5).abs() < 0.001);
+        assert!((LevelMeter::position(Linear, -6.02, -60.0) - 0.5).abs() < 0.001);
+        assert_eq!(LevelMeter::position(Linear, -60.0, -60.0), 0.0);
+        assert_eq!(LevelMeter::position(Linear, 0.0, -60.0), 1.0);
+        for scale in [Linear, Db, K20, K14] {
+            let db = LevelMeter::db_at(scale, 0.25, -60.0);
+            assert!((LevelMeter::position(scale, db, -60.0) - 0.25).abs() < 0.001);
+        }
+
+        // Color bands: linear matches dB, K-system bands sit around its 0
+        assert_eq!(LevelMeter::color_for_db(Linear, -10.0), Color::Yellow);
+        assert_eq!(LevelMeter::color_for_db(K20, -21.0), Color::Green);
+        assert_eq!(LevelMeter::color_for_db(K20, -20.0), Color::Yellow);
+        assert_eq!(LevelMeter::color_for_db(K20, -16.0), Color::Red);
+        assert_eq!(LevelMeter::color_for_db(K14, -15.0), Color::Green);
+        assert_eq!(LevelMeter::color_for_db(K14, -12.0), Color::Yellow);
+        assert_eq!(LevelMeter::color_for_db(K14, -10.0), Color::Red);
+
+        assert_eq!(LevelMeter::readout(Db, -12.0, -60.0), " -12dB");
+        assert_eq!(LevelMeter::readout(Linear, -6.0206, -60.0), "  50%");
+        assert_eq!(LevelMeter::readout(K20, -17.0, -60.0), "  +3K20");
+        assert_eq!(LevelMeter::readout(K14, -20.0, -60.0), "  -6K14");
+        assert_eq!(LevelMeter::readout(K20, -60.0, -60.0), "  -∞K20");
+    }
+
+    #[test]
+    fn test_normalize_db_with_floor() {
+        assert_eq!(LevelMeter::normalize_db(0.0, -60.0), 1.0);
+        assert_eq!(LevelMeter::normalize_db(-60.0, -60.0), 0.0);
+        assert!((LevelMeter::normalize_db(-30.0, -60.0) - 0.5).abs() < 0.001);
+
+        // A lower floor stretches the same level further up the meter
+        assert!((LevelMeter::normalize_db(-30.0, -80.0) - 0.625).abs() < 0.001);
+        assert!((LevelMeter::normalize_db(-70.0, -80.0) - 0.125).abs() < 0.001);
+        assert_eq!(LevelMeter::normalize_db(-70.0, -60.0), 0.0);
+    }
+
+    /// First column of the meter bar in a rendered row
+    fn meter_start(buf: &Buffer, y: u16) -> Option<u16> {
+        (0..buf.area.width).find(|&x| buf[(x, y)].symbol() == "░")
+    }
+
+    #[test]
+    fn test_wide_labels_keep_meter_columns() {
+        let area = Rect::new(0, 0, 40, 3);
+        let mut buf = Buffer::empty(area);
+        // "日本語" is three characters, nine bytes and six columns
+        let labels = [
+            "日本語".to_string(),
+            "Host".to_string(),
+            "Guest".to_string(),
+        ];
+        MultiLevelMeter::new(&[0.0; 3], &labels).render(area, &mut buf);
+
+        assert_eq!(meter_start(&buf, 0), Some(7));
+        assert_eq!(meter_start(&buf, 1), Some(7));
+        assert_eq!(meter_start(&buf, 2), Some(7));
+
+        // An over-long name is cut with an ellipsis instead of pushing the
+        // meter off the row
+        let mut buf = Buffer::empty(Rect::new(0, 0, 40, 1));
+        LevelMeter::new(0.0)
+            .label("マイクマイクマイクマイクマイクマイク")
+            .render(Rect::new(0, 0, 40, 1), &mut buf);
+        assert_eq!(meter_start(&buf, 0), Some(17));
+        assert_eq!(buf[(14, 0)].symbol(), "…");
+        assert_eq!(buf[(36, 0)].symbol(), "B");
     }
 }
@@ -3,9 +3,60 @@
 #![allow(unused_imports)]
 
 mod channel_picker;
+mod correlation_meter;
 mod device_list;
+mod gain_slider;
 mod level_meter;
 
 pub use channel_picker::*;
+pub use correlation_meter::*;
 pub use device_list::*;
+pub use gain_slider::*;
 pub use level_meter::*;
+
+use std::borrow::Cow;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Shorten `text` to at most `max_width` terminal columns, ending it with
+/// `ellipsis` when anything had to be cut
+///
+/// Widths are display columns, so wide (e.g. CJK) characters count as two.
+pub fn truncate_to_width<'a>(text: &'a str, max_width: usize, ellipsis: &str) -> Cow<'a, str> {
+    if text.width() <= max_width {
+        return Cow::Borrowed(text);
+    }
+
+    let budget = max_width.saturating_sub(ellipsis.width());
+    let mut used = 0;
+    let mut out = String::new();
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > budget {
+            break;
+        }
+        used += w;
+        out.push(c);
+    }
+    if ellipsis.width() <= max_width {
+        out.push_str(ellipsis);
+    }
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_to_width_counts_columns() {
+        assert_eq!(truncate_to_width("Host", 10, "…"), "Host");
+        assert_eq!(truncate_to_width("Podcast Guest", 8, "…"), "Podcast…");
+
+        // Two columns per character: a wide char that would straddle the
+        // limit is dropped rather than split
+        let cut = truncate_to_width("日本語マイク", 8, "…");
+        assert_eq!(cut, "日本語…");
+        assert!(cut.width() <= 8);
+        assert_eq!(truncate_to_width("日本語マイク", 12, "…"), "日本語マイク");
+    }
+}